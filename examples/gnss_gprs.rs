@@ -24,7 +24,7 @@ async fn post_request(sim: &SIM868, gnss_data: GNSSData) -> Result<String, rpi_s
         url: String::from("http://httpbin.org/post"),
     };
 
-    Ok(sim.gprs.request(req).await??)
+    sim.gprs.request(req).await?
 }
 
 #[tokio::main]
@@ -32,7 +32,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Debug);
 
     // turn on hat if turned off
-    if let Err(_) = sim.hat.is_on().await? {
+    if sim.hat.is_on().await?.is_err() {
         sim.hat.turn_on().await?
     }
 
@@ -51,8 +51,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .await??;
 
     // wait for the network connection
-    while let Ok(network_strenght) = sim.hat.network_strength().await? {
-        if network_strenght > 0 {
+    while let Ok(strength) = sim.hat.signal_strength().await? {
+        if strength.has_signal() {
             break;
         }
         sleep(Duration::from_secs(2));
@@ -0,0 +1,52 @@
+//! Example of running the crate against the virtual SIM868 - no hardware needed.
+//!
+//! `cargo run --example simulator --features simulator`
+
+use rpi_sim868::{
+    hat::PowerDownMode,
+    simulator::{Simulator, SimulatorConfig},
+    sms::MessageStorage,
+    SIM868,
+};
+use std::time::Duration;
+use tokio::time::sleep;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let simulator: Simulator = Simulator::new(SimulatorConfig {
+        fix_delay: Duration::from_secs(2),
+        ..Default::default()
+    });
+    let sim: SIM868 = SIM868::with_transport(simulator.clone(), rpi_sim868::LogLevelFilter::Info);
+
+    sim.hat.init().await??;
+
+    // waiting for the GSM network connection...
+    while !sim.hat.signal_strength().await??.has_signal() {
+        sleep(Duration::from_secs(1)).await;
+    }
+
+    sim.sms
+        .send("+4799999999", "Hello from the simulator!")
+        .await??;
+    println!("Sent: {:?}", simulator.sent_messages());
+
+    simulator.receive_sms("+4788888888", "Hi there!");
+    for message in sim.sms.get_messages(MessageStorage::UNREAD).await?? {
+        println!("Received: {message:?}");
+    }
+
+    sim.gnss.turn_on().await??;
+    loop {
+        match sim.gnss.get_data().await? {
+            Ok(gnss_data) => {
+                println!("Position: {gnss_data:?}");
+                break;
+            }
+            Err(_) => sleep(Duration::from_secs(1)).await,
+        }
+    }
+
+    sim.hat.turn_off(PowerDownMode::Normal).await??;
+    Ok(())
+}
@@ -0,0 +1,101 @@
+#ifndef RPI_SIM868_H
+#define RPI_SIM868_H
+
+/* Generated by cbindgen from src/ffi.rs - regenerate with `cbindgen --config cbindgen.toml --output include/rpi_sim868.h`. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define SIM868_OK 0
+
+// Null pointer or a string which isn't valid UTF-8 (or JSON in [`sim868_http_post`]) has been passed.
+#define SIM868_ERROR_INVALID_ARGUMENT -1
+
+// The task has failed, see [`sim868_last_error`].
+#define SIM868_ERROR_FAILED -2
+
+typedef struct Sim868 Sim868;
+
+// GNSS fix returned by [`sim868_gnss_get_fix`], see [`GNSSData`].
+typedef struct Sim868GnssFix {
+  double lat;
+  double lon;
+  // NaN if the modem hasn't reported it, as the speed and the course.
+  float alt;
+  float ground_speed;
+  float ground_course;
+  uint8_t sats_in_view;
+  uint8_t sats_in_use;
+  // UTC Unix timestamp in seconds.
+  int64_t timestamp;
+} Sim868GnssFix;
+
+// Connects to the HAT through the UART at `path`, eg. `/dev/ttyS0`. Returns null on failure.
+//
+// # Safety
+// `path` must be a valid nul-terminated string. The returned pointer must be released with [`sim868_free`].
+struct Sim868 *sim868_new(const char *path,
+                          uint32_t baud_rate);
+
+// # Safety
+// `sim` must be returned by [`sim868_new`] and not used afterwards. Null is ignored.
+void sim868_free(struct Sim868 *sim);
+
+// Turns on the HAT, see [`crate::hat::Hat::turn_on`].
+//
+// # Safety
+// `sim` must be returned by [`sim868_new`].
+int32_t sim868_turn_on(const struct Sim868 *sim);
+
+// Sends the SMS `text` to the `number`.
+//
+// # Safety
+// `sim` must be returned by [`sim868_new`], `number` and `text` must be valid nul-terminated strings.
+int32_t sim868_sms_send(const struct Sim868 *sim,
+                        const char *number,
+                        const char *text);
+
+// Turns on the GNSS module, which has to be done before reading the fix.
+//
+// # Safety
+// `sim` must be returned by [`sim868_new`].
+int32_t sim868_gnss_turn_on(const struct Sim868 *sim);
+
+// Writes the current GNSS fix into `fix`.
+//
+// # Safety
+// `sim` must be returned by [`sim868_new`], `fix` must point to a writable [`Sim868GnssFix`].
+int32_t sim868_gnss_get_fix(const struct Sim868 *sim, struct Sim868GnssFix *fix);
+
+// Configures the APN, which has to be done before making HTTP requests.
+//
+// # Safety
+// `sim` must be returned by [`sim868_new`], `apn`, `user` and `password` must be valid nul-terminated strings.
+int32_t sim868_gprs_init(const struct Sim868 *sim,
+                         const char *apn,
+                         const char *user,
+                         const char *password);
+
+// Sends the `json` body to the `url` and copies the response body into `response` of `response_length` bytes.
+// The response is truncated if it doesn't fit, pass null to ignore it.
+//
+// # Safety
+// `sim` must be returned by [`sim868_new`], `url` and `json` must be valid nul-terminated strings,
+// `response` must be null or point to at least `response_length` writable bytes.
+int32_t sim868_http_post(const struct Sim868 *sim,
+                         const char *url,
+                         const char *json,
+                         char *response,
+                         size_t response_length);
+
+// Copies the message of the last error on the calling thread into `buffer` of `length` bytes.
+// Returns the number of bytes copied, without the terminating nul byte.
+//
+// # Safety
+// `buffer` must be null or point to at least `length` writable bytes.
+size_t sim868_last_error(char *buffer, size_t length);
+
+#endif  /* RPI_SIM868_H */
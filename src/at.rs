@@ -0,0 +1,261 @@
+//! AT module
+//!
+//! Escape hatch for the commands the crate doesn't wrap (yet), eg. the vendor-specific `AT+CBAND` or
+//! `AT+CNETLIGHT`. [`AT::send`] runs the command as a regular task, so it waits for its turn in the queue
+//! instead of racing against the library traffic on the UART.
+//!
+//! ```no_run
+//! use rpi_sim868::{at::ResponsePattern, SIM868};
+//!
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! #[tokio::main]
+//! async fn main() -> Result<(), rpi_sim868::Error> {
+//!     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//!     let bands: String = sim
+//!         .at
+//!         .send_now("AT+CBAND?", ResponsePattern::Ok, None)
+//!         .await?;
+//!     println!("{bands}");
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`AT::batch`] runs several commands as one task, holding the port from the first to the last, so nothing
+//! else is sent in between:
+//!
+//! ```no_run
+//! use rpi_sim868::{at::ResponsePattern, SIM868};
+//!
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! #[tokio::main]
+//! async fn main() -> Result<(), rpi_sim868::Error> {
+//!     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//!     let responses: Vec<String> = sim
+//!         .at
+//!         .batch()
+//!         .command("AT+CNETLIGHT=1", ResponsePattern::Ok, None)
+//!         .command("AT+CNETLIGHT?", ResponsePattern::Ok, None)
+//!         .run_now()
+//!         .await?;
+//!     println!("{}", responses[1]);
+//!     Ok(())
+//! }
+//! ```
+
+#[cfg(feature = "blocking")]
+use crate::blocking::block_on_task;
+use crate::{
+    ack_check,
+    error::Error,
+    error_check, join_task,
+    serial_port::{spawn_task, CommandBatch, SerialPort, TaskPriority},
+    Module, ResolverReturn, TaskJoinHandle,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+/// Response ending the command sent with [`AT::send`].
+#[derive(Debug, Clone)]
+pub enum ResponsePattern {
+    /// The final `OK`.
+    Ok,
+    /// The response containing the text, eg. `+CBAND:`.
+    Contains(String),
+    /// The response matching the regular expression, eg. `\+CNETLIGHT: \d`.
+    Regex(regex::Regex),
+}
+
+impl ResponsePattern {
+    pub fn matches(&self, response: &str) -> bool {
+        match self {
+            ResponsePattern::Ok => ack_check(response),
+            ResponsePattern::Contains(text) => response.contains(text.as_str()),
+            ResponsePattern::Regex(regex) => regex.is_match(response),
+        }
+    }
+}
+
+/// Whatever the modem has sent, accumulated by [`send`] until it matches the pattern.
+fn chunk_resolver(result: String) -> ResolverReturn<String> {
+    match result.is_empty() {
+        true => Err(Error::NotResolved),
+        false => Ok(result),
+    }
+}
+
+fn send(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    (command, expected, timeout): (String, ResponsePattern, Option<Duration>),
+) -> ResolverReturn<String> {
+    let timeout: Duration = timeout.unwrap_or(serial_port.timeouts.query);
+    let deadline: Instant = Instant::now() + timeout;
+
+    let mut response: String = serial_port.process(
+        task_id,
+        format!("{}\n", command.trim_end()),
+        chunk_resolver,
+        Some(timeout),
+    )?;
+    loop {
+        if expected.matches(&response) {
+            return Ok(response);
+        }
+        if error_check(&response) {
+            return Err(Error::AtCommandFailed(response));
+        }
+        let remaining: Duration = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::NotResolved);
+        }
+        response.push_str(&serial_port.read(task_id, chunk_resolver, Some(remaining))?);
+    }
+}
+
+fn batch(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    steps: Vec<(String, ResponsePattern, Option<Duration>)>,
+) -> ResolverReturn<Vec<String>> {
+    let batch: CommandBatch<String> = steps.into_iter().fold(
+        CommandBatch::new(),
+        |batch, (command, expected, timeout)| {
+            let resolver = move |response: String| {
+                if expected.matches(&response) {
+                    return Ok(response);
+                }
+                match error_check(&response) {
+                    true => Err(Error::AtCommandFailed(response)),
+                    false => Err(Error::NotResolved),
+                }
+            };
+            batch.step(format!("{}\n", command.trim_end()), resolver, timeout)
+        },
+    );
+    serial_port.process_batch(task_id, batch)
+}
+
+/// Commands run by [`Batch::run`] in order, see [`AT::batch`].
+pub struct Batch {
+    serial_port: Arc<SerialPort>,
+    steps: Vec<(String, ResponsePattern, Option<Duration>)>,
+}
+
+impl Batch {
+    /// Appends the raw `command`, resolved like with [`AT::send`].
+    pub fn command(
+        mut self,
+        command: &str,
+        expected: ResponsePattern,
+        timeout: Option<Duration>,
+    ) -> Self {
+        self.steps.push((command.to_string(), expected, timeout));
+        self
+    }
+
+    /// Runs the commands and returns their responses, in order. Stops at the first failed command - the
+    /// following ones aren't sent.
+    pub fn run(self) -> TaskJoinHandle<Vec<String>> {
+        let description: String = format!("Sending batch of {} commands...", self.steps.len());
+        spawn_task(
+            self.serial_port,
+            TaskPriority::NORMAL,
+            batch,
+            Some(description),
+            self.steps,
+        )
+    }
+}
+
+impl Batch {
+    /// [`Batch::run`] returning the result directly.
+    pub async fn run_now(self) -> ResolverReturn<Vec<String>> {
+        join_task(self.run()).await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Batch {
+    /// Blocking version of [`Batch::run`].
+    pub fn run_blocking(self) -> ResolverReturn<Vec<String>> {
+        block_on_task(|| self.run())
+    }
+}
+
+/// AT Module
+///
+/// The handle doesn't keep the connection open, see [`crate::SIM868`].
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone)]
+pub struct AT {
+    serial_port: Arc<SerialPort>,
+}
+
+impl Module for AT {
+    fn new(serial_port: Arc<SerialPort>) -> Self {
+        AT { serial_port }
+    }
+}
+
+impl AT {
+    /// Sends the raw `command` (eg. `AT+CNETLIGHT?`) and returns the whole response once it matches `expected`.
+    /// Fails with [`Error::AtCommandFailed`] if the modem answers with `ERROR` instead, and with
+    /// [`Error::NotResolved`] if nothing matching arrives within the `timeout` (the query timeout by default).
+    ///
+    /// The command is sent as it is - unlike [`crate::command::AtCommand`], nothing is escaped.
+    pub fn send(
+        &self,
+        command: &str,
+        expected: ResponsePattern,
+        timeout: Option<Duration>,
+    ) -> TaskJoinHandle<String> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            send,
+            Some(format!("Sending {}...", command.trim_end())),
+            (command.to_string(), expected, timeout),
+        )
+    }
+
+    /// Empty batch of commands, run as one task without releasing the port in between - eg. a multi-step
+    /// configuration which shouldn't be interleaved with the other tasks.
+    pub fn batch(&self) -> Batch {
+        Batch {
+            serial_port: self.serial_port.clone(),
+            steps: Vec::new(),
+        }
+    }
+}
+
+impl AT {
+    /// [`AT::send`] returning the result directly.
+    pub async fn send_now(
+        &self,
+        command: &str,
+        expected: ResponsePattern,
+        timeout: Option<Duration>,
+    ) -> ResolverReturn<String> {
+        join_task(self.send(command, expected, timeout)).await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl AT {
+    /// Blocking version of [`AT::send`].
+    pub fn send_blocking(
+        &self,
+        command: &str,
+        expected: ResponsePattern,
+        timeout: Option<Duration>,
+    ) -> ResolverReturn<String> {
+        block_on_task(|| self.send(command, expected, timeout))
+    }
+}
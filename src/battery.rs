@@ -0,0 +1,155 @@
+//! Battery module
+//!
+//! [`BatteryMonitor`] reads the battery status (see [`crate::hat::Hat::battery_status`]) periodically and
+//! publishes [`Event::LowBattery`] and [`Event::UnderVoltage`] once the charge or the voltage drops below the
+//! threshold - eg. for a solar powered tracker to cut down its reporting before the modem browns out. Each
+//! event is published once, until the value is back above the threshold.
+//!
+//! ```no_run
+//! use rpi_sim868::{
+//!     battery::{BatteryMonitor, BatteryMonitorConfig},
+//!     events::Event,
+//!     SIM868,
+//! };
+//!
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! #[tokio::main]
+//! async fn main() -> Result<(), rpi_sim868::Error> {
+//!     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//!     let mut events = sim.events();
+//!     let monitor: BatteryMonitor = BatteryMonitor::start(&sim, BatteryMonitorConfig::default());
+//!     while let Ok(event) = events.recv().await {
+//!         if let Event::LowBattery { charge } = event {
+//!             println!("{charge} % left");
+//!         }
+//!     }
+//!     monitor.stop();
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{events::Event, hat::Battery, SIM868};
+use std::time::Duration;
+use tokio::{task::JoinHandle, time::sleep};
+
+/// Configuration of the [`BatteryMonitor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatteryMonitorConfig {
+    /// Time between the readings, 60 s by default.
+    pub interval: Duration,
+    /// Charge (%) below which [`Event::LowBattery`] is published, 20 % by default.
+    pub low_charge: u8,
+    /// Voltage (mV) below which [`Event::UnderVoltage`] is published, 3500 mV by default - ahead of the modem's
+    /// own [`Event::UnderVoltageWarning`].
+    pub min_voltage: u16,
+}
+
+impl Default for BatteryMonitorConfig {
+    fn default() -> Self {
+        BatteryMonitorConfig {
+            interval: Duration::from_secs(60),
+            low_charge: 20,
+            min_voltage: 3500,
+        }
+    }
+}
+
+/// Monitoring task running in the background, see the [module](self) documentation.
+pub struct BatteryMonitor {
+    handle: JoinHandle<()>,
+}
+
+async fn monitor(sim: SIM868, config: BatteryMonitorConfig) {
+    // the events are published when the value drops below the threshold, not on every reading
+    let mut low_charge: bool = false;
+    let mut under_voltage: bool = false;
+
+    loop {
+        match sim.hat.battery_status_now().await {
+            Ok(Battery {
+                charge, voltage, ..
+            }) => {
+                if charge < config.low_charge && !low_charge {
+                    let _ = sim.serial_port.events.send(Event::LowBattery { charge });
+                }
+                if voltage < config.min_voltage && !under_voltage {
+                    let _ = sim.serial_port.events.send(Event::UnderVoltage { voltage });
+                }
+                low_charge = charge < config.low_charge;
+                under_voltage = voltage < config.min_voltage;
+            }
+            Err(e) => log::info!("Battery monitor - battery status not available: {e}"),
+        }
+        sleep(config.interval).await;
+    }
+}
+
+impl BatteryMonitor {
+    /// Starts reading the battery status in the background. The monitor keeps a handle of the `sim`, so the
+    /// connection stays open until it's stopped.
+    pub fn start(sim: &SIM868, config: BatteryMonitorConfig) -> BatteryMonitor {
+        BatteryMonitor {
+            handle: tokio::spawn(monitor(sim.clone(), config)),
+        }
+    }
+
+    pub fn stop(self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{MockResponse, MockTransport},
+        LogLevelFilter,
+    };
+    use tokio::sync::broadcast::Receiver;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn publishes_threshold_crossings() {
+        let mock: MockTransport = MockTransport::new();
+        for (charge, voltage) in [
+            (50, 3600),
+            (15, 3400),
+            (12, 3380),
+            (50, 3600),
+            (19, 3600),
+            (60, 3450),
+        ] {
+            mock.once(
+                "AT+CBC",
+                MockResponse::lines(&[&format!("+CBC: 0,{charge},{voltage}"), "OK"]),
+            );
+        }
+        mock.on("AT+CBC", MockResponse::lines(&["+CBC: 0,60,3600", "OK"]));
+        let sim: SIM868 = SIM868::with_transport(mock.clone(), LogLevelFilter::Off);
+        let mut events: Receiver<Event> = sim.events();
+
+        let config: BatteryMonitorConfig = BatteryMonitorConfig {
+            interval: Duration::from_millis(10),
+            ..BatteryMonitorConfig::default()
+        };
+        let monitor: BatteryMonitor = BatteryMonitor::start(&sim, config);
+        while mock.written().len() < 7 {
+            sleep(Duration::from_millis(10)).await;
+        }
+        monitor.stop();
+
+        let published: Vec<Event> = std::iter::from_fn(|| events.try_recv().ok()).collect();
+        // published once on falling below the threshold, again after recovering above it
+        assert_eq!(
+            published,
+            [
+                Event::LowBattery { charge: 15 },
+                Event::UnderVoltage { voltage: 3400 },
+                Event::LowBattery { charge: 19 },
+                Event::UnderVoltage { voltage: 3450 },
+            ]
+        );
+    }
+}
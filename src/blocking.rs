@@ -0,0 +1,33 @@
+//! Runtime behind the `_blocking` methods, available with the `blocking` feature.
+//!
+//! The methods must not be called from within an asynchronous context, as tokio doesn't allow to block
+//! a runtime thread.
+
+use crate::{error::Error, ResolverReturn, TaskJoinHandle};
+use lazy_static::lazy_static;
+use std::future::Future;
+use tokio::runtime::Runtime;
+
+lazy_static! {
+    static ref RUNTIME: Runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_time()
+        .build()
+        .expect("Critical error: can't start the tokio runtime.");
+}
+
+/// Spawns the task on the shared runtime and waits for its result.
+pub(crate) fn block_on_task<T, F>(task: F) -> ResolverReturn<T>
+where
+    F: FnOnce() -> TaskJoinHandle<T>,
+{
+    let _guard: tokio::runtime::EnterGuard<'_> = RUNTIME.enter();
+    let result: Result<ResolverReturn<T>, tokio::task::JoinError> = RUNTIME.block_on(task());
+    result.map_err(Error::from)?
+}
+
+pub(crate) fn block_on<T, F>(future: F) -> T
+where
+    F: Future<Output = T>,
+{
+    RUNTIME.block_on(future)
+}
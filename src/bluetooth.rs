@@ -0,0 +1,927 @@
+//! Bluetooth module
+//!
+//! Available with the `bluetooth` feature.
+//!
+//! See [`Bluetooth`] to discover available methods. The SIM868 includes Bluetooth 3.0, which has to be powered on
+//! with [`Bluetooth::turn_on`] before scanning or configuring it.
+//!
+//! ```no_run
+//! use rpi_sim868::{bluetooth::BluetoothDevice, SIM868};
+//! use std::time::Duration;
+//!
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! #[tokio::main]
+//! async fn main() -> Result<(), rpi_sim868::Error> {
+//!     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//!     sim.bluetooth.turn_on_now().await?;
+//!     sim.bluetooth.set_name_now("balloon").await?;
+//!
+//!     let devices: Vec<BluetoothDevice> = sim.bluetooth.scan_now(Duration::from_secs(15)).await?;
+//!     for device in devices {
+//!         println!("{} ({}) {} dBm", device.name, device.address, device.rssi);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! # Serial Port Profile
+//!
+//! The SPP connection exchanges text with a nearby device (eg. a phone with a Bluetooth terminal app) without
+//! any network coverage. Connect to a scanned device with [`Bluetooth::connect`], or accept the incoming
+//! connection (announced by [`crate::events::Event::BluetoothConnectionRequest`]) with [`Bluetooth::accept`].
+//!
+//! ```no_run
+//! # use rpi_sim868::SIM868;
+//! # use std::time::Duration;
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), rpi_sim868::Error> {
+//! # let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//! let connection = sim.bluetooth.accept_now().await?;
+//! sim.bluetooth.send_now("position?\n").await?;
+//! let reply: String = sim.bluetooth.receive_now(Duration::from_secs(30)).await?;
+//! sim.bluetooth.disconnect_now(connection.id).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Pairing
+//!
+//! Pairing requests, started by the other device or by [`Bluetooth::pair`], are published as
+//! [`crate::events::Event::BluetoothPairingRequest`] and answered with [`Bluetooth::accept_pairing`] or
+//! [`Bluetooth::reject_pairing`], so the application can approve the new devices.
+//!
+//! ```no_run
+//! # use rpi_sim868::{events::Event, SIM868};
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), rpi_sim868::Error> {
+//! # let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//! let mut events = sim.events();
+//! while let Ok(event) = events.recv().await {
+//!     match event {
+//!         Event::BluetoothPairingRequest { name, .. } if name == "Pixel 7" => {
+//!             sim.bluetooth.accept_pairing_now(None).await?
+//!         }
+//!         Event::BluetoothPairingRequest { .. } => sim.bluetooth.reject_pairing_now().await?,
+//!         _ => (),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The modem reports the received data as soon as it arrives. [`Bluetooth::receive`] returns the data reported
+//! while it's waiting, the data arriving during the other tasks is only published as
+//! [`crate::events::Event::BluetoothData`].
+
+#[cfg(feature = "blocking")]
+use crate::blocking::block_on_task;
+use crate::{
+    ack_check,
+    command::AtCommand,
+    error::{Error, ErrorKind},
+    error_check, generic_resolver, join_task, nth_field, parse_field,
+    profile::Feature,
+    serial_port::{spawn_task, SerialPort, TaskPriority},
+    Module, ResolverReturn, TaskJoinHandle, BLUETOOTH_CONNECT_REGEX, BLUETOOTH_HOST_REGEX,
+    BLUETOOTH_PAIRED_REGEX, BLUETOOTH_PROFILE_REGEX, BLUETOOTH_SCAN_REGEX,
+    BLUETOOTH_SPP_DATA_REGEX, BLUETOOTH_STATUS_REGEX, BLUETOOTH_VISIBILITY_REGEX,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+/// Scan duration accepted by `AT+BTSCAN`.
+const MIN_SCAN_TIME: Duration = Duration::from_secs(10);
+const MAX_SCAN_TIME: Duration = Duration::from_secs(60);
+/// Time the scan results may arrive after the scan duration.
+const SCAN_MARGIN: Duration = Duration::from_secs(5);
+/// Time the remote device has to accept the connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(20);
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+/// Most bytes `AT+BTSPPSEND` takes at once.
+const SEND_LIMIT: usize = 1024;
+
+/// Device found by [`Bluetooth::scan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BluetoothDevice {
+    /// Id assigned by the modem, used to pair or connect with the device.
+    pub id: u8,
+    pub name: String,
+    /// MAC address, eg. `78:dd:08:4d:94:a4`.
+    pub address: String,
+    /// dBm
+    pub rssi: i16,
+}
+
+/// Local name and address returned by [`Bluetooth::host`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BluetoothHost {
+    pub name: String,
+    pub address: String,
+}
+
+/// SPP connection established by [`Bluetooth::connect`] or [`Bluetooth::accept`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BluetoothConnection {
+    /// Id assigned by the modem, used to disconnect.
+    pub id: u8,
+    pub name: String,
+    pub address: String,
+}
+
+/// Device returned by [`Bluetooth::paired_devices`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BluetoothPairedDevice {
+    /// Id assigned by the modem, used to unpair.
+    pub id: u8,
+    pub name: String,
+    pub address: String,
+}
+
+/// Devices found in a single read of the scan, and whether the scan has finished.
+struct ScanBurst {
+    devices: Vec<BluetoothDevice>,
+    finished: bool,
+}
+
+fn scan_resolver(result: String) -> ResolverReturn<ScanBurst> {
+    let mut burst: ScanBurst = ScanBurst {
+        devices: Vec::new(),
+        finished: false,
+    };
+    for captured in BLUETOOTH_SCAN_REGEX.captures_iter(&result) {
+        let Some(data) = captured.name("data") else {
+            burst.finished = true;
+            continue;
+        };
+        let fields: &Vec<&str> = &data.as_str().split(',').collect();
+        let rssi: &str = nth_field(fields, 3, "bluetooth_rssi")?;
+        burst.devices.push(BluetoothDevice {
+            id: parse_field("bluetooth_id", nth_field(fields, 0, "bluetooth_id")?)?,
+            name: nth_field(fields, 1, "bluetooth_name")?
+                .trim_matches('"')
+                .to_string(),
+            address: nth_field(fields, 2, "bluetooth_address")?.to_string(),
+            rssi: parse_field("bluetooth_rssi", rssi.trim())?,
+        });
+    }
+
+    match burst.finished || !burst.devices.is_empty() || ack_check(&result) {
+        true => Ok(burst),
+        false => Err(Error::NotResolved),
+    }
+}
+
+fn scan(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    duration: Duration,
+) -> ResolverReturn<Vec<BluetoothDevice>> {
+    serial_port.require(Feature::BLUETOOTH)?;
+    let duration: Duration = duration.clamp(MIN_SCAN_TIME, MAX_SCAN_TIME);
+    let deadline: Instant = Instant::now() + duration + SCAN_MARGIN;
+
+    let mut burst: ScanBurst = serial_port.process(
+        task_id,
+        format!("AT+BTSCAN=1,{}\n", duration.as_secs()),
+        scan_resolver,
+        None,
+    )?;
+    let mut devices: Vec<BluetoothDevice> = Vec::new();
+    loop {
+        devices.append(&mut burst.devices);
+        let remaining: Duration = deadline.saturating_duration_since(Instant::now());
+        if burst.finished || remaining.is_zero() {
+            return Ok(devices);
+        }
+        burst = match serial_port.read(task_id, scan_resolver, Some(remaining)) {
+            Ok(burst) => burst,
+            // the modem hasn't reported the end of the scan
+            Err(e) if e.kind() == ErrorKind::NotResolved => return Ok(devices),
+            Err(e) => return Err(e),
+        };
+    }
+}
+
+fn is_on(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<bool> {
+    fn resolver(result: String) -> ResolverReturn<bool> {
+        match BLUETOOTH_STATUS_REGEX.captures(&result) {
+            Some(captured) => {
+                let status: u8 = parse_field("bluetooth_status", &captured["status"])?;
+                Ok(status != 0)
+            }
+            None => Err(Error::NotResolved),
+        }
+    }
+
+    serial_port.require(Feature::BLUETOOTH)?;
+    serial_port.process(task_id, "AT+BTSTATUS?\n".to_string(), resolver, None)
+}
+
+fn paired_devices(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<Vec<BluetoothPairedDevice>> {
+    fn resolver(result: String) -> ResolverReturn<Vec<BluetoothPairedDevice>> {
+        generic_resolver(&result, Error::BluetoothProblem)?;
+        BLUETOOTH_PAIRED_REGEX
+            .captures_iter(&result)
+            .map(|captured: regex::Captures<'_>| {
+                Ok(BluetoothPairedDevice {
+                    id: parse_field("bluetooth_paired_id", &captured["id"])?,
+                    name: captured["name"].to_string(),
+                    address: captured["address"].to_string(),
+                })
+            })
+            .collect()
+    }
+
+    serial_port.require(Feature::BLUETOOTH)?;
+    serial_port.process(task_id, "AT+BTSTATUS?\n".to_string(), resolver, None)
+}
+
+fn command(serial_port: &Arc<SerialPort>, task_id: &Uuid, command: String) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::BluetoothProblem)
+    }
+
+    serial_port.require(Feature::BLUETOOTH)?;
+    serial_port.process(task_id, command, resolver, None)
+}
+
+fn host(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<BluetoothHost> {
+    fn resolver(result: String) -> ResolverReturn<BluetoothHost> {
+        match BLUETOOTH_HOST_REGEX.captures(&result) {
+            Some(captured) => Ok(BluetoothHost {
+                name: captured["name"].trim_matches('"').to_string(),
+                address: captured["address"].to_string(),
+            }),
+            None => Err(Error::NotResolved),
+        }
+    }
+
+    serial_port.require(Feature::BLUETOOTH)?;
+    serial_port.process(task_id, "AT+BTHOST?\n".to_string(), resolver, None)
+}
+
+fn is_visible(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<bool> {
+    fn resolver(result: String) -> ResolverReturn<bool> {
+        match BLUETOOTH_VISIBILITY_REGEX.captures(&result) {
+            Some(captured) => Ok(&captured["visibility"] == "1"),
+            None => Err(Error::NotResolved),
+        }
+    }
+
+    serial_port.require(Feature::BLUETOOTH)?;
+    serial_port.process(task_id, "AT+BTVIS?\n".to_string(), resolver, None)
+}
+
+fn connection_resolver(result: String) -> ResolverReturn<BluetoothConnection> {
+    if error_check(&result) {
+        return Err(Error::BluetoothProblem);
+    }
+    match BLUETOOTH_CONNECT_REGEX.captures(&result) {
+        Some(captured) => Ok(BluetoothConnection {
+            id: parse_field("bluetooth_connection_id", &captured["id"])?,
+            name: captured["name"].to_string(),
+            address: captured["address"].to_string(),
+        }),
+        None => Err(Error::NotResolved),
+    }
+}
+
+fn connect(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    device_id: u8,
+) -> ResolverReturn<BluetoothConnection> {
+    fn profile_resolver(result: String) -> ResolverReturn<Option<u8>> {
+        generic_resolver(&result, Error::BluetoothProblem)?;
+        BLUETOOTH_PROFILE_REGEX
+            .captures_iter(&result)
+            .find(|captured: &regex::Captures<'_>| &captured["name"] == "SPP")
+            .map(|captured: regex::Captures<'_>| parse_field("bluetooth_profile", &captured["id"]))
+            .transpose()
+    }
+
+    serial_port.require(Feature::BLUETOOTH)?;
+    let profile: u8 = serial_port
+        .process(
+            task_id,
+            format!("AT+BTGETPROF={device_id}\n"),
+            profile_resolver,
+            None,
+        )?
+        // the device doesn't offer the Serial Port Profile
+        .ok_or(Error::BluetoothProblem)?;
+    serial_port.process(
+        task_id,
+        format!("AT+BTCONNECT={device_id},{profile}\n"),
+        connection_resolver,
+        Some(CONNECT_TIMEOUT),
+    )
+}
+
+fn accept(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<BluetoothConnection> {
+    serial_port.require(Feature::BLUETOOTH)?;
+    serial_port.process(
+        task_id,
+        "AT+BTACPT=1\n".to_string(),
+        connection_resolver,
+        Some(CONNECT_TIMEOUT),
+    )
+}
+
+/// Splits the `data` into the chunks of up to [`SEND_LIMIT`] bytes, at the character boundaries.
+fn chunks(data: &str) -> Vec<&str> {
+    let mut chunks: Vec<&str> = Vec::new();
+    let mut rest: &str = data;
+    while !rest.is_empty() {
+        let mut end: usize = SEND_LIMIT.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
+
+fn send(serial_port: &Arc<SerialPort>, task_id: &Uuid, data: String) -> ResolverReturn<()> {
+    fn prompt_resolver(result: String) -> ResolverReturn<()> {
+        if error_check(&result) {
+            return Err(Error::BluetoothProblem);
+        }
+        match result.trim_end().ends_with('>') {
+            true => Ok(()),
+            false => Err(Error::NotResolved),
+        }
+    }
+
+    fn resolver(result: String) -> ResolverReturn<()> {
+        if error_check(&result) || result.contains("SEND FAIL") {
+            return Err(Error::BluetoothProblem);
+        }
+        match result.contains("SEND OK") {
+            true => Ok(()),
+            false => Err(Error::NotResolved),
+        }
+    }
+
+    serial_port.require(Feature::BLUETOOTH)?;
+    // the data is written once the modem prompts for it, otherwise it's taken for the commands
+    for chunk in chunks(&data) {
+        serial_port.process(
+            task_id,
+            AtCommand::set("+BTSPPSEND").number(chunk.len()).into(),
+            prompt_resolver,
+            Some(SEND_TIMEOUT),
+        )?;
+        serial_port.write(task_id, chunk)?;
+        serial_port.read(task_id, resolver, Some(SEND_TIMEOUT))?;
+    }
+    Ok(())
+}
+
+fn receive(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    timeout: Duration,
+) -> ResolverReturn<String> {
+    fn resolver(result: String) -> ResolverReturn<String> {
+        let data: String = BLUETOOTH_SPP_DATA_REGEX
+            .captures_iter(&result)
+            .map(|captured: regex::Captures<'_>| captured["data"].to_string())
+            .collect();
+        match data.is_empty() {
+            true => Err(Error::NotResolved),
+            false => Ok(data),
+        }
+    }
+
+    serial_port.require(Feature::BLUETOOTH)?;
+    serial_port.read(task_id, resolver, Some(timeout))
+}
+
+/// Bluetooth Module
+///
+/// The handle doesn't keep the connection open, see [`crate::SIM868`].
+#[derive(Clone)]
+pub struct Bluetooth {
+    serial_port: Arc<SerialPort>,
+}
+
+impl Module for Bluetooth {
+    fn new(serial_port: Arc<SerialPort>) -> Self {
+        Bluetooth { serial_port }
+    }
+}
+
+impl Bluetooth {
+    /// Checks if the Bluetooth is powered on.
+    pub fn is_on(&self) -> TaskJoinHandle<bool> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            is_on,
+            Some("Checking Bluetooth status...".to_string()),
+            (),
+        )
+    }
+
+    pub fn turn_on(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            command,
+            Some("Turning Bluetooth on...".to_string()),
+            "AT+BTPOWER=1\n".to_string(),
+        )
+    }
+
+    pub fn turn_off(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            command,
+            Some("Turning Bluetooth off...".to_string()),
+            "AT+BTPOWER=0\n".to_string(),
+        )
+    }
+
+    /// Scans for the nearby devices for `duration` (10-60 s, as supported by the modem).
+    pub fn scan(&self, duration: Duration) -> TaskJoinHandle<Vec<BluetoothDevice>> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            scan,
+            Some(format!("Scanning Bluetooth devices for {duration:?}...")),
+            duration,
+        )
+    }
+
+    /// Local name and address of the modem.
+    pub fn host(&self) -> TaskJoinHandle<BluetoothHost> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            host,
+            Some("Reading Bluetooth host...".to_string()),
+            (),
+        )
+    }
+
+    /// Sets the local name the modem is visible under, sent as a quoted (escaped) string.
+    pub fn set_name(&self, name: &str) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            command,
+            Some(format!("Setting Bluetooth name to {name}...")),
+            AtCommand::set("+BTHOST").string(name).into(),
+        )
+    }
+
+    /// Checks if the modem is visible to the other devices.
+    pub fn is_visible(&self) -> TaskJoinHandle<bool> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            is_visible,
+            Some("Checking Bluetooth visibility...".to_string()),
+            (),
+        )
+    }
+
+    pub fn set_visible(&self, visible: bool) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            command,
+            Some(format!("Setting Bluetooth visibility to {visible}...")),
+            format!("AT+BTVIS={}\n", visible as u8),
+        )
+    }
+
+    /// Opens the SPP connection with the device `device_id` returned by [`Bluetooth::scan`].
+    pub fn connect(&self, device_id: u8) -> TaskJoinHandle<BluetoothConnection> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            connect,
+            Some(format!("Connecting Bluetooth device {device_id}...")),
+            device_id,
+        )
+    }
+
+    /// Accepts the incoming connection, see [`crate::events::Event::BluetoothConnectionRequest`].
+    pub fn accept(&self) -> TaskJoinHandle<BluetoothConnection> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::HIGH,
+            accept,
+            Some("Accepting Bluetooth connection...".to_string()),
+            (),
+        )
+    }
+
+    /// Rejects the incoming connection.
+    pub fn reject(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::HIGH,
+            command,
+            Some("Rejecting Bluetooth connection...".to_string()),
+            "AT+BTACPT=0\n".to_string(),
+        )
+    }
+
+    pub fn disconnect(&self, connection_id: u8) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            command,
+            Some(format!(
+                "Disconnecting Bluetooth connection {connection_id}..."
+            )),
+            format!("AT+BTDISCONN={connection_id}\n"),
+        )
+    }
+
+    /// Sends `data` over the SPP connection, in the chunks of up to 1024 bytes (the most `AT+BTSPPSEND`
+    /// takes). The chunks sent before a failed one have been delivered.
+    pub fn send(&self, data: &str) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            send,
+            Some(format!("Sending {} bytes of Bluetooth data...", data.len())),
+            data.to_string(),
+        )
+    }
+
+    /// Starts pairing with the device `device_id` returned by [`Bluetooth::scan`]. The modem continues with
+    /// [`crate::events::Event::BluetoothPairingRequest`] and reports the result with
+    /// [`crate::events::Event::BluetoothPaired`].
+    pub fn pair(&self, device_id: u8) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            command,
+            Some(format!("Pairing Bluetooth device {device_id}...")),
+            format!("AT+BTPAIR=0,{device_id}\n"),
+        )
+    }
+
+    /// Accepts the pairing request, confirming the passkey or, if the request has none, with the `pin`. Only
+    /// the letters and digits of the `pin` are sent.
+    pub fn accept_pairing(&self, pin: Option<&str>) -> TaskJoinHandle<()> {
+        let input: String = match pin {
+            Some(pin) => AtCommand::set("+BTPAIR").number(2).token(pin).into(),
+            None => "AT+BTPAIR=1,1\n".to_string(),
+        };
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::HIGH,
+            command,
+            Some("Accepting Bluetooth pairing...".to_string()),
+            input,
+        )
+    }
+
+    pub fn reject_pairing(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::HIGH,
+            command,
+            Some("Rejecting Bluetooth pairing...".to_string()),
+            "AT+BTPAIR=1,0\n".to_string(),
+        )
+    }
+
+    /// Devices paired (bonded) with the modem.
+    pub fn paired_devices(&self) -> TaskJoinHandle<Vec<BluetoothPairedDevice>> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            paired_devices,
+            Some("Reading paired Bluetooth devices...".to_string()),
+            (),
+        )
+    }
+
+    /// Removes the pairing with the device `id` returned by [`Bluetooth::paired_devices`], 0 unpairs all.
+    pub fn unpair(&self, id: u8) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            command,
+            Some(format!("Unpairing Bluetooth device {id}...")),
+            format!("AT+BTUNPAIR={id}\n"),
+        )
+    }
+
+    /// Waits up to `timeout` for the data received over the SPP connection.
+    pub fn receive(&self, timeout: Duration) -> TaskJoinHandle<String> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            receive,
+            Some("Receiving Bluetooth data...".to_string()),
+            timeout,
+        )
+    }
+}
+
+impl Bluetooth {
+    /// [`Bluetooth::is_on`] returning the result directly.
+    pub async fn is_on_now(&self) -> ResolverReturn<bool> {
+        join_task(self.is_on()).await
+    }
+
+    /// [`Bluetooth::turn_on`] returning the result directly.
+    pub async fn turn_on_now(&self) -> ResolverReturn<()> {
+        join_task(self.turn_on()).await
+    }
+
+    /// [`Bluetooth::turn_off`] returning the result directly.
+    pub async fn turn_off_now(&self) -> ResolverReturn<()> {
+        join_task(self.turn_off()).await
+    }
+
+    /// [`Bluetooth::scan`] returning the result directly.
+    pub async fn scan_now(&self, duration: Duration) -> ResolverReturn<Vec<BluetoothDevice>> {
+        join_task(self.scan(duration)).await
+    }
+
+    /// [`Bluetooth::host`] returning the result directly.
+    pub async fn host_now(&self) -> ResolverReturn<BluetoothHost> {
+        join_task(self.host()).await
+    }
+
+    /// [`Bluetooth::set_name`] returning the result directly.
+    pub async fn set_name_now(&self, name: &str) -> ResolverReturn<()> {
+        join_task(self.set_name(name)).await
+    }
+
+    /// [`Bluetooth::is_visible`] returning the result directly.
+    pub async fn is_visible_now(&self) -> ResolverReturn<bool> {
+        join_task(self.is_visible()).await
+    }
+
+    /// [`Bluetooth::set_visible`] returning the result directly.
+    pub async fn set_visible_now(&self, visible: bool) -> ResolverReturn<()> {
+        join_task(self.set_visible(visible)).await
+    }
+
+    /// [`Bluetooth::connect`] returning the result directly.
+    pub async fn connect_now(&self, device_id: u8) -> ResolverReturn<BluetoothConnection> {
+        join_task(self.connect(device_id)).await
+    }
+
+    /// [`Bluetooth::accept`] returning the result directly.
+    pub async fn accept_now(&self) -> ResolverReturn<BluetoothConnection> {
+        join_task(self.accept()).await
+    }
+
+    /// [`Bluetooth::reject`] returning the result directly.
+    pub async fn reject_now(&self) -> ResolverReturn<()> {
+        join_task(self.reject()).await
+    }
+
+    /// [`Bluetooth::disconnect`] returning the result directly.
+    pub async fn disconnect_now(&self, connection_id: u8) -> ResolverReturn<()> {
+        join_task(self.disconnect(connection_id)).await
+    }
+
+    /// [`Bluetooth::send`] returning the result directly.
+    pub async fn send_now(&self, data: &str) -> ResolverReturn<()> {
+        join_task(self.send(data)).await
+    }
+
+    /// [`Bluetooth::pair`] returning the result directly.
+    pub async fn pair_now(&self, device_id: u8) -> ResolverReturn<()> {
+        join_task(self.pair(device_id)).await
+    }
+
+    /// [`Bluetooth::accept_pairing`] returning the result directly.
+    pub async fn accept_pairing_now(&self, pin: Option<&str>) -> ResolverReturn<()> {
+        join_task(self.accept_pairing(pin)).await
+    }
+
+    /// [`Bluetooth::reject_pairing`] returning the result directly.
+    pub async fn reject_pairing_now(&self) -> ResolverReturn<()> {
+        join_task(self.reject_pairing()).await
+    }
+
+    /// [`Bluetooth::paired_devices`] returning the result directly.
+    pub async fn paired_devices_now(&self) -> ResolverReturn<Vec<BluetoothPairedDevice>> {
+        join_task(self.paired_devices()).await
+    }
+
+    /// [`Bluetooth::unpair`] returning the result directly.
+    pub async fn unpair_now(&self, id: u8) -> ResolverReturn<()> {
+        join_task(self.unpair(id)).await
+    }
+
+    /// [`Bluetooth::receive`] returning the result directly.
+    pub async fn receive_now(&self, timeout: Duration) -> ResolverReturn<String> {
+        join_task(self.receive(timeout)).await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Bluetooth {
+    /// Blocking version of [`Bluetooth::is_on`].
+    pub fn is_on_blocking(&self) -> ResolverReturn<bool> {
+        block_on_task(|| self.is_on())
+    }
+
+    /// Blocking version of [`Bluetooth::turn_on`].
+    pub fn turn_on_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.turn_on())
+    }
+
+    /// Blocking version of [`Bluetooth::turn_off`].
+    pub fn turn_off_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.turn_off())
+    }
+
+    /// Blocking version of [`Bluetooth::scan`].
+    pub fn scan_blocking(&self, duration: Duration) -> ResolverReturn<Vec<BluetoothDevice>> {
+        block_on_task(|| self.scan(duration))
+    }
+
+    /// Blocking version of [`Bluetooth::host`].
+    pub fn host_blocking(&self) -> ResolverReturn<BluetoothHost> {
+        block_on_task(|| self.host())
+    }
+
+    /// Blocking version of [`Bluetooth::set_name`].
+    pub fn set_name_blocking(&self, name: &str) -> ResolverReturn<()> {
+        block_on_task(|| self.set_name(name))
+    }
+
+    /// Blocking version of [`Bluetooth::is_visible`].
+    pub fn is_visible_blocking(&self) -> ResolverReturn<bool> {
+        block_on_task(|| self.is_visible())
+    }
+
+    /// Blocking version of [`Bluetooth::set_visible`].
+    pub fn set_visible_blocking(&self, visible: bool) -> ResolverReturn<()> {
+        block_on_task(|| self.set_visible(visible))
+    }
+
+    /// Blocking version of [`Bluetooth::connect`].
+    pub fn connect_blocking(&self, device_id: u8) -> ResolverReturn<BluetoothConnection> {
+        block_on_task(|| self.connect(device_id))
+    }
+
+    /// Blocking version of [`Bluetooth::accept`].
+    pub fn accept_blocking(&self) -> ResolverReturn<BluetoothConnection> {
+        block_on_task(|| self.accept())
+    }
+
+    /// Blocking version of [`Bluetooth::reject`].
+    pub fn reject_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.reject())
+    }
+
+    /// Blocking version of [`Bluetooth::disconnect`].
+    pub fn disconnect_blocking(&self, connection_id: u8) -> ResolverReturn<()> {
+        block_on_task(|| self.disconnect(connection_id))
+    }
+
+    /// Blocking version of [`Bluetooth::send`].
+    pub fn send_blocking(&self, data: &str) -> ResolverReturn<()> {
+        block_on_task(|| self.send(data))
+    }
+
+    /// Blocking version of [`Bluetooth::pair`].
+    pub fn pair_blocking(&self, device_id: u8) -> ResolverReturn<()> {
+        block_on_task(|| self.pair(device_id))
+    }
+
+    /// Blocking version of [`Bluetooth::accept_pairing`].
+    pub fn accept_pairing_blocking(&self, pin: Option<&str>) -> ResolverReturn<()> {
+        block_on_task(|| self.accept_pairing(pin))
+    }
+
+    /// Blocking version of [`Bluetooth::reject_pairing`].
+    pub fn reject_pairing_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.reject_pairing())
+    }
+
+    /// Blocking version of [`Bluetooth::paired_devices`].
+    pub fn paired_devices_blocking(&self) -> ResolverReturn<Vec<BluetoothPairedDevice>> {
+        block_on_task(|| self.paired_devices())
+    }
+
+    /// Blocking version of [`Bluetooth::unpair`].
+    pub fn unpair_blocking(&self, id: u8) -> ResolverReturn<()> {
+        block_on_task(|| self.unpair(id))
+    }
+
+    /// Blocking version of [`Bluetooth::receive`].
+    pub fn receive_blocking(&self, timeout: Duration) -> ResolverReturn<String> {
+        block_on_task(|| self.receive(timeout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        testing::{MockResponse, MockTransport},
+        LogLevelFilter, SIM868,
+    };
+
+    fn connect() -> (SIM868, MockTransport) {
+        let mock: MockTransport = MockTransport::new();
+        mock.set_default(MockResponse::ok());
+        (
+            SIM868::with_transport(mock.clone(), LogLevelFilter::Off),
+            mock,
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn escapes_name() {
+        let (sim, mock) = connect();
+        sim.bluetooth.set_name("Tracker 1").await.unwrap().unwrap();
+        sim.bluetooth
+            .set_name("x\r\nAT+CPOWD=1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            mock.written(),
+            [
+                r#"AT+BTHOST="Tracker 1""#,
+                r#"AT+BTHOST="x\0D\0AAT+CPOWD=1""#
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn filters_pairing_pin() {
+        let (sim, mock) = connect();
+        sim.bluetooth
+            .accept_pairing(Some("0000"))
+            .await
+            .unwrap()
+            .unwrap();
+        sim.bluetooth
+            .accept_pairing(Some("1234\r\nAT+CPOWD=1"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            mock.written(),
+            ["AT+BTPAIR=2,0000", "AT+BTPAIR=2,1234ATCPOWD1"]
+        );
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn sends_data_in_chunks_after_prompt() {
+        use crate::simulator::{Simulator, SimulatorConfig};
+
+        let simulator: Simulator = Simulator::new(SimulatorConfig::default());
+        let sim: SIM868 = SIM868::with_transport(simulator.clone(), LogLevelFilter::Off);
+        sim.bluetooth.turn_on_now().await.unwrap();
+        sim.bluetooth.connect_now(1).await.unwrap();
+
+        let data: String = format!("{}ż{}", "a".repeat(1023), "b".repeat(1000));
+        sim.bluetooth.send_now(&data).await.unwrap();
+        sim.bluetooth.send_now("AT+CPOWD=1\r\n").await.unwrap();
+        assert_eq!(
+            simulator.sent_spp_data(),
+            [
+                "a".repeat(1023),
+                format!("ż{}", "b".repeat(1000)),
+                "AT+CPOWD=1\r\n".to_string()
+            ]
+        );
+        assert!(simulator.is_powered());
+    }
+}
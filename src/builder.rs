@@ -0,0 +1,236 @@
+#[cfg(feature = "gnss")]
+use crate::Module;
+use crate::{
+    error::Error, hat::PowerPin, profile::ModemProfile, retry::RetryPolicy,
+    serial_port::DEFAULT_PRIORITY_AGING, timeouts::CommandTimeouts, transport::Transport,
+    LogColors, LogLevelFilter, SIM868,
+};
+use std::{sync::Arc, time::Duration};
+
+/// Builder of [`SIM868`], returned by [`SIM868::builder`].
+///
+/// ```no_run
+/// use rpi_sim868::{profile::ModemProfile, LogLevelFilter, SIM868};
+///
+/// # #[cfg(not(feature = "rpi"))]
+/// # fn main() {}
+/// # #[cfg(feature = "rpi")]
+/// # fn main() -> Result<(), rpi_sim868::Error> {
+/// let sim: SIM868 = SIM868::builder()
+///     .uart("/dev/ttyAMA0", 9600)
+///     .profile(ModemProfile::SIM808)
+///     .log_level(LogLevelFilter::Info)
+///     .init_command("AT+CLIP=1")
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SIM868Builder {
+    #[cfg(feature = "rpi")]
+    uart_config: crate::transport::UartConfig,
+    transport: Option<Box<dyn Transport>>,
+    log_level: LogLevelFilter,
+    log_colors: LogColors,
+    profile: ModemProfile,
+    timeouts: CommandTimeouts,
+    power_pin: Option<Box<dyn PowerPin>>,
+    power_pin_disabled: bool,
+    power_pulse: Option<Duration>,
+    boot_timeout: Option<Duration>,
+    init_commands: Vec<String>,
+    priority_aging: Option<Duration>,
+    retry_policy: RetryPolicy,
+    #[cfg(feature = "gnss")]
+    gnss_transport: Option<Box<dyn Transport>>,
+    #[cfg(feature = "gprs")]
+    max_response_size: Option<usize>,
+}
+
+impl Default for SIM868Builder {
+    fn default() -> Self {
+        SIM868Builder {
+            #[cfg(feature = "rpi")]
+            uart_config: crate::transport::UartConfig::default(),
+            transport: None,
+            log_level: LogLevelFilter::Off,
+            log_colors: LogColors::default(),
+            profile: ModemProfile::default(),
+            timeouts: CommandTimeouts::default(),
+            power_pin: None,
+            power_pin_disabled: false,
+            power_pulse: None,
+            boot_timeout: None,
+            init_commands: Vec::new(),
+            priority_aging: Some(DEFAULT_PRIORITY_AGING),
+            retry_policy: RetryPolicy::default(),
+            #[cfg(feature = "gnss")]
+            gnss_transport: None,
+            #[cfg(feature = "gprs")]
+            max_response_size: Some(crate::gprs::DEFAULT_MAX_RESPONSE_SIZE),
+        }
+    }
+}
+
+impl SIM868Builder {
+    /// Raspberry Pi UART to connect through, `/dev/ttyS0` at 115200 bauds by default.
+    #[cfg(feature = "rpi")]
+    pub fn uart(mut self, path: &str, baud_rate: u32) -> Self {
+        self.uart_config.path = path.to_string();
+        self.uart_config.baud_rate = baud_rate;
+        self
+    }
+
+    /// Raspberry Pi UART with the parity, stop bits, flow control and read timing other than the defaults of
+    /// [`crate::transport::UartConfig`], eg. for a carrier board other than the Waveshare HAT.
+    #[cfg(feature = "rpi")]
+    pub fn uart_config(mut self, config: crate::transport::UartConfig) -> Self {
+        self.uart_config = config;
+        self
+    }
+
+    /// Connects through the `transport` instead of the UART.
+    pub fn transport<T>(mut self, transport: T) -> Self
+    where
+        T: Transport + 'static,
+    {
+        self.transport = Some(Box::new(transport));
+        self
+    }
+
+    /// Level of the crate's logger, [`LogLevelFilter::Off`] by default.
+    pub fn log_level(mut self, log_level: LogLevelFilter) -> Self {
+        self.log_level = log_level;
+        self
+    }
+
+    /// Coloring of the log messages, [`LogColors::Auto`] by default. Use [`LogColors::Never`] when the logs
+    /// are collected by journald or syslog.
+    pub fn log_colors(mut self, log_colors: LogColors) -> Self {
+        self.log_colors = log_colors;
+        self
+    }
+
+    pub fn profile(mut self, profile: ModemProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Response timeouts of the AT commands, see [`CommandTimeouts`] for the defaults.
+    pub fn timeouts(mut self, timeouts: CommandTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Pin toggling the power instead of the GPIO 4, see [`crate::hat::Hat::set_power_pin`].
+    pub fn power_pin<P>(mut self, pin: P) -> Self
+    where
+        P: PowerPin + 'static,
+    {
+        self.power_pin = Some(Box::new(pin));
+        self
+    }
+
+    /// No power pin (and no GPIO) for a modem powered on its own, see [`crate::hat::Hat::disable_power_pin`].
+    pub fn without_power_pin(mut self) -> Self {
+        self.power_pin_disabled = true;
+        self
+    }
+
+    /// Raspberry Pi GPIO (BCM numbering) connected to the PWRKEY, instead of the GPIO 4 of the Waveshare HAT.
+    #[cfg(feature = "rpi")]
+    pub fn power_gpio(self, number: u8) -> Self {
+        self.power_pin(crate::hat::GpioPowerPin::new(number))
+    }
+
+    /// Time the PWRKEY is held low, see [`crate::hat::Hat::set_power_pulse`].
+    pub fn power_pulse(mut self, pulse: Duration) -> Self {
+        self.power_pulse = Some(pulse);
+        self
+    }
+
+    /// Time the modem has to boot, see [`crate::hat::Hat::set_boot_timeout`].
+    pub fn boot_timeout(mut self, timeout: Duration) -> Self {
+        self.boot_timeout = Some(timeout);
+        self
+    }
+
+    /// Appends the `command` (without the trailing newline) to the sequence run by [`crate::hat::Hat::init`].
+    pub fn init_command(mut self, command: &str) -> Self {
+        self.init_commands.push(command.to_string());
+        self
+    }
+
+    /// Time the task waits in the queue before its priority is raised by one level (eg. from `NORMAL` to
+    /// `HIGH`, ahead of the tasks queued later), so a flood of the high priority tasks (eg. the call handling)
+    /// can't starve the rest. 10 s by default, `None` disables it.
+    pub fn priority_aging(mut self, aging: Option<Duration>) -> Self {
+        self.priority_aging = aging;
+        self
+    }
+
+    /// Runs the GNSS module over the separate `transport` with its own task queue, eg. a channel of the
+    /// [multiplexer](crate::cmux::Multiplexer), so its tasks don't wait behind the rest.
+    #[cfg(feature = "gnss")]
+    pub fn gnss_transport<T>(mut self, transport: T) -> Self
+    where
+        T: Transport + 'static,
+    {
+        self.gnss_transport = Some(Box::new(transport));
+        self
+    }
+
+    /// Policy of repeating the failed AT commands, see [`RetryPolicy`]. The commands aren't repeated by default.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Longest HTTP response read, see [`crate::gprs::GPRS::set_max_response_size`].
+    #[cfg(feature = "gprs")]
+    pub fn max_response_size(mut self, bytes: Option<usize>) -> Self {
+        self.max_response_size = bytes;
+        self
+    }
+
+    /// Opens the connection, failing if the UART can't be opened - or with [`Error::TransportNotSet`] if there
+    /// is no [`SIM868Builder::transport`] in the build without the `rpi` feature.
+    pub fn build(self) -> Result<SIM868, Error> {
+        let transport: Box<dyn Transport> = match self.transport {
+            Some(transport) => transport,
+            #[cfg(feature = "rpi")]
+            None => Box::new(crate::transport::open_uart_with(&self.uart_config)?),
+            #[cfg(not(feature = "rpi"))]
+            None => return Err(Error::TransportNotSet),
+        };
+
+        let mut sim: SIM868 = SIM868::from_transport(
+            transport,
+            self.profile,
+            self.timeouts,
+            self.log_level,
+            self.log_colors,
+        );
+        if let Some(power_pin) = self.power_pin {
+            sim.hat.set_power_pin_boxed(power_pin);
+        }
+        if self.power_pin_disabled {
+            sim.hat.disable_power_pin();
+        }
+        if let Some(pulse) = self.power_pulse {
+            sim.hat.set_power_pulse(pulse);
+        }
+        if let Some(timeout) = self.boot_timeout {
+            sim.hat.set_boot_timeout(timeout);
+        }
+        sim.hat.init_commands = Arc::new(self.init_commands);
+        sim.serial_port.set_priority_aging(self.priority_aging);
+        sim.serial_port.set_retry_policy(self.retry_policy);
+        #[cfg(feature = "gnss")]
+        if let Some(transport) = self.gnss_transport {
+            sim.gnss = crate::gnss::GNSS::new(sim.serial_port.channel(transport));
+        }
+        #[cfg(feature = "gprs")]
+        sim.gprs.set_max_response_size(self.max_response_size);
+        Ok(sim)
+    }
+}
@@ -0,0 +1,142 @@
+//! Capabilities module
+//!
+//! The firmware revisions of the same modem differ in the commands they know - eg. `AT+HTTPSSL` or the
+//! Bluetooth commands are missing in some builds. [`crate::hat::Hat::init`] reads the revision (`AT+CGMR`)
+//! and probes the optional commands with their test form (`AT+<command>=?`); the modules consult the
+//! detected [`Capabilities`] (see [`crate::SIM868::capabilities`]) and fail fast with
+//! [`crate::Error::UnsupportedByFirmware`] instead of the modem's bare `ERROR`.
+
+use crate::{
+    error::{Error, ErrorKind},
+    error_check,
+    serial_port::SerialPort,
+    ResolverReturn, ACK_REGEX, FIRMWARE_REVISION_REGEX, HTTP_ACTION_METHODS_REGEX,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Highest `AT+HTTPACTION` method of the SIM800 series firmware, 2 - HEAD.
+const BASIC_HTTP_METHODS: u8 = 2;
+
+/// GNSS command set of the firmware.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GnssVariant {
+    /// No GNSS commands.
+    #[default]
+    None,
+    /// `AT+CGPS...` of the SIM808 firmware older than R14, not supported by [`crate::gnss::GNSS`].
+    CGPS,
+    /// `AT+CGNS...` used by [`crate::gnss::GNSS`].
+    CGNS,
+}
+
+/// Optional capability of the firmware, see [`Capabilities::supports`].
+#[allow(clippy::upper_case_acronyms, non_camel_case_types)]
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Capability {
+    BLUETOOTH,
+    /// `AT+CGNS...` commands.
+    GNSS,
+    /// HTTPS requests (`AT+HTTPSSL`).
+    HTTPS,
+    /// `AT+HTTPACTION` methods other than GET, POST and HEAD.
+    EXTENDED_HTTP_METHODS,
+}
+
+/// Capabilities of the modem's firmware, detected by [`crate::hat::Hat::init`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Capabilities {
+    /// Firmware revision, eg. `1418B05SIM868M32_BT`.
+    pub revision: String,
+    pub https: bool,
+    pub extended_http_methods: bool,
+    pub bluetooth: bool,
+    pub gnss: GnssVariant,
+}
+
+impl Capabilities {
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::BLUETOOTH => self.bluetooth,
+            Capability::GNSS => self.gnss == GnssVariant::CGNS,
+            Capability::HTTPS => self.https,
+            Capability::EXTENDED_HTTP_METHODS => self.extended_http_methods,
+        }
+    }
+}
+
+/// Sends the test `command`, returning the response if the firmware knows the command.
+fn probe(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    command: &str,
+) -> ResolverReturn<Option<String>> {
+    fn resolver(result: String) -> ResolverReturn<Option<String>> {
+        if error_check(&result) {
+            return Ok(None);
+        }
+        match ACK_REGEX.is_match(&result) {
+            true => Ok(Some(result)),
+            false => Err(Error::NotResolved),
+        }
+    }
+
+    match serial_port.process(
+        task_id,
+        format!("AT{command}=?\n"),
+        resolver,
+        Some(serial_port.timeouts.query),
+    ) {
+        Err(e) if e.kind() == ErrorKind::Cme => Ok(None),
+        result => result,
+    }
+}
+
+pub(crate) fn detect(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+) -> ResolverReturn<Capabilities> {
+    fn revision_resolver(result: String) -> ResolverReturn<String> {
+        if error_check(&result) {
+            return Err(Error::HatInitFailed);
+        }
+        match FIRMWARE_REVISION_REGEX.captures(&result) {
+            Some(captured) => Ok(captured["revision"].to_string()),
+            None => Err(Error::NotResolved),
+        }
+    }
+
+    let revision: String = serial_port.process(
+        task_id,
+        "AT+CGMR\n".to_string(),
+        revision_resolver,
+        Some(serial_port.timeouts.query),
+    )?;
+    let http_methods: u8 = probe(serial_port, task_id, "+HTTPACTION")?
+        .and_then(|result: String| {
+            HTTP_ACTION_METHODS_REGEX
+                .captures(&result)
+                .and_then(|captured: regex::Captures<'_>| captured["last"].parse().ok())
+        })
+        .unwrap_or(BASIC_HTTP_METHODS);
+    let gnss: GnssVariant = match probe(serial_port, task_id, "+CGNSPWR")? {
+        Some(_) => GnssVariant::CGNS,
+        None => match probe(serial_port, task_id, "+CGPSPWR")? {
+            Some(_) => GnssVariant::CGPS,
+            None => GnssVariant::None,
+        },
+    };
+
+    Ok(Capabilities {
+        revision,
+        https: probe(serial_port, task_id, "+HTTPSSL")?.is_some(),
+        extended_http_methods: http_methods > BASIC_HTTP_METHODS,
+        bluetooth: probe(serial_port, task_id, "+BTPOWER")?.is_some(),
+        gnss,
+    })
+}
@@ -0,0 +1,330 @@
+//! Clock module
+//!
+//! Available with the `clock` feature.
+//!
+//! Raspberry Pi has no RTC, so a headless tracker without the internet starts with a wildly wrong clock.
+//! [`Clock`] reads the time of the modem's RTC, which follows the network time (NITZ, once enabled with
+//! [`Clock::enable_network_time_sync`]), can be updated from an NTP server over GPRS with [`Clock::ntp_sync`]
+//! or set with [`Clock::set_time`], and sets the system clock from it with [`Clock::sync_system_clock`].
+//!
+//! ```no_run
+//! use rpi_sim868::SIM868;
+//!
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! #[tokio::main]
+//! async fn main() -> Result<(), rpi_sim868::Error> {
+//!     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//!     // the GPRS bearer has to be open, see rpi_sim868::gprs::GPRS::init
+//!     sim.clock.ntp_sync_now("pool.ntp.org").await?;
+//!
+//!     let offset: chrono::Duration = sim.clock.sync_system_clock().await?;
+//!     println!("the system clock was {} s behind", offset.num_seconds());
+//!     Ok(())
+//! }
+//! ```
+
+#[cfg(feature = "blocking")]
+use crate::blocking::{block_on, block_on_task};
+use crate::{
+    command::AtCommand,
+    error::Error,
+    error_check, generic_resolver, join_task, parse_field,
+    serial_port::{spawn_task, SerialPort, TaskPriority},
+    Module, ResolverReturn, TaskJoinHandle, CLOCK_NTP_REGEX, CLOCK_REGEX,
+};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDateTime, Offset, TimeZone, Utc};
+use std::{sync::Arc, time::Duration};
+use uuid::Uuid;
+
+/// The RTC starts from 2004 (SIM868) or 1980 (other SIMCom modems) after powering on.
+const MIN_SYNCHRONISED_YEAR: i32 = 2020;
+const NTP_TIMEOUT: Duration = Duration::from_secs(20);
+/// Offset (in milliseconds) below which the system clock is left as it is.
+const MAX_DRIFT_MS: i64 = 1000;
+
+fn parse_clock(raw: &str) -> ResolverReturn<DateTime<FixedOffset>> {
+    let parse_failure = || Error::ParseFailure {
+        field: "clock",
+        raw: raw.to_string(),
+    };
+
+    // yy/MM/dd,hh:mm:ss±zz, the timezone in quarters of an hour
+    let (datetime, timezone) = raw.get(..17).zip(raw.get(17..)).ok_or_else(parse_failure)?;
+    let quarters: i32 = timezone.parse().map_err(|_| parse_failure())?;
+    let offset: FixedOffset =
+        FixedOffset::east_opt(quarters * 15 * 60).ok_or_else(parse_failure)?;
+    NaiveDateTime::parse_from_str(datetime, "%y/%m/%d,%H:%M:%S")
+        .ok()
+        .and_then(|datetime: NaiveDateTime| offset.from_local_datetime(&datetime).single())
+        .ok_or_else(parse_failure)
+}
+
+/// yy/MM/dd,hh:mm:ss±zz of `AT+CCLK`, in UTC if the offset isn't a whole number of quarters of an hour.
+fn format_clock(datetime: &DateTime<FixedOffset>) -> String {
+    let offset: i32 = datetime.offset().local_minus_utc();
+    let datetime: DateTime<FixedOffset> = match offset % (15 * 60) {
+        0 => *datetime,
+        _ => datetime.with_timezone(&Utc.fix()),
+    };
+    format!(
+        "{}{:+03}",
+        datetime.format("%y/%m/%d,%H:%M:%S"),
+        datetime.offset().local_minus_utc() / (15 * 60)
+    )
+}
+
+fn time(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<DateTime<FixedOffset>> {
+    fn resolver(result: String) -> ResolverReturn<DateTime<FixedOffset>> {
+        let Some(captured) = CLOCK_REGEX.captures(&result) else {
+            return Err(Error::NotResolved);
+        };
+        let datetime: DateTime<FixedOffset> = parse_clock(&captured["datetime"])?;
+        match datetime.year() >= MIN_SYNCHRONISED_YEAR {
+            true => Ok(datetime),
+            false => Err(Error::ClockNotSynchronised),
+        }
+    }
+
+    serial_port.process(task_id, "AT+CCLK?\n".to_string(), resolver, None)
+}
+
+fn set_time(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    datetime: DateTime<FixedOffset>,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::ClockNotSet)
+    }
+
+    serial_port.process(
+        task_id,
+        AtCommand::set("+CCLK")
+            .string(&format_clock(&datetime))
+            .into(),
+        resolver,
+        None,
+    )
+}
+
+fn enable_network_time_sync(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    enabled: bool,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::ClockNotSet)
+    }
+
+    let command: String = AtCommand::set("+CLTS").number(enabled as u8).into();
+    serial_port.process(task_id, command.clone(), resolver, None)?;
+    serial_port.persist_setting("CLTS", command);
+    Ok(())
+}
+
+fn ntp_sync(serial_port: &Arc<SerialPort>, task_id: &Uuid, server: String) -> ResolverReturn<()> {
+    fn ack_resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::ClockNtpFailed(0))
+    }
+
+    fn resolver(result: String) -> ResolverReturn<()> {
+        if error_check(&result) {
+            return Err(Error::ClockNtpFailed(0));
+        }
+        let Some(captured) = CLOCK_NTP_REGEX.captures(&result) else {
+            return Err(Error::NotResolved);
+        };
+        match parse_field("ntp_code", &captured["code"])? {
+            1 => Ok(()),
+            code => Err(Error::ClockNtpFailed(code)),
+        }
+    }
+
+    serial_port.process(task_id, "AT+CNTPCID=1\n".to_string(), ack_resolver, None)?;
+    // the timezone is set to UTC, like the network time
+    serial_port.process(
+        task_id,
+        AtCommand::set("+CNTP").string(&server).number(0).into(),
+        ack_resolver,
+        None,
+    )?;
+    serial_port.process(
+        task_id,
+        "AT+CNTP\n".to_string(),
+        resolver,
+        Some(NTP_TIMEOUT),
+    )
+}
+
+/// Sets the system clock with `clock_settime(CLOCK_REALTIME)`, the errno is logged.
+#[cfg(unix)]
+fn set_system_clock(datetime: DateTime<Utc>) -> ResolverReturn<()> {
+    let time: libc::timespec = libc::timespec {
+        tv_sec: datetime.timestamp() as libc::time_t,
+        tv_nsec: datetime.timestamp_subsec_nanos() as libc::c_long,
+    };
+    // SAFETY: the timespec is valid for the duration of the call, which doesn't keep it
+    match unsafe { libc::clock_settime(libc::CLOCK_REALTIME, &time) } {
+        0 => Ok(()),
+        _ => {
+            log::warn!(
+                "Clock - setting the system clock has failed: {}",
+                std::io::Error::last_os_error()
+            );
+            Err(Error::SystemClockSetFailed)
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn set_system_clock(_: DateTime<Utc>) -> ResolverReturn<()> {
+    Err(Error::SystemClockSetFailed)
+}
+
+/// Clock Module
+///
+/// The handle doesn't keep the connection open, see [`crate::SIM868`].
+#[derive(Clone)]
+pub struct Clock {
+    serial_port: Arc<SerialPort>,
+}
+
+impl Module for Clock {
+    fn new(serial_port: Arc<SerialPort>) -> Self {
+        Clock { serial_port }
+    }
+}
+
+impl Clock {
+    /// Time of the modem's RTC (in the network's timezone). Fails with [`Error::ClockNotSynchronised`] until it
+    /// has been set by the network or NTP.
+    pub fn time(&self) -> TaskJoinHandle<DateTime<FixedOffset>> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            time,
+            Some("Reading the clock...".to_string()),
+            (),
+        )
+    }
+
+    /// Sets the modem's RTC, eg. from the GNSS time. The offset of the `datetime` is kept as the timezone of
+    /// the RTC, unless it isn't a whole number of quarters of an hour - the time is set in UTC then.
+    pub fn set_time(&self, datetime: DateTime<FixedOffset>) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_time,
+            Some(format!("Setting the clock to {datetime}...")),
+            datetime,
+        )
+    }
+
+    /// Enables (`AT+CLTS=1`) or disables the update of the RTC by the network time (NITZ), published as
+    /// [`crate::events::Event::NetworkTimeUpdated`]. The network sends the time on the registration, so the
+    /// RTC follows it from the next one; [`crate::hat::Hat::init`] restores the setting after a power cycle.
+    /// Not every operator sends the time.
+    pub fn enable_network_time_sync(&self, enabled: bool) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            enable_network_time_sync,
+            Some(format!(
+                "{} the network time sync...",
+                if enabled { "Enabling" } else { "Disabling" }
+            )),
+            enabled,
+        )
+    }
+
+    /// Updates the modem's RTC from the NTP `server`, eg. `pool.ntp.org`. Requires the open GPRS bearer.
+    pub fn ntp_sync(&self, server: &str) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            ntp_sync,
+            Some(format!("Synchronising the clock with {server}...")),
+            server.to_string(),
+        )
+    }
+
+    /// Offset of the modem's clock from the system clock, positive if the system clock is behind.
+    pub async fn system_clock_offset(&self) -> ResolverReturn<chrono::Duration> {
+        let modem: DateTime<FixedOffset> = self.time_now().await?;
+        Ok(modem.with_timezone(&Utc) - Utc::now())
+    }
+
+    /// Sets the system clock to the modem's time if it's off by more than a second, returning the offset
+    /// (see [`Clock::system_clock_offset`]). Requires the `CAP_SYS_TIME` capability, eg. running as root -
+    /// fails with [`Error::SystemClockSetFailed`] without it, and on the hosts other than Unix.
+    pub async fn sync_system_clock(&self) -> ResolverReturn<chrono::Duration> {
+        let modem: DateTime<Utc> = self.time_now().await?.with_timezone(&Utc);
+        let offset: chrono::Duration = modem - Utc::now();
+        if offset.num_milliseconds().abs() > MAX_DRIFT_MS {
+            set_system_clock(modem)?;
+            log::info!("System clock set to {modem}, it was off by {offset}.");
+        }
+        Ok(offset)
+    }
+}
+
+impl Clock {
+    /// [`Clock::time`] returning the result directly.
+    pub async fn time_now(&self) -> ResolverReturn<DateTime<FixedOffset>> {
+        join_task(self.time()).await
+    }
+
+    /// [`Clock::set_time`] returning the result directly.
+    pub async fn set_time_now(&self, datetime: DateTime<FixedOffset>) -> ResolverReturn<()> {
+        join_task(self.set_time(datetime)).await
+    }
+
+    /// [`Clock::enable_network_time_sync`] returning the result directly.
+    pub async fn enable_network_time_sync_now(&self, enabled: bool) -> ResolverReturn<()> {
+        join_task(self.enable_network_time_sync(enabled)).await
+    }
+
+    /// [`Clock::ntp_sync`] returning the result directly.
+    pub async fn ntp_sync_now(&self, server: &str) -> ResolverReturn<()> {
+        join_task(self.ntp_sync(server)).await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Clock {
+    /// Blocking version of [`Clock::time`].
+    pub fn time_blocking(&self) -> ResolverReturn<DateTime<FixedOffset>> {
+        block_on_task(|| self.time())
+    }
+
+    /// Blocking version of [`Clock::set_time`].
+    pub fn set_time_blocking(&self, datetime: DateTime<FixedOffset>) -> ResolverReturn<()> {
+        block_on_task(|| self.set_time(datetime))
+    }
+
+    /// Blocking version of [`Clock::enable_network_time_sync`].
+    pub fn enable_network_time_sync_blocking(&self, enabled: bool) -> ResolverReturn<()> {
+        block_on_task(|| self.enable_network_time_sync(enabled))
+    }
+
+    /// Blocking version of [`Clock::ntp_sync`].
+    pub fn ntp_sync_blocking(&self, server: &str) -> ResolverReturn<()> {
+        block_on_task(|| self.ntp_sync(server))
+    }
+
+    /// Blocking version of [`Clock::system_clock_offset`].
+    pub fn system_clock_offset_blocking(&self) -> ResolverReturn<chrono::Duration> {
+        block_on(self.system_clock_offset())
+    }
+
+    /// Blocking version of [`Clock::sync_system_clock`].
+    pub fn sync_system_clock_blocking(&self) -> ResolverReturn<chrono::Duration> {
+        block_on(self.sync_system_clock())
+    }
+}
@@ -0,0 +1,449 @@
+//! CMUX module
+//!
+//! Available with the `cmux` feature.
+//!
+//! With `AT+CMUX=0` the modem multiplexes several virtual serial ports (DLCs) over the one UART, framed as
+//! described by GSM 07.10 (the basic option). [`Multiplexer::start`] switches the modem to the multiplexer
+//! mode and opens the channels; each [`CmuxChannel`] is a [`Transport`] of its own, so the GNSS module can run
+//! on a dedicated channel (see [`crate::SIM868Builder::gnss_transport`]) with its own task queue - a long HTTP
+//! transaction then doesn't hold up the GNSS fixes.
+//!
+//! The modem has to be switched on before the multiplexer is started, and it leaves the multiplexer mode
+//! when it restarts. Dropping the [`Multiplexer`] and all its channels closes the multiplexer, the modem then
+//! takes the AT commands on the UART again.
+//!
+//! ```no_run
+//! use rpi_sim868::{cmux::Multiplexer, SIM868};
+//!
+//! # fn main() -> Result<(), rpi_sim868::Error> {
+//! let uart = rppal::uart::Uart::with_path("/dev/ttyS0", 115200, rppal::uart::Parity::None, 8, 1)?;
+//! let mux: Multiplexer = Multiplexer::start(uart, 2)?;
+//! let sim: SIM868 = SIM868::builder()
+//!     .transport(mux.channel(1))
+//!     .gnss_transport(mux.channel(2))
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    error::Error,
+    transport::{Queue, Transport},
+    ACK_REGEX,
+};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+const MUTEX_POISONED_MSG: &str = "Critical error: Mutex is poisoned.";
+const FLAG: u8 = 0xF9;
+/// Largest information field of a frame, the modem's default `N1`.
+const MAX_FRAME_SIZE: usize = 127;
+/// Time the modem has to answer the command switching the mode or opening a channel.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Highest channel of SIM800 series modems.
+const MAX_CHANNELS: u8 = 3;
+
+// frame types, without the poll/final bit
+const SABM: u8 = 0x2F;
+const UA: u8 = 0x63;
+const DM: u8 = 0x0F;
+const DISC: u8 = 0x43;
+const UIH: u8 = 0xEF;
+const POLL_FINAL: u8 = 0x10;
+
+// control channel messages
+const MSC_COMMAND: u8 = 0xE3;
+const MSC_RESPONSE: u8 = 0xE1;
+const CLD_COMMAND: u8 = 0xC3;
+/// RTC, RTR and DV set - the terminal is ready to exchange data.
+const MSC_SIGNALS: u8 = 0x8D;
+
+/// CRC-8 of GSM 07.10 (reversed polynomial `0xE0`).
+fn crc(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0xFF, |crc: u8, byte: &u8| {
+        (0..8).fold(crc ^ byte, |crc: u8, _| match crc & 1 {
+            1 => (crc >> 1) ^ 0xE0,
+            _ => crc >> 1,
+        })
+    })
+}
+
+/// Frame sent by the terminal (the command/response bit set) on the `dlci` channel.
+fn encode(dlci: u8, control: u8, data: &[u8]) -> Vec<u8> {
+    let mut frame: Vec<u8> = vec![FLAG, (dlci << 2) | 0x03, control];
+    // the length is sent in one byte up to 127, in two above it
+    match data.len() {
+        length @ 0..=127 => frame.push(((length as u8) << 1) | 0x01),
+        length => frame.extend_from_slice(&[((length & 0x7F) as u8) << 1, (length >> 7) as u8]),
+    }
+    let fcs: u8 = 0xFF - crc(&frame[1..]);
+    frame.extend_from_slice(data);
+    frame.extend_from_slice(&[fcs, FLAG]);
+    frame
+}
+
+/// Frame received from the modem.
+struct Frame {
+    dlci: u8,
+    control: u8,
+    data: Vec<u8>,
+}
+
+/// Whether the header (flag, address, control, length) can start a frame: the address ends its field, the
+/// frame type is known and the information field fits in [`MAX_FRAME_SIZE`].
+fn valid_header(header: &[u8], length: usize) -> bool {
+    header[1] & 0x01 == 0x01
+        && [SABM, UA, DM, DISC, UIH].contains(&(header[2] & !POLL_FINAL))
+        && length <= MAX_FRAME_SIZE
+}
+
+/// Takes the first complete frame off the `buffer`, skipping the bytes which don't form a valid one.
+fn decode(buffer: &mut Vec<u8>) -> Option<Frame> {
+    loop {
+        // the frame starts after a flag, the bytes before the first one are the rest of a lost frame
+        let flag: usize = buffer.iter().position(|byte: &u8| *byte == FLAG)?;
+        let start: usize = buffer[flag..]
+            .iter()
+            .position(|byte: &u8| *byte != FLAG)
+            .map_or(buffer.len(), |start: usize| flag + start);
+        // keeps the opening flag until the rest of the frame arrives
+        buffer.drain(..start - 1);
+        // flag, address, control, length (1 or 2 bytes), data, FCS, flag
+        let long: bool = buffer.get(3)? & 0x01 == 0;
+        let header: usize = if long { 5 } else { 4 };
+        let length: usize = match long {
+            true => (buffer[3] >> 1) as usize | (*buffer.get(4)? as usize) << 7,
+            false => (buffer[3] >> 1) as usize,
+        };
+        // the header is checked before waiting for the data, the FCS follows the data
+        if !valid_header(&buffer[..header], length) {
+            buffer.remove(0);
+            continue;
+        }
+        if buffer.len() < header + length + 2 {
+            return None;
+        }
+        let valid: bool = 0xFF - crc(&buffer[1..header]) == buffer[header + length]
+            && buffer[header + length + 1] == FLAG;
+        if !valid {
+            // not the start of a frame (or a corrupted one) - resynchronise on the next flag
+            buffer.remove(0);
+            continue;
+        }
+        let frame: Frame = Frame {
+            dlci: buffer[1] >> 2,
+            // the poll/final bit isn't significant for the multiplexer
+            control: buffer[2] & !POLL_FINAL,
+            data: buffer[header..header + length].to_vec(),
+        };
+        buffer.drain(..header + length + 1);
+        return Some(frame);
+    }
+}
+
+struct Mux {
+    transport: Box<dyn Transport>,
+    /// Received bytes which haven't formed a complete frame yet.
+    received: Vec<u8>,
+    /// Data received on the channels, not yet read.
+    channels: BTreeMap<u8, VecDeque<u8>>,
+    /// Answers to the frames opening the channels, by channel.
+    answers: BTreeMap<u8, u8>,
+}
+
+impl Mux {
+    fn send(&mut self, dlci: u8, control: u8, data: &[u8]) -> Result<(), Error> {
+        self.transport.write(&encode(dlci, control, data))?;
+        Ok(())
+    }
+
+    /// Reads the data received so far and distributes the frames to the channels.
+    fn pump(&mut self) -> Result<(), Error> {
+        let mut read_buffer: [u8; 256] = [0; 256];
+        loop {
+            match self.transport.read(&mut read_buffer)? {
+                0 => break,
+                length => self.received.extend_from_slice(&read_buffer[..length]),
+            }
+        }
+        while let Some(frame) = decode(&mut self.received) {
+            match (frame.dlci, frame.control) {
+                (0, UIH) => self.control_message(&frame.data)?,
+                (dlci, UIH) => self.channels.entry(dlci).or_default().extend(frame.data),
+                (dlci, DISC) => {
+                    log::warn!("CMUX - the modem has closed the channel {dlci}.");
+                    self.send(dlci, UA | POLL_FINAL, &[])?;
+                }
+                (dlci, answer) => {
+                    self.answers.insert(dlci, answer);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Acknowledges the modem status command, the rest of the control messages are our commands' responses.
+    fn control_message(&mut self, data: &[u8]) -> Result<(), Error> {
+        if let [MSC_COMMAND, length, ..] = data {
+            let mut response: Vec<u8> = data.to_vec();
+            response[0] = MSC_RESPONSE;
+            response.truncate(2 + (*length >> 1) as usize);
+            self.send(0, UIH, &response)?;
+        }
+        Ok(())
+    }
+
+    /// Opens the `dlci` channel, waiting for the modem's acknowledgement.
+    fn open(&mut self, dlci: u8) -> Result<(), Error> {
+        self.answers.remove(&dlci);
+        self.send(dlci, SABM | POLL_FINAL, &[])?;
+        let start: Instant = Instant::now();
+        while start.elapsed() < RESPONSE_TIMEOUT {
+            self.pump()?;
+            match self.answers.remove(&dlci) {
+                Some(UA) => {
+                    self.channels.entry(dlci).or_default();
+                    return match dlci {
+                        0 => Ok(()),
+                        // the modem doesn't send the data until the terminal reports being ready
+                        dlci => self.send(
+                            0,
+                            UIH,
+                            &[MSC_COMMAND, 0x05, (dlci << 2) | 0x03, MSC_SIGNALS],
+                        ),
+                    };
+                }
+                Some(DM) => break,
+                _ => (),
+            }
+        }
+        Err(Error::CmuxChannelNotOpened(dlci))
+    }
+}
+
+impl Drop for Mux {
+    fn drop(&mut self) {
+        // closes down the multiplexer, the modem then takes the AT commands on the UART again
+        if let Err(e) = self.send(0, UIH, &[CLD_COMMAND, 0x01]) {
+            log::warn!("CMUX - closing the multiplexer has failed: {e}");
+        }
+    }
+}
+
+/// GSM 07.10 multiplexer, see the [module](self) documentation.
+#[derive(Clone)]
+pub struct Multiplexer {
+    mux: Arc<Mutex<Mux>>,
+}
+
+impl Multiplexer {
+    /// Switches the modem to the multiplexer mode (`AT+CMUX=0`) and opens the `channels` (1 to 3) on the
+    /// `transport`. Fails with [`Error::CmuxNotStarted`] if the modem refuses the mode (eg. it's switched off),
+    /// or [`Error::CmuxChannelNotOpened`].
+    pub fn start<T>(mut transport: T, channels: u8) -> Result<Multiplexer, Error>
+    where
+        T: Transport + 'static,
+    {
+        transport.flush(Queue::Both)?;
+        transport.write(b"AT+CMUX=0\r\n")?;
+        let mut response: Vec<u8> = Vec::new();
+        let mut read_buffer: [u8; 64] = [0; 64];
+        let start: Instant = Instant::now();
+        while !ACK_REGEX.is_match(&String::from_utf8_lossy(&response)) {
+            if start.elapsed() > RESPONSE_TIMEOUT {
+                return Err(Error::CmuxNotStarted);
+            }
+            let length: usize = transport.read(&mut read_buffer)?;
+            response.extend_from_slice(&read_buffer[..length]);
+        }
+
+        let mut mux: Mux = Mux {
+            transport: Box::new(transport),
+            received: Vec::new(),
+            channels: BTreeMap::new(),
+            answers: BTreeMap::new(),
+        };
+        for dlci in 0..=channels.clamp(1, MAX_CHANNELS) {
+            mux.open(dlci)?;
+        }
+        log::info!("CMUX - {channels} channel(s) opened.");
+        Ok(Multiplexer {
+            mux: Arc::new(Mutex::new(mux)),
+        })
+    }
+
+    /// Transport of the `dlci` channel, opened by [`Multiplexer::start`].
+    pub fn channel(&self, dlci: u8) -> CmuxChannel {
+        CmuxChannel {
+            dlci,
+            mux: self.mux.clone(),
+        }
+    }
+}
+
+/// Virtual serial port of the [`Multiplexer`].
+pub struct CmuxChannel {
+    dlci: u8,
+    mux: Arc<Mutex<Mux>>,
+}
+
+impl CmuxChannel {
+    pub fn dlci(&self) -> u8 {
+        self.dlci
+    }
+}
+
+impl Transport for CmuxChannel {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let mut mux = self.mux.lock().expect(MUTEX_POISONED_MSG);
+        let empty: bool = mux
+            .channels
+            .get(&self.dlci)
+            .map_or(true, VecDeque::is_empty);
+        if empty {
+            mux.pump()?;
+        }
+        let Some(received) = mux.channels.get_mut(&self.dlci) else {
+            return Err(Error::CmuxChannelNotOpened(self.dlci));
+        };
+        let length: usize = buffer.len().min(received.len());
+        for (byte, received) in buffer.iter_mut().zip(received.drain(..length)) {
+            *byte = received;
+        }
+        Ok(length)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
+        let mut mux = self.mux.lock().expect(MUTEX_POISONED_MSG);
+        for chunk in data.chunks(MAX_FRAME_SIZE) {
+            mux.send(self.dlci, UIH, chunk)?;
+        }
+        Ok(data.len())
+    }
+
+    /// Discards the data received on the channel. The output isn't buffered by the multiplexer, and the
+    /// buffers of the UART are shared with the other channels, so they're left as they are.
+    fn flush(&mut self, queue: Queue) -> Result<(), Error> {
+        if queue != Queue::Output {
+            let mut mux = self.mux.lock().expect(MUTEX_POISONED_MSG);
+            if let Some(received) = mux.channels.get_mut(&self.dlci) {
+                received.clear();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `UA` of the control channel, as sent by the modem.
+    const UA_FRAME: &[u8] = &[0xF9, 0x03, 0x73, 0x01, 0xD7, 0xF9];
+
+    #[test]
+    fn encodes_frames() {
+        assert_eq!(
+            encode(0, SABM | POLL_FINAL, &[]),
+            [0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9]
+        );
+        assert_eq!(
+            encode(1, SABM | POLL_FINAL, &[]),
+            [0xF9, 0x07, 0x3F, 0x01, 0xDE, 0xF9]
+        );
+        assert_eq!(
+            encode(1, UIH, b"AT\r"),
+            [
+                0xF9,
+                0x07,
+                0xEF,
+                0x07,
+                b'A',
+                b'T',
+                b'\r',
+                0xFF - crc(&[0x07, 0xEF, 0x07]),
+                0xF9
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_frame() {
+        let mut buffer: Vec<u8> = UA_FRAME.to_vec();
+        let frame: Frame = decode(&mut buffer).unwrap();
+        assert_eq!((frame.dlci, frame.control, frame.data), (0, UA, Vec::new()));
+        assert!(decode(&mut buffer).is_none());
+    }
+
+    #[test]
+    fn decodes_split_frame() {
+        let frame: Vec<u8> = encode(2, UIH, b"\r\nOK\r\n");
+        let mut buffer: Vec<u8> = Vec::new();
+        for byte in &frame[..frame.len() - 1] {
+            buffer.push(*byte);
+            assert!(decode(&mut buffer).is_none());
+        }
+        buffer.push(FLAG);
+        let decoded: Frame = decode(&mut buffer).unwrap();
+        assert_eq!((decoded.dlci, decoded.control), (2, UIH));
+        assert_eq!(decoded.data, b"\r\nOK\r\n");
+    }
+
+    #[test]
+    fn decodes_full_frame() {
+        let data: Vec<u8> = vec![b'a'; MAX_FRAME_SIZE];
+        let mut buffer: Vec<u8> = encode(1, UIH, &data);
+        assert_eq!(decode(&mut buffer).unwrap().data, data);
+    }
+
+    #[test]
+    fn skips_oversized_frames() {
+        let data: Vec<u8> = (0..=199).collect();
+        let oversized: Vec<u8> = encode(1, UIH, &data);
+        assert_eq!(&oversized[3..5], [(200 & 0x7F) << 1, 200 >> 7]);
+        let mut buffer: Vec<u8> = [&oversized[..], UA_FRAME].concat();
+        let frame: Frame = decode(&mut buffer).unwrap();
+        assert_eq!((frame.dlci, frame.control), (0, UA));
+    }
+
+    #[test]
+    fn resynchronises_on_invalid_header() {
+        // an unknown frame type claiming more data than has been received doesn't hold up the next frame
+        let mut buffer: Vec<u8> = [&[FLAG, 0x07, 0x55, (100 << 1) | 0x01][..], UA_FRAME].concat();
+        let frame: Frame = decode(&mut buffer).unwrap();
+        assert_eq!((frame.dlci, frame.control), (0, UA));
+        // and neither does a length above the N1
+        let mut buffer: Vec<u8> = [&[FLAG, 0x07, UIH, 0x00, 0x02][..], UA_FRAME].concat();
+        let frame: Frame = decode(&mut buffer).unwrap();
+        assert_eq!((frame.dlci, frame.control), (0, UA));
+    }
+
+    #[test]
+    fn decodes_consecutive_frames() {
+        // the closing flag of one frame opens the next one
+        let mut buffer: Vec<u8> = [
+            &encode(1, UIH, b"a")[..],
+            &encode(2, UIH, b"b")[1..],
+            UA_FRAME,
+        ]
+        .concat();
+        assert_eq!(decode(&mut buffer).unwrap().data, b"a");
+        assert_eq!(decode(&mut buffer).unwrap().data, b"b");
+        assert_eq!(decode(&mut buffer).unwrap().control, UA);
+        assert!(decode(&mut buffer).is_none());
+    }
+
+    #[test]
+    fn skips_invalid_frames() {
+        let mut corrupted: Vec<u8> = encode(1, UIH, b"lost");
+        // the FCS of UIH covers only the header
+        let fcs: usize = corrupted.len() - 2;
+        corrupted[fcs] ^= 0xFF;
+        let mut buffer: Vec<u8> = [&[b'O', b'K', 0x00][..], &corrupted, UA_FRAME].concat();
+        let frame: Frame = decode(&mut buffer).unwrap();
+        assert_eq!((frame.dlci, frame.control), (0, UA));
+    }
+}
@@ -0,0 +1,232 @@
+//! AT command module
+//!
+//! [`AtCommand`] builds the command line from typed parameters. The strings are quoted and the characters
+//! which would end the string or the command line (`"`, CR, LF and the other control characters) are escaped
+//! as `\` followed by two hex digits (3GPP TS 27.007), so the user data passed as a parameter can't inject
+//! other commands. Backslashes are left as they are, eg. for the `\r\n` separating the HTTP headers of
+//! `AT+HTTPPARA="USERDATA",...`.
+//!
+//! ```
+//! use rpi_sim868::command::AtCommand;
+//!
+//! let command: AtCommand = AtCommand::set("+SAPBR")
+//!     .number(3)
+//!     .number(1)
+//!     .token("APN")
+//!     .string("internet\"\r\nAT+CPOWD=1");
+//! assert_eq!(
+//!     command.to_string(),
+//!     r#"AT+SAPBR=3,1,APN,"internet\22\0D\0AAT+CPOWD=1""#
+//! );
+//! ```
+
+use std::fmt::{Display, Formatter, Write};
+
+/// Integer parameter of [`AtCommand::number`].
+pub trait Number: Display + Copy {}
+
+macro_rules! numbers {
+    ($($number:ty),*) => {
+        $(impl Number for $number {})*
+    };
+}
+
+numbers!(u8, u16, u32, u64, usize, i8, i16, i32, i64);
+
+/// AT command line, see the [module](self) documentation.
+///
+/// Converting it into `String` terminates the line with `\n`, ready to be written to the modem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtCommand {
+    line: String,
+    parameters: usize,
+}
+
+impl AtCommand {
+    /// Execution command, eg. `AT+HTTPINIT` for `"+HTTPINIT"`.
+    pub fn execute(name: &str) -> Self {
+        AtCommand {
+            line: format!("AT{name}"),
+            parameters: 0,
+        }
+    }
+
+    /// Read command, eg. `AT+CREG?` for `"+CREG"`.
+    pub fn read(name: &str) -> Self {
+        AtCommand::execute(&format!("{name}?"))
+    }
+
+    /// Write command taking the parameters appended with [`AtCommand::number`], [`AtCommand::token`] and
+    /// [`AtCommand::string`], eg. `AT+CMGD=3`.
+    pub fn set(name: &str) -> Self {
+        AtCommand::execute(&format!("{name}="))
+    }
+
+    fn separate(&mut self) {
+        if self.parameters > 0 {
+            self.line.push(',');
+        }
+        self.parameters += 1;
+    }
+
+    pub fn number<N: Number>(mut self, number: N) -> Self {
+        self.separate();
+        // writing into a String doesn't fail
+        let _ = write!(self.line, "{number}");
+        self
+    }
+
+    /// Unquoted parameter defined by the command, eg. `URL` in `AT+HTTPPARA=URL,...`. Anything but letters,
+    /// digits and `_` is dropped - use [`AtCommand::string`] for the user data.
+    pub fn token(mut self, token: &str) -> Self {
+        self.separate();
+        self.line.extend(
+            token
+                .chars()
+                .filter(|c: &char| c.is_ascii_alphanumeric() || *c == '_'),
+        );
+        self
+    }
+
+    /// Unquoted file path of the `AT+FS*` commands, eg. `C:\User\EPO.DAT`. Anything but letters, digits, `_`,
+    /// `.`, `:` and `\` is dropped.
+    pub fn path(mut self, path: &str) -> Self {
+        self.separate();
+        self.line.extend(
+            path.chars().filter(|c: &char| {
+                c.is_ascii_alphanumeric() || matches!(*c, '_' | '.' | ':' | '\\')
+            }),
+        );
+        self
+    }
+
+    /// Quoted string parameter, escaped.
+    pub fn string(mut self, string: &str) -> Self {
+        self.separate();
+        self.line.push('"');
+        for c in string.chars() {
+            match c {
+                '"' | '\u{0}'..='\u{1F}' | '\u{7F}' => {
+                    let _ = write!(self.line, "\\{:02X}", c as u32);
+                }
+                c => self.line.push(c),
+            }
+        }
+        self.line.push('"');
+        self
+    }
+}
+
+impl Display for AtCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.line)
+    }
+}
+
+impl From<AtCommand> for String {
+    fn from(command: AtCommand) -> Self {
+        command.line + "\n"
+    }
+}
+
+/// Removes the characters ending (Ctrl+Z) or cancelling (Esc) the data entered after a command like
+/// `AT+CMGS`, so the rest of the data isn't executed as commands.
+#[cfg(feature = "sms")]
+pub(crate) fn data(text: &str) -> String {
+    text.chars()
+        .filter(|c: &char| !matches!(c, '\u{1A}' | '\u{1B}'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!(
+            AtCommand::set("+BTHOST")
+                .string("say \"hi\"\r\n")
+                .to_string(),
+            r#"AT+BTHOST="say \22hi\22\0D\0A""#
+        );
+        assert_eq!(
+            AtCommand::set("+BTHOST")
+                .string("\u{0}\u{1A}\u{1B}\u{7F}")
+                .to_string(),
+            r#"AT+BTHOST="\00\1A\1B\7F""#
+        );
+        // non-ASCII characters and backslashes are kept
+        assert_eq!(
+            AtCommand::set("+BTHOST").string("Żółw ü\\r\\n").to_string(),
+            r#"AT+BTHOST="Żółw ü\r\n""#
+        );
+        assert_eq!(
+            AtCommand::set("+BTHOST").string("").to_string(),
+            r#"AT+BTHOST="""#
+        );
+    }
+
+    #[test]
+    fn filters_tokens_and_paths() {
+        assert_eq!(
+            AtCommand::set("+HTTPPARA")
+                .token("URL\",\"x\r\nAT+CPOWD=1")
+                .to_string(),
+            "AT+HTTPPARA=URLxATCPOWD1"
+        );
+        assert_eq!(
+            AtCommand::set("+HTTPPARA").token("USER_DATA ą").to_string(),
+            "AT+HTTPPARA=USER_DATA"
+        );
+        assert_eq!(
+            AtCommand::set("+FSDEL")
+                .path("C:\\User\\EPO.DAT\r\nAT+CPOWD=1,\"")
+                .to_string(),
+            "AT+FSDEL=C:\\User\\EPO.DATATCPOWD1"
+        );
+    }
+
+    #[test]
+    fn separates_parameters() {
+        assert_eq!(
+            AtCommand::set("+CMGS")
+                .string("+4799999999")
+                .number(145u8)
+                .to_string(),
+            r#"AT+CMGS="+4799999999",145"#
+        );
+        assert_eq!(
+            AtCommand::set("+SAPBR")
+                .number(3)
+                .number(-1i8)
+                .token("APN")
+                .path("C:\\")
+                .string("internet")
+                .to_string(),
+            r#"AT+SAPBR=3,-1,APN,C:\,"internet""#
+        );
+        assert_eq!(
+            AtCommand::set("+CMGD").number(3u16).to_string(),
+            "AT+CMGD=3"
+        );
+        assert_eq!(AtCommand::set("+CFUN").to_string(), "AT+CFUN=");
+    }
+
+    #[test]
+    fn terminates_line() {
+        assert_eq!(
+            String::from(AtCommand::execute("+HTTPINIT")),
+            "AT+HTTPINIT\n"
+        );
+        assert_eq!(String::from(AtCommand::read("+CREG")), "AT+CREG?\n");
+        let command: String = AtCommand::set("+CMGD").number(3).into();
+        assert_eq!(command, "AT+CMGD=3\n");
+    }
+
+    #[cfg(feature = "sms")]
+    #[test]
+    fn strips_data_terminators() {
+        assert_eq!(data("Hi\u{1A}AT+CPOWD=1\u{1B}\r\n"), "HiAT+CPOWD=1\r\n");
+    }
+}
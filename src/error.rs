@@ -1,146 +1,1003 @@
+use crate::{
+    capabilities::Capability,
+    profile::{Feature, ModemProfile},
+};
+use std::{io, sync::Arc, time::Duration};
+use uuid::Uuid;
+
+/// Equipment errors (`+CME ERROR: <n>`) as defined by 3GPP TS 27.007.
+///
+/// Reported by the modem once the numeric error format is enabled by [`crate::hat::Hat::init`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmeError {
+    PhoneFailure,
+    NoConnectionToPhone,
+    OperationNotAllowed,
+    OperationNotSupported,
+    PhSimPinRequired,
+    SimNotInserted,
+    SimPinRequired,
+    SimPukRequired,
+    SimFailure,
+    SimBusy,
+    SimWrong,
+    IncorrectPassword,
+    SimPin2Required,
+    SimPuk2Required,
+    MemoryFull,
+    InvalidIndex,
+    NotFound,
+    MemoryFailure,
+    TextStringTooLong,
+    InvalidCharactersInTextString,
+    DialStringTooLong,
+    InvalidCharactersInDialString,
+    NoNetworkService,
+    NetworkTimeout,
+    NetworkNotAllowed,
+    Unknown,
+    /// Code not covered by the variants above.
+    Other(u16),
+}
+
+/// Message service errors (`+CMS ERROR: <n>`) as defined by 3GPP TS 27.005.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmsError {
+    MeFailure,
+    SmsServiceReserved,
+    OperationNotAllowed,
+    OperationNotSupported,
+    InvalidPduModeParameter,
+    InvalidTextModeParameter,
+    SimNotInserted,
+    SimPinRequired,
+    SimFailure,
+    SimBusy,
+    SimWrong,
+    SimPukRequired,
+    MemoryFailure,
+    InvalidMemoryIndex,
+    MemoryFull,
+    SmscAddressUnknown,
+    NoNetworkService,
+    NetworkTimeout,
+    Unknown,
+    /// Code not covered by the variants above.
+    Other(u16),
+}
+
 /// returned by [`Error::kind`] method.
+///
+/// New kinds can be added in minor releases, so matches should include a wildcard arm.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ErrorKind {
+    AtCommandFailed,
+    BluetoothProblem,
+    ClockNotSet,
+    ClockNotSynchronised,
+    ClockNtpFailed,
+    Closed,
+    Cme,
+    Cms,
+    CmuxChannelNotOpened,
+    CmuxNotStarted,
+    FsDeleteFailed,
+    FsFileNotFound,
+    FsWriteFailed,
+    GnssAssistanceNotLoaded,
     GnssModuleOff,
     GnssNotFixed,
     GnssProblem,
     GprsApnConfigSetFailed,
     GprsConnectionCloseFailed,
     GprsConnectionOpenFailed,
+    GprsDownloadFailed,
     GprsHttpRequestFailed,
+    GprsLocationNotFound,
     GprsNoConnection,
+    Gpio,
+    GprsQosConfigFailed,
     HatAlreadyOff,
     HatAlreadyOn,
+    HatBandNotSet,
+    HatBaudRateNotDetected,
+    HatBaudRateNotSet,
+    HatCellInfoNotRead,
+    HatConfigurationNotRestored,
+    HatConfigurationNotSaved,
+    HatFunctionalityNotSet,
+    HatIdentityNotRead,
+    HatInitFailed,
+    HatJammingDetectionNotSet,
+    HatNetLightNotSet,
+    HatNoPowerPin,
+    HatNotBooted,
+    HatNotPoweredDown,
+    HatOperatorNotSelected,
+    HatRegistrationNotRead,
+    HatSimPinRejected,
+    HatSleepModeNotSet,
+    Io,
     JsonSerialisationFailed,
     NotResolved,
+    ParseFailure,
+    PhoneAutoAnswerNotSet,
+    PhoneCallEnded,
     PhoneCallNotAnswered,
     PhoneCallNotCalled,
     PhoneCallNotEnded,
     RequestBodyParsingFailed,
+    ResponseTooLarge,
+    SmsDeliveryNotSet,
     SmsNotSent,
+    SmsPartNotSent,
     SmsProblemWithReadingMessages,
     SmsProblemWithSettingTextMode,
     SmsRemoveMessageFailed,
+    SystemClockSetFailed,
+    TaskCancelled,
+    TaskTimeout,
     TokioJoinError,
+    TransportBaudRateUnsupported,
+    TransportNotSet,
     Uart,
+    UnsupportedByFirmware,
+    UnsupportedByModem,
     UrlParse,
 }
 
 /// RPi SIM868 Error enum.
-#[derive(Debug)]
+///
+/// Wrapped errors of other crates are reference counted, so the error can be cloned. Some variants depend on the
+/// enabled features, so matches need a wildcard arm.
+#[non_exhaustive]
+#[derive(Debug, Clone)]
 pub enum Error {
+    /// The modem has answered the command sent with [`crate::at::AT::send`] with `ERROR` - holds the response.
+    AtCommandFailed(String),
+    BluetoothProblem,
+    ClockNotSet,
+    /// The modem's RTC still runs from its power-on default, see [`crate::clock::Clock::time`].
+    ClockNotSynchronised,
+    /// `+CNTP` result code, eg. 61 - network error, 64 - timeout.
+    ClockNtpFailed(u8),
+    /// The connection has been closed by [`crate::SIM868::close`] or dropping the last [`crate::SIM868`] clone.
+    Closed,
+    /// Equipment error reported by the modem as `+CME ERROR: <n>`.
+    Cme(CmeError),
+    /// Message service error reported by the modem as `+CMS ERROR: <n>`.
+    Cms(CmsError),
+    /// The modem hasn't opened the CMUX channel, see [`crate::cmux::Multiplexer::start`].
+    CmuxChannelNotOpened(u8),
+    /// The modem hasn't accepted `AT+CMUX=0`.
+    CmuxNotStarted,
+    /// Error raised while processing an AT command, along with the command, task id and the time spent on it.
+    /// [`Error::kind`] and [`Error::is_retryable`] refer to the wrapped error.
+    Context {
+        command: String,
+        task_id: Uuid,
+        elapsed: Duration,
+        error: Box<Error>,
+    },
+    FsDeleteFailed,
+    FsFileNotFound,
+    FsWriteFailed,
+    GnssAssistanceNotLoaded,
     GnssModuleOff,
     GnssNotFixed,
     GnssProblem,
     GprsApnConfigSetFailed,
     GprsConnectionCloseFailed,
     GprsConnectionOpenFailed,
+    /// HTTP status of the file downloaded by the modem, eg. 404 - not found, 60x - network error.
+    GprsDownloadFailed(u16),
     GprsHttpRequestFailed,
+    /// `+CIPGSMLOC` code of the location service, eg. 404 - not found, 601 - network error.
+    GprsLocationNotFound(u16),
     GprsNoConnection,
+    #[cfg(feature = "rpi")]
+    Gpio(Arc<rppal::gpio::Error>),
+    GprsQosConfigFailed,
     HatAlreadyOff,
     HatAlreadyOn,
-    JsonSerialisationFailed(serde_json::Error),
+    HatBandNotSet,
+    /// [`crate::hat::Hat::autobaud`] hasn't got `OK` at any of the baud rates.
+    HatBaudRateNotDetected,
+    HatBaudRateNotSet,
+    HatCellInfoNotRead,
+    HatConfigurationNotRestored,
+    HatConfigurationNotSaved,
+    HatFunctionalityNotSet,
+    HatIdentityNotRead,
+    HatInitFailed,
+    HatJammingDetectionNotSet,
+    HatNetLightNotSet,
+    /// Built without the `rpi` feature and [`crate::hat::Hat::set_power_pin`] hasn't been called.
+    HatNoPowerPin,
+    HatNotBooted,
+    HatNotPoweredDown,
+    HatOperatorNotSelected,
+    HatRegistrationNotRead,
+    HatSimPinRejected,
+    HatSleepModeNotSet,
+    /// I/O error of the transport other than the Raspberry Pi UART.
+    Io(Arc<io::Error>),
+    #[cfg(feature = "gprs")]
+    JsonSerialisationFailed(Arc<serde_json::Error>),
     NotResolved,
+    /// The modem response could not be parsed - `field` names the value, `raw` holds the offending input.
+    ParseFailure {
+        field: &'static str,
+        raw: String,
+    },
+    PhoneAutoAnswerNotSet,
+    /// The call has ended, or couldn't be made, with the final result code (eg. `BUSY`).
+    #[cfg(feature = "phone")]
+    PhoneCallEnded(crate::phone::CallEndReason),
     PhoneCallNotAnswered,
     PhoneCallNotCalled,
     PhoneCallNotEnded,
-    RequestBodyParsingFailed(serde_url_params::Error),
+    #[cfg(feature = "gprs")]
+    RequestBodyParsingFailed(Arc<serde_url_params::Error>),
+    /// The HTTP response is longer than the limit set by [`crate::gprs::GPRS::set_max_response_size`].
+    ResponseTooLarge {
+        size: usize,
+        limit: usize,
+    },
+    SmsDeliveryNotSet,
     SmsNotSent,
+    /// Part of the text sent by [`crate::sms::SMS::send_split`] hasn't been sent, the previous parts have been.
+    SmsPartNotSent {
+        sent: usize,
+        parts: usize,
+        error: Box<Error>,
+    },
     SmsProblemWithReadingMessages,
     SmsProblemWithSettingTextMode,
     SmsRemoveMessageFailed,
-    TokioJoinError(tokio::task::JoinError),
-    Uart(rppal::uart::Error),
+    SystemClockSetFailed,
+    /// The task has been cancelled with [`crate::TaskJoinHandle::cancel`].
+    TaskCancelled,
+    /// The task has exceeded its deadline, see [`crate::TaskJoinHandle::with_deadline`].
+    TaskTimeout(Duration),
+    TokioJoinError(Arc<tokio::task::JoinError>),
+    /// [`crate::transport::Transport::set_baud_rate`] isn't implemented by the transport.
+    TransportBaudRateUnsupported,
+    /// Built without the `rpi` feature, [`crate::SIM868Builder::transport`] is required.
+    TransportNotSet,
+    #[cfg(feature = "rpi")]
+    Uart(Arc<rppal::uart::Error>),
+    /// The firmware `revision` lacks the `capability` (see [`crate::capabilities`]), so no command has been sent.
+    UnsupportedByFirmware {
+        revision: String,
+        capability: Capability,
+    },
+    /// The selected [`ModemProfile`] lacks the `feature`, so no command has been sent.
+    UnsupportedByModem {
+        profile: ModemProfile,
+        feature: Feature,
+    },
+    #[cfg(feature = "gprs")]
     UrlParse(url::ParseError),
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
+            Error::AtCommandFailed(ref response) => write!(f, "AT - the modem has answered the command with ERROR - received: {:?}", response),
+            Error::BluetoothProblem => write!(f, "Bluetooth - the command has failed, make sure the module is switched on."),
+            Error::ClockNotSet => write!(f, "Clock - the modem has refused the time or the network time setting."),
+            Error::ClockNotSynchronised => write!(f, "Clock - the modem's clock hasn't been synchronised with the network yet."),
+            Error::ClockNtpFailed(ref code) => write!(f, "Clock - NTP synchronisation has failed with the code {}.", code),
+            Error::Closed => write!(f, "Serial port - the connection is closed."),
+            Error::Cme(ref err) => write!(f, "Modem - CME error {}: {}", err.code(), err),
+            Error::Cms(ref err) => write!(f, "Modem - CMS error {}: {}", err.code(), err),
+            Error::CmuxChannelNotOpened(dlci) => write!(f, "CMUX - the modem has not opened the channel {}.", dlci),
+            Error::CmuxNotStarted => write!(f, "CMUX - the modem has not entered the multiplexer mode. Make sure the hat is switched on."),
+            Error::Context { command, task_id, elapsed, error } => write!(f, "{} [command: {:?}, task: {}, elapsed: {:?}]", error, command, task_id, elapsed),
+            Error::FsDeleteFailed => write!(f, "FS - deleting the file has failed."),
+            Error::FsFileNotFound => write!(f, "FS - the file does not exist."),
+            Error::FsWriteFailed => write!(f, "FS - writing the file has failed."),
+            Error::GnssAssistanceNotLoaded => write!(f, "GNSS - the assistance data has not been loaded - check the file and the time of the RTC."),
             Error::GnssModuleOff => write!(f, "GNSS - module is off."),
             Error::GnssNotFixed => write!(f, "GNSS - position is not fixed - check GSM antenna."),
             Error::GnssProblem => write!(f, "GNSS - problem with the module."),
             Error::GprsApnConfigSetFailed => write!(f, "GPRS - setting APN Configuration has failed."),
             Error::GprsConnectionCloseFailed => write!(f, "GPRS - closing the connection has failed."),
             Error::GprsConnectionOpenFailed => write!(f, "GPRS - opening the connection has failed. Make sure you provide valid APN configuration during sim868.gprs.init call."),
+            Error::GprsDownloadFailed(code) => write!(f, "GPRS - the download has failed with the HTTP status {}.", code),
             Error::GprsHttpRequestFailed => write!(f, "GPRS - HTTP request has failed."),
+            Error::GprsLocationNotFound(code) => write!(f, "GPRS - the location service has failed with the code {}.", code),
             Error::GprsNoConnection => write!(f, "GPRS - no connection to the network."),
+            #[cfg(feature = "rpi")]
+            Error::Gpio(ref err) => write!(f, "GPIO error: {}", err),
+            Error::GprsQosConfigFailed => write!(f, "GPRS - setting or reading the QoS profile has failed."),
             Error::HatAlreadyOff => write!(f, "HAT - already switched off."),
             Error::HatAlreadyOn => write!(f, "HAT - already switched on."),
+            Error::HatBandNotSet => write!(f, "HAT - setting the band has failed."),
+            Error::HatBaudRateNotDetected => write!(f, "HAT - the modem has responded at none of the probed baud rates."),
+            Error::HatBaudRateNotSet => write!(f, "HAT - the modem has refused the baud rate."),
+            Error::HatCellInfoNotRead => write!(f, "HAT - reading the cell information has failed."),
+            Error::HatConfigurationNotRestored => write!(f, "HAT - restoring the configuration has failed."),
+            Error::HatConfigurationNotSaved => write!(f, "HAT - saving the configuration has failed."),
+            Error::HatFunctionalityNotSet => write!(f, "HAT - the modem has refused the functionality level."),
+            Error::HatIdentityNotRead => write!(f, "HAT - the modem has failed to report the identifier."),
+            Error::HatInitFailed => write!(f, "HAT - running the initialisation sequence has failed."),
+            Error::HatJammingDetectionNotSet => write!(f, "HAT - setting the jamming detection has failed."),
+            Error::HatNetLightNotSet => write!(f, "HAT - setting the net light has failed."),
+            Error::HatNoPowerPin => write!(f, "Hat - no power pin is set, see Hat::set_power_pin."),
+            Error::HatNotBooted => write!(f, "HAT - the modem has not come up after toggling the power or restarting."),
+            Error::HatNotPoweredDown => write!(f, "HAT - the modem is still on after powering down."),
+            Error::HatOperatorNotSelected => write!(f, "HAT - the modem has refused the operator selection."),
+            Error::HatRegistrationNotRead => write!(f, "HAT - reading the network registration has failed."),
+            Error::HatSimPinRejected => write!(f, "HAT - the SIM has refused the PIN operation."),
+            Error::HatSleepModeNotSet => write!(f, "HAT - the modem has refused the sleep mode."),
+            Error::Io(ref err) => write!(f, "I/O error: {}", err),
+            #[cfg(feature = "gprs")]
             Error::JsonSerialisationFailed(ref err) => write!(f, "Object has failed when serialising to JSON: {}", err),
             Error::NotResolved => write!(f, "Task NotResolved - please check if the hat is switched on."),
+            Error::ParseFailure { field, raw } => write!(f, "Parsing of the {} value has failed - received: {:?}", field, raw),
+            Error::PhoneAutoAnswerNotSet => write!(f, "Phone - setting the auto-answer has failed."),
+            #[cfg(feature = "phone")]
+            Error::PhoneCallEnded(reason) => write!(f, "Phone - the call has ended with the {:?} result code.", reason),
             Error::PhoneCallNotAnswered => write!(f, "Phone - there was an error while trying to answer the call."),
             Error::PhoneCallNotCalled => write!(f, "Phone - there was an error while trying to make a call - please check the network strength."),
             Error::PhoneCallNotEnded => write!(f, "Phone - there was an error while trying to end a call - it could end previously eg. other side has hanged up."),
+            #[cfg(feature = "gprs")]
             Error::RequestBodyParsingFailed(ref err) => write!(f, "Request body parsing has failed: {}", err),
+            Error::ResponseTooLarge { size, limit } => write!(f, "GPRS - HTTP response of {} bytes exceeds the limit of {} bytes.", size, limit),
+            Error::SmsDeliveryNotSet => write!(f, "SMS - there was an error while trying to set the new message indications."),
             Error::SmsNotSent => write!(f, "SMS - there was an error while trying to send an SMS - please check the network strength."),
+            Error::SmsPartNotSent { sent, parts, error } => write!(f, "SMS - only {} of {} parts have been sent: {}", sent, parts, error),
             Error::SmsProblemWithReadingMessages => write!(f, "SMS - problem with reading the messages."),
             Error::SmsProblemWithSettingTextMode => write!(f, "SMS - problem with setting the text mode."),
             Error::SmsRemoveMessageFailed => write!(f, "SMS - problem with removing the message/s."),
+            Error::SystemClockSetFailed => write!(f, "Clock - setting the system clock has failed, make sure the process has the CAP_SYS_TIME capability (eg. runs as root)."),
+            Error::TaskCancelled => write!(f, "Task has been cancelled."),
+            Error::TaskTimeout(deadline) => write!(f, "Task has exceeded its deadline of {:?}.", deadline),
             Error::TokioJoinError(ref err) => write!(f, "Tokio task join error: {}", err),
+            Error::TransportBaudRateUnsupported => write!(f, "The transport doesn't support changing the baud rate."),
+            Error::TransportNotSet => write!(f, "No transport is set, see SIM868Builder::transport."),
+            #[cfg(feature = "rpi")]
             Error::Uart(ref err) => write!(f, "Uart error: {}", err),
+            Error::UnsupportedByFirmware { ref revision, capability } => write!(f, "Modem - {:?} is not supported by the firmware {}.", capability, revision),
+            Error::UnsupportedByModem { profile, feature } => {
+                write!(f, "Modem - {:?} is not supported by {:?}.", feature, profile)
+            }
+            #[cfg(feature = "gprs")]
             Error::UrlParse(ref err) => write!(f, "URL parsing error: {}", err),
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Context { ref error, .. } | Error::SmsPartNotSent { ref error, .. } => {
+                Some(error.as_ref())
+            }
+            #[cfg(feature = "rpi")]
+            Error::Gpio(ref err) => Some(err.as_ref()),
+            Error::Io(ref err) => Some(err.as_ref()),
+            #[cfg(feature = "gprs")]
+            Error::JsonSerialisationFailed(ref err) => Some(err.as_ref()),
+            #[cfg(feature = "gprs")]
+            Error::RequestBodyParsingFailed(ref err) => Some(err.as_ref()),
+            Error::TokioJoinError(ref err) => Some(err.as_ref()),
+            #[cfg(feature = "rpi")]
+            Error::Uart(ref err) => Some(err.as_ref()),
+            #[cfg(feature = "gprs")]
+            Error::UrlParse(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// Error has to stay usable across threads and in boxed trait objects, eg. anyhow::Error.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+    assert_send_sync::<Error>();
+};
+
+/// Errors are compared structurally, skipping the [`Error::Context`] wrappers, so a failed command
+/// equals the bare error, eg. `Error::Cme(CmeError::SimBusy)`. Wrapped errors of other crates are
+/// compared by their kind - [`io::ErrorKind`], the category and position of the JSON error, or the message.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        let (left, right) = (self.root(), other.root());
+        match (left, right) {
+            (Error::AtCommandFailed(a), Error::AtCommandFailed(b)) => a == b,
+            (Error::ClockNtpFailed(a), Error::ClockNtpFailed(b)) => a == b,
+            (Error::Cme(a), Error::Cme(b)) => a == b,
+            (Error::Cms(a), Error::Cms(b)) => a == b,
+            (Error::CmuxChannelNotOpened(a), Error::CmuxChannelNotOpened(b)) => a == b,
+            (Error::GprsDownloadFailed(a), Error::GprsDownloadFailed(b)) => a == b,
+            (Error::GprsLocationNotFound(a), Error::GprsLocationNotFound(b)) => a == b,
+            #[cfg(feature = "rpi")]
+            (Error::Gpio(a), Error::Gpio(b)) => a.to_string() == b.to_string(),
+            (Error::Io(a), Error::Io(b)) => a.kind() == b.kind(),
+            #[cfg(feature = "gprs")]
+            (Error::JsonSerialisationFailed(a), Error::JsonSerialisationFailed(b)) => {
+                a.classify() == b.classify() && a.line() == b.line() && a.column() == b.column()
+            }
+            (
+                Error::ParseFailure { field, raw },
+                Error::ParseFailure {
+                    field: other_field,
+                    raw: other_raw,
+                },
+            ) => field == other_field && raw == other_raw,
+            #[cfg(feature = "phone")]
+            (Error::PhoneCallEnded(a), Error::PhoneCallEnded(b)) => a == b,
+            #[cfg(feature = "gprs")]
+            (Error::RequestBodyParsingFailed(a), Error::RequestBodyParsingFailed(b)) => {
+                a.to_string() == b.to_string()
+            }
+            (
+                Error::ResponseTooLarge { size, limit },
+                Error::ResponseTooLarge {
+                    size: other_size,
+                    limit: other_limit,
+                },
+            ) => size == other_size && limit == other_limit,
+            (
+                Error::SmsPartNotSent { sent, parts, error },
+                Error::SmsPartNotSent {
+                    sent: other_sent,
+                    parts: other_parts,
+                    error: other_error,
+                },
+            ) => sent == other_sent && parts == other_parts && error == other_error,
+            (Error::TaskTimeout(a), Error::TaskTimeout(b)) => a == b,
+            (Error::TokioJoinError(a), Error::TokioJoinError(b)) => {
+                a.is_cancelled() == b.is_cancelled() && a.is_panic() == b.is_panic()
+            }
+            #[cfg(feature = "rpi")]
+            (Error::Uart(a), Error::Uart(b)) => a.to_string() == b.to_string(),
+            (
+                Error::UnsupportedByFirmware {
+                    revision,
+                    capability,
+                },
+                Error::UnsupportedByFirmware {
+                    revision: other_revision,
+                    capability: other_capability,
+                },
+            ) => revision == other_revision && capability == other_capability,
+            (
+                Error::UnsupportedByModem { profile, feature },
+                Error::UnsupportedByModem {
+                    profile: other_profile,
+                    feature: other_feature,
+                },
+            ) => profile == other_profile && feature == other_feature,
+            #[cfg(feature = "gprs")]
+            (Error::UrlParse(a), Error::UrlParse(b)) => a == b,
+            // the remaining variants carry no data
+            _ => std::mem::discriminant(left) == std::mem::discriminant(right),
+        }
+    }
+}
 
 impl Error {
+    /// Whether the failure is likely transient (network, SIM busy, timeouts), so the same task may succeed if repeated.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Cme(ref err) => err.is_retryable(),
+            Error::Cms(ref err) => err.is_retryable(),
+            Error::Context { ref error, .. } | Error::SmsPartNotSent { ref error, .. } => {
+                error.is_retryable()
+            }
+            Error::ClockNotSynchronised
+            | Error::ClockNtpFailed(_)
+            | Error::GnssNotFixed
+            | Error::GprsConnectionOpenFailed
+            | Error::GprsHttpRequestFailed
+            | Error::GprsNoConnection
+            | Error::NotResolved
+            | Error::PhoneCallNotCalled
+            | Error::SmsNotSent
+            | Error::TaskTimeout(_)
+            | Error::Io(_) => true,
+            #[cfg(feature = "rpi")]
+            Error::Uart(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the underlying error, skipping the [`Error::Context`] wrappers.
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { ref error, .. } => error.root(),
+            _ => self,
+        }
+    }
+
+    fn io_error_kind(&self) -> io::ErrorKind {
+        match self.root() {
+            Error::Cme(CmeError::NetworkTimeout) | Error::Cms(CmsError::NetworkTimeout) => {
+                io::ErrorKind::TimedOut
+            }
+            Error::Cme(CmeError::OperationNotSupported)
+            | Error::Cms(CmsError::OperationNotSupported) => io::ErrorKind::Unsupported,
+            Error::Cme(CmeError::OperationNotAllowed)
+            | Error::Cme(CmeError::IncorrectPassword)
+            | Error::Cme(CmeError::SimPinRequired)
+            | Error::Cme(CmeError::SimPukRequired)
+            | Error::Cms(CmsError::OperationNotAllowed)
+            | Error::Cms(CmsError::SimPinRequired)
+            | Error::Cms(CmsError::SimPukRequired)
+            | Error::HatSimPinRejected
+            | Error::SystemClockSetFailed => io::ErrorKind::PermissionDenied,
+            Error::Closed
+            | Error::CmuxChannelNotOpened(_)
+            | Error::CmuxNotStarted
+            | Error::GprsNoConnection
+            | Error::HatBaudRateNotDetected => io::ErrorKind::NotConnected,
+            Error::TaskCancelled => io::ErrorKind::Interrupted,
+            #[cfg(feature = "phone")]
+            Error::PhoneCallEnded(_) => io::ErrorKind::ConnectionRefused,
+            #[cfg(feature = "gprs")]
+            Error::JsonSerialisationFailed(_) | Error::RequestBodyParsingFailed(_) => {
+                io::ErrorKind::InvalidData
+            }
+            Error::ParseFailure { .. } | Error::ResponseTooLarge { .. } => {
+                io::ErrorKind::InvalidData
+            }
+            Error::TransportBaudRateUnsupported
+            | Error::UnsupportedByFirmware { .. }
+            | Error::UnsupportedByModem { .. } => io::ErrorKind::Unsupported,
+            Error::Io(ref err) => err.kind(),
+            Error::NotResolved | Error::TaskTimeout(_) => io::ErrorKind::TimedOut,
+            #[cfg(feature = "rpi")]
+            Error::Uart(ref err) => match err.as_ref() {
+                rppal::uart::Error::Io(ref io_err) => io_err.kind(),
+                rppal::uart::Error::InvalidValue => io::ErrorKind::InvalidInput,
+                _ => io::ErrorKind::Other,
+            },
+            #[cfg(feature = "gprs")]
+            Error::UrlParse(_) => io::ErrorKind::InvalidInput,
+            _ => io::ErrorKind::Other,
+        }
+    }
+
     pub fn kind(&self) -> ErrorKind {
         match self {
+            Error::AtCommandFailed(_) => ErrorKind::AtCommandFailed,
+            Error::BluetoothProblem => ErrorKind::BluetoothProblem,
+            Error::ClockNotSet => ErrorKind::ClockNotSet,
+            Error::ClockNotSynchronised => ErrorKind::ClockNotSynchronised,
+            Error::ClockNtpFailed(_) => ErrorKind::ClockNtpFailed,
+            Error::Closed => ErrorKind::Closed,
+            Error::Cme(ref _e) => ErrorKind::Cme,
+            Error::Cms(ref _e) => ErrorKind::Cms,
+            Error::CmuxChannelNotOpened(_) => ErrorKind::CmuxChannelNotOpened,
+            Error::CmuxNotStarted => ErrorKind::CmuxNotStarted,
+            Error::Context { ref error, .. } => error.kind(),
+            Error::FsDeleteFailed => ErrorKind::FsDeleteFailed,
+            Error::FsFileNotFound => ErrorKind::FsFileNotFound,
+            Error::FsWriteFailed => ErrorKind::FsWriteFailed,
+            Error::GnssAssistanceNotLoaded => ErrorKind::GnssAssistanceNotLoaded,
             Error::GnssModuleOff => ErrorKind::GnssModuleOff,
             Error::GnssNotFixed => ErrorKind::GnssNotFixed,
             Error::GnssProblem => ErrorKind::GnssProblem,
             Error::GprsApnConfigSetFailed => ErrorKind::GprsApnConfigSetFailed,
             Error::GprsConnectionCloseFailed => ErrorKind::GprsConnectionCloseFailed,
             Error::GprsConnectionOpenFailed => ErrorKind::GprsConnectionOpenFailed,
+            Error::GprsDownloadFailed(_) => ErrorKind::GprsDownloadFailed,
             Error::GprsHttpRequestFailed => ErrorKind::GprsHttpRequestFailed,
+            Error::GprsLocationNotFound(_) => ErrorKind::GprsLocationNotFound,
             Error::GprsNoConnection => ErrorKind::GprsNoConnection,
+            #[cfg(feature = "rpi")]
+            Error::Gpio(ref _e) => ErrorKind::Gpio,
+            Error::GprsQosConfigFailed => ErrorKind::GprsQosConfigFailed,
             Error::HatAlreadyOff => ErrorKind::HatAlreadyOff,
             Error::HatAlreadyOn => ErrorKind::HatAlreadyOn,
+            Error::HatBandNotSet => ErrorKind::HatBandNotSet,
+            Error::HatBaudRateNotDetected => ErrorKind::HatBaudRateNotDetected,
+            Error::HatBaudRateNotSet => ErrorKind::HatBaudRateNotSet,
+            Error::HatCellInfoNotRead => ErrorKind::HatCellInfoNotRead,
+            Error::HatConfigurationNotRestored => ErrorKind::HatConfigurationNotRestored,
+            Error::HatConfigurationNotSaved => ErrorKind::HatConfigurationNotSaved,
+            Error::HatFunctionalityNotSet => ErrorKind::HatFunctionalityNotSet,
+            Error::HatIdentityNotRead => ErrorKind::HatIdentityNotRead,
+            Error::HatInitFailed => ErrorKind::HatInitFailed,
+            Error::HatJammingDetectionNotSet => ErrorKind::HatJammingDetectionNotSet,
+            Error::HatNetLightNotSet => ErrorKind::HatNetLightNotSet,
+            Error::HatNoPowerPin => ErrorKind::HatNoPowerPin,
+            Error::HatNotBooted => ErrorKind::HatNotBooted,
+            Error::HatNotPoweredDown => ErrorKind::HatNotPoweredDown,
+            Error::HatOperatorNotSelected => ErrorKind::HatOperatorNotSelected,
+            Error::HatRegistrationNotRead => ErrorKind::HatRegistrationNotRead,
+            Error::HatSimPinRejected => ErrorKind::HatSimPinRejected,
+            Error::HatSleepModeNotSet => ErrorKind::HatSleepModeNotSet,
+            Error::Io(ref _e) => ErrorKind::Io,
+            #[cfg(feature = "gprs")]
             Error::JsonSerialisationFailed(ref _e) => ErrorKind::JsonSerialisationFailed,
             Error::NotResolved => ErrorKind::NotResolved,
+            Error::ParseFailure { .. } => ErrorKind::ParseFailure,
+            Error::PhoneAutoAnswerNotSet => ErrorKind::PhoneAutoAnswerNotSet,
+            #[cfg(feature = "phone")]
+            Error::PhoneCallEnded(_) => ErrorKind::PhoneCallEnded,
             Error::PhoneCallNotAnswered => ErrorKind::PhoneCallNotAnswered,
             Error::PhoneCallNotCalled => ErrorKind::PhoneCallNotCalled,
             Error::PhoneCallNotEnded => ErrorKind::PhoneCallNotEnded,
+            #[cfg(feature = "gprs")]
             Error::RequestBodyParsingFailed(ref _e) => ErrorKind::RequestBodyParsingFailed,
+            Error::ResponseTooLarge { .. } => ErrorKind::ResponseTooLarge,
+            Error::SmsDeliveryNotSet => ErrorKind::SmsDeliveryNotSet,
             Error::SmsNotSent => ErrorKind::SmsNotSent,
+            Error::SmsPartNotSent { .. } => ErrorKind::SmsPartNotSent,
             Error::SmsProblemWithReadingMessages => ErrorKind::SmsProblemWithReadingMessages,
             Error::SmsProblemWithSettingTextMode => ErrorKind::SmsProblemWithSettingTextMode,
             Error::SmsRemoveMessageFailed => ErrorKind::SmsRemoveMessageFailed,
+            Error::SystemClockSetFailed => ErrorKind::SystemClockSetFailed,
+            Error::TaskCancelled => ErrorKind::TaskCancelled,
+            Error::TaskTimeout(_) => ErrorKind::TaskTimeout,
             Error::TokioJoinError(ref _e) => ErrorKind::TokioJoinError,
+            Error::TransportBaudRateUnsupported => ErrorKind::TransportBaudRateUnsupported,
+            Error::TransportNotSet => ErrorKind::TransportNotSet,
+            #[cfg(feature = "rpi")]
             Error::Uart(ref _e) => ErrorKind::Uart,
+            Error::UnsupportedByFirmware { .. } => ErrorKind::UnsupportedByFirmware,
+            Error::UnsupportedByModem { .. } => ErrorKind::UnsupportedByModem,
+            #[cfg(feature = "gprs")]
             Error::UrlParse(ref _e) => ErrorKind::UrlParse,
         }
     }
 }
 
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        io::Error::new(err.io_error_kind(), err)
+    }
+}
+
+#[cfg(feature = "rpi")]
 impl From<rppal::uart::Error> for Error {
     fn from(err: rppal::uart::Error) -> Error {
-        Error::Uart(err)
+        Error::Uart(Arc::new(err))
     }
 }
 
+#[cfg(feature = "rpi")]
+impl From<rppal::gpio::Error> for Error {
+    fn from(err: rppal::gpio::Error) -> Error {
+        Error::Gpio(Arc::new(err))
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(Arc::new(err))
+    }
+}
+
+#[cfg(feature = "gprs")]
 impl From<url::ParseError> for Error {
     fn from(err: url::ParseError) -> Error {
         Error::UrlParse(err)
     }
 }
 
+#[cfg(feature = "gprs")]
 impl From<serde_url_params::Error> for Error {
     fn from(err: serde_url_params::Error) -> Error {
-        Error::RequestBodyParsingFailed(err)
+        Error::RequestBodyParsingFailed(Arc::new(err))
     }
 }
 
+#[cfg(feature = "gprs")]
 impl From<serde_json::Error> for Error {
     fn from(err: serde_json::Error) -> Error {
-        Error::JsonSerialisationFailed(err)
+        Error::JsonSerialisationFailed(Arc::new(err))
     }
 }
 
 impl From<tokio::task::JoinError> for Error {
     fn from(err: tokio::task::JoinError) -> Error {
-        Error::TokioJoinError(err)
+        Error::TokioJoinError(Arc::new(err))
+    }
+}
+
+impl CmeError {
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            CmeError::SimBusy
+                | CmeError::NoNetworkService
+                | CmeError::NetworkTimeout
+                | CmeError::Unknown
+        )
+    }
+
+    pub fn from_code(code: u16) -> Self {
+        match code {
+            0 => CmeError::PhoneFailure,
+            1 => CmeError::NoConnectionToPhone,
+            3 => CmeError::OperationNotAllowed,
+            4 => CmeError::OperationNotSupported,
+            5 => CmeError::PhSimPinRequired,
+            10 => CmeError::SimNotInserted,
+            11 => CmeError::SimPinRequired,
+            12 => CmeError::SimPukRequired,
+            13 => CmeError::SimFailure,
+            14 => CmeError::SimBusy,
+            15 => CmeError::SimWrong,
+            16 => CmeError::IncorrectPassword,
+            17 => CmeError::SimPin2Required,
+            18 => CmeError::SimPuk2Required,
+            20 => CmeError::MemoryFull,
+            21 => CmeError::InvalidIndex,
+            22 => CmeError::NotFound,
+            23 => CmeError::MemoryFailure,
+            24 => CmeError::TextStringTooLong,
+            25 => CmeError::InvalidCharactersInTextString,
+            26 => CmeError::DialStringTooLong,
+            27 => CmeError::InvalidCharactersInDialString,
+            30 => CmeError::NoNetworkService,
+            31 => CmeError::NetworkTimeout,
+            32 => CmeError::NetworkNotAllowed,
+            100 => CmeError::Unknown,
+            other => CmeError::Other(other),
+        }
+    }
+
+    pub fn code(&self) -> u16 {
+        match self {
+            CmeError::PhoneFailure => 0,
+            CmeError::NoConnectionToPhone => 1,
+            CmeError::OperationNotAllowed => 3,
+            CmeError::OperationNotSupported => 4,
+            CmeError::PhSimPinRequired => 5,
+            CmeError::SimNotInserted => 10,
+            CmeError::SimPinRequired => 11,
+            CmeError::SimPukRequired => 12,
+            CmeError::SimFailure => 13,
+            CmeError::SimBusy => 14,
+            CmeError::SimWrong => 15,
+            CmeError::IncorrectPassword => 16,
+            CmeError::SimPin2Required => 17,
+            CmeError::SimPuk2Required => 18,
+            CmeError::MemoryFull => 20,
+            CmeError::InvalidIndex => 21,
+            CmeError::NotFound => 22,
+            CmeError::MemoryFailure => 23,
+            CmeError::TextStringTooLong => 24,
+            CmeError::InvalidCharactersInTextString => 25,
+            CmeError::DialStringTooLong => 26,
+            CmeError::InvalidCharactersInDialString => 27,
+            CmeError::NoNetworkService => 30,
+            CmeError::NetworkTimeout => 31,
+            CmeError::NetworkNotAllowed => 32,
+            CmeError::Unknown => 100,
+            CmeError::Other(code) => *code,
+        }
+    }
+}
+
+impl std::fmt::Display for CmeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CmeError::PhoneFailure => write!(f, "phone failure."),
+            CmeError::NoConnectionToPhone => write!(f, "no connection to phone."),
+            CmeError::OperationNotAllowed => write!(f, "operation not allowed."),
+            CmeError::OperationNotSupported => write!(f, "operation not supported."),
+            CmeError::PhSimPinRequired => write!(f, "PH-SIM PIN required."),
+            CmeError::SimNotInserted => write!(f, "SIM not inserted."),
+            CmeError::SimPinRequired => write!(f, "SIM PIN required."),
+            CmeError::SimPukRequired => write!(f, "SIM PUK required."),
+            CmeError::SimFailure => write!(f, "SIM failure."),
+            CmeError::SimBusy => write!(f, "SIM busy."),
+            CmeError::SimWrong => write!(f, "SIM wrong."),
+            CmeError::IncorrectPassword => write!(f, "incorrect password."),
+            CmeError::SimPin2Required => write!(f, "SIM PIN2 required."),
+            CmeError::SimPuk2Required => write!(f, "SIM PUK2 required."),
+            CmeError::MemoryFull => write!(f, "memory full."),
+            CmeError::InvalidIndex => write!(f, "invalid index."),
+            CmeError::NotFound => write!(f, "not found."),
+            CmeError::MemoryFailure => write!(f, "memory failure."),
+            CmeError::TextStringTooLong => write!(f, "text string too long."),
+            CmeError::InvalidCharactersInTextString => {
+                write!(f, "invalid characters in text string.")
+            }
+            CmeError::DialStringTooLong => write!(f, "dial string too long."),
+            CmeError::InvalidCharactersInDialString => {
+                write!(f, "invalid characters in dial string.")
+            }
+            CmeError::NoNetworkService => write!(f, "no network service."),
+            CmeError::NetworkTimeout => write!(f, "network timeout."),
+            CmeError::NetworkNotAllowed => write!(f, "network not allowed - emergency calls only."),
+            CmeError::Unknown => write!(f, "unknown error."),
+            CmeError::Other(_) => write!(f, "unrecognised error code."),
+        }
+    }
+}
+
+impl CmsError {
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            CmsError::MeFailure
+                | CmsError::SimBusy
+                | CmsError::NoNetworkService
+                | CmsError::NetworkTimeout
+                | CmsError::Unknown
+        )
+    }
+
+    pub fn from_code(code: u16) -> Self {
+        match code {
+            300 => CmsError::MeFailure,
+            301 => CmsError::SmsServiceReserved,
+            302 => CmsError::OperationNotAllowed,
+            303 => CmsError::OperationNotSupported,
+            304 => CmsError::InvalidPduModeParameter,
+            305 => CmsError::InvalidTextModeParameter,
+            310 => CmsError::SimNotInserted,
+            311 => CmsError::SimPinRequired,
+            313 => CmsError::SimFailure,
+            314 => CmsError::SimBusy,
+            315 => CmsError::SimWrong,
+            316 => CmsError::SimPukRequired,
+            320 => CmsError::MemoryFailure,
+            321 => CmsError::InvalidMemoryIndex,
+            322 => CmsError::MemoryFull,
+            330 => CmsError::SmscAddressUnknown,
+            331 => CmsError::NoNetworkService,
+            332 => CmsError::NetworkTimeout,
+            500 => CmsError::Unknown,
+            other => CmsError::Other(other),
+        }
+    }
+
+    pub fn code(&self) -> u16 {
+        match self {
+            CmsError::MeFailure => 300,
+            CmsError::SmsServiceReserved => 301,
+            CmsError::OperationNotAllowed => 302,
+            CmsError::OperationNotSupported => 303,
+            CmsError::InvalidPduModeParameter => 304,
+            CmsError::InvalidTextModeParameter => 305,
+            CmsError::SimNotInserted => 310,
+            CmsError::SimPinRequired => 311,
+            CmsError::SimFailure => 313,
+            CmsError::SimBusy => 314,
+            CmsError::SimWrong => 315,
+            CmsError::SimPukRequired => 316,
+            CmsError::MemoryFailure => 320,
+            CmsError::InvalidMemoryIndex => 321,
+            CmsError::MemoryFull => 322,
+            CmsError::SmscAddressUnknown => 330,
+            CmsError::NoNetworkService => 331,
+            CmsError::NetworkTimeout => 332,
+            CmsError::Unknown => 500,
+            CmsError::Other(code) => *code,
+        }
+    }
+}
+
+impl std::fmt::Display for CmsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CmsError::MeFailure => write!(f, "ME failure."),
+            CmsError::SmsServiceReserved => write!(f, "SMS service of ME reserved."),
+            CmsError::OperationNotAllowed => write!(f, "operation not allowed."),
+            CmsError::OperationNotSupported => write!(f, "operation not supported."),
+            CmsError::InvalidPduModeParameter => write!(f, "invalid PDU mode parameter."),
+            CmsError::InvalidTextModeParameter => write!(f, "invalid text mode parameter."),
+            CmsError::SimNotInserted => write!(f, "SIM not inserted."),
+            CmsError::SimPinRequired => write!(f, "SIM PIN required."),
+            CmsError::SimFailure => write!(f, "SIM failure."),
+            CmsError::SimBusy => write!(f, "SIM busy."),
+            CmsError::SimWrong => write!(f, "SIM wrong."),
+            CmsError::SimPukRequired => write!(f, "SIM PUK required."),
+            CmsError::MemoryFailure => write!(f, "memory failure."),
+            CmsError::InvalidMemoryIndex => write!(f, "invalid memory index."),
+            CmsError::MemoryFull => write!(f, "memory full."),
+            CmsError::SmscAddressUnknown => write!(f, "SMSC address unknown."),
+            CmsError::NoNetworkService => write!(f, "no network service."),
+            CmsError::NetworkTimeout => write!(f, "network timeout."),
+            CmsError::Unknown => write!(f, "unknown error."),
+            CmsError::Other(_) => write!(f, "unrecognised error code."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        at::ResponsePattern,
+        testing::{MockResponse, MockTransport},
+        LogLevelFilter, SIM868,
+    };
+    use std::time::Duration;
+
+    const CME_CODES: &[u16] = &[
+        0, 1, 3, 4, 5, 10, 11, 12, 13, 14, 15, 16, 17, 18, 20, 21, 22, 23, 24, 25, 26, 27, 30, 31,
+        32, 100,
+    ];
+    const CMS_CODES: &[u16] = &[
+        300, 301, 302, 303, 304, 305, 310, 311, 313, 314, 315, 316, 320, 321, 322, 330, 331, 332,
+        500,
+    ];
+
+    #[test]
+    fn cme_codes_round_trip() {
+        for code in CME_CODES {
+            let err: CmeError = CmeError::from_code(*code);
+            assert!(!matches!(err, CmeError::Other(_)), "{code}");
+            assert_eq!(err.code(), *code);
+        }
+        assert_eq!(CmeError::from_code(2), CmeError::Other(2));
+        assert_eq!(CmeError::from_code(2).code(), 2);
+        assert_eq!(CmeError::from_code(10), CmeError::SimNotInserted);
+        assert_eq!(CmeError::from_code(30), CmeError::NoNetworkService);
+    }
+
+    #[test]
+    fn cms_codes_round_trip() {
+        for code in CMS_CODES {
+            let err: CmsError = CmsError::from_code(*code);
+            assert!(!matches!(err, CmsError::Other(_)), "{code}");
+            assert_eq!(err.code(), *code);
+        }
+        assert_eq!(CmsError::from_code(312), CmsError::Other(312));
+        assert_eq!(CmsError::from_code(312).code(), 312);
+        assert_eq!(CmsError::from_code(310), CmsError::SimNotInserted);
+        assert_eq!(CmsError::from_code(330), CmsError::SmscAddressUnknown);
+    }
+
+    async fn device_error(response: MockResponse) -> Error {
+        let mock: MockTransport = MockTransport::new();
+        mock.on("AT+CPIN?", response);
+        let sim: SIM868 = SIM868::with_transport(mock, LogLevelFilter::Off);
+        sim.at
+            .send_now(
+                "AT+CPIN?",
+                ResponsePattern::Contains("+CPIN:".into()),
+                Some(Duration::from_secs(1)),
+            )
+            .await
+            .unwrap_err()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn maps_device_errors() {
+        let cme: Error = device_error(MockResponse::cme(10)).await;
+        assert_eq!(cme.kind(), ErrorKind::Cme);
+        assert!(matches!(cme.root(), Error::Cme(CmeError::SimNotInserted)));
+
+        let cms: Error = device_error(MockResponse::cms(310)).await;
+        assert_eq!(cms.kind(), ErrorKind::Cms);
+        assert!(matches!(cms.root(), Error::Cms(CmsError::SimNotInserted)));
+
+        let other: Error = device_error(MockResponse::cme(999)).await;
+        assert!(matches!(other.root(), Error::Cme(CmeError::Other(999))));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn compares_errors_structurally() {
+        let cme: Error = device_error(MockResponse::cme(14)).await;
+        assert!(matches!(cme, Error::Context { .. }));
+        assert_eq!(cme, Error::Cme(CmeError::SimBusy));
+        assert_ne!(cme, Error::Cme(CmeError::SimNotInserted));
+        assert_ne!(cme, Error::Cms(CmsError::Other(14)));
+
+        let io = |kind| Error::Io(Arc::new(io::Error::new(kind, "transport")));
+        assert_eq!(io(io::ErrorKind::TimedOut), io(io::ErrorKind::TimedOut));
+        assert_ne!(io(io::ErrorKind::TimedOut), io(io::ErrorKind::BrokenPipe));
+
+        let part = |error| Error::SmsPartNotSent {
+            sent: 1,
+            parts: 3,
+            error: Box::new(error),
+        };
+        assert_eq!(part(Error::SmsNotSent), part(Error::SmsNotSent));
+        assert_ne!(part(Error::SmsNotSent), part(Error::NotResolved));
+        assert_eq!(Error::TaskCancelled, Error::TaskCancelled);
+        assert_ne!(Error::TaskCancelled, Error::Closed);
     }
 }
@@ -0,0 +1,636 @@
+//! Events module
+//!
+//! Unsolicited result codes (URCs) sent by the modem, eg. `RING` or `+CMTI`, are removed from the responses
+//! handed to the resolvers and published as [`Event`]s instead. Subscribe with [`crate::SIM868::events`].
+//!
+//! While no task is running, a background thread reads the serial port, so the URCs arriving between the tasks
+//! are published too, instead of being flushed by the next command. The tasks resolving on the URCs (eg.
+//! [`crate::phone::Phone::get_incoming_call`]) only see the ones arriving while they run - subscribe to the
+//! events to catch the rest.
+//!
+//! ```no_run
+//! # use rpi_sim868::{events::Event, SIM868};
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! # #[tokio::main]
+//! # async fn main() {
+//! # let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//! let mut events = sim.events();
+//! while let Ok(event) = events.recv().await {
+//!     if let Event::SmsReceived { index, .. } = event {
+//!         println!("New SMS at index {index}");
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::{serial_port::SerialPort, REGEX_COMP_ERROR, URC_CLIP_REGEX};
+use lazy_static::lazy_static;
+use regex::Regex;
+use tokio::sync::broadcast::Sender;
+
+/// Number of events buffered for every subscriber before the oldest ones are dropped.
+pub(crate) const EVENTS_CAPACITY: usize = 64;
+/// Number of NMEA sentences buffered for every subscriber, the modem sends a dozen of them every second.
+#[cfg(feature = "gnss")]
+pub(crate) const NMEA_CAPACITY: usize = 256;
+
+lazy_static! {
+    static ref URC_CMTI_REGEX: Regex =
+        Regex::new(r#"^\+CMTI: "(?<storage>[^"]*)",(?<index>\d+)$"#).expect(REGEX_COMP_ERROR);
+    static ref URC_BTCONNECTING_REGEX: Regex =
+        Regex::new(r#"^\+BTCONNECTING: "(?<address>[^"]*)","(?<profile>[^"]*)"$"#)
+            .expect(REGEX_COMP_ERROR);
+    static ref URC_BTCONNECT_REGEX: Regex = Regex::new(
+        r#"^\+BTCONNECT: (?<id>\d+),"(?<name>[^"]*)",(?<address>[0-9A-Fa-f:]+),"(?<profile>[^"]*)"$"#
+    )
+    .expect(REGEX_COMP_ERROR);
+    static ref URC_BTDISCONN_REGEX: Regex =
+        Regex::new(r#"^\+BTDISCONN: "(?<name>[^"]*)",(?<address>[0-9A-Fa-f:]+),"(?<profile>[^"]*)"$"#)
+            .expect(REGEX_COMP_ERROR);
+    static ref URC_BTPAIRING_REGEX: Regex = Regex::new(
+        r#"^\+BTPAIRING: "(?<name>[^"]*)",(?<address>[0-9A-Fa-f:]+)(?:,(?<passkey>\d+))?$"#
+    )
+    .expect(REGEX_COMP_ERROR);
+    static ref URC_BTPAIR_REGEX: Regex =
+        Regex::new(r#"^\+BTPAIR: (?<id>\d+),"(?<name>[^"]*)",(?<address>[0-9A-Fa-f:]+)$"#)
+            .expect(REGEX_COMP_ERROR);
+    static ref URC_BTSPPDATA_REGEX: Regex =
+        Regex::new(r"^\+BTSPPDATA: (?<id>\d+),\d+,(?<data>.*)$").expect(REGEX_COMP_ERROR);
+}
+
+#[cfg(feature = "sms")]
+lazy_static! {
+    static ref URC_CMT_REGEX: Regex = Regex::new(r"^\+CMT: (?<data>.+)$").expect(REGEX_COMP_ERROR);
+}
+
+#[cfg(feature = "gnss")]
+lazy_static! {
+    static ref URC_UGNSINF_REGEX: Regex =
+        Regex::new(r"^\+UGNSINF: (?<data>.+)$").expect(REGEX_COMP_ERROR);
+    /// GPS, GLONASS, Galileo, BeiDou or the combined (`GN`) talker, and the checksum.
+    static ref NMEA_SENTENCE_REGEX: Regex =
+        Regex::new(r"^\$G[PNLAB][A-Z]{3},[^*]*\*[0-9A-Fa-f]{2}$").expect(REGEX_COMP_ERROR);
+}
+
+/// Event published on the modem's unsolicited result code, or by the [watchdog](crate::watchdog) and the
+/// [battery monitor](crate::battery).
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Event {
+    /// `RING` - incoming call.
+    Ring,
+    /// `NO CARRIER`, `BUSY`, `NO ANSWER` or `NO DIALTONE` - the call has ended, or couldn't be made.
+    #[cfg(feature = "phone")]
+    CallEnded(crate::phone::CallEndReason),
+    /// `+CLIP` - caller identification of the incoming call.
+    IncomingCall {
+        caller_id: String,
+    },
+    /// `+CMTI` - new SMS has been stored at the `index` of the `storage`.
+    SmsReceived {
+        storage: String,
+        index: u16,
+    },
+    /// `+CMT` - new SMS has been delivered without being stored, see `SMS::set_direct_delivery`.
+    #[cfg(feature = "sms")]
+    SmsDelivered(crate::sms::Message),
+    UnderVoltageWarning,
+    UnderVoltagePowerDown,
+    OverVoltageWarning,
+    OverVoltagePowerDown,
+    /// `NORMAL POWER DOWN`
+    PowerDown,
+    /// [`crate::hat::Hat::turn_on`] has toggled the power, the modem is booting.
+    PowerKeyToggled,
+    /// `RDY` - the modem has booted.
+    Ready,
+    CallReady,
+    SmsReady,
+    /// `*PSUTTZ` - the network has updated the time of the RTC, see `Clock::enable_network_time_sync` (`clock`
+    /// feature).
+    NetworkTimeUpdated,
+    /// `+SJDR: JAMMING DETECTED` - the GSM link is being jammed, see [`crate::hat::Hat::enable_jamming_detect`].
+    JammingDetected,
+    /// `+SJDR: INTERFERENCE DETECTED` - interference too weak to be reported as jamming.
+    InterferenceDetected,
+    /// `+SJDR: NO JAMMING` - the jamming has stopped.
+    JammingCleared,
+    /// `+UGNSINF` - the position reported by the modem, see [`crate::gnss::GNSS::subscribe`]. The reports
+    /// without a fix aren't published.
+    #[cfg(feature = "gnss")]
+    GnssFix(crate::gnss::GNSSData),
+    /// The device has entered the `fence` of the [`crate::gnss::Geofence`], `fix` is the first position inside.
+    #[cfg(feature = "gnss")]
+    GeofenceEntered {
+        fence: String,
+        fix: crate::gnss::GNSSData,
+    },
+    /// The device has left the `fence`, `fix` is the first position outside.
+    #[cfg(feature = "gnss")]
+    GeofenceExited {
+        fence: String,
+        fix: crate::gnss::GNSSData,
+    },
+    /// The device has stayed in the `fence` for its dwell time, published once per stay.
+    #[cfg(feature = "gnss")]
+    GeofenceDwell {
+        fence: String,
+        fix: crate::gnss::GNSSData,
+        /// Time since the fence has been entered.
+        dwell: std::time::Duration,
+    },
+    /// `+BTCONNECTING` - the device at `address` asks to connect, accept it with
+    /// `Bluetooth::accept` (`bluetooth` feature).
+    BluetoothConnectionRequest {
+        address: String,
+        profile: String,
+    },
+    /// `+BTCONNECT` - the connection `id` with the device has been established.
+    BluetoothConnected {
+        id: u8,
+        name: String,
+        address: String,
+        profile: String,
+    },
+    /// `+BTDISCONN` - the connection with the device has been closed.
+    BluetoothDisconnected {
+        name: String,
+        address: String,
+        profile: String,
+    },
+    /// `+BTPAIRING` - the device asks to pair. Compare the `passkey` with the one shown by the device, or enter
+    /// the PIN if there is none, and answer with `Bluetooth::accept_pairing` or `Bluetooth::reject_pairing`.
+    BluetoothPairingRequest {
+        name: String,
+        address: String,
+        passkey: Option<String>,
+    },
+    /// `+BTPAIR` - the device has been paired, `id` is its id in `Bluetooth::paired_devices`.
+    BluetoothPaired {
+        id: u8,
+        name: String,
+        address: String,
+    },
+    /// `+BTSPPDATA` - `data` has been received over the SPP connection `id`.
+    BluetoothData {
+        id: u8,
+        data: String,
+    },
+    /// The battery `charge` (%) has dropped below the threshold of [`crate::battery::BatteryMonitor`].
+    LowBattery {
+        charge: u8,
+    },
+    /// The supply `voltage` (mV) has dropped below the threshold of [`crate::battery::BatteryMonitor`].
+    UnderVoltage {
+        voltage: u16,
+    },
+    /// The modem has stopped responding and [`crate::watchdog::ModemWatchdog`] has recovered it.
+    Recovered,
+    /// Recovering the modem which has stopped responding has failed, see [`crate::watchdog::ModemWatchdog`].
+    Unreachable,
+}
+
+/// Matches a single response line against the known URCs.
+pub(crate) fn parse_urc(line: &str) -> Option<Event> {
+    let event: Event = match line {
+        "RING" => Event::Ring,
+        "UNDER-VOLTAGE WARNNING" | "UNDER-VOLTAGE WARNING" => Event::UnderVoltageWarning,
+        "UNDER-VOLTAGE POWER DOWN" => Event::UnderVoltagePowerDown,
+        "OVER-VOLTAGE WARNNING" | "OVER-VOLTAGE WARNING" => Event::OverVoltageWarning,
+        "OVER-VOLTAGE POWER DOWN" => Event::OverVoltagePowerDown,
+        "RDY" => Event::Ready,
+        "Call Ready" => Event::CallReady,
+        "SMS Ready" => Event::SmsReady,
+        _ if line.starts_with("*PSUTTZ:") => Event::NetworkTimeUpdated,
+        "+SJDR: JAMMING DETECTED" => Event::JammingDetected,
+        "+SJDR: INTERFERENCE DETECTED" => Event::InterferenceDetected,
+        "+SJDR: NO JAMMING" => Event::JammingCleared,
+        _ => {
+            if let Some(captured) = URC_BTCONNECTING_REGEX.captures(line) {
+                return Some(Event::BluetoothConnectionRequest {
+                    address: captured["address"].to_string(),
+                    profile: captured["profile"].to_string(),
+                });
+            }
+            if let Some(captured) = URC_BTPAIRING_REGEX.captures(line) {
+                return Some(Event::BluetoothPairingRequest {
+                    name: captured["name"].to_string(),
+                    address: captured["address"].to_string(),
+                    passkey: captured
+                        .name("passkey")
+                        .map(|passkey: regex::Match<'_>| passkey.as_str().to_string()),
+                });
+            }
+            if let Some(captured) = URC_BTPAIR_REGEX.captures(line) {
+                return Some(Event::BluetoothPaired {
+                    id: captured["id"].parse().ok()?,
+                    name: captured["name"].to_string(),
+                    address: captured["address"].to_string(),
+                });
+            }
+            if let Some(captured) = URC_BTDISCONN_REGEX.captures(line) {
+                return Some(Event::BluetoothDisconnected {
+                    name: captured["name"].to_string(),
+                    address: captured["address"].to_string(),
+                    profile: captured["profile"].to_string(),
+                });
+            }
+            let captured: regex::Captures<'_> = URC_CMTI_REGEX.captures(line)?;
+            Event::SmsReceived {
+                storage: captured["storage"].to_string(),
+                index: captured["index"].parse().ok()?,
+            }
+        }
+    };
+
+    Some(event)
+}
+
+/// Matches a single response line against the URCs the tasks resolve on, which are kept in the text.
+fn parse_resolved_urc(line: &str) -> Option<Event> {
+    if line == "NORMAL POWER DOWN" {
+        return Some(Event::PowerDown);
+    }
+    #[cfg(feature = "phone")]
+    if let Some(reason) = crate::phone::CallEndReason::from_result(line) {
+        return Some(Event::CallEnded(reason));
+    }
+    if let Some(captured) = URC_CLIP_REGEX.captures(line) {
+        let data: Vec<&str> = captured["data"].split(',').collect();
+        return Some(Event::IncomingCall {
+            caller_id: data[0].replace('"', ""),
+        });
+    }
+    if let Some(captured) = URC_BTCONNECT_REGEX.captures(line) {
+        return Some(Event::BluetoothConnected {
+            id: captured["id"].parse().ok()?,
+            name: captured["name"].to_string(),
+            address: captured["address"].to_string(),
+            profile: captured["profile"].to_string(),
+        });
+    }
+    let captured: regex::Captures<'_> = URC_BTSPPDATA_REGEX.captures(line)?;
+    Some(Event::BluetoothData {
+        id: captured["id"].parse().ok()?,
+        data: captured["data"].to_string(),
+    })
+}
+
+/// Body of a response which isn't matched against the URCs, as it's data - eg. the text of SMS could be `RING`.
+#[derive(Debug, Default)]
+enum Payload {
+    #[default]
+    None,
+    /// Texts of the messages listed (`+CMGL`) or read (`+CMGR`), until the final result code.
+    #[cfg(feature = "sms")]
+    StoredMessages,
+    /// Text of the delivered message following the `+CMT` header, see [`UrcFilter`].
+    #[cfg(feature = "sms")]
+    DeliveredMessage { header: String, text: String },
+    /// Bytes of the HTTP response (`+HTTPREAD: <length>`) yet to come.
+    #[cfg(feature = "gprs")]
+    HttpData(usize),
+}
+
+/// Removes the URC lines from the modem output, publishing them as events of the `serial_port`. While the NMEA
+/// output is on, the NMEA sentences are removed too, and published on their own channel (see
+/// [`crate::gnss::GNSS::nmea_stream`]).
+///
+/// The final call result codes, `+CLIP`, `+BTCONNECT`, `+BTSPPDATA` and `+CMT` are published but kept in the
+/// text, as the tasks (eg. [`crate::phone::Phone::get_incoming_call`]) resolve on them.
+///
+/// The payload following the headers of `+CMGL`, `+CMGR`, `+CMT` and `+HTTPREAD` is passed through as it is,
+/// so the filter keeps track of it across the reads - until the next command is written.
+///
+/// The line breaks of the `+CMT` text are `\n`, the modem ends the text with `\r\n`. The text runs until the
+/// blank line starting whatever the modem sends next (eg. `\r\nRING\r\n`), or until the end of the data
+/// received so far once its last line is ended with `\r\n`.
+#[derive(Debug, Default)]
+pub(crate) struct UrcFilter {
+    payload: Payload,
+}
+
+impl UrcFilter {
+    /// Forgets the payload being read, once the next command has been written.
+    pub(crate) fn reset(&mut self) {
+        self.payload = Payload::None;
+    }
+
+    /// Filters the complete `text` lines read, see [`UrcFilter`].
+    pub(crate) fn filter(&mut self, text: &str, serial_port: &SerialPort) -> String {
+        let events: &Sender<Event> = &serial_port.events;
+        #[cfg(feature = "gnss")]
+        let nmea_output: bool = serial_port.state().nmea_output == Some(true);
+        let mut filtered: String = String::with_capacity(text.len());
+
+        for line in text.split_inclusive('\n') {
+            let trimmed: &str = line.trim();
+
+            match std::mem::take(&mut self.payload) {
+                Payload::None => (),
+                #[cfg(feature = "sms")]
+                Payload::StoredMessages => {
+                    if !is_final_result(trimmed) {
+                        self.payload = Payload::StoredMessages;
+                        filtered.push_str(line);
+                        continue;
+                    }
+                }
+                #[cfg(feature = "sms")]
+                Payload::DeliveredMessage { header, mut text } => match line {
+                    "\r\n" if text.ends_with("\r\n") => deliver_message(&header, &text, events),
+                    _ => {
+                        text.push_str(line);
+                        self.payload = Payload::DeliveredMessage { header, text };
+                        filtered.push_str(line);
+                        continue;
+                    }
+                },
+                #[cfg(feature = "gprs")]
+                Payload::HttpData(length) => {
+                    // the line break following the data ends its last line
+                    if line.len() < length {
+                        self.payload = Payload::HttpData(length - line.len());
+                    }
+                    filtered.push_str(line);
+                    continue;
+                }
+            }
+
+            #[cfg(feature = "sms")]
+            {
+                if let Some(captured) = URC_CMT_REGEX.captures(trimmed) {
+                    self.payload = Payload::DeliveredMessage {
+                        header: captured["data"].to_string(),
+                        text: String::new(),
+                    };
+                    filtered.push_str(line);
+                    continue;
+                }
+                if trimmed.starts_with("+CMGL:") || trimmed.starts_with("+CMGR:") {
+                    self.payload = Payload::StoredMessages;
+                    filtered.push_str(line);
+                    continue;
+                }
+            }
+
+            #[cfg(feature = "gprs")]
+            if let Some(length) = trimmed.strip_prefix("+HTTPREAD:") {
+                if let Ok(length) = length.trim().parse::<usize>() {
+                    self.payload = Payload::HttpData(length);
+                }
+                filtered.push_str(line);
+                continue;
+            }
+
+            #[cfg(feature = "gnss")]
+            if nmea_output && NMEA_SENTENCE_REGEX.is_match(trimmed) {
+                // no subscribers is not an error
+                let _ = serial_port.nmea.send(trimmed.to_string());
+                continue;
+            }
+
+            #[cfg(feature = "gnss")]
+            if let Some(captured) = URC_UGNSINF_REGEX.captures(trimmed) {
+                match crate::gnss::parse_gnss_info(&captured["data"]) {
+                    Ok(fix) => {
+                        let _ = events.send(Event::GnssFix(fix));
+                    }
+                    Err(e) => log::debug!("URC received: +UGNSINF without a fix ({e})"),
+                }
+                continue;
+            }
+
+            if let Some(event) = parse_resolved_urc(trimmed) {
+                log::debug!("URC received: {event:?}");
+                let _ = events.send(event);
+            } else if let Some(event) = parse_urc(trimmed) {
+                log::debug!("URC received: {event:?}");
+                // no subscribers is not an error
+                let _ = events.send(event);
+                continue;
+            }
+
+            filtered.push_str(line);
+        }
+
+        #[cfg(feature = "sms")]
+        if let Payload::DeliveredMessage { header, text } = &self.payload {
+            if text.ends_with("\r\n") {
+                deliver_message(header, text, events);
+                self.payload = Payload::None;
+            }
+        }
+
+        filtered
+    }
+}
+
+/// Publishes the message delivered with `+CMT`.
+#[cfg(feature = "sms")]
+fn deliver_message(header: &str, text: &str, events: &Sender<Event>) {
+    match crate::sms::parse_delivered_message(header, text) {
+        Ok(message) => {
+            log::debug!("URC received: +CMT from {}", message.sender);
+            let _ = events.send(Event::SmsDelivered(message));
+        }
+        Err(e) => log::warn!("Unable to parse the delivered message: {e}"),
+    }
+}
+
+/// Whether the `line` is the final result code ending the response.
+#[cfg(feature = "sms")]
+fn is_final_result(line: &str) -> bool {
+    matches!(line, "OK" | "ERROR")
+        || line.starts_with("+CMS ERROR:")
+        || line.starts_with("+CME ERROR:")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{profile::ModemProfile, testing::MockTransport, timeouts::CommandTimeouts};
+    use tokio::sync::broadcast::Receiver;
+
+    fn serial_port() -> SerialPort {
+        SerialPort::new(
+            Box::new(MockTransport::new()),
+            ModemProfile::default(),
+            CommandTimeouts::default(),
+        )
+    }
+
+    fn published(events: &mut Receiver<Event>) -> Vec<Event> {
+        std::iter::from_fn(|| events.try_recv().ok()).collect()
+    }
+
+    #[test]
+    fn parses_urcs() {
+        assert_eq!(parse_urc("RING"), Some(Event::Ring));
+        assert_eq!(
+            parse_urc("UNDER-VOLTAGE WARNNING"),
+            Some(Event::UnderVoltageWarning)
+        );
+        assert_eq!(
+            parse_urc(r#"+CMTI: "SM",12"#),
+            Some(Event::SmsReceived {
+                storage: "SM".to_string(),
+                index: 12
+            })
+        );
+        assert_eq!(
+            parse_urc(r#"+BTPAIRING: "Phone",A4:50:46:1C:2B:3D,123456"#),
+            Some(Event::BluetoothPairingRequest {
+                name: "Phone".to_string(),
+                address: "A4:50:46:1C:2B:3D".to_string(),
+                passkey: Some("123456".to_string())
+            })
+        );
+        assert_eq!(parse_urc(r#"+CMTI: "SM",x"#), None);
+        assert_eq!(parse_urc("OK"), None);
+        // the URCs the tasks resolve on aren't removed from the text
+        assert_eq!(parse_urc("NORMAL POWER DOWN"), None);
+        assert_eq!(
+            parse_resolved_urc("NORMAL POWER DOWN"),
+            Some(Event::PowerDown)
+        );
+        assert_eq!(
+            parse_resolved_urc(r#"+CLIP: "+4799999999",145,"",0,"",0"#),
+            Some(Event::IncomingCall {
+                caller_id: "+4799999999".to_string()
+            })
+        );
+        assert_eq!(
+            parse_resolved_urc("+BTSPPDATA: 1,5,hello"),
+            Some(Event::BluetoothData {
+                id: 1,
+                data: "hello".to_string()
+            })
+        );
+        assert_eq!(parse_resolved_urc("RING"), None);
+    }
+
+    #[test]
+    fn filters_urcs() {
+        let serial_port: SerialPort = serial_port();
+        let mut events: Receiver<Event> = serial_port.events.subscribe();
+        let mut filter: UrcFilter = UrcFilter::default();
+
+        assert_eq!(
+            filter.filter("\r\nRING\r\n\r\nNORMAL POWER DOWN\r\n", &serial_port),
+            "\r\n\r\nNORMAL POWER DOWN\r\n"
+        );
+        assert_eq!(published(&mut events), [Event::Ring, Event::PowerDown]);
+    }
+
+    #[cfg(feature = "phone")]
+    #[test]
+    fn parses_call_results() {
+        assert_eq!(
+            parse_resolved_urc("BUSY"),
+            Some(Event::CallEnded(crate::phone::CallEndReason::Busy))
+        );
+        assert_eq!(
+            parse_resolved_urc("NO CARRIER"),
+            Some(Event::CallEnded(crate::phone::CallEndReason::NoCarrier))
+        );
+    }
+
+    #[cfg(all(feature = "sms", feature = "gprs"))]
+    #[test]
+    fn filters_interleaved_urcs() {
+        let serial_port: SerialPort = serial_port();
+        let mut events: Receiver<Event> = serial_port.events.subscribe();
+        let mut filter: UrcFilter = UrcFilter::default();
+        let header: &str = r#"+CMT: "+4799999999","","24/01/15,12:30:45+04""#;
+
+        let filtered: Vec<String> = [
+            "\r\nRING\r\n\r\n+CMTI: \"SM\",3\r\n".to_string(),
+            format!("\r\nUNDER-VOLTAGE WARNNING\r\n\r\n{header}\r\n"),
+            // the text of the delivered message isn't matched against the URCs
+            "RING\r\n".to_string(),
+            "\r\n+HTTPREAD: 6\r\n".to_string(),
+            // neither is the HTTP response
+            "RING\r\nOK\r\n".to_string(),
+            "\r\nRING\r\n".to_string(),
+        ]
+        .iter()
+        .map(|text: &String| filter.filter(text, &serial_port))
+        .collect();
+        assert_eq!(
+            filtered,
+            [
+                "\r\n\r\n".to_string(),
+                format!("\r\n\r\n{header}\r\n"),
+                "RING\r\n".to_string(),
+                "\r\n+HTTPREAD: 6\r\n".to_string(),
+                "RING\r\nOK\r\n".to_string(),
+                "\r\n".to_string(),
+            ]
+        );
+
+        let published: Vec<Event> = published(&mut events);
+        assert_eq!(published.len(), 5);
+        assert_eq!(
+            published[..3],
+            [
+                Event::Ring,
+                Event::SmsReceived {
+                    storage: "SM".to_string(),
+                    index: 3
+                },
+                Event::UnderVoltageWarning,
+            ]
+        );
+        match &published[3] {
+            Event::SmsDelivered(message) => {
+                assert_eq!((message.index, message.text.as_str()), (None, "RING"));
+                assert_eq!(message.sender, "+4799999999");
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+        assert_eq!(published[4], Event::Ring);
+    }
+
+    #[cfg(feature = "sms")]
+    #[test]
+    fn passes_listed_messages_through() {
+        let serial_port: SerialPort = serial_port();
+        let mut events: Receiver<Event> = serial_port.events.subscribe();
+        let mut filter: UrcFilter = UrcFilter::default();
+        let listed: &str =
+            "\r\n+CMGL: 1,\"REC READ\",\"+4799999999\",\"\",\"24/01/15,12:30:45+04\"\r\nRING\r\n";
+
+        assert_eq!(filter.filter(listed, &serial_port), listed);
+        assert_eq!(
+            filter.filter("UNDER-VOLTAGE WARNNING\r\n\r\nOK\r\n", &serial_port),
+            "UNDER-VOLTAGE WARNNING\r\n\r\nOK\r\n"
+        );
+        assert!(published(&mut events).is_empty());
+        // the final result code ends the texts
+        assert_eq!(filter.filter("\r\nRING\r\n", &serial_port), "\r\n");
+        assert_eq!(published(&mut events), [Event::Ring]);
+    }
+
+    #[cfg(feature = "gnss")]
+    #[test]
+    fn routes_nmea_sentences() {
+        let serial_port: SerialPort = serial_port();
+        let mut nmea: Receiver<String> = serial_port.nmea.subscribe();
+        let mut filter: UrcFilter = UrcFilter::default();
+        let sentence: &str = "$GNGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47";
+        let text: String = format!("{sentence}\r\n\r\nOK\r\n");
+
+        // kept while the NMEA output is off
+        assert_eq!(filter.filter(&text, &serial_port), text);
+        assert!(nmea.try_recv().is_err());
+
+        serial_port.update_state(|state| state.nmea_output = Some(true));
+        assert_eq!(filter.filter(&text, &serial_port), "\r\nOK\r\n");
+        assert_eq!(nmea.try_recv().unwrap(), sentence);
+        assert!(nmea.try_recv().is_err());
+    }
+}
@@ -0,0 +1,282 @@
+//! FFI module
+//!
+//! Available with the `ffi` feature. The shared library (`librpi_sim868.so`) is built with
+//! `cargo rustc --release --features ffi --crate-type cdylib`.
+//!
+//! Minimal C API for sending SMS, reading the GNSS fix and making HTTP POST requests, so the existing C or Python
+//! (ctypes, cffi) software can reuse the crate. The header is at `include/rpi_sim868.h`, regenerate it with
+//! `cbindgen --config cbindgen.toml --output include/rpi_sim868.h`.
+//!
+//! Functions block until the task is done and return [`SIM868_OK`] or a negative error code. The message of the
+//! last error on the calling thread is available through [`sim868_last_error`].
+
+use crate::{
+    error::Error,
+    gnss::GNSSData,
+    gprs::{ApnConfig, ContentType, Request, RequestMethod},
+    SIM868,
+};
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    ptr,
+};
+
+pub const SIM868_OK: i32 = 0;
+/// Null pointer or a string which isn't valid UTF-8 (or JSON in [`sim868_http_post`]) has been passed.
+pub const SIM868_ERROR_INVALID_ARGUMENT: i32 = -1;
+/// The task has failed, see [`sim868_last_error`].
+pub const SIM868_ERROR_FAILED: i32 = -2;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// GNSS fix returned by [`sim868_gnss_get_fix`], see [`GNSSData`].
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct Sim868GnssFix {
+    pub lat: f64,
+    pub lon: f64,
+    /// NaN if the modem hasn't reported it, as the speed and the course.
+    pub alt: f32,
+    pub ground_speed: f32,
+    pub ground_course: f32,
+    pub sats_in_view: u8,
+    pub sats_in_use: u8,
+    /// UTC Unix timestamp in seconds.
+    pub timestamp: i64,
+}
+
+impl From<GNSSData> for Sim868GnssFix {
+    fn from(data: GNSSData) -> Self {
+        Sim868GnssFix {
+            lat: data.lat,
+            lon: data.lon,
+            alt: data.alt.unwrap_or(f32::NAN),
+            ground_speed: data.ground_speed.unwrap_or(f32::NAN),
+            ground_course: data.ground_course.unwrap_or(f32::NAN),
+            sats_in_view: data.sats_in_view,
+            sats_in_use: data.sats_in_use,
+            timestamp: data.utc_datetime.timestamp(),
+        }
+    }
+}
+
+fn set_last_error(msg: String) {
+    // panicking across the C boundary aborts, so the message falls back instead
+    let msg: CString = CString::new(msg.replace('\0', ""))
+        .unwrap_or_else(|_| CString::new("Unknown error.").unwrap_or_default());
+    LAST_ERROR.with(|last_error: &RefCell<Option<CString>>| *last_error.borrow_mut() = Some(msg));
+}
+
+fn status(result: Result<(), Error>) -> i32 {
+    match result {
+        Ok(_) => SIM868_OK,
+        Err(err) => {
+            set_last_error(err.to_string());
+            SIM868_ERROR_FAILED
+        }
+    }
+}
+
+/// Returns the string behind the pointer, or `None` (setting the last error) if it's null or not UTF-8.
+unsafe fn to_str<'a>(name: &str, raw: *const c_char) -> Option<&'a str> {
+    if raw.is_null() {
+        set_last_error(format!("{name} - null pointer."));
+        return None;
+    }
+    match CStr::from_ptr(raw).to_str() {
+        Ok(text) => Some(text),
+        Err(_) => {
+            set_last_error(format!("{name} - not a valid UTF-8 string."));
+            None
+        }
+    }
+}
+
+unsafe fn to_sim<'a>(sim: *const SIM868) -> Option<&'a SIM868> {
+    if sim.is_null() {
+        set_last_error("sim - null pointer.".to_string());
+    }
+    sim.as_ref()
+}
+
+/// Copies `text` into the C `buffer` of `length` bytes, truncating it and always terminating with a nul byte.
+unsafe fn copy_to_buffer(text: &[u8], buffer: *mut c_char, length: usize) -> usize {
+    if buffer.is_null() || length == 0 {
+        return 0;
+    }
+    let copied: usize = text.len().min(length - 1);
+    ptr::copy_nonoverlapping(text.as_ptr() as *const c_char, buffer, copied);
+    *buffer.add(copied) = 0;
+    copied
+}
+
+/// Connects to the HAT through the UART at `path`, eg. `/dev/ttyS0`. Returns null on failure.
+///
+/// # Safety
+/// `path` must be a valid nul-terminated string. The returned pointer must be released with [`sim868_free`].
+#[no_mangle]
+pub unsafe extern "C" fn sim868_new(path: *const c_char, baud_rate: u32) -> *mut SIM868 {
+    let Some(path) = to_str("path", path) else {
+        return ptr::null_mut();
+    };
+    match SIM868::builder().uart(path, baud_rate).build() {
+        Ok(sim) => Box::into_raw(Box::new(sim)),
+        Err(err) => {
+            set_last_error(format!("Can't open the UART at {path}: {err}"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+/// `sim` must be returned by [`sim868_new`] and not used afterwards. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn sim868_free(sim: *mut SIM868) {
+    if !sim.is_null() {
+        drop(Box::from_raw(sim));
+    }
+}
+
+/// Turns on the HAT, see [`crate::hat::Hat::turn_on`].
+///
+/// # Safety
+/// `sim` must be returned by [`sim868_new`].
+#[no_mangle]
+pub unsafe extern "C" fn sim868_turn_on(sim: *const SIM868) -> i32 {
+    match to_sim(sim) {
+        Some(sim) => status(sim.hat.turn_on_blocking()),
+        None => SIM868_ERROR_INVALID_ARGUMENT,
+    }
+}
+
+/// Sends the SMS `text` to the `number`.
+///
+/// # Safety
+/// `sim` must be returned by [`sim868_new`], `number` and `text` must be valid nul-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn sim868_sms_send(
+    sim: *const SIM868,
+    number: *const c_char,
+    text: *const c_char,
+) -> i32 {
+    let (Some(sim), Some(number), Some(text)) =
+        (to_sim(sim), to_str("number", number), to_str("text", text))
+    else {
+        return SIM868_ERROR_INVALID_ARGUMENT;
+    };
+    status(sim.sms.send_blocking(number, text))
+}
+
+/// Turns on the GNSS module, which has to be done before reading the fix.
+///
+/// # Safety
+/// `sim` must be returned by [`sim868_new`].
+#[no_mangle]
+pub unsafe extern "C" fn sim868_gnss_turn_on(sim: *const SIM868) -> i32 {
+    match to_sim(sim) {
+        Some(sim) => status(sim.gnss.turn_on_blocking()),
+        None => SIM868_ERROR_INVALID_ARGUMENT,
+    }
+}
+
+/// Writes the current GNSS fix into `fix`.
+///
+/// # Safety
+/// `sim` must be returned by [`sim868_new`], `fix` must point to a writable [`Sim868GnssFix`].
+#[no_mangle]
+pub unsafe extern "C" fn sim868_gnss_get_fix(sim: *const SIM868, fix: *mut Sim868GnssFix) -> i32 {
+    let Some(sim) = to_sim(sim) else {
+        return SIM868_ERROR_INVALID_ARGUMENT;
+    };
+    if fix.is_null() {
+        set_last_error("fix - null pointer.".to_string());
+        return SIM868_ERROR_INVALID_ARGUMENT;
+    }
+    status(sim.gnss.get_data_blocking().map(|data: GNSSData| {
+        *fix = data.into();
+    }))
+}
+
+/// Configures the APN, which has to be done before making HTTP requests.
+///
+/// # Safety
+/// `sim` must be returned by [`sim868_new`], `apn`, `user` and `password` must be valid nul-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn sim868_gprs_init(
+    sim: *const SIM868,
+    apn: *const c_char,
+    user: *const c_char,
+    password: *const c_char,
+) -> i32 {
+    let (Some(sim), Some(apn), Some(user), Some(password)) = (
+        to_sim(sim),
+        to_str("apn", apn),
+        to_str("user", user),
+        to_str("password", password),
+    ) else {
+        return SIM868_ERROR_INVALID_ARGUMENT;
+    };
+    status(sim.gprs.init_blocking(ApnConfig {
+        apn: apn.to_string(),
+        user: user.to_string(),
+        password: password.to_string(),
+    }))
+}
+
+/// Sends the `json` body to the `url` and copies the response body into `response` of `response_length` bytes.
+/// The response is truncated if it doesn't fit, pass null to ignore it.
+///
+/// # Safety
+/// `sim` must be returned by [`sim868_new`], `url` and `json` must be valid nul-terminated strings,
+/// `response` must be null or point to at least `response_length` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sim868_http_post(
+    sim: *const SIM868,
+    url: *const c_char,
+    json: *const c_char,
+    response: *mut c_char,
+    response_length: usize,
+) -> i32 {
+    let (Some(sim), Some(url), Some(json)) =
+        (to_sim(sim), to_str("url", url), to_str("json", json))
+    else {
+        return SIM868_ERROR_INVALID_ARGUMENT;
+    };
+    let data: serde_json::Value = match serde_json::from_str(json) {
+        Ok(data) => data,
+        Err(err) => {
+            set_last_error(format!("json - {err}"));
+            return SIM868_ERROR_INVALID_ARGUMENT;
+        }
+    };
+
+    let result: Result<String, Error> = sim.gprs.request_blocking(Request {
+        content_type: Some(ContentType::Json),
+        data,
+        userdata_header: None,
+        method: RequestMethod::POST,
+        url: url.to_string(),
+    });
+    status(result.map(|body: String| {
+        copy_to_buffer(body.as_bytes(), response, response_length);
+    }))
+}
+
+/// Copies the message of the last error on the calling thread into `buffer` of `length` bytes.
+/// Returns the number of bytes copied, without the terminating nul byte.
+///
+/// # Safety
+/// `buffer` must be null or point to at least `length` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sim868_last_error(buffer: *mut c_char, length: usize) -> usize {
+    LAST_ERROR.with(
+        |last_error: &RefCell<Option<CString>>| match *last_error.borrow() {
+            Some(ref msg) => copy_to_buffer(msg.as_bytes(), buffer, length),
+            None => copy_to_buffer(b"", buffer, length),
+        },
+    )
+}
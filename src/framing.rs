@@ -0,0 +1,267 @@
+//! Framing of the modem output
+//!
+//! The transport returns whatever has arrived by the time of the read, so a response (eg. a long `+CGNSINF`
+//! line at 115200 baud) is often split across the reads. [`FramedTransport`] accumulates the received bytes and
+//! hands out only the complete, `\r\n` terminated lines - including the final result codes (`OK`, `ERROR`,
+//! `+CME ERROR: <n>`...) - leaving the unfinished line in the buffer until the rest of it arrives.
+//! The `> ` prompt of the commands taking data (eg. `AT+CMGS`) isn't followed by a line break, so it's
+//! handed out as it is.
+//!
+//! Until echo is disabled with `ATE0` (the first command of [`crate::hat::Hat::init`]), the modem repeats
+//! every command line before its response - the echo is dropped here, so the resolvers only see the response
+//! and a pattern can't match the command itself (eg. `+CGNSPWR=1` of `AT+CGNSPWR=1`).
+
+use crate::{
+    error::Error,
+    events::UrcFilter,
+    transport::{ClosedTransport, Queue, Transport},
+};
+use std::time::{Duration, Instant};
+
+/// Prompt for the data of commands like `AT+CMGS` or `AT+CIPSEND`.
+const DATA_PROMPT: &[u8] = b"> ";
+/// Terminator of the SMS text.
+const CTRL_Z: u8 = 0x1A;
+
+pub(crate) struct FramedTransport {
+    transport: Box<dyn Transport>,
+    /// Received bytes which haven't been handed out yet.
+    buffer: Vec<u8>,
+    /// Line written last, dropped if it's the first one received.
+    echo: Option<String>,
+    last_write: Option<Instant>,
+    /// URC filter of the lines handed out, holding the payload being read.
+    pub(crate) urc_filter: UrcFilter,
+}
+
+impl FramedTransport {
+    pub(crate) fn new(transport: Box<dyn Transport>) -> Self {
+        FramedTransport {
+            transport,
+            buffer: Vec::new(),
+            echo: None,
+            last_write: None,
+            urc_filter: UrcFilter::default(),
+        }
+    }
+
+    /// Time since the last write, `None` if nothing has been written yet.
+    pub(crate) fn idle(&self) -> Option<Duration> {
+        self.last_write
+            .map(|last_write: Instant| last_write.elapsed())
+    }
+
+    /// Drops the transport, the following reads and writes fail with [`Error::Closed`].
+    pub(crate) fn close(&mut self) {
+        self.transport = Box::new(ClosedTransport);
+        self.buffer.clear();
+    }
+
+    pub(crate) fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.transport.write(data)?;
+        self.last_write = Some(Instant::now());
+        self.urc_filter.reset();
+        // the command line, or the first line of the data (eg. the text of SMS)
+        let line: &[u8] = data
+            .split(|byte: &u8| matches!(*byte, b'\r' | b'\n' | CTRL_Z))
+            .next()
+            .unwrap_or_default();
+        self.echo = Some(decode(line.to_vec()).trim().to_string()).filter(|line| !line.is_empty());
+        Ok(())
+    }
+
+    /// Discards the data buffered in the `queue`, the unfinished line included.
+    pub(crate) fn flush(&mut self, queue: Queue) -> Result<(), Error> {
+        if queue != Queue::Output {
+            self.buffer.clear();
+        }
+        self.transport.flush(queue)
+    }
+
+    /// Switches the transport to the `baud_rate`, discarding the data received at the previous one.
+    pub(crate) fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Error> {
+        self.buffer.clear();
+        self.transport.set_baud_rate(baud_rate)
+    }
+
+    /// Reads the data received so far, until the read of the transport times out, and returns the complete
+    /// lines, if any.
+    pub(crate) fn read_lines(&mut self) -> Result<Option<String>, Error> {
+        let mut read_buffer: [u8; 64] = [0; 64];
+        loop {
+            match self.transport.read(&mut read_buffer)? {
+                0 => break,
+                length => self.buffer.extend_from_slice(&read_buffer[..length]),
+            }
+        }
+
+        let framed: usize = match self.buffer.iter().rposition(|byte: &u8| *byte == b'\n') {
+            _ if self.buffer.ends_with(DATA_PROMPT) => self.buffer.len(),
+            Some(end) => end + 1,
+            None => return Ok(None),
+        };
+        let lines: String = decode(self.buffer.drain(..framed).collect());
+        Ok(Some(self.strip_echo(lines)))
+    }
+
+    /// Drops the echo of the line written last, expected before anything else the modem sends.
+    fn strip_echo(&mut self, lines: String) -> String {
+        if self.echo.is_none() {
+            return lines;
+        }
+        let mut stripped: String = String::with_capacity(lines.len());
+        for line in lines.split_inclusive('\n') {
+            if line.trim().is_empty() || self.echo.is_none() {
+                stripped.push_str(line);
+                continue;
+            }
+            match self.echo.take().as_deref() == Some(line.trim()) {
+                // the line break ending the echo starts the response (`AT\r` + `\r\nOK\r\n`)
+                true => stripped.push_str("\r\n"),
+                false => stripped.push_str(line),
+            }
+        }
+        stripped
+    }
+
+    /// Takes the unfinished line left in the buffer, eg. once the response hasn't been completed in time.
+    pub(crate) fn take_rest(&mut self) -> Option<String> {
+        match self.buffer.is_empty() {
+            true => None,
+            false => Some(decode(std::mem::take(&mut self.buffer))),
+        }
+    }
+}
+
+fn decode(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    };
+
+    /// Transport reading the bursts pushed by the test, up to the size of the read buffer at a time.
+    #[derive(Clone, Default)]
+    struct Bursts {
+        received: Arc<Mutex<VecDeque<u8>>>,
+    }
+
+    impl Bursts {
+        fn push(&self, data: &[u8]) {
+            self.received.lock().unwrap().extend(data);
+        }
+    }
+
+    impl Transport for Bursts {
+        fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+            let mut received = self.received.lock().unwrap();
+            let length: usize = buffer.len().min(received.len());
+            for (byte, received) in buffer.iter_mut().zip(received.drain(..length)) {
+                *byte = received;
+            }
+            Ok(length)
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
+            Ok(data.len())
+        }
+
+        fn flush(&mut self, _: Queue) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn framed() -> (FramedTransport, Bursts) {
+        let bursts: Bursts = Bursts::default();
+        (FramedTransport::new(Box::new(bursts.clone())), bursts)
+    }
+
+    #[test]
+    fn holds_unfinished_line() {
+        let (mut framed, bursts) = framed();
+        bursts.push(b"\r\n+CGNSINF: 1,1,2024");
+        assert_eq!(framed.read_lines().unwrap().as_deref(), Some("\r\n"));
+        assert_eq!(framed.read_lines().unwrap(), None);
+        bursts.push(b"0101120000.000,51.5\r\n\r\nOK\r\n");
+        assert_eq!(
+            framed.read_lines().unwrap().as_deref(),
+            Some("+CGNSINF: 1,1,20240101120000.000,51.5\r\n\r\nOK\r\n")
+        );
+        assert_eq!(framed.take_rest(), None);
+    }
+
+    #[test]
+    fn reads_lines_longer_than_read_buffer() {
+        let (mut framed, bursts) = framed();
+        let line: String = format!("+HTTPREAD: {}", "x".repeat(200));
+        bursts.push(format!("\r\n{line}\r\n").as_bytes());
+        assert_eq!(
+            framed.read_lines().unwrap(),
+            Some(format!("\r\n{line}\r\n"))
+        );
+    }
+
+    #[test]
+    fn joins_split_characters() {
+        let (mut framed, bursts) = framed();
+        bursts.push(b"\r\nCze\xC5");
+        assert_eq!(framed.read_lines().unwrap().as_deref(), Some("\r\n"));
+        bursts.push(b"\x9B\xC4\x87\r\n");
+        assert_eq!(framed.read_lines().unwrap().as_deref(), Some("Cześć\r\n"));
+    }
+
+    #[test]
+    fn hands_out_data_prompt() {
+        let (mut framed, bursts) = framed();
+        bursts.push(b"\r\n> ");
+        assert_eq!(framed.read_lines().unwrap().as_deref(), Some("\r\n> "));
+    }
+
+    #[test]
+    fn takes_rest_once_timed_out() {
+        let (mut framed, bursts) = framed();
+        bursts.push(b"\r\nOK\r\n+CPIN: REA");
+        assert_eq!(framed.read_lines().unwrap().as_deref(), Some("\r\nOK\r\n"));
+        assert_eq!(framed.take_rest().as_deref(), Some("+CPIN: REA"));
+        assert_eq!(framed.take_rest(), None);
+    }
+
+    #[test]
+    fn strips_split_echo() {
+        let (mut framed, bursts) = framed();
+        framed.write(b"AT+CSQ\r\n").unwrap();
+        bursts.push(b"AT+C");
+        assert_eq!(framed.read_lines().unwrap(), None);
+        bursts.push(b"SQ\r\r\n+CSQ: 20,0\r\n\r\nOK\r\n");
+        assert_eq!(
+            framed.read_lines().unwrap().as_deref(),
+            Some("\r\n+CSQ: 20,0\r\n\r\nOK\r\n")
+        );
+    }
+
+    #[test]
+    fn keeps_response_without_echo() {
+        let (mut framed, bursts) = framed();
+        framed.write(b"AT+CSQ\r\n").unwrap();
+        bursts.push(b"\r\n+CSQ: 20,0\r\n\r\nOK\r\n");
+        assert_eq!(
+            framed.read_lines().unwrap().as_deref(),
+            Some("\r\n+CSQ: 20,0\r\n\r\nOK\r\n")
+        );
+    }
+
+    #[test]
+    fn flush_drops_unfinished_line() {
+        let (mut framed, bursts) = framed();
+        bursts.push(b"\r\n+CSQ: 2");
+        framed.read_lines().unwrap();
+        framed.flush(Queue::Input).unwrap();
+        bursts.push(b"\r\nOK\r\n");
+        assert_eq!(framed.read_lines().unwrap().as_deref(), Some("\r\nOK\r\n"));
+    }
+}
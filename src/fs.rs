@@ -0,0 +1,205 @@
+//! File system module
+//!
+//! The modem has a small flash file system for the data it uses by itself, eg. the GNSS assistance data (see
+//! [`crate::gnss::GNSS::update_assistance`]). [`FileSystem`] writes the files of the user directory
+//! (`C:\User\`) from the host (`AT+FSWRITE`), in chunks of up to 10 kB, as the data is taken in binary.
+//!
+//! ```no_run
+//! use rpi_sim868::SIM868;
+//!
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! #[tokio::main]
+//! async fn main() -> Result<(), rpi_sim868::Error> {
+//!     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//!     sim.fs.write_now("config.bin", vec![0x01, 0x02, 0x03]).await?;
+//!     println!("{} bytes", sim.fs.size_now("config.bin").await?);
+//!     Ok(())
+//! }
+//! ```
+
+#[cfg(feature = "blocking")]
+use crate::blocking::block_on_task;
+use crate::{
+    command::AtCommand,
+    error::Error,
+    error_check, generic_resolver, join_task, parse_field,
+    serial_port::{spawn_task, SerialPort, TaskPriority},
+    Module, ResolverReturn, TaskJoinHandle, FS_SIZE_REGEX,
+};
+use std::{sync::Arc, time::Duration};
+use uuid::Uuid;
+
+/// Directory of the files written by the host.
+pub const USER_DIRECTORY: &str = "C:\\User\\";
+/// Largest data of a single `AT+FSWRITE`.
+const MAX_CHUNK_SIZE: usize = 10240;
+/// Time (in seconds) the modem waits for the data of `AT+FSWRITE`.
+const INPUT_TIME: u8 = 10;
+const WRITE_TIMEOUT: Duration = Duration::from_secs(INPUT_TIME as u64 + 2);
+
+fn path(name: &str) -> String {
+    format!("{USER_DIRECTORY}{name}")
+}
+
+/// Replaces the file `name` of the user directory with the `data`.
+pub(crate) fn write_file(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    (name, data): (String, Vec<u8>),
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::FsWriteFailed)
+    }
+
+    fn prompt_resolver(result: String) -> ResolverReturn<()> {
+        if error_check(&result) {
+            return Err(Error::FsWriteFailed);
+        }
+        match result.contains('>') {
+            true => Ok(()),
+            false => Err(Error::NotResolved),
+        }
+    }
+
+    // `AT+FSCREATE` fails on the file which exists already
+    let _ = delete_file(serial_port, task_id, name.clone());
+    serial_port.process(
+        task_id,
+        AtCommand::set("+FSCREATE").path(&path(&name)).into(),
+        resolver,
+        None,
+    )?;
+    for (index, chunk) in data.chunks(MAX_CHUNK_SIZE).enumerate() {
+        // the first chunk from the beginning (0), the next ones appended (1)
+        serial_port.process(
+            task_id,
+            AtCommand::set("+FSWRITE")
+                .path(&path(&name))
+                .number((index > 0) as u8)
+                .number(chunk.len())
+                .number(INPUT_TIME)
+                .into(),
+            prompt_resolver,
+            None,
+        )?;
+        serial_port.write(task_id, chunk)?;
+        serial_port.read(task_id, resolver, Some(WRITE_TIMEOUT))?;
+    }
+    Ok(())
+}
+
+fn delete_file(serial_port: &Arc<SerialPort>, task_id: &Uuid, name: String) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::FsDeleteFailed)
+    }
+    serial_port.process(
+        task_id,
+        AtCommand::set("+FSDEL").path(&path(&name)).into(),
+        resolver,
+        None,
+    )
+}
+
+fn file_size(serial_port: &Arc<SerialPort>, task_id: &Uuid, name: String) -> ResolverReturn<usize> {
+    fn resolver(result: String) -> ResolverReturn<usize> {
+        if error_check(&result) {
+            return Err(Error::FsFileNotFound);
+        }
+        match FS_SIZE_REGEX.captures(&result) {
+            Some(captured) => parse_field("file_size", &captured["size"]),
+            None => Err(Error::NotResolved),
+        }
+    }
+    serial_port.process(
+        task_id,
+        AtCommand::set("+FSFLSIZE").path(&path(&name)).into(),
+        resolver,
+        None,
+    )
+}
+
+/// File system module, see the [module](self) documentation. The file names are relative to
+/// [`USER_DIRECTORY`].
+///
+/// The handle doesn't keep the connection open, see [`crate::SIM868`].
+#[derive(Clone)]
+pub struct FileSystem {
+    serial_port: Arc<SerialPort>,
+}
+
+impl Module for FileSystem {
+    fn new(serial_port: Arc<SerialPort>) -> Self {
+        FileSystem { serial_port }
+    }
+}
+
+impl FileSystem {
+    /// Writes the `data` to the file `name`, replacing the file if it exists.
+    pub fn write(&self, name: &str, data: Vec<u8>) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            write_file,
+            Some(format!("Writing {} bytes to {name}...", data.len())),
+            (name.to_string(), data),
+        )
+    }
+
+    /// Size of the file `name` in bytes, fails with [`Error::FsFileNotFound`] if there is no such file.
+    pub fn size(&self, name: &str) -> TaskJoinHandle<usize> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            file_size,
+            Some(format!("Reading the size of {name}...")),
+            name.to_string(),
+        )
+    }
+
+    pub fn delete(&self, name: &str) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            delete_file,
+            Some(format!("Deleting {name}...")),
+            name.to_string(),
+        )
+    }
+}
+
+impl FileSystem {
+    /// [`FileSystem::write`] returning the result directly.
+    pub async fn write_now(&self, name: &str, data: Vec<u8>) -> ResolverReturn<()> {
+        join_task(self.write(name, data)).await
+    }
+
+    /// [`FileSystem::size`] returning the result directly.
+    pub async fn size_now(&self, name: &str) -> ResolverReturn<usize> {
+        join_task(self.size(name)).await
+    }
+
+    /// [`FileSystem::delete`] returning the result directly.
+    pub async fn delete_now(&self, name: &str) -> ResolverReturn<()> {
+        join_task(self.delete(name)).await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl FileSystem {
+    /// Blocking version of [`FileSystem::write`].
+    pub fn write_blocking(&self, name: &str, data: Vec<u8>) -> ResolverReturn<()> {
+        block_on_task(|| self.write(name, data))
+    }
+
+    /// Blocking version of [`FileSystem::size`].
+    pub fn size_blocking(&self, name: &str) -> ResolverReturn<usize> {
+        block_on_task(|| self.size(name))
+    }
+
+    /// Blocking version of [`FileSystem::delete`].
+    pub fn delete_blocking(&self, name: &str) -> ResolverReturn<()> {
+        block_on_task(|| self.delete(name))
+    }
+}
@@ -3,110 +3,998 @@
 //! See [`GNSS`] to discover available methods.
 //!
 //! ⚠️ Please remember to turn on the GPS module by [`GNSS::turn_on`] before attempting to check for localization.
+//!
+//! [`GNSS::update_assistance`] loads the assistance data (MediaTek's EPO file of the satellite orbits for the
+//! next days) into the receiver, cutting the time to the first fix from minutes to seconds:
+//!
+//! ```no_run
+//! # use rpi_sim868::{gnss::{AssistanceSource, EPO_URL}, SIM868};
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), rpi_sim868::Error> {
+//! # let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//! // the GPRS bearer has to be configured (see rpi_sim868::gprs::GPRS::init), and the RTC set
+//! sim.gnss
+//!     .update_assistance_now(AssistanceSource::Download(EPO_URL.to_string()))
+//!     .await?;
+//! sim.gnss.turn_on_now().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`GNSS::restart`] restarts the receiver - a cold start after the device has been moved far away while
+//! powered off is faster than waiting for the outdated ephemeris to time out. [`GNSS::measure_ttff`] restarts
+//! the receiver and measures the time to the first fix, eg. for comparing antennas. [`GNSS::wait_for_fix`]
+//! waits for the fix good enough to be used (see [`FixCriteria`]). [`GNSS::configure`] selects the
+//! constellations, the NMEA sentences and the rate of the fixes (see [`GnssConfig`]). [`TrackLogger`] records the position
+//! periodically into a bounded ring buffer, optionally saved to a file, and exports the track as GPX or CSV -
+//! the track is kept on the device even if the uploads fail. [`GNSS::geofence`] watches the circular and
+//! polygon areas (see [`Fence`]), publishing the events when the device enters, leaves or stays in them.
+//! [`GNSS::duty_cycle`] powers the receiver on and off on a schedule to save the battery, eg. of a solar
+//! tracker, keeping the last fix for the queries in between.
+//!
+//! The position is in decimal degrees, [`GNSSData::dms`], [`GNSSData::utm`] and [`GNSSData::mgrs`] render it
+//! in degrees, minutes and seconds, and in the UTM and MGRS grids.
+//!
+//! [`GNSS::subscribe`] has the modem report the position by itself (`AT+CGNSURC`), so a tracker gets the fixes
+//! pushed instead of queueing a [`GNSS::get_data`] task every time:
+//!
+//! ```no_run
+//! # use rpi_sim868::{gnss::FixStream, SIM868};
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), rpi_sim868::Error> {
+//! # let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//! sim.gnss.turn_on_now().await?;
+//! let mut fixes: FixStream = sim.gnss.subscribe(1).await?;
+//! while let Some(fix) = fixes.next().await {
+//!     println!("{} {}", fix.lat, fix.lon);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`GNSS::nmea_stream`] turns on the NMEA output of the receiver (`AT+CGNSTST`) for the tools speaking NMEA
+//! (eg. gpsd) - the raw RMC, GGA, GSA and GSV sentences, which the `AT+CGNSINF` summary doesn't cover. The
+//! sentences are kept out of the responses of the other commands meanwhile.
+//!
+//! With the `nmea` feature, raw NMEA sentences can be parsed into the typed structs of the [`nmea`] crate
+//! (re-exported here) by [`parse_nmea`] and [`parse_nmea_sentences`] (or [`NmeaStream::next_parsed`]), and
+//! [`GNSS::satellites`] reports the satellites in view (from the GSV sentences) for diagnosing the antenna
+//! placement.
 
+#[cfg(feature = "blocking")]
+use crate::blocking::{block_on, block_on_task};
 use crate::{
-    error::Error,
-    generic_resolver,
+    command::AtCommand,
+    error::{Error, ErrorKind},
+    events::Event,
+    fs::write_file,
+    generic_resolver, join_task, nth_field, parse_field,
+    profile::Feature,
     serial_port::{spawn_task, SerialPort, TaskPriority},
-    Module, ResolverReturn, TaskJoinHandle, GNSS_DATA_REGEX, GNSS_POWER_REGEX, PARSING_ERROR,
+    Module, ResolverReturn, TaskJoinHandle, GNSS_DATA_REGEX, GNSS_POWER_REGEX, SIM868,
+};
+use chrono::{NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{ErrorKind as IoErrorKind, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::broadcast::{error::RecvError, Receiver},
+    task::JoinHandle,
+    time::sleep,
 };
-use chrono::{TimeZone, Utc};
-use std::sync::Arc;
 use uuid::Uuid;
 
+#[cfg(feature = "nmea")]
+pub use nmea;
+
+/// Time the NMEA output is captured for, the modem sends a complete set of sentences every second.
+#[cfg(feature = "nmea")]
+const NMEA_CAPTURE_TIME: Duration = Duration::from_millis(2500);
+/// MediaTek's EPO file of the GPS and GLONASS satellites (3 days), see [`AssistanceSource::Download`].
+pub const EPO_URL: &str = "http://wepodownload.mediatek.com/EPO_GR_3_1.DAT";
+/// Name of the EPO file in the [file system](crate::fs) of the modem.
+const EPO_FILE: &str = "EPO_GR_3_1.DAT";
+/// Time after which [`GNSS::measure_ttff`] gives up, a cold start under the open sky takes up to a minute.
+const MAX_TTFF: Duration = Duration::from_secs(15 * 60);
+const FIX_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const MUTEX_POISONED_MSG: &str = "Critical error: Mutex is poisoned.";
+
+/// Parses a single NMEA sentence into its typed struct.
+///
+/// ```
+/// use rpi_sim868::gnss::{nmea::ParseResult, parse_nmea};
+///
+/// let sentence: &str = "$GPGGA,092750.000,5321.6802,N,00630.3372,W,1,8,1.03,61.7,M,55.2,M,,*76";
+/// assert!(matches!(parse_nmea(sentence), Ok(ParseResult::GGA(_))));
+/// ```
+#[cfg(feature = "nmea")]
+pub fn parse_nmea(sentence: &str) -> Result<nmea::ParseResult, Error> {
+    let sentence: &str = sentence.trim();
+    nmea::parse_str(sentence).map_err(|_| Error::ParseFailure {
+        field: "nmea_sentence",
+        raw: sentence.to_string(),
+    })
+}
+
+/// Parses every NMEA sentence found in the `text`, skipping the lines which aren't sentences (eg. `OK`).
+#[cfg(feature = "nmea")]
+pub fn parse_nmea_sentences(
+    text: &str,
+) -> impl Iterator<Item = Result<nmea::ParseResult, Error>> + '_ {
+    text.lines()
+        .map(str::trim)
+        .filter(|line: &&str| line.starts_with('$'))
+        .map(parse_nmea)
+}
+
+/// Satellite system, see [`satellites_in_view`].
+#[cfg(feature = "nmea")]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Constellation {
+    GPS,
+    GLONASS,
+    GALILEO,
+    BEIDOU,
+    QZSS,
+    NAVIC,
+}
+
+#[cfg(feature = "nmea")]
+impl From<nmea::sentences::GnssType> for Constellation {
+    fn from(gnss_type: nmea::sentences::GnssType) -> Self {
+        use nmea::sentences::GnssType;
+        match gnss_type {
+            GnssType::Gps => Constellation::GPS,
+            GnssType::Glonass => Constellation::GLONASS,
+            GnssType::Galileo => Constellation::GALILEO,
+            GnssType::Beidou => Constellation::BEIDOU,
+            GnssType::Qzss => Constellation::QZSS,
+            GnssType::NavIC => Constellation::NAVIC,
+        }
+    }
+}
+
+/// Satellite reported by the GSV sentences, see [`satellites_in_view`].
+#[cfg(feature = "nmea")]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SatelliteInView {
+    pub prn: u32,
+    /// degrees
+    pub elevation: Option<f32>,
+    /// degrees from the true north
+    pub azimuth: Option<f32>,
+    /// dB-Hz, `None` if the satellite isn't tracked
+    pub snr: Option<f32>,
+}
+
+/// Groups the satellites reported by the GSV sentences in the `text` by constellation, sorted by PRN.
+/// If the text contains several sets of the sentences, the latest report of each satellite is kept.
+///
+/// ```
+/// use rpi_sim868::gnss::{satellites_in_view, Constellation};
+///
+/// let text: &str = "$GPGSV,1,1,02,05,41,302,39,12,22,054,*72\r\nOK\r\n";
+/// let satellites = satellites_in_view(text);
+/// assert_eq!(satellites[&Constellation::GPS].len(), 2);
+/// assert_eq!(satellites[&Constellation::GPS][1].snr, None);
+/// ```
+#[cfg(feature = "nmea")]
+pub fn satellites_in_view(text: &str) -> BTreeMap<Constellation, Vec<SatelliteInView>> {
+    let mut satellites: BTreeMap<Constellation, BTreeMap<u32, SatelliteInView>> = BTreeMap::new();
+    for sentence in parse_nmea_sentences(text) {
+        let Ok(nmea::ParseResult::GSV(gsv)) = sentence else {
+            continue;
+        };
+        for satellite in gsv.sats_info.iter().flatten() {
+            satellites
+                .entry(satellite.gnss_type().into())
+                .or_default()
+                .insert(
+                    satellite.prn(),
+                    SatelliteInView {
+                        prn: satellite.prn(),
+                        elevation: satellite.elevation(),
+                        azimuth: satellite.azimuth(),
+                        snr: satellite.snr(),
+                    },
+                );
+        }
+    }
+    satellites
+        .into_iter()
+        .map(|(constellation, by_prn)| (constellation, by_prn.into_values().collect()))
+        .collect()
+}
+
+/// Dimension of the fix, see [`GNSSData::fix_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FixMode {
+    /// Position without the altitude, from 3 satellites.
+    Fix2D,
+    /// Position with the altitude.
+    Fix3D,
+}
+
+impl FixMode {
+    /// Fix mode field of `AT+CGNSINF`, numbered as in the GSA sentence.
+    fn from_raw(mode: u8) -> Option<FixMode> {
+        match mode {
+            2 => Some(FixMode::Fix2D),
+            3 => Some(FixMode::Fix3D),
+            _ => None,
+        }
+    }
+}
+
 /// Type returned from [`GNSS::get_data`] method.
-#[derive(Debug)]
+///
+/// The values the modem leaves out of some of the reports (eg. the altitude and the speed right after the fix
+/// has been acquired) are `None` then.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GNSSData {
-    pub lat: f32,
-    pub lon: f32,
+    /// Degrees, south negative.
+    pub lat: f64,
+    /// Degrees, west negative.
+    pub lon: f64,
     /// Meters above MSL
-    pub alt: f32,
+    pub alt: Option<f32>,
     /// km/h
-    pub ground_speed: f32,
+    pub ground_speed: Option<f32>,
     /// degrees
-    pub ground_course: f32,
+    pub ground_course: Option<f32>,
+    /// `None` if the firmware doesn't tell 2D and 3D fixes apart.
+    pub fix_mode: Option<FixMode>,
+    /// Horizontal dilution of precision, the lower the better - the fixes above 5 are rather poor.
+    pub hdop: Option<f32>,
+    /// Position (3D) dilution of precision.
+    pub pdop: Option<f32>,
+    /// Vertical dilution of precision.
+    pub vdop: Option<f32>,
+    /// GPS and GLONASS satellites in view.
     pub sats_in_view: u8,
     pub sats_in_use: u8,
+    /// GLONASS satellites out of the ones in use.
+    pub glonass_in_use: Option<u8>,
+    /// dB-Hz, the strongest signal received.
+    pub cn0_max: Option<u8>,
+    /// Horizontal position accuracy, meters.
+    pub hpa: Option<f32>,
+    /// Vertical position accuracy, meters.
+    pub vpa: Option<f32>,
     pub utc_datetime: chrono::DateTime<Utc>,
 }
 
-fn get_data(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<GNSSData> {
-    fn resolver(result: String) -> ResolverReturn<GNSSData> {
-        let Some(captured) = GNSS_DATA_REGEX.captures(&result) else {
-            return Err(Error::NotResolved);
+impl GNSSData {
+    /// Position in degrees, minutes and seconds, see [`dms`].
+    pub fn dms(&self) -> String {
+        dms(self.lat, self.lon)
+    }
+
+    /// Position in the UTM grid, `None` near the poles (beyond 80° S and 84° N).
+    pub fn utm(&self) -> Option<Utm> {
+        Utm::from_degrees(self.lat, self.lon)
+    }
+
+    /// Position in the MGRS grid to a metre, eg. `30U XC 99330 10142`, `None` near the poles.
+    pub fn mgrs(&self) -> Option<String> {
+        self.utm().as_ref().map(Utm::mgrs)
+    }
+}
+
+/// Degrees, minutes and seconds (to a hundredth of a second, 0.3 m) of the position in degrees.
+///
+/// ```
+/// assert_eq!(rpi_sim868::gnss::dms(51.5072, -0.1276), "51°30'25.92\"N 0°07'39.36\"W");
+/// ```
+pub fn dms(lat: f64, lon: f64) -> String {
+    fn angle(degrees: f64, hemispheres: (char, char)) -> String {
+        let hemisphere: char = match degrees < 0.0 {
+            true => hemispheres.1,
+            false => hemispheres.0,
         };
+        // rounded as a whole, so the seconds don't round up to 60
+        let hundredths: u64 = (degrees.abs() * 360_000.0).round() as u64;
+        format!(
+            "{}°{:02}'{:02}.{:02}\"{hemisphere}",
+            hundredths / 360_000,
+            hundredths / 6000 % 60,
+            hundredths / 100 % 60,
+            hundredths % 100
+        )
+    }
+    format!("{} {}", angle(lat, ('N', 'S')), angle(lon, ('E', 'W')))
+}
 
-        let data: &Vec<&str> = &captured["data"].split(",").collect();
+/// Semi-major axis of the WGS 84 ellipsoid, meters.
+const WGS84_A: f64 = 6_378_137.0;
+const WGS84_F: f64 = 1.0 / 298.257_223_563;
+/// Scale factor on the central meridian of the UTM zones.
+const UTM_K0: f64 = 0.9996;
 
-        if data[0].parse::<u8>().expect(PARSING_ERROR) == 0 {
-            return Err(Error::GnssModuleOff);
+/// Position in the Universal Transverse Mercator grid (WGS 84), see [`GNSSData::utm`].
+///
+/// ```
+/// use rpi_sim868::gnss::Utm;
+///
+/// // the CN Tower
+/// let utm: Utm = Utm::from_degrees(43.642567, -79.387139).unwrap();
+/// assert_eq!(utm.to_string(), "17T 630084 4833438");
+/// assert_eq!(utm.mgrs(), "17T PJ 30084 33438");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Utm {
+    /// 1 to 60, 6° of longitude each.
+    pub zone: u8,
+    /// Latitude band, `C` to `X` (without `I` and `O`), 8° of latitude each - `N` and above are north of the
+    /// equator.
+    pub band: char,
+    /// Meters, 500 km on the central meridian of the zone.
+    pub easting: f64,
+    /// Meters from the equator, from 10000 km below it south of the equator.
+    pub northing: f64,
+}
+
+impl Utm {
+    /// Converts the position in degrees with the Krüger series (accurate to a millimetre within the zone),
+    /// `None` beyond 80° S and 84° N, where the polar grid is used instead.
+    pub fn from_degrees(lat: f64, lon: f64) -> Option<Utm> {
+        if !(-80.0..=84.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            return None;
         }
-        if data[1].parse::<u8>().expect(PARSING_ERROR) == 0 {
-            return Err(Error::GnssNotFixed);
+        let mut zone: u8 = ((lon + 180.0) / 6.0).floor() as u8 % 60 + 1;
+        // the zones widened over Norway and Svalbard
+        if (56.0..64.0).contains(&lat) && (3.0..12.0).contains(&lon) {
+            zone = 32;
         }
+        if lat >= 72.0 && (0.0..42.0).contains(&lon) {
+            zone = match lon {
+                lon if lon < 9.0 => 31,
+                lon if lon < 21.0 => 33,
+                lon if lon < 33.0 => 35,
+                _ => 37,
+            };
+        }
+        // X spans 12° up to 84° N
+        let band: char = b"CDEFGHJKLMNPQRSTUVWXX"[((lat + 80.0) / 8.0).floor() as usize] as char;
 
-        let year: &str = &data[2][..=3];
-        let month: &str = &data[2][4..=5];
-        let day: &str = &data[2][6..=7];
-        let hour: &str = &data[2][8..=9];
-        let minutes: &str = &data[2][10..=11];
-        let seconds: &str = &data[2][12..=13];
+        let n: f64 = WGS84_F / (2.0 - WGS84_F);
+        let e: f64 = (WGS84_F * (2.0 - WGS84_F)).sqrt();
+        let rectifying_radius: f64 =
+            WGS84_A / (1.0 + n) * (1.0 + n.powi(2) / 4.0 + n.powi(4) / 64.0);
+        let alpha: [f64; 3] = [
+            n / 2.0 - 2.0 / 3.0 * n.powi(2) + 5.0 / 16.0 * n.powi(3),
+            13.0 / 48.0 * n.powi(2) - 3.0 / 5.0 * n.powi(3),
+            61.0 / 240.0 * n.powi(3),
+        ];
 
-        let utc_datetime: chrono::DateTime<Utc> = Utc
-            .with_ymd_and_hms(
-                year.parse().expect(PARSING_ERROR),
-                month.parse().expect(PARSING_ERROR),
-                day.parse().expect(PARSING_ERROR),
-                hour.parse().expect(PARSING_ERROR),
-                minutes.parse().expect(PARSING_ERROR),
-                seconds.parse().expect(PARSING_ERROR),
-            )
-            .unwrap();
+        let central_meridian: f64 = f64::from(zone) * 6.0 - 183.0;
+        let (phi, lambda) = (lat.to_radians(), (lon - central_meridian).to_radians());
+        // conformal latitude
+        let t: f64 = (phi.sin().atanh() - e * (e * phi.sin()).atanh()).sinh();
+        let xi: f64 = t.atan2(lambda.cos());
+        let eta: f64 = (lambda.sin() / (1.0 + t.powi(2)).sqrt()).atanh();
+        let (mut x, mut y) = (eta, xi);
+        for (j, alpha) in (1..).map(f64::from).zip(alpha) {
+            x += alpha * (2.0 * j * xi).cos() * (2.0 * j * eta).sinh();
+            y += alpha * (2.0 * j * xi).sin() * (2.0 * j * eta).cosh();
+        }
 
-        Ok(GNSSData {
-            utc_datetime,
-            lat: data[3].parse().expect(PARSING_ERROR),
-            lon: data[4].parse().expect(PARSING_ERROR),
-            alt: data[5].parse().expect(PARSING_ERROR),
-            ground_speed: data[6].parse().expect(PARSING_ERROR),
-            ground_course: data[7].parse().expect(PARSING_ERROR),
-            sats_in_view: data[14].parse().expect(PARSING_ERROR),
-            sats_in_use: data[15].parse().expect(PARSING_ERROR),
+        Some(Utm {
+            zone,
+            band,
+            easting: 500_000.0 + UTM_K0 * rectifying_radius * x,
+            northing: match lat < 0.0 {
+                true => 10_000_000.0,
+                false => 0.0,
+            } + UTM_K0 * rectifying_radius * y,
         })
     }
 
-    serial_port.process(task_id, "AT+CGNSINF\n".to_string(), resolver, None)
+    /// Military Grid Reference System position to a metre - the zone and band, the 100 km square and the
+    /// easting and northing within it.
+    pub fn mgrs(&self) -> String {
+        const COLUMNS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+        const ROWS: &[u8] = b"ABCDEFGHJKLMNPQRSTUV";
+        let (easting, northing) = (self.easting.floor() as u64, self.northing.floor() as u64);
+        // the column letters repeat every 3 zones, the row letters are shifted by 5 in the even ones
+        let set: usize = (usize::from(self.zone) - 1) % 3;
+        let column: usize =
+            (set * 8 + (easting / 100_000) as usize).saturating_sub(1) % COLUMNS.len();
+        let row: usize =
+            ((northing / 100_000) as usize + 5 * (1 - usize::from(self.zone % 2))) % ROWS.len();
+        format!(
+            "{}{} {}{} {:05} {:05}",
+            self.zone,
+            self.band,
+            COLUMNS[column] as char,
+            ROWS[row] as char,
+            easting % 100_000,
+            northing % 100_000
+        )
+    }
+}
+
+/// Zone and band, easting and northing in meters, eg. `30U 699330 5710142`.
+impl std::fmt::Display for Utm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{} {:.0} {:.0}",
+            self.zone,
+            self.band,
+            self.easting.floor(),
+            self.northing.floor()
+        )
+    }
+}
+
+/// Value of the field at `index`, `None` if it's empty or the response is shorter.
+fn optional_field<T: std::str::FromStr>(
+    data: &[&str],
+    index: usize,
+    field: &'static str,
+) -> ResolverReturn<Option<T>> {
+    match data.get(index).map(|raw: &&str| raw.trim()) {
+        None | Some("") => Ok(None),
+        Some(raw) => parse_field(field, raw).map(Some),
+    }
+}
+
+/// Parses the navigation information fields, the same in the `+CGNSINF` response and the `+UGNSINF` URC.
+/// The fix without the position (the receiver is still acquiring it) is [`Error::GnssNotFixed`], the
+/// other values may be missing.
+pub(crate) fn parse_gnss_info(data: &str) -> ResolverReturn<GNSSData> {
+    let data: &Vec<&str> = &data.split(',').collect();
+
+    if parse_field::<u8>("run_status", nth_field(data, 0, "run_status")?)? == 0 {
+        return Err(Error::GnssModuleOff);
+    }
+    if parse_field::<u8>("fix_status", nth_field(data, 1, "fix_status")?)? == 0 {
+        return Err(Error::GnssNotFixed);
+    }
+
+    // yyyyMMddhhmmss.sss
+    let raw_datetime: &str = nth_field(data, 2, "utc_datetime")?;
+    let utc_datetime: chrono::DateTime<Utc> =
+        NaiveDateTime::parse_from_str(raw_datetime.trim(), "%Y%m%d%H%M%S%.f")
+            .map(|datetime: NaiveDateTime| Utc.from_utc_datetime(&datetime))
+            .map_err(|_| Error::ParseFailure {
+                field: "utc_datetime",
+                raw: raw_datetime.to_string(),
+            })?;
+
+    let (Some(lat), Some(lon)) = (
+        optional_field(data, 3, "lat")?,
+        optional_field(data, 4, "lon")?,
+    ) else {
+        return Err(Error::GnssNotFixed);
+    };
+
+    Ok(GNSSData {
+        utc_datetime,
+        lat,
+        lon,
+        alt: optional_field(data, 5, "alt")?,
+        ground_speed: optional_field(data, 6, "ground_speed")?,
+        ground_course: optional_field(data, 7, "ground_course")?,
+        fix_mode: optional_field(data, 8, "fix_mode")?.and_then(FixMode::from_raw),
+        // 9 and 13 are reserved
+        hdop: optional_field(data, 10, "hdop")?,
+        pdop: optional_field(data, 11, "pdop")?,
+        vdop: optional_field(data, 12, "vdop")?,
+        sats_in_view: optional_field(data, 14, "sats_in_view")?.unwrap_or_default(),
+        sats_in_use: optional_field(data, 15, "sats_in_use")?.unwrap_or_default(),
+        glonass_in_use: optional_field(data, 16, "glonass_in_use")?,
+        cn0_max: optional_field(data, 18, "cn0_max")?,
+        hpa: optional_field(data, 19, "hpa")?,
+        vpa: optional_field(data, 20, "vpa")?,
+    })
+}
+
+/// Last fix kept by the duty cycle, see [`GNSS::duty_cycle`].
+#[derive(Default)]
+pub(crate) struct FixCache {
+    active: bool,
+    /// The receiver has been turned off by the duty cycle.
+    engine_off: bool,
+    last_fix: Option<GNSSData>,
+}
+
+impl FixCache {
+    fn cached_fix(&self) -> Option<GNSSData> {
+        self.last_fix.clone().filter(|_| self.active)
+    }
+
+    fn update(&mut self, fix: &GNSSData) {
+        if self.active {
+            self.last_fix = Some(fix.clone());
+        }
+    }
+}
+
+/// Position read from the receiver (`AT+CGNSINF`), never the one cached by the duty cycle.
+fn read_fix(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<GNSSData> {
+    fn resolver(result: String) -> ResolverReturn<GNSSData> {
+        match GNSS_DATA_REGEX.captures(&result) {
+            Some(captured) => parse_gnss_info(&captured["data"]),
+            None => Err(Error::NotResolved),
+        }
+    }
+
+    serial_port.require(Feature::GNSS)?;
+    serial_port.process(
+        task_id,
+        "AT+CGNSINF\n".to_string(),
+        resolver,
+        Some(serial_port.timeouts.gnss),
+    )
+}
+
+pub(crate) fn get_data(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<GNSSData> {
+    let (engine_off, cached) = {
+        let cache = serial_port.fix_cache();
+        (cache.engine_off, cache.cached_fix())
+    };
+    if let (true, Some(fix)) = (engine_off, &cached) {
+        return Ok(fix.clone());
+    }
+    let result: ResolverReturn<GNSSData> = read_fix(serial_port, task_id, ());
+    match (result, cached) {
+        (Ok(fix), _) => {
+            serial_port.fix_cache().update(&fix);
+            Ok(fix)
+        }
+        // the receiver turned on by the duty cycle is reacquiring the fix
+        (Err(e), Some(fix)) if e.kind() == ErrorKind::GnssNotFixed => Ok(fix),
+        (Err(e), _) => Err(e),
+    }
 }
 
 fn is_on(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<bool> {
     fn resolver(result: String) -> ResolverReturn<bool> {
         match GNSS_POWER_REGEX.captures(&result) {
             Some(captured) => {
-                let status: u8 = captured["number"].parse().expect(PARSING_ERROR);
-                Ok(if status == 1 { true } else { false })
+                let status: u8 = parse_field("power_status", &captured["number"])?;
+                Ok(status == 1)
             }
             None => Err(Error::NotResolved),
         }
     }
 
-    serial_port.process(task_id, "AT+CGNSPWR?\n".to_string(), resolver, None)
+    serial_port.require(Feature::GNSS)?;
+    let result: ResolverReturn<bool> = serial_port.process(
+        task_id,
+        "AT+CGNSPWR?\n".to_string(),
+        resolver,
+        Some(serial_port.timeouts.gnss),
+    );
+    serial_port.update_state(|state| state.gnss_power = result.as_ref().ok().copied());
+    result
 }
 
 fn turn_on(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<()> {
     fn resolver(result: String) -> ResolverReturn<()> {
         generic_resolver(&result, Error::GnssProblem)
     }
-    serial_port.process(task_id, "AT+CGNSPWR=1\n".to_string(), resolver, None)
+    serial_port.require(Feature::GNSS)?;
+    if serial_port.state().gnss_power == Some(true) {
+        return Ok(());
+    }
+    let result: ResolverReturn<()> = serial_port.process(
+        task_id,
+        "AT+CGNSPWR=1\n".to_string(),
+        resolver,
+        Some(serial_port.timeouts.gnss),
+    );
+    serial_port.update_state(|state| state.gnss_power = result.as_ref().ok().map(|_| true));
+    result
+}
+
+/// Sets the interval of the `+UGNSINF` reports to every `rate` fixes (`AT+CGNSURC`), 0 turns them off.
+fn set_report_rate(serial_port: &Arc<SerialPort>, task_id: &Uuid, rate: u8) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::GnssProblem)
+    }
+    serial_port.require(Feature::GNSS)?;
+    serial_port.process(
+        task_id,
+        format!("AT+CGNSURC={rate}\n"),
+        resolver,
+        Some(serial_port.timeouts.gnss),
+    )
+}
+
+/// Fixes reported by the modem, returned from [`GNSS::subscribe`].
+///
+/// The reports without a fix are skipped, and so are the ones missed while the subscriber has been lagging
+/// behind (see [`crate::SIM868::events`]). Dropping the stream doesn't stop the reports, see
+/// [`GNSS::unsubscribe`].
+pub struct FixStream {
+    events: Receiver<Event>,
+}
+
+impl FixStream {
+    /// Waits for the next fix, `None` once the connection has been closed.
+    pub async fn next(&mut self) -> Option<GNSSData> {
+        loop {
+            match self.events.recv().await {
+                Ok(Event::GnssFix(fix)) => return Some(fix),
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Blocking version of [`FixStream::next`].
+    #[cfg(feature = "blocking")]
+    pub fn next_blocking(&mut self) -> Option<GNSSData> {
+        block_on(self.next())
+    }
+}
+
+/// Turns the NMEA output on the serial port on or off (`AT+CGNSTST`).
+fn set_nmea_output(serial_port: &Arc<SerialPort>, task_id: &Uuid, on: bool) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::GnssProblem)
+    }
+    serial_port.require(Feature::GNSS)?;
+    let result: ResolverReturn<()> = serial_port.process(
+        task_id,
+        format!("AT+CGNSTST={}\n", on as u8),
+        resolver,
+        Some(serial_port.timeouts.gnss),
+    );
+    serial_port.update_state(|state| state.nmea_output = result.as_ref().ok().map(|_| on));
+    result
+}
+
+/// NMEA sentences sent by the receiver, returned from [`GNSS::nmea_stream`].
+///
+/// The sentences missed while the subscriber has been lagging behind (more than 256 of them) are skipped.
+/// Dropping the stream doesn't turn the output off, see [`GNSS::stop_nmea_stream`].
+pub struct NmeaStream {
+    sentences: Receiver<String>,
+}
+
+impl NmeaStream {
+    /// Waits for the next sentence (eg. `$GPRMC,...*7A`, without the line break), `None` once the connection
+    /// has been closed.
+    pub async fn next(&mut self) -> Option<String> {
+        loop {
+            match self.sentences.recv().await {
+                Ok(sentence) => return Some(sentence),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// [`NmeaStream::next`] parsed by [`parse_nmea`]. The sentences the parser doesn't support are returned as
+    /// [`Error::ParseFailure`].
+    #[cfg(feature = "nmea")]
+    pub async fn next_parsed(&mut self) -> Option<Result<nmea::ParseResult, Error>> {
+        self.next()
+            .await
+            .map(|sentence: String| parse_nmea(&sentence))
+    }
+
+    /// Blocking version of [`NmeaStream::next`].
+    #[cfg(feature = "blocking")]
+    pub fn next_blocking(&mut self) -> Option<String> {
+        block_on(self.next())
+    }
+}
+
+/// Kind of the GNSS receiver restart, differing in the data kept from the previous fix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RestartMode {
+    /// Discards the almanac, ephemeris, time and position, as if the receiver were moved far away.
+    Cold,
+    /// Keeps the almanac, time and position, discards the ephemeris.
+    Warm,
+    /// Keeps everything, re-acquires the satellites.
+    Hot,
+}
+
+fn restart(serial_port: &Arc<SerialPort>, task_id: &Uuid, mode: RestartMode) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::GnssProblem)
+    }
+
+    let command: &str = match mode {
+        RestartMode::Cold => "AT+CGNSCOLD\n",
+        RestartMode::Warm => "AT+CGNSWARM\n",
+        RestartMode::Hot => "AT+CGNSHOT\n",
+    };
+    serial_port.require(Feature::GNSS)?;
+    serial_port.process(
+        task_id,
+        command.to_string(),
+        resolver,
+        Some(serial_port.timeouts.gnss),
+    )
+}
+
+/// Where [`GNSS::update_assistance`] takes the EPO file from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AssistanceSource {
+    /// Downloaded by the modem over GPRS from the URL (`AT+HTTPTOFS`), eg. [`EPO_URL`]. The bearer must be
+    /// configured by `GPRS::init`, it's opened for the download if it's closed (`gprs` feature).
+    #[cfg(feature = "gprs")]
+    Download(String),
+    /// EPO file downloaded by the host, eg. `std::fs::read("EPO_GR_3_1.DAT")?` - written to the modem over the
+    /// serial port.
+    File(Vec<u8>),
+}
+
+fn update_assistance(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    source: AssistanceSource,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::GnssAssistanceNotLoaded)
+    }
+
+    serial_port.require(Feature::GNSS)?;
+    match source {
+        #[cfg(feature = "gprs")]
+        AssistanceSource::Download(url) => {
+            let path: String = format!("{}{EPO_FILE}", crate::fs::USER_DIRECTORY);
+            let length: usize = crate::gprs::download_to_file(serial_port, task_id, &url, &path)?;
+            log::info!("GNSS - {length} bytes of the assistance data downloaded.");
+        }
+        AssistanceSource::File(data) => {
+            write_file(serial_port, task_id, (EPO_FILE.to_string(), data))?
+        }
+    }
+    // checks the file, then has the receiver use it
+    for command in ["AT+CGNSCHK=3,1\n", "AT+CGNSAID=31,1,1\n"] {
+        serial_port.process(
+            task_id,
+            command.to_string(),
+            resolver,
+            Some(serial_port.timeouts.gnss),
+        )?;
+    }
+    Ok(())
+}
+
+/// Quality of the fix [`GNSS::wait_for_fix`] waits for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FixCriteria {
+    /// Satellites in use, 4 by default - the fewest for a 3D fix.
+    pub min_sats: u8,
+    /// Highest HDOP, 5 by default. The fixes the modem reports without it don't meet the criteria.
+    pub max_hdop: Option<f32>,
+    /// Time after which [`GNSS::wait_for_fix`] gives up, 15 minutes by default.
+    pub timeout: Duration,
+}
+
+impl Default for FixCriteria {
+    fn default() -> Self {
+        FixCriteria {
+            min_sats: 4,
+            max_hdop: Some(5.0),
+            timeout: MAX_TTFF,
+        }
+    }
+}
+
+impl FixCriteria {
+    pub fn is_met_by(&self, fix: &GNSSData) -> bool {
+        let hdop_met: bool = match self.max_hdop {
+            Some(max_hdop) => fix.hdop.is_some_and(|hdop: f32| hdop <= max_hdop),
+            None => true,
+        };
+        fix.sats_in_use >= self.min_sats && hdop_met
+    }
+}
+
+/// Result of [`GNSS::measure_ttff`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TtffMeasurement {
+    pub mode: RestartMode,
+    /// Time from the restart to the first fix.
+    pub time_to_first_fix: Duration,
+    /// The first fix, with its quality (satellites in view and in use).
+    pub fix: GNSSData,
+}
+
+/// NMEA sentences output by the receiver, see [`GnssConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SentenceMask {
+    /// Fix data - the position, altitude and quality.
+    pub gga: bool,
+    /// Position and time.
+    pub gll: bool,
+    /// Dilution of precision and the satellites in use.
+    pub gsa: bool,
+    /// Satellites in view, used by [`GNSS::satellites`].
+    pub gsv: bool,
+    /// Recommended minimum - the position, speed, course and date.
+    pub rmc: bool,
+    /// Speed and course.
+    pub vtg: bool,
+    /// Date and time.
+    pub zda: bool,
+}
+
+impl SentenceMask {
+    pub const ALL: SentenceMask = SentenceMask {
+        gga: true,
+        gll: true,
+        gsa: true,
+        gsv: true,
+        rmc: true,
+        vtg: true,
+        zda: true,
+    };
+
+    /// `$PMTK314` (the output of each sentence type, every fix or never) of the mask.
+    fn command(&self) -> String {
+        let [gll, rmc, vtg, gga, gsa, gsv, zda] = [
+            self.gll, self.rmc, self.vtg, self.gga, self.gsa, self.gsv, self.zda,
+        ]
+        .map(u8::from);
+        // GRS, GST and the reserved ones aren't output by the receiver, nor the MCHN after the ZDA
+        format!("PMTK314,{gll},{rmc},{vtg},{gga},{gsa},{gsv},0,0,0,0,0,0,0,0,0,0,0,{zda},0")
+    }
+}
+
+/// The receiver's default output - all the sentences but GLL and ZDA.
+impl Default for SentenceMask {
+    fn default() -> Self {
+        SentenceMask {
+            gll: false,
+            zda: false,
+            ..SentenceMask::ALL
+        }
+    }
+}
+
+/// Configuration of the receiver, see [`GNSS::configure`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GnssConfig {
+    /// Whether the GLONASS satellites are used along with the GPS ones, on by default. GPS only takes less
+    /// power, but fixes slower and less accurately.
+    pub glonass: bool,
+    /// NMEA sentences output by [`GNSS::nmea_stream`], fewer of them load the serial port less.
+    pub nmea_sentences: SentenceMask,
+    /// Fixes per second (Hz) - 1, the default, up to 10.
+    pub rate: u8,
+}
+
+impl Default for GnssConfig {
+    fn default() -> Self {
+        GnssConfig {
+            glonass: true,
+            nmea_sentences: SentenceMask::default(),
+            rate: 1,
+        }
+    }
+}
+
+/// Sends the PMTK commands of the `config` to the receiver (`AT+CGNSCMD`).
+fn configure(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    config: GnssConfig,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::GnssProblem)
+    }
+
+    serial_port.require(Feature::GNSS)?;
+    let commands: [String; 3] = [
+        // GPS, GLONASS, and the Galileo and BeiDou the receiver of the SIM868 doesn't track
+        format!("PMTK353,1,{},0,0,0", u8::from(config.glonass)),
+        config.nmea_sentences.command(),
+        // time between the fixes, ms
+        format!("PMTK220,{}", 1000 / u16::from(config.rate.clamp(1, 10))),
+    ];
+    for command in commands {
+        let checksum: u8 = command
+            .bytes()
+            .fold(0, |checksum: u8, byte: u8| checksum ^ byte);
+        serial_port.process(
+            task_id,
+            AtCommand::set("+CGNSCMD")
+                .number(0)
+                .string(&format!("${command}*{checksum:02X}"))
+                .into(),
+            resolver,
+            Some(serial_port.timeouts.gnss),
+        )?;
+    }
+    Ok(())
 }
 
 fn turn_off(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<()> {
     fn resolver(result: String) -> ResolverReturn<()> {
         generic_resolver(&result, Error::GnssProblem)
     }
-    serial_port.process(task_id, "AT+CGNSPWR=0\n".to_string(), resolver, None)
+    serial_port.require(Feature::GNSS)?;
+    let result: ResolverReturn<()> = serial_port.process(
+        task_id,
+        "AT+CGNSPWR=0\n".to_string(),
+        resolver,
+        Some(serial_port.timeouts.gnss),
+    );
+    serial_port.update_state(|state| state.gnss_power = result.as_ref().ok().map(|_| false));
+    result
+}
+
+/// Captures the NMEA output (`AT+CGNSTST=1`) and parses the satellites in view from it. The output is left on
+/// if it was on already, eg. for [`GNSS::nmea_stream`].
+#[cfg(feature = "nmea")]
+fn satellites(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<BTreeMap<Constellation, Vec<SatelliteInView>>> {
+    // the sentences are taken out of the response, and published
+    fn resolver(_: String) -> ResolverReturn<()> {
+        Err(Error::NotResolved)
+    }
+
+    serial_port.require(Feature::GNSS)?;
+    let mut sentences: Receiver<String> = serial_port.nmea.subscribe();
+    let was_on: bool = serial_port.state().nmea_output == Some(true);
+    if !was_on {
+        set_nmea_output(serial_port, task_id, true)?;
+    }
+
+    // reads the serial port until the capture time is over
+    let captured: ResolverReturn<()> =
+        match serial_port.read(task_id, resolver, Some(NMEA_CAPTURE_TIME)) {
+            Ok(_) | Err(Error::NotResolved) => Ok(()),
+            Err(e) => Err(e),
+        };
+
+    // the output is turned off before returning an error, so it doesn't flood the following commands
+    if !was_on {
+        set_nmea_output(serial_port, task_id, false)?;
+    }
+    captured?;
+    let mut output: String = String::new();
+    while let Ok(sentence) = sentences.try_recv() {
+        output.push_str(&sentence);
+        output.push('\n');
+    }
+    Ok(satellites_in_view(&output))
 }
 
 /// GNSS Module
+///
+/// The handle doesn't keep the connection open, see [`crate::SIM868`].
+#[derive(Clone)]
 pub struct GNSS {
     serial_port: Arc<SerialPort>,
 }
@@ -151,7 +1039,8 @@ impl GNSS {
         )
     }
 
-    // Get fixed GNSS data.
+    /// Get fixed GNSS data. Fails with [`Error::GnssNotFixed`] until the receiver has the position, and with
+    /// [`Error::ParseFailure`] on a malformed field - a missing one is `None` in the [`GNSSData`].
     pub fn get_data(&self) -> TaskJoinHandle<GNSSData> {
         spawn_task(
             self.serial_port.clone(),
@@ -161,4 +1050,1107 @@ impl GNSS {
             (),
         )
     }
+
+    /// Has the modem report the position every `rate` fixes (the receiver fixes once a second, so 1 - at 1 Hz)
+    /// and returns the stream of the reported fixes, see [`FixStream`]. The reports stop once the GNSS module
+    /// or the modem has been restarted, or by [`GNSS::unsubscribe`]. The GNSS module must be on.
+    pub async fn subscribe(&self, rate: u8) -> ResolverReturn<FixStream> {
+        // subscribed first, so the first report isn't missed
+        let events: Receiver<Event> = self.serial_port.events.subscribe();
+        join_task(spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_report_rate,
+            Some("Enabling GNSS reports...".to_string()),
+            rate.max(1),
+        ))
+        .await?;
+        Ok(FixStream { events })
+    }
+
+    /// Turns on the NMEA output of the receiver and returns the stream of the sentences, see [`NmeaStream`]. The
+    /// output stops once the GNSS module or the modem has been restarted, or by [`GNSS::stop_nmea_stream`].
+    /// The GNSS module must be on.
+    pub async fn nmea_stream(&self) -> ResolverReturn<NmeaStream> {
+        // subscribed first, so the first sentences aren't missed
+        let sentences: Receiver<String> = self.serial_port.nmea.subscribe();
+        join_task(spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_nmea_output,
+            Some("Turning NMEA output on...".to_string()),
+            true,
+        ))
+        .await?;
+        Ok(NmeaStream { sentences })
+    }
+
+    /// Turns off the NMEA output turned on by [`GNSS::nmea_stream`].
+    pub fn stop_nmea_stream(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_nmea_output,
+            Some("Turning NMEA output off...".to_string()),
+            false,
+        )
+    }
+
+    /// Turns off the reports enabled by [`GNSS::subscribe`].
+    pub fn unsubscribe(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_report_rate,
+            Some("Disabling GNSS reports...".to_string()),
+            0,
+        )
+    }
+
+    /// Loads the assistance data (AGPS) from the `source` into the receiver. The modem needs the current time
+    /// in its RTC (see `Clock::ntp_sync`, `clock` feature) for the data to be valid - fails with
+    /// [`Error::GnssAssistanceNotLoaded`] otherwise. The data is used from the next start of the GNSS module,
+    /// so it's best loaded before [`GNSS::turn_on`].
+    pub fn update_assistance(&self, source: AssistanceSource) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            update_assistance,
+            Some("Updating GNSS assistance data...".to_string()),
+            source,
+        )
+    }
+
+    /// Restarts the receiver in the `mode` (`AT+CGNSCOLD`, `AT+CGNSWARM` or `AT+CGNSHOT`), see [`RestartMode`].
+    /// The GNSS module must be on.
+    pub fn restart(&self, mode: RestartMode) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            restart,
+            Some(format!("Restarting GNSS module ({mode:?})...")),
+            mode,
+        )
+    }
+
+    /// Selects the constellations, the NMEA sentences and the rate of the fixes, see [`GnssConfig`]. The GNSS
+    /// module must be on, and the receiver may forget the configuration once it's turned off - configure it
+    /// after every [`GNSS::turn_on`].
+    pub fn configure(&self, config: GnssConfig) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            configure,
+            Some("Configuring GNSS...".to_string()),
+            config,
+        )
+    }
+
+    /// Restarts the receiver in the `mode` and polls the position every second until it's fixed, returning
+    /// the time it took. Fails with [`Error::GnssNotFixed`] after 15 minutes. The GNSS module must be on. The
+    /// position is read from the receiver every time, the fix cached by [`GNSS::duty_cycle`] isn't used.
+    pub async fn measure_ttff(&self, mode: RestartMode) -> ResolverReturn<TtffMeasurement> {
+        self.restart_now(mode).await?;
+        let restarted_at: Instant = Instant::now();
+
+        while restarted_at.elapsed() < MAX_TTFF {
+            let fix: ResolverReturn<GNSSData> = join_task(spawn_task(
+                self.serial_port.clone(),
+                TaskPriority::NORMAL,
+                read_fix,
+                Some("Getting GNSS data...".to_string()),
+                (),
+            ))
+            .await;
+            match fix {
+                Ok(fix) => {
+                    let time_to_first_fix: Duration = restarted_at.elapsed();
+                    log::info!("GNSS - {mode:?} start fixed in {time_to_first_fix:?}.");
+                    return Ok(TtffMeasurement {
+                        mode,
+                        time_to_first_fix,
+                        fix,
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::GnssNotFixed => sleep(FIX_POLL_INTERVAL).await,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(Error::GnssNotFixed)
+    }
+
+    /// Polls the position every second until the fix meets the `criteria`, returning it. Fails with
+    /// [`Error::GnssNotFixed`] once the timeout of the criteria is over. The GNSS module must be on.
+    pub async fn wait_for_fix(&self, criteria: FixCriteria) -> ResolverReturn<GNSSData> {
+        let started_at: Instant = Instant::now();
+        while started_at.elapsed() < criteria.timeout {
+            match self.get_data_now().await {
+                Ok(fix) if criteria.is_met_by(&fix) => return Ok(fix),
+                Ok(_) => (),
+                Err(e) if e.kind() == ErrorKind::GnssNotFixed => (),
+                Err(e) => return Err(e),
+            }
+            sleep(FIX_POLL_INTERVAL).await;
+        }
+        Err(Error::GnssNotFixed)
+    }
+
+    /// Starts polling the position every `interval` in the background and publishing
+    /// [`Event::GeofenceEntered`], [`Event::GeofenceExited`] and [`Event::GeofenceDwell`] for the fences added
+    /// to the returned [`Geofence`], until it's stopped or dropped. The positions are skipped while the receiver
+    /// has no fix.
+    pub fn geofence(&self, interval: Duration) -> Geofence {
+        let fences: Arc<Mutex<BTreeMap<String, FenceState>>> = Arc::default();
+        Geofence {
+            fences: fences.clone(),
+            handle: tokio::spawn(watch_fences(self.clone(), fences, interval)),
+        }
+    }
+
+    /// Turns the GNSS module on for the `on` time and off for the `off` time, repeatedly, in the background -
+    /// the receiver draws about 25 mA while on. The last fix of every window is cached: [`GNSS::get_data`]
+    /// returns it while the module is off, and until the receiver has fixed again in the next window (check
+    /// its `utc_datetime` for the age). The `on` time has to be long enough to fix, a hot start takes a couple
+    /// of seconds. The cycle runs until the returned [`DutyCycle`] is stopped or dropped.
+    pub fn duty_cycle(&self, on: Duration, off: Duration) -> DutyCycle {
+        *self.serial_port.fix_cache() = FixCache {
+            active: true,
+            ..FixCache::default()
+        };
+        DutyCycle {
+            serial_port: self.serial_port.clone(),
+            handle: tokio::spawn(run_duty_cycle(self.clone(), on, off)),
+        }
+    }
+
+    /// Satellites in view grouped by constellation, read from the NMEA output captured for a couple of
+    /// seconds. The satellites with no SNR are in view but not received, eg. blocked by the antenna placement.
+    #[cfg(feature = "nmea")]
+    pub fn satellites(&self) -> TaskJoinHandle<BTreeMap<Constellation, Vec<SatelliteInView>>> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            satellites,
+            Some("Reading the satellites in view...".to_string()),
+            (),
+        )
+    }
+}
+
+impl GNSS {
+    /// [`GNSS::is_on`] returning the result directly.
+    pub async fn is_on_now(&self) -> ResolverReturn<bool> {
+        join_task(self.is_on()).await
+    }
+
+    /// [`GNSS::turn_on`] returning the result directly.
+    pub async fn turn_on_now(&self) -> ResolverReturn<()> {
+        join_task(self.turn_on()).await
+    }
+
+    /// [`GNSS::turn_off`] returning the result directly.
+    pub async fn turn_off_now(&self) -> ResolverReturn<()> {
+        join_task(self.turn_off()).await
+    }
+
+    /// [`GNSS::get_data`] returning the result directly.
+    pub async fn get_data_now(&self) -> ResolverReturn<GNSSData> {
+        join_task(self.get_data()).await
+    }
+
+    /// [`GNSS::update_assistance`] returning the result directly.
+    pub async fn update_assistance_now(&self, source: AssistanceSource) -> ResolverReturn<()> {
+        join_task(self.update_assistance(source)).await
+    }
+
+    /// [`GNSS::configure`] returning the result directly.
+    pub async fn configure_now(&self, config: GnssConfig) -> ResolverReturn<()> {
+        join_task(self.configure(config)).await
+    }
+
+    /// [`GNSS::restart`] returning the result directly.
+    pub async fn restart_now(&self, mode: RestartMode) -> ResolverReturn<()> {
+        join_task(self.restart(mode)).await
+    }
+
+    /// [`GNSS::unsubscribe`] returning the result directly.
+    pub async fn unsubscribe_now(&self) -> ResolverReturn<()> {
+        join_task(self.unsubscribe()).await
+    }
+
+    /// [`GNSS::stop_nmea_stream`] returning the result directly.
+    pub async fn stop_nmea_stream_now(&self) -> ResolverReturn<()> {
+        join_task(self.stop_nmea_stream()).await
+    }
+
+    /// [`GNSS::satellites`] returning the result directly.
+    #[cfg(feature = "nmea")]
+    pub async fn satellites_now(
+        &self,
+    ) -> ResolverReturn<BTreeMap<Constellation, Vec<SatelliteInView>>> {
+        join_task(self.satellites()).await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl GNSS {
+    /// Blocking version of [`GNSS::is_on`].
+    pub fn is_on_blocking(&self) -> ResolverReturn<bool> {
+        block_on_task(|| self.is_on())
+    }
+
+    /// Blocking version of [`GNSS::turn_on`].
+    pub fn turn_on_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.turn_on())
+    }
+
+    /// Blocking version of [`GNSS::turn_off`].
+    pub fn turn_off_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.turn_off())
+    }
+
+    /// Blocking version of [`GNSS::get_data`].
+    pub fn get_data_blocking(&self) -> ResolverReturn<GNSSData> {
+        block_on_task(|| self.get_data())
+    }
+
+    /// Blocking version of [`GNSS::update_assistance`].
+    pub fn update_assistance_blocking(&self, source: AssistanceSource) -> ResolverReturn<()> {
+        block_on_task(|| self.update_assistance(source))
+    }
+
+    /// Blocking version of [`GNSS::configure`].
+    pub fn configure_blocking(&self, config: GnssConfig) -> ResolverReturn<()> {
+        block_on_task(|| self.configure(config))
+    }
+
+    /// Blocking version of [`GNSS::restart`].
+    pub fn restart_blocking(&self, mode: RestartMode) -> ResolverReturn<()> {
+        block_on_task(|| self.restart(mode))
+    }
+
+    /// Blocking version of [`GNSS::subscribe`].
+    pub fn subscribe_blocking(&self, rate: u8) -> ResolverReturn<FixStream> {
+        block_on(self.subscribe(rate))
+    }
+
+    /// Blocking version of [`GNSS::unsubscribe`].
+    pub fn unsubscribe_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.unsubscribe())
+    }
+
+    /// Blocking version of [`GNSS::nmea_stream`].
+    pub fn nmea_stream_blocking(&self) -> ResolverReturn<NmeaStream> {
+        block_on(self.nmea_stream())
+    }
+
+    /// Blocking version of [`GNSS::stop_nmea_stream`].
+    pub fn stop_nmea_stream_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.stop_nmea_stream())
+    }
+
+    /// Blocking version of [`GNSS::measure_ttff`].
+    pub fn measure_ttff_blocking(&self, mode: RestartMode) -> ResolverReturn<TtffMeasurement> {
+        block_on(self.measure_ttff(mode))
+    }
+
+    /// Blocking version of [`GNSS::wait_for_fix`].
+    pub fn wait_for_fix_blocking(&self, criteria: FixCriteria) -> ResolverReturn<GNSSData> {
+        block_on(self.wait_for_fix(criteria))
+    }
+
+    /// Blocking version of [`GNSS::satellites`].
+    #[cfg(feature = "nmea")]
+    pub fn satellites_blocking(
+        &self,
+    ) -> ResolverReturn<BTreeMap<Constellation, Vec<SatelliteInView>>> {
+        block_on_task(|| self.satellites())
+    }
+}
+
+/// Position recorded by the [`TrackLogger`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackPoint {
+    pub utc_datetime: chrono::DateTime<Utc>,
+    pub lat: f64,
+    pub lon: f64,
+    /// Meters above MSL
+    pub alt: Option<f32>,
+    /// km/h
+    pub ground_speed: Option<f32>,
+    /// degrees
+    pub ground_course: Option<f32>,
+    pub sats_in_use: u8,
+    pub hdop: Option<f32>,
+}
+
+impl From<&GNSSData> for TrackPoint {
+    fn from(fix: &GNSSData) -> Self {
+        TrackPoint {
+            utc_datetime: fix.utc_datetime,
+            lat: fix.lat,
+            lon: fix.lon,
+            alt: fix.alt,
+            ground_speed: fix.ground_speed,
+            ground_course: fix.ground_course,
+            sats_in_use: fix.sats_in_use,
+            hdop: fix.hdop,
+        }
+    }
+}
+
+const TRACK_CSV_HEADER: &str =
+    "utc_datetime,lat,lon,alt,ground_speed,ground_course,sats_in_use,hdop";
+
+fn csv_value<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map(|value: T| value.to_string()).unwrap_or_default()
+}
+
+impl TrackPoint {
+    fn time(&self) -> String {
+        self.utc_datetime.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+
+    fn csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{}",
+            self.time(),
+            self.lat,
+            self.lon,
+            csv_value(self.alt),
+            csv_value(self.ground_speed),
+            csv_value(self.ground_course),
+            self.sats_in_use,
+            csv_value(self.hdop),
+        )
+    }
+
+    fn from_csv(line: &str) -> ResolverReturn<TrackPoint> {
+        let data: &Vec<&str> = &line.split(',').collect();
+        let raw_datetime: &str = nth_field(data, 0, "utc_datetime")?;
+        let utc_datetime: chrono::DateTime<Utc> =
+            chrono::DateTime::parse_from_rfc3339(raw_datetime)
+                .map(|datetime| datetime.with_timezone(&Utc))
+                .map_err(|_| Error::ParseFailure {
+                    field: "utc_datetime",
+                    raw: raw_datetime.to_string(),
+                })?;
+        Ok(TrackPoint {
+            utc_datetime,
+            lat: parse_field("lat", nth_field(data, 1, "lat")?)?,
+            lon: parse_field("lon", nth_field(data, 2, "lon")?)?,
+            alt: optional_field(data, 3, "alt")?,
+            ground_speed: optional_field(data, 4, "ground_speed")?,
+            ground_course: optional_field(data, 5, "ground_course")?,
+            sats_in_use: parse_field("sats_in_use", nth_field(data, 6, "sats_in_use")?)?,
+            hdop: optional_field(data, 7, "hdop")?,
+        })
+    }
+
+    fn gpx(&self) -> String {
+        let mut point: String =
+            format!("      <trkpt lat=\"{}\" lon=\"{}\">\n", self.lat, self.lon);
+        if let Some(alt) = self.alt {
+            point.push_str(&format!("        <ele>{alt}</ele>\n"));
+        }
+        point.push_str(&format!("        <time>{}</time>\n", self.time()));
+        point.push_str(&format!("        <sat>{}</sat>\n", self.sats_in_use));
+        if let Some(hdop) = self.hdop {
+            point.push_str(&format!("        <hdop>{hdop}</hdop>\n"));
+        }
+        point.push_str("      </trkpt>\n");
+        point
+    }
+}
+
+/// Configuration of the [`TrackLogger`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackLoggerConfig {
+    /// Time between the positions, 10 s by default.
+    pub interval: Duration,
+    /// Number of the positions kept, 10000 by default (a day at the default interval). The oldest ones are
+    /// dropped first.
+    pub capacity: usize,
+    /// CSV file the track is saved to, `None` (the default) keeps it in memory only.
+    pub path: Option<PathBuf>,
+}
+
+impl Default for TrackLoggerConfig {
+    fn default() -> Self {
+        TrackLoggerConfig {
+            interval: Duration::from_secs(10),
+            capacity: 10000,
+            path: None,
+        }
+    }
+}
+
+struct Track {
+    points: VecDeque<TrackPoint>,
+    capacity: usize,
+    path: Option<PathBuf>,
+    /// Points written to the file, the dropped ones included.
+    saved: usize,
+}
+
+impl Track {
+    fn load(config: &TrackLoggerConfig) -> Result<Self, Error> {
+        let mut track: Track = Track {
+            points: VecDeque::new(),
+            capacity: config.capacity.max(1),
+            path: config.path.clone(),
+            saved: 0,
+        };
+        let Some(path) = &config.path else {
+            return Ok(track);
+        };
+        let text: String = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == IoErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        for line in text.lines().skip(1) {
+            if !line.trim().is_empty() {
+                track.points.push_back(TrackPoint::from_csv(line.trim())?);
+            }
+        }
+        track.saved = track.points.len();
+        track.enforce_capacity();
+        Ok(track)
+    }
+
+    fn enforce_capacity(&mut self) {
+        while self.points.len() > self.capacity {
+            self.points.pop_front();
+        }
+    }
+
+    fn csv(&self) -> String {
+        let mut text: String = format!("{TRACK_CSV_HEADER}\n");
+        for point in &self.points {
+            text.push_str(&point.csv());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Write of the whole track, `None` if it's kept in memory only.
+    fn rewrite(&mut self) -> Option<TrackWrite> {
+        let path: PathBuf = self.path.clone()?;
+        self.saved = self.points.len();
+        Some(TrackWrite::Rewrite {
+            path,
+            text: self.csv(),
+        })
+    }
+
+    fn push(&mut self, point: TrackPoint) -> Option<TrackWrite> {
+        let line: String = point.csv();
+        self.points.push_back(point);
+        self.enforce_capacity();
+        let path: PathBuf = self.path.clone()?;
+        // the points are appended, the file is compacted once it holds twice the capacity
+        if self.saved == 0 || self.saved >= 2 * self.capacity {
+            return self.rewrite();
+        }
+        self.saved += 1;
+        Some(TrackWrite::Append { path, line })
+    }
+}
+
+/// Change of the track file, made once the lock of the points has been released.
+enum TrackWrite {
+    Append { path: PathBuf, line: String },
+    Rewrite { path: PathBuf, text: String },
+}
+
+impl TrackWrite {
+    /// The file is rewritten through a temporary one, so it isn't left half-written.
+    fn run(self) -> Result<(), Error> {
+        match self {
+            TrackWrite::Append { path, line } => {
+                let mut file: std::fs::File =
+                    std::fs::OpenOptions::new().append(true).open(path)?;
+                writeln!(file, "{line}")?;
+            }
+            TrackWrite::Rewrite { path, text } => {
+                let temporary: PathBuf = path.with_extension("tmp");
+                std::fs::write(&temporary, text)?;
+                std::fs::rename(&temporary, path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Track shared by the [`TrackLogger`] and its task. The points are locked only to be updated or read, the
+/// file is written holding the lock of its own, in the order of the updates.
+struct SharedTrack {
+    points: Mutex<Track>,
+    file: Mutex<()>,
+}
+
+impl SharedTrack {
+    fn track(&self) -> std::sync::MutexGuard<'_, Track> {
+        self.points.lock().expect(MUTEX_POISONED_MSG)
+    }
+
+    /// Updates the track and writes the change to the file.
+    fn update(&self, update: impl FnOnce(&mut Track) -> Option<TrackWrite>) -> Result<(), Error> {
+        let _file: std::sync::MutexGuard<'_, ()> = self.file.lock().expect(MUTEX_POISONED_MSG);
+        let write: Option<TrackWrite> = update(&mut self.track());
+        match write.map(TrackWrite::run) {
+            Some(Err(e)) => {
+                // the whole track is written again with the next point
+                self.track().saved = 0;
+                Err(e)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Logging task running in the background, see [`TrackLogger::start`].
+///
+/// The track survives the restarts of the application if it's saved to a file: it's appended to, and
+/// loaded again by the next logger started with the same path.
+///
+/// ```no_run
+/// use rpi_sim868::{
+///     gnss::{TrackLogger, TrackLoggerConfig},
+///     SIM868,
+/// };
+/// use std::time::Duration;
+///
+/// # #[cfg(not(feature = "rpi"))]
+/// # fn main() {}
+/// # #[cfg(feature = "rpi")]
+/// #[tokio::main]
+/// async fn main() -> Result<(), rpi_sim868::Error> {
+///     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+///     sim.gnss.turn_on_now().await?;
+///     let logger: TrackLogger = TrackLogger::start(
+///         &sim,
+///         TrackLoggerConfig {
+///             path: Some("/var/lib/tracker/track.csv".into()),
+///             ..TrackLoggerConfig::default()
+///         },
+///     )?;
+///
+///     tokio::time::sleep(Duration::from_secs(3600)).await;
+///     std::fs::write("/var/lib/tracker/track.gpx", logger.gpx())?;
+///     logger.stop();
+///     Ok(())
+/// }
+/// ```
+pub struct TrackLogger {
+    track: Arc<SharedTrack>,
+    handle: JoinHandle<()>,
+}
+
+async fn log_track(gnss: GNSS, track: Arc<SharedTrack>, interval: Duration) {
+    loop {
+        match gnss.get_data_now().await {
+            Ok(fix) => {
+                let point: TrackPoint = TrackPoint::from(&fix);
+                let track: Arc<SharedTrack> = track.clone();
+                let saved: Result<(), Error> =
+                    tokio::task::spawn_blocking(move || track.update(|track| track.push(point)))
+                        .await
+                        .unwrap_or_else(|e| Err(Error::Io(Arc::new(e.into()))));
+                if let Err(e) = saved {
+                    log::warn!("Track logger - saving the track has failed: {e}");
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::GnssNotFixed => {
+                log::debug!("Track logger - no fix, the position is skipped.")
+            }
+            Err(e) if e.kind() == ErrorKind::Closed => {
+                log::info!("Track logger - the connection has been closed, logging stopped.");
+                return;
+            }
+            Err(e) => log::info!("Track logger - position not available: {e}"),
+        }
+        sleep(interval).await;
+    }
+}
+
+impl TrackLogger {
+    /// Starts recording the position in the background, loading the track saved to the file of the `config`
+    /// (if any). The positions are skipped while the receiver has no fix. The logger doesn't keep the
+    /// connection open: it stops once the last clone of the `sim` has been dropped, or once it's stopped or
+    /// dropped itself.
+    pub fn start(sim: &SIM868, config: TrackLoggerConfig) -> Result<TrackLogger, Error> {
+        let track: Arc<SharedTrack> = Arc::new(SharedTrack {
+            points: Mutex::new(Track::load(&config)?),
+            file: Mutex::new(()),
+        });
+        Ok(TrackLogger {
+            track: track.clone(),
+            handle: tokio::spawn(log_track(sim.gnss.clone(), track, config.interval)),
+        })
+    }
+
+    fn track(&self) -> std::sync::MutexGuard<'_, Track> {
+        self.track.track()
+    }
+
+    /// Recorded positions, the oldest first.
+    pub fn points(&self) -> Vec<TrackPoint> {
+        self.track().points.iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.track().points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.track().points.is_empty()
+    }
+
+    /// Drops the recorded positions, emptying the file too.
+    pub fn clear(&self) -> Result<(), Error> {
+        self.track.update(|track: &mut Track| {
+            track.points.clear();
+            track.rewrite()
+        })
+    }
+
+    /// Track as CSV, with the header line and empty values for the ones the modem hasn't reported.
+    pub fn csv(&self) -> String {
+        self.track().csv()
+    }
+
+    /// Track as GPX 1.1, eg. for loading it into a map application.
+    pub fn gpx(&self) -> String {
+        let mut text: String = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<gpx version=\"1.1\" creator=\"rpi_sim868\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+            "  <trk>\n",
+            "    <trkseg>\n",
+        ));
+        for point in &self.track().points {
+            text.push_str(&point.gpx());
+        }
+        text.push_str("    </trkseg>\n  </trk>\n</gpx>\n");
+        text
+    }
+
+    pub fn stop(self) {
+        self.handle.abort();
+    }
+}
+
+impl Drop for TrackLogger {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Mean radius of the Earth, meters.
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Great-circle distance in meters between two positions (degrees).
+pub fn distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat_from, lat_to) = (from.0.to_radians(), to.0.to_radians());
+    let half_chord: f64 = ((lat_to - lat_from) / 2.0).sin().powi(2)
+        + lat_from.cos() * lat_to.cos() * ((to.1 - from.1).to_radians() / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * half_chord.sqrt().asin()
+}
+
+/// Area of the [`Fence`], in degrees.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FenceArea {
+    /// Within `radius` meters from the centre.
+    Circle { lat: f64, lon: f64, radius: f64 },
+    /// Within the polygon of the `(lat, lon)` vertices, in order, including the edges and the vertices. The edges
+    /// are straight lines on the map, which is accurate enough for the fences up to tens of kilometres.
+    Polygon(Vec<(f64, f64)>),
+}
+
+impl FenceArea {
+    /// ```
+    /// use rpi_sim868::gnss::FenceArea;
+    ///
+    /// let park = FenceArea::Circle { lat: 51.5073, lon: -0.1657, radius: 500.0 };
+    /// assert!(park.contains(51.5080, -0.1650));
+    /// let square = FenceArea::Polygon(vec![(51.0, -1.0), (51.0, 0.0), (52.0, 0.0), (52.0, -1.0)]);
+    /// assert!(!square.contains(51.5, 0.1));
+    /// assert!(square.contains(51.5, 0.0));
+    /// ```
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        match self {
+            FenceArea::Circle {
+                lat: centre_lat,
+                lon: centre_lon,
+                radius,
+            } => distance((*centre_lat, *centre_lon), (lat, lon)) <= *radius,
+            // the ray casting - the point is inside if a ray from it crosses the edges an odd number of times
+            FenceArea::Polygon(vertices) => {
+                let mut inside: bool = false;
+                let edges = vertices.iter().zip(vertices.iter().cycle().skip(1));
+                for (&(lat_a, lon_a), &(lat_b, lon_b)) in edges {
+                    if on_edge((lat, lon), (lat_a, lon_a), (lat_b, lon_b)) {
+                        return true;
+                    }
+                    if (lat_a > lat) != (lat_b > lat)
+                        && lon < lon_a + (lat - lat_a) * (lon_b - lon_a) / (lat_b - lat_a)
+                    {
+                        inside = !inside;
+                    }
+                }
+                inside
+            }
+        }
+    }
+}
+
+/// Tolerance of [`on_edge`] in square degrees, around a millimetre off a kilometre long edge.
+const EDGE_TOLERANCE: f64 = 1e-10;
+
+/// Whether the point lies on the edge from `a` to `b`, the ray casting alone counts only some of the edges in.
+fn on_edge(point: (f64, f64), a: (f64, f64), b: (f64, f64)) -> bool {
+    let cross: f64 = (b.0 - a.0) * (point.1 - a.1) - (b.1 - a.1) * (point.0 - a.0);
+    cross.abs() <= EDGE_TOLERANCE
+        && point.0 >= a.0.min(b.0)
+        && point.0 <= a.0.max(b.0)
+        && point.1 >= a.1.min(b.1)
+        && point.1 <= a.1.max(b.1)
+}
+
+/// Area watched by the [`Geofence`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fence {
+    /// Name the events refer to the fence with, unique within the [`Geofence`].
+    pub name: String,
+    pub area: FenceArea,
+    /// Time inside the fence after which [`Event::GeofenceDwell`] is published, `None` for no dwell events.
+    pub dwell_time: Option<Duration>,
+}
+
+struct FenceState {
+    fence: Fence,
+    /// `None` until the first fix.
+    inside: Option<bool>,
+    entered_at: Option<Instant>,
+    dwell_reported: bool,
+}
+
+impl FenceState {
+    fn update(&mut self, fix: &GNSSData) -> Option<Event> {
+        let inside: bool = self.fence.area.contains(fix.lat, fix.lon);
+        let was_inside: Option<bool> = self.inside.replace(inside);
+        let fence: String = self.fence.name.clone();
+        match (was_inside, inside) {
+            (Some(true), false) => {
+                self.entered_at = None;
+                Some(Event::GeofenceExited {
+                    fence,
+                    fix: fix.clone(),
+                })
+            }
+            (Some(false) | None, true) => {
+                self.entered_at = Some(Instant::now());
+                self.dwell_reported = false;
+                Some(Event::GeofenceEntered {
+                    fence,
+                    fix: fix.clone(),
+                })
+            }
+            (Some(true), true) => {
+                let dwell: Duration = self.entered_at?.elapsed();
+                if self.dwell_reported || self.fence.dwell_time.map_or(true, |time| dwell < time) {
+                    return None;
+                }
+                self.dwell_reported = true;
+                Some(Event::GeofenceDwell {
+                    fence,
+                    fix: fix.clone(),
+                    dwell,
+                })
+            }
+            (_, false) => None,
+        }
+    }
+}
+
+/// Fences watched in the background, see [`GNSS::geofence`].
+///
+/// ```no_run
+/// use rpi_sim868::{
+///     events::Event,
+///     gnss::{Fence, FenceArea, Geofence},
+///     SIM868,
+/// };
+/// use std::time::Duration;
+///
+/// # #[cfg(not(feature = "rpi"))]
+/// # fn main() {}
+/// # #[cfg(feature = "rpi")]
+/// #[tokio::main]
+/// async fn main() -> Result<(), rpi_sim868::Error> {
+///     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+///     sim.gnss.turn_on_now().await?;
+///     let mut events = sim.events();
+///     let geofence: Geofence = sim.gnss.geofence(Duration::from_secs(10));
+///     geofence.add(Fence {
+///         name: "yard".to_string(),
+///         area: FenceArea::Circle { lat: 51.5073, lon: -0.1657, radius: 200.0 },
+///         dwell_time: None,
+///     });
+///     while let Ok(event) = events.recv().await {
+///         if let Event::GeofenceExited { fence, fix } = event {
+///             println!("left the {fence} at {} {}", fix.lat, fix.lon);
+///         }
+///     }
+///     geofence.stop();
+///     Ok(())
+/// }
+/// ```
+pub struct Geofence {
+    fences: Arc<Mutex<BTreeMap<String, FenceState>>>,
+    handle: JoinHandle<()>,
+}
+
+async fn watch_fences(
+    gnss: GNSS,
+    fences: Arc<Mutex<BTreeMap<String, FenceState>>>,
+    interval: Duration,
+) {
+    loop {
+        match gnss.get_data_now().await {
+            Ok(fix) => {
+                let events: Vec<Event> = fences
+                    .lock()
+                    .expect(MUTEX_POISONED_MSG)
+                    .values_mut()
+                    .filter_map(|state: &mut FenceState| state.update(&fix))
+                    .collect();
+                for event in events {
+                    let _ = gnss.serial_port.events.send(event);
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::GnssNotFixed => {
+                log::debug!("Geofence - no fix, the fences are left as they are.")
+            }
+            Err(e) if e.kind() == ErrorKind::Closed => {
+                log::info!("Geofence - the connection has been closed, watching stopped.");
+                return;
+            }
+            Err(e) => log::info!("Geofence - position not available: {e}"),
+        }
+        sleep(interval).await;
+    }
+}
+
+impl Geofence {
+    fn fences(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, FenceState>> {
+        self.fences.lock().expect(MUTEX_POISONED_MSG)
+    }
+
+    /// Starts watching the `fence`, replacing the one of the same name. It's entered with the first fix inside
+    /// it, no event is published if the first fix is outside.
+    pub fn add(&self, fence: Fence) {
+        self.fences().insert(
+            fence.name.clone(),
+            FenceState {
+                fence,
+                inside: None,
+                entered_at: None,
+                dwell_reported: false,
+            },
+        );
+    }
+
+    /// Stops watching the fence `name`, returning it.
+    pub fn remove(&self, name: &str) -> Option<Fence> {
+        self.fences()
+            .remove(name)
+            .map(|state: FenceState| state.fence)
+    }
+
+    pub fn list(&self) -> Vec<Fence> {
+        self.fences()
+            .values()
+            .map(|state: &FenceState| state.fence.clone())
+            .collect()
+    }
+
+    /// Whether the last fix has been inside the fence `name`, `None` if there is no such fence or no fix yet.
+    pub fn is_inside(&self, name: &str) -> Option<bool> {
+        self.fences().get(name)?.inside
+    }
+
+    pub fn stop(self) {
+        self.handle.abort();
+    }
+}
+
+impl Drop for Geofence {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Duty cycle running in the background, see [`GNSS::duty_cycle`].
+pub struct DutyCycle {
+    serial_port: Arc<SerialPort>,
+    handle: JoinHandle<()>,
+}
+
+async fn run_duty_cycle(gnss: GNSS, on: Duration, off: Duration) {
+    loop {
+        gnss.serial_port.fix_cache().engine_off = false;
+        match gnss.turn_on_now().await {
+            Ok(()) => (),
+            Err(e) if e.kind() == ErrorKind::Closed => {
+                log::info!("GNSS duty cycle - the connection has been closed, the cycle stopped.");
+                return;
+            }
+            Err(e) => log::warn!("GNSS duty cycle - turning on the GNSS module has failed: {e}"),
+        }
+        sleep(on).await;
+        // the last position of the window is cached for the queries until the next one
+        if let Err(e) = gnss.get_data_now().await {
+            log::info!("GNSS duty cycle - no fix in the window: {e}");
+        }
+        match gnss.turn_off_now().await {
+            Ok(()) => gnss.serial_port.fix_cache().engine_off = true,
+            Err(e) => log::warn!("GNSS duty cycle - turning off the GNSS module has failed: {e}"),
+        }
+        sleep(off).await;
+    }
+}
+
+impl DutyCycle {
+    /// Stops the duty cycle, leaving the GNSS module on or off as it is, and forgets the cached fix.
+    pub fn stop(self) {
+        drop(self);
+    }
+}
+
+impl Drop for DutyCycle {
+    fn drop(&mut self) {
+        self.handle.abort();
+        *self.serial_port.fix_cache() = FixCache::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> FenceArea {
+        FenceArea::Polygon(vec![(51.0, -1.0), (51.0, 0.0), (52.0, 0.0), (52.0, -1.0)])
+    }
+
+    #[test]
+    fn polygon_contains_edges() {
+        let square: FenceArea = square();
+        for (lat, lon) in [(51.0, -0.5), (51.5, 0.0), (52.0, -0.5), (51.5, -1.0)] {
+            assert!(square.contains(lat, lon), "{lat}, {lon}");
+        }
+        for (lat, lon) in [
+            (50.999, -0.5),
+            (51.5, 0.001),
+            (52.001, -0.5),
+            (51.5, -1.001),
+        ] {
+            assert!(!square.contains(lat, lon), "{lat}, {lon}");
+        }
+    }
+
+    #[test]
+    fn polygon_contains_vertices() {
+        let square: FenceArea = square();
+        for (lat, lon) in [(51.0, -1.0), (51.0, 0.0), (52.0, 0.0), (52.0, -1.0)] {
+            assert!(square.contains(lat, lon), "{lat}, {lon}");
+        }
+        // the ray from the point passes through the vertex
+        assert!(!square.contains(52.0, 0.5));
+        assert!(!square.contains(51.0, -1.5));
+    }
+
+    #[test]
+    fn polygon_contains_concave() {
+        // a "V" open to the north, with the notch at (51.5, 0.0)
+        let notch: FenceArea = FenceArea::Polygon(vec![
+            (51.0, -1.0),
+            (51.0, 1.0),
+            (52.0, 1.0),
+            (51.5, 0.0),
+            (52.0, -1.0),
+        ]);
+        assert!(notch.contains(51.5, 0.0));
+        assert!(notch.contains(51.75, 0.5));
+        assert!(notch.contains(51.25, 0.0));
+        assert!(!notch.contains(51.75, 0.0));
+        assert!(!notch.contains(51.5, 1.5));
+    }
+
+    fn utm(lat: f64, lon: f64) -> (String, String) {
+        let utm: Utm = Utm::from_degrees(lat, lon).unwrap();
+        (utm.to_string(), utm.mgrs())
+    }
+
+    #[test]
+    fn utm_norway_zone() {
+        // Bergen, in the widened 32V
+        assert_eq!(
+            utm(60.3913, 5.3221),
+            ("32V 297353 6700648".into(), "32V KN 97353 00648".into())
+        );
+        assert_eq!(
+            utm(60.0, 3.0),
+            ("32V 165640 6666593".into(), "32V JM 65640 66593".into())
+        );
+        assert_eq!(
+            utm(56.0, 11.99),
+            ("32V 686448 6210114".into(), "32V PH 86448 10114".into())
+        );
+        // just outside the V band
+        assert_eq!(
+            utm(55.99, 5.0),
+            ("31U 624758 6206771".into(), "31U FC 24758 06771".into())
+        );
+        assert_eq!(
+            utm(64.0, 5.0),
+            ("31W 597812 7098548".into(), "31W EL 97812 98548".into())
+        );
+    }
+
+    #[test]
+    fn utm_svalbard_zones() {
+        // Longyearbyen
+        assert_eq!(
+            utm(78.2232, 15.6267),
+            ("33X 514278 8683355".into(), "33X WG 14278 83355".into())
+        );
+        assert_eq!(
+            utm(72.0, 8.99),
+            ("31X 706293 7999199".into(), "31X GV 06293 99199".into())
+        );
+        assert_eq!(
+            utm(72.0, 9.0),
+            ("33X 293363 7999233".into(), "33X TV 93363 99233".into())
+        );
+    }
+
+    #[test]
+    fn utm_polar_limits() {
+        assert_eq!(
+            utm(84.0, 0.0),
+            ("31X 465005 9329005".into(), "31X DP 65005 29005".into())
+        );
+        assert_eq!(
+            utm(-80.0, 0.0),
+            ("31C 441867 1116915".into(), "31C DM 41867 16915".into())
+        );
+        assert_eq!(Utm::from_degrees(84.01, 0.0), None);
+        assert_eq!(Utm::from_degrees(-80.01, 0.0), None);
+    }
 }
@@ -8,11 +8,12 @@
 //! ```
 //! #[derive(serde::Serialize)]
 //! struct Coordinates {
-//!     lat: f32,
-//!     lon: f32,
+//!     lat: f64,
+//!     lon: f64,
 //! }
 //!
 //! // OR you can also use serde_json crate.
+//! use serde_json::Value;
 //!
 //! let data = r#"{
 //!    "name": "John Doe",
@@ -27,61 +28,119 @@
 //! let v: Value = serde_json::from_str(data).unwrap();
 //! ```
 //!
-//! ⚠️ Unfortunately, the SIM868 doesn't support HTTPS requests, so please use HTTP.
+//! Responses longer than [`DEFAULT_MAX_RESPONSE_SIZE`] fail with [`Error::ResponseTooLarge`] without being read,
+//! so a misbehaving server can't exhaust the memory of a Pi Zero, see [`GPRS::set_max_response_size`].
+//!
+//! [`GPRS::gsm_location`] asks the SIMCom location service for the approximate position of the serving cell - a
+//! coarse fallback when the GNSS antenna is obstructed.
+//!
+//! On congested networks, the quality of service of the PDP context can be requested with
+//! [`GPRS::set_requested_qos`] (and the lowest acceptable one with [`GPRS::set_minimum_qos`]) before attaching.
+//!
+//! ⚠️ Unfortunately, most SIM868 firmware revisions don't support HTTPS requests (and the rest only the outdated
+//! TLS versions), so please use HTTP. HTTPS requests fail with [`Error::UnsupportedByFirmware`] unless
+//! [`crate::hat::Hat::init`] has found `AT+HTTPSSL`, see [`crate::capabilities`].
 //!
 //! ⚠️ Prior to use for making requests, it is crucial to execute the [`GPRS::init`]
 //! method with your [Access Point Name (APN) configuration](`ApnConfig`),
 //! ensuring the GPRS connection can be made.
 
+#[cfg(feature = "blocking")]
+use crate::blocking::block_on_task;
 use crate::{
+    ack_check,
+    command::AtCommand,
     error::Error,
-    error_check, generic_resolver, http,
-    serial_port::{spawn_task, SerialPort, TaskPriority},
-    Module, ResolverReturn, TaskJoinHandle, GPRS_CONN_STATUS_REGEX, PARSING_ERROR,
+    error_check, generic_resolver, http, join_task, nth_field, parse_field,
+    serial_port::{spawn_task, CommandBatch, SerialPort, TaskPriority},
+    Module, ResolverReturn, TaskJoinHandle, GPRS_CONN_STATUS_REGEX, GPRS_LOCATION_REGEX,
+    GPRS_QOS_REGEX,
 };
-use std::{sync::Arc, time::Duration};
+use chrono::NaiveDateTime;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::watch;
 use uuid::Uuid;
 
+const MUTEX_POISONED_MSG: &str = "Critical error: Mutex is poisoned.";
+
+/// Limit of the HTTP response length (in bytes) unless set by [`GPRS::set_max_response_size`].
+pub const DEFAULT_MAX_RESPONSE_SIZE: usize = 64 * 1024;
+/// Time the location service has to answer `AT+CIPGSMLOC`.
+const LOCATION_TIMEOUT: Duration = Duration::from_secs(60);
+
 fn conn_status(serial_port: &Arc<SerialPort>, task_id: &Uuid) -> ResolverReturn<u8> {
     fn resolver(result: String) -> ResolverReturn<u8> {
         if error_check(&result) {
             return Err(Error::GprsNoConnection);
         }
         if let Some(captured) = GPRS_CONN_STATUS_REGEX.captures(&result) {
-            let res: &Vec<&str> = &captured["data"].split(",").collect();
-            Ok(res[1].parse::<u8>().expect(PARSING_ERROR))
+            let res: &Vec<&str> = &captured["data"].split(',').collect();
+            parse_field("bearer_status", nth_field(res, 1, "bearer_status")?)
         } else {
-            return Err(Error::NotResolved);
+            Err(Error::NotResolved)
         }
     }
 
-    serial_port.process(task_id, "AT+SAPBR=2,1\n".to_string(), resolver, None)
+    let status: u8 = serial_port.process(
+        task_id,
+        AtCommand::set("+SAPBR").number(2).number(1).into(),
+        resolver,
+        None,
+    )?;
+    serial_port.update_state(|state| {
+        state.bearer_open = match status {
+            1 => Some(true),
+            3 => Some(false),
+            // connecting or closing
+            _ => None,
+        }
+    });
+    Ok(status)
 }
 
 fn conn_open(serial_port: &Arc<SerialPort>, task_id: &Uuid) -> ResolverReturn<()> {
     fn resolver(result: String) -> ResolverReturn<()> {
-        return generic_resolver(&result, Error::GprsConnectionOpenFailed);
+        generic_resolver(&result, Error::GprsConnectionOpenFailed)
     }
 
-    serial_port.process(
+    let result: ResolverReturn<()> = serial_port.process(
         task_id,
-        "AT+SAPBR=1,1\n".to_string(),
+        AtCommand::set("+SAPBR").number(1).number(1).into(),
         resolver,
-        Some(Duration::from_secs(20)),
-    )
+        Some(serial_port.timeouts.sapbr),
+    );
+    serial_port.update_state(|state| state.bearer_open = result.as_ref().ok().map(|_| true));
+    result
+}
+
+/// Opens the bearer unless it's open already, `true` if it has been opened.
+fn conn_ensure(serial_port: &Arc<SerialPort>, task_id: &Uuid) -> ResolverReturn<bool> {
+    let closed: bool = match serial_port.state().bearer_open {
+        Some(open) => !open,
+        None => conn_status(serial_port, task_id)? == 3,
+    };
+    if closed {
+        conn_open(serial_port, task_id)?;
+    }
+    Ok(closed)
 }
 
 fn conn_close(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<()> {
     fn resolver(result: String) -> ResolverReturn<()> {
-        return generic_resolver(&result, Error::GprsConnectionCloseFailed);
+        generic_resolver(&result, Error::GprsConnectionCloseFailed)
     }
 
-    serial_port.process(
+    let result: ResolverReturn<()> = serial_port.process(
         task_id,
-        "AT+CGATT=0\n".to_string(),
+        AtCommand::set("+CGATT").number(0).into(),
         resolver,
-        Some(Duration::from_secs(10)),
-    )
+        Some(serial_port.timeouts.sapbr),
+    );
+    serial_port.update_state(|state| state.bearer_open = result.as_ref().ok().map(|_| false));
+    result
 }
 
 fn init(
@@ -93,40 +152,153 @@ fn init(
         generic_resolver(&result, Error::GprsApnConfigSetFailed)
     }
 
-    let commands: [String; 4] = [
-        "AT+SAPBR=3,1,Contype,GPRS\n".to_string(),
-        format!("AT+SAPBR=3,1,APN,\"{}\"\n", apn_config.apn),
-        format!("AT+SAPBR=3,1,USER,\"{}\"\n", apn_config.user),
-        format!("AT+SAPBR=3,1,PWD,\"{}\"\n", apn_config.password),
+    let bearer_parameter = |parameter: &str| {
+        AtCommand::set("+SAPBR")
+            .number(3)
+            .number(1)
+            .token(parameter)
+    };
+    let commands: [AtCommand; 4] = [
+        bearer_parameter("Contype").token("GPRS"),
+        bearer_parameter("APN").string(&apn_config.apn),
+        bearer_parameter("USER").string(&apn_config.user),
+        bearer_parameter("PWD").string(&apn_config.password),
     ];
 
-    for command in commands {
-        serial_port.process(task_id, command, resolver, None)?
+    let batch: CommandBatch<()> = commands
+        .into_iter()
+        .fold(CommandBatch::new(), |batch, command: AtCommand| {
+            batch.step(command.into(), resolver, None)
+        });
+    serial_port.process_batch(task_id, batch)?;
+    Ok(())
+}
+
+/// QoS profile (GPRS Release 97) of the PDP context 1. The classes are defined by 3GPP TS 23.060, 0 leaves
+/// the one subscribed with the network.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QosProfile {
+    /// 1 (high) - 3 (low)
+    pub precedence: u8,
+    /// 1 (lowest delay) - 4 (best effort)
+    pub delay: u8,
+    /// 1 (most reliable) - 5 (unacknowledged, unprotected)
+    pub reliability: u8,
+    /// peak throughput, 1 (up to 1 kB/s) - 9 (up to 256 kB/s)
+    pub peak: u8,
+    /// mean throughput, 1 (100 B/h) - 18 (50 MB/h), 31 for best effort
+    pub mean: u8,
+}
+
+/// `AT+CGQREQ` for the requested profile, `AT+CGQMIN` for the minimum acceptable one.
+fn set_qos(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    (command, qos): (&'static str, QosProfile),
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::GprsQosConfigFailed)
     }
 
-    Ok(())
+    serial_port.process(
+        task_id,
+        AtCommand::set(command)
+            .number(1)
+            .number(qos.precedence)
+            .number(qos.delay)
+            .number(qos.reliability)
+            .number(qos.peak)
+            .number(qos.mean)
+            .into(),
+        resolver,
+        None,
+    )
+}
+
+fn qos(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    command: &'static str,
+) -> ResolverReturn<QosProfile> {
+    fn resolver(result: String) -> ResolverReturn<QosProfile> {
+        if error_check(&result) {
+            return Err(Error::GprsQosConfigFailed);
+        }
+        let Some(captured) = GPRS_QOS_REGEX.captures(&result) else {
+            // the context has no profile set
+            return match ack_check(&result) {
+                true => Ok(QosProfile::default()),
+                false => Err(Error::NotResolved),
+            };
+        };
+        let data: &Vec<&str> = &captured["data"].split(',').collect();
+        Ok(QosProfile {
+            precedence: parse_field("precedence", nth_field(data, 0, "precedence")?)?,
+            delay: parse_field("delay", nth_field(data, 1, "delay")?)?,
+            reliability: parse_field("reliability", nth_field(data, 2, "reliability")?)?,
+            peak: parse_field("peak", nth_field(data, 3, "peak")?)?,
+            mean: parse_field("mean", nth_field(data, 4, "mean")?)?,
+        })
+    }
+
+    serial_port.process(task_id, AtCommand::read(command).into(), resolver, None)
+}
+
+/// Direction of the [`TransferProgress`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransferDirection {
+    /// Request body (`AT+HTTPDATA`).
+    #[default]
+    Upload,
+    /// Response (`AT+HTTPREAD`).
+    Download,
+}
+
+/// Bytes of the HTTP request body or response transferred so far, see [`GPRS::progress`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransferProgress {
+    pub direction: TransferDirection,
+    pub transferred: usize,
+    pub total: usize,
+}
+
+/// Settings of the [`GPRS`] passed to the request task.
+struct RequestOptions {
+    max_response_size: Option<usize>,
+    progress: Arc<watch::Sender<TransferProgress>>,
 }
 
 fn request<T>(
     serial_port: &Arc<SerialPort>,
     task_id: &Uuid,
     req: Request<T>,
+    options: &RequestOptions,
 ) -> ResolverReturn<String>
 where
     T: serde::Serialize,
 {
     // terminate - just in case if previous http was initiated and wasn't terminated afterwards
-    let _ = http::terminate(serial_port, task_id);
-    let status: u8 = conn_status(serial_port, task_id)?;
-    if status == 3 {
-        conn_open(serial_port, task_id)?;
+    if serial_port.state().http_active != Some(false) {
+        let _ = http::terminate(serial_port, task_id);
     }
+    // the bearer closed after the previous request is opened straight away
+    conn_ensure(serial_port, task_id)?;
     http::init(serial_port, task_id, &req)?;
     if matches!(req.method, RequestMethod::POST) {
-        http::data(serial_port, task_id, &req)?;
+        http::data(serial_port, task_id, &req, &options.progress)?;
+    }
+    let size: usize = http::action(serial_port, task_id, req.method)?;
+    if let Some(limit) = options
+        .max_response_size
+        .filter(|limit: &usize| size > *limit)
+    {
+        http::terminate(serial_port, task_id)?;
+        return Err(Error::ResponseTooLarge { size, limit });
     }
-    http::action(serial_port, task_id, req.method)?;
-    let read: String = http::read(serial_port, task_id)?;
+    let read: String = http::read(serial_port, task_id, size, &options.progress)?;
     http::terminate(serial_port, task_id)?;
     Ok(read)
 }
@@ -134,46 +306,162 @@ where
 fn request_wrapper<T>(
     serial_port: &Arc<SerialPort>,
     task_id: &Uuid,
-    req: Request<T>,
+    (req, options): (Request<T>, RequestOptions),
 ) -> ResolverReturn<String>
 where
     T: serde::Serialize,
 {
-    let result: Result<String, Error> = request(serial_port, task_id, req);
+    let result: Result<String, Error> = request(serial_port, task_id, req, &options);
     // always close the connection afterwards
     conn_close(serial_port, task_id, ())?;
     result
 }
 
+/// Approximate position of the serving cell, see [`GPRS::gsm_location`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GsmLocation {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Time of the network (UTC).
+    pub time: NaiveDateTime,
+}
+
+fn gsm_location(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<GsmLocation> {
+    fn resolver(result: String) -> ResolverReturn<GsmLocation> {
+        if error_check(&result) {
+            return Err(Error::GprsNoConnection);
+        }
+        let Some(captured) = GPRS_LOCATION_REGEX.captures(&result) else {
+            return Err(Error::NotResolved);
+        };
+        match (
+            &captured["code"],
+            captured.name("lat"),
+            captured.name("lon"),
+        ) {
+            ("0", Some(latitude), Some(longitude)) => {
+                let time: String = format!("{} {}", &captured["date"], &captured["time"]);
+                Ok(GsmLocation {
+                    latitude: parse_field("latitude", latitude.as_str())?,
+                    longitude: parse_field("longitude", longitude.as_str())?,
+                    time: NaiveDateTime::parse_from_str(&time, "%Y/%m/%d %H:%M:%S").map_err(
+                        |_| Error::ParseFailure {
+                            field: "time",
+                            raw: time.clone(),
+                        },
+                    )?,
+                })
+            }
+            (code, ..) => Err(Error::GprsLocationNotFound(parse_field(
+                "location_code",
+                code,
+            )?)),
+        }
+    }
+
+    let opened: bool = conn_ensure(serial_port, task_id)?;
+    let location: ResolverReturn<GsmLocation> = serial_port.process(
+        task_id,
+        AtCommand::set("+CIPGSMLOC").number(1).number(1).into(),
+        resolver,
+        Some(LOCATION_TIMEOUT),
+    );
+    // the bearer found open is left for the caller
+    if opened {
+        conn_close(serial_port, task_id, ())?;
+    }
+    location
+}
+
+/// Has the modem download the `url` to the file at `path` of its file system, returning the length of the
+/// file. Like [`GPRS::gsm_location`], the bearer is opened for the download if it's closed.
+#[cfg(feature = "gnss")]
+pub(crate) fn download_to_file(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    url: &str,
+    path: &str,
+) -> ResolverReturn<usize> {
+    if serial_port.state().http_active != Some(false) {
+        let _ = http::terminate(serial_port, task_id);
+    }
+    let opened: bool = conn_ensure(serial_port, task_id)?;
+    let downloaded: ResolverReturn<usize> = http::download_to_file(serial_port, task_id, url, path);
+    if opened {
+        conn_close(serial_port, task_id, ())?;
+    }
+    downloaded
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ApnConfig {
     pub apn: String,
     pub user: String,
     pub password: String,
 }
 
+/// The handle doesn't keep the connection open, see [`crate::SIM868`].
+#[derive(Clone)]
 pub struct GPRS {
     serial_port: Arc<SerialPort>,
+    /// Shared by the clones, like the rest of the state.
+    max_response_size: Arc<Mutex<Option<usize>>>,
+    progress: Arc<watch::Sender<TransferProgress>>,
 }
 
 impl Module for GPRS {
     fn new(serial_port: Arc<crate::serial_port::SerialPort>) -> Self {
-        GPRS { serial_port }
+        GPRS {
+            serial_port,
+            max_response_size: Arc::new(Mutex::new(Some(DEFAULT_MAX_RESPONSE_SIZE))),
+            progress: Arc::new(watch::channel(TransferProgress::default()).0),
+        }
     }
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RequestMethod {
     GET,
     POST,
     HEAD,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ContentType {
     FormUrlencoded,
     Json,
 }
 
+/// HTTP request of [`GPRS::request`]. The `data` is sent as JSON or URL encoded, see [`ContentType`] - with the
+/// `serde` feature, the data types of the crate (eg. [`crate::gnss::GNSSData`] or [`crate::hat::HealthReport`])
+/// can be sent as they are:
+///
+/// ```no_run
+/// # #[cfg(all(feature = "serde", feature = "gnss"))]
+/// # async fn post_position(sim: &rpi_sim868::SIM868) -> Result<(), rpi_sim868::Error> {
+/// use rpi_sim868::gprs::{ContentType, Request, RequestMethod};
+///
+/// let fix: rpi_sim868::gnss::GNSSData = sim.gnss.get_data_now().await?;
+/// sim.gprs
+///     .request_now(Request {
+///         content_type: Some(ContentType::Json),
+///         data: fix,
+///         userdata_header: None,
+///         method: RequestMethod::POST,
+///         url: "http://example.com/position".to_string(),
+///     })
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
 pub struct Request<T>
 where
     T: serde::Serialize,
@@ -186,7 +474,8 @@ where
 }
 
 impl GPRS {
-    /// Creates request GET, POST, or HEAD. Because of SIM868 limitations, HTTPS requests are not supported.
+    /// Creates request GET, POST, or HEAD. Because of SIM868 limitations, HTTPS requests are generally not supported,
+    /// see the [module](self) documentation.
     pub fn request<T>(&self, req: Request<T>) -> TaskJoinHandle<String>
     where
         T: serde::Serialize + Send + 'static,
@@ -199,10 +488,28 @@ impl GPRS {
                 "Creating {:?} request to {}...",
                 req.method, req.url
             )),
-            req,
+            (
+                req,
+                RequestOptions {
+                    max_response_size: *self.max_response_size.lock().expect(MUTEX_POISONED_MSG),
+                    progress: self.progress.clone(),
+                },
+            ),
         )
     }
 
+    /// Progress of the request body being sent and the response being read by [`GPRS::request`], updated
+    /// after every kilobyte. Shared by the clones, so it follows the requests made from any of them.
+    pub fn progress(&self) -> watch::Receiver<TransferProgress> {
+        self.progress.subscribe()
+    }
+
+    /// Longest HTTP response (in bytes) read by [`GPRS::request`], longer ones fail with
+    /// [`Error::ResponseTooLarge`]. [`DEFAULT_MAX_RESPONSE_SIZE`] by default, `None` disables the limit.
+    pub fn set_max_response_size(&self, bytes: Option<usize>) {
+        *self.max_response_size.lock().expect(MUTEX_POISONED_MSG) = bytes;
+    }
+
     /// The APN should be initialised before using GPRS.
     pub fn init(&self, apn_config: ApnConfig) -> TaskJoinHandle<()> {
         spawn_task(
@@ -214,6 +521,65 @@ impl GPRS {
         )
     }
 
+    /// Requests the QoS `profile` for the following attachments (`AT+CGQREQ`).
+    pub fn set_requested_qos(&self, profile: QosProfile) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_qos,
+            Some(format!("Requesting QoS profile {profile:?}...")),
+            ("+CGQREQ", profile),
+        )
+    }
+
+    /// Sets the lowest QoS `profile` accepted from the network (`AT+CGQMIN`), the attachment fails if it
+    /// offers less.
+    pub fn set_minimum_qos(&self, profile: QosProfile) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_qos,
+            Some(format!("Setting minimum QoS profile {profile:?}...")),
+            ("+CGQMIN", profile),
+        )
+    }
+
+    /// QoS profile set by [`GPRS::set_requested_qos`], all zeros (subscribed) if not set.
+    pub fn requested_qos(&self) -> TaskJoinHandle<QosProfile> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            qos,
+            Some("Reading requested QoS profile...".to_string()),
+            "+CGQREQ",
+        )
+    }
+
+    /// QoS profile set by [`GPRS::set_minimum_qos`], all zeros (subscribed) if not set.
+    pub fn minimum_qos(&self) -> TaskJoinHandle<QosProfile> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            qos,
+            Some("Reading minimum QoS profile...".to_string()),
+            "+CGQMIN",
+        )
+    }
+
+    /// Approximate position of the serving cell and the network time from the SIMCom location service
+    /// (`AT+CIPGSMLOC`). Opens the bearer (see [`GPRS::init`]) if it's closed, and closes it afterwards. Fails
+    /// with [`Error::GprsLocationNotFound`] carrying the code of the service, eg. 404 if the cell isn't
+    /// known.
+    pub fn gsm_location(&self) -> TaskJoinHandle<GsmLocation> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            gsm_location,
+            Some("Reading GSM location...".to_string()),
+            (),
+        )
+    }
+
     /// Closes GPRS connection
     pub fn close_connection(&self) -> TaskJoinHandle<()> {
         spawn_task(
@@ -225,3 +591,94 @@ impl GPRS {
         )
     }
 }
+
+impl GPRS {
+    /// [`GPRS::request`] returning the result directly.
+    pub async fn request_now<T>(&self, req: Request<T>) -> ResolverReturn<String>
+    where
+        T: serde::Serialize + Send + 'static,
+    {
+        join_task(self.request(req)).await
+    }
+
+    /// [`GPRS::init`] returning the result directly.
+    pub async fn init_now(&self, apn_config: ApnConfig) -> ResolverReturn<()> {
+        join_task(self.init(apn_config)).await
+    }
+
+    /// [`GPRS::set_requested_qos`] returning the result directly.
+    pub async fn set_requested_qos_now(&self, profile: QosProfile) -> ResolverReturn<()> {
+        join_task(self.set_requested_qos(profile)).await
+    }
+
+    /// [`GPRS::set_minimum_qos`] returning the result directly.
+    pub async fn set_minimum_qos_now(&self, profile: QosProfile) -> ResolverReturn<()> {
+        join_task(self.set_minimum_qos(profile)).await
+    }
+
+    /// [`GPRS::requested_qos`] returning the result directly.
+    pub async fn requested_qos_now(&self) -> ResolverReturn<QosProfile> {
+        join_task(self.requested_qos()).await
+    }
+
+    /// [`GPRS::minimum_qos`] returning the result directly.
+    pub async fn minimum_qos_now(&self) -> ResolverReturn<QosProfile> {
+        join_task(self.minimum_qos()).await
+    }
+
+    /// [`GPRS::gsm_location`] returning the result directly.
+    pub async fn gsm_location_now(&self) -> ResolverReturn<GsmLocation> {
+        join_task(self.gsm_location()).await
+    }
+
+    /// [`GPRS::close_connection`] returning the result directly.
+    pub async fn close_connection_now(&self) -> ResolverReturn<()> {
+        join_task(self.close_connection()).await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl GPRS {
+    /// Blocking version of [`GPRS::request`].
+    pub fn request_blocking<T>(&self, req: Request<T>) -> ResolverReturn<String>
+    where
+        T: serde::Serialize + Send + 'static,
+    {
+        block_on_task(|| self.request(req))
+    }
+
+    /// Blocking version of [`GPRS::init`].
+    pub fn init_blocking(&self, apn_config: ApnConfig) -> ResolverReturn<()> {
+        block_on_task(|| self.init(apn_config))
+    }
+
+    /// Blocking version of [`GPRS::set_requested_qos`].
+    pub fn set_requested_qos_blocking(&self, profile: QosProfile) -> ResolverReturn<()> {
+        block_on_task(|| self.set_requested_qos(profile))
+    }
+
+    /// Blocking version of [`GPRS::set_minimum_qos`].
+    pub fn set_minimum_qos_blocking(&self, profile: QosProfile) -> ResolverReturn<()> {
+        block_on_task(|| self.set_minimum_qos(profile))
+    }
+
+    /// Blocking version of [`GPRS::requested_qos`].
+    pub fn requested_qos_blocking(&self) -> ResolverReturn<QosProfile> {
+        block_on_task(|| self.requested_qos())
+    }
+
+    /// Blocking version of [`GPRS::minimum_qos`].
+    pub fn minimum_qos_blocking(&self) -> ResolverReturn<QosProfile> {
+        block_on_task(|| self.minimum_qos())
+    }
+
+    /// Blocking version of [`GPRS::gsm_location`].
+    pub fn gsm_location_blocking(&self) -> ResolverReturn<GsmLocation> {
+        block_on_task(|| self.gsm_location())
+    }
+
+    /// Blocking version of [`GPRS::close_connection`].
+    pub fn close_connection_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.close_connection())
+    }
+}
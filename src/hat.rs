@@ -1,24 +1,249 @@
 //! Hat module
 //!
 //! See [`Hat`] to discover available methods.
+//!
+//! The HAT is powered on and off by pulling its PWRKEY low. By default it's the Raspberry Pi GPIO 4 pin (`rpi`
+//! feature), use [`Hat::set_power_pin`] to drive it with your own [`PowerPin`] implementation. A modem
+//! powered on its own (eg. a USB-connected board) doesn't need the pin at all, see [`Hat::disable_power_pin`].
 
+#[cfg(feature = "blocking")]
+use crate::blocking::{block_on, block_on_task};
 use crate::{
-    ack_check,
-    error::{Error, ErrorKind},
+    ack_check, capabilities,
+    command::AtCommand,
+    error::{CmeError, Error, ErrorKind},
+    error_check,
+    events::Event,
+    generic_resolver, join_task, parse_field,
     serial_port::{spawn_task, SerialPort, TaskPriority},
-    Module, ResolverReturn, TaskJoinHandle, HAT_SIGNAL_STRENGHT_REGEX, PARSING_ERROR,
+    state::ModemState,
+    Module, ResolverReturn, TaskJoinHandle, CLOCK_REGEX, FIRMWARE_REVISION_REGEX, HAT_BAND_REGEX,
+    HAT_BATTERY_REGEX, HAT_CELL_REGEX, HAT_IDENTITY_REGEX, HAT_OPERATOR_LIST_REGEX,
+    HAT_OPERATOR_REGEX, HAT_REGISTRATION_LOCATION_REGEX, HAT_REGISTRATION_REGEX,
+    HAT_SIGNAL_STRENGHT_REGEX, HAT_SIM_STATUS_REGEX,
 };
+#[cfg(feature = "rpi")]
 use rppal::gpio::{Gpio, OutputPin};
-use std::{sync::Arc, thread::sleep, time::Duration};
+use std::{
+    num::ParseIntError,
+    sync::{Arc, Mutex},
+    thread::sleep,
+    time::{Duration, Instant},
+};
 use uuid::Uuid;
 
+#[cfg(feature = "rpi")]
 const TOGGLE_POWER_PIN: u8 = 4;
+const MUTEX_POISONED_MSG: &str = "Critical error: Mutex is poisoned.";
+
+/// Baud rates of `AT+IPR`, in the order probed by [`Hat::autobaud`] - the most common first.
+pub(crate) const BAUD_RATES: [u32; 10] = [
+    115200, 9600, 57600, 38400, 19200, 4800, 2400, 1200, 230400, 460800,
+];
+/// Time the modem has to answer the `AT` probing the baud rate.
+const AUTOBAUD_TIMEOUT: Duration = Duration::from_millis(300);
+/// Time the modem has to scan the networks, or to register to the selected operator (`AT+COPS`).
+const OPERATOR_TIMEOUT: Duration = Duration::from_secs(120);
+/// Time the modem has to switch the functionality level (`AT+CFUN`).
+const FUNCTIONALITY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time the modem has to come up after the reset.
+const RESET_TIMEOUT: Duration = Duration::from_secs(30);
+/// Time the modem has to answer `AT` after the power has been toggled, unless set with
+/// [`Hat::set_boot_timeout`].
+const BOOT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Pause between the `AT` probes of the booting modem.
+const BOOT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Time the SIM has to check the PIN (`AT+CPIN`, `AT+CLCK`, `AT+CPWD`).
+const SIM_PIN_TIMEOUT: Duration = Duration::from_secs(15);
+/// Time the modem has to log off the network and confirm the normal power down.
+const POWER_DOWN_TIMEOUT: Duration = Duration::from_secs(15);
+/// Time the modem takes to switch off after the urgent power down.
+const URGENT_POWER_DOWN_DELAY: Duration = Duration::from_millis(500);
+
+/// Commands sent by [`Hat::init`], in order.
+const INIT_SEQUENCE: [&str; 2] = [
+    // no echo of the commands, the responses only
+    "ATE0\n",
+    // report equipment errors as numeric `+CME ERROR: <n>` codes
+    "AT+CMEE=1\n",
+];
+
+/// Output pin connected to the PWRKEY of the modem.
+pub trait PowerPin: Send {
+    fn set_low(&mut self) -> Result<(), Error>;
+    fn set_high(&mut self) -> Result<(), Error>;
+}
+
+#[cfg(feature = "rpi")]
+impl PowerPin for OutputPin {
+    fn set_low(&mut self) -> Result<(), Error> {
+        OutputPin::set_low(self);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Error> {
+        OutputPin::set_high(self);
+        Ok(())
+    }
+}
+
+/// Raspberry Pi GPIO pin, claimed on the first use.
+#[cfg(feature = "rpi")]
+pub struct GpioPowerPin {
+    number: u8,
+    pin: Option<OutputPin>,
+}
+
+#[cfg(feature = "rpi")]
+impl GpioPowerPin {
+    pub fn new(number: u8) -> Self {
+        GpioPowerPin { number, pin: None }
+    }
+
+    fn pin(&mut self) -> Result<&mut OutputPin, Error> {
+        if self.pin.is_none() {
+            self.pin = Some(Gpio::new()?.get(self.number)?.into_output());
+        }
+        Ok(self
+            .pin
+            .as_mut()
+            .expect("Critical error: GPIO pin is not set."))
+    }
+}
+
+#[cfg(feature = "rpi")]
+impl PowerPin for GpioPowerPin {
+    fn set_low(&mut self) -> Result<(), Error> {
+        self.pin()?.set_low();
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Error> {
+        self.pin()?.set_high();
+        Ok(())
+    }
+}
+
+/// Power pin of the build without the `rpi` feature, until [`Hat::set_power_pin`] replaces it.
+#[cfg(not(feature = "rpi"))]
+struct NoPowerPin;
+
+#[cfg(not(feature = "rpi"))]
+impl PowerPin for NoPowerPin {
+    fn set_low(&mut self) -> Result<(), Error> {
+        Err(Error::HatNoPowerPin)
+    }
+
+    fn set_high(&mut self) -> Result<(), Error> {
+        Err(Error::HatNoPowerPin)
+    }
+}
+
+/// Coarse signal level of the `+CSQ` RSSI.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SignalLevel {
+    /// RSSI 0-1, -111 dBm or less.
+    NoSignal,
+    /// RSSI 2-9.
+    Marginal,
+    /// RSSI 10-14.
+    OK,
+    /// RSSI 15-19.
+    Good,
+    /// RSSI 20-31, -73 dBm or more.
+    Excellent,
+    /// RSSI 99, not known or not detectable.
+    Unknown,
+}
 
+/// Signal strength returned by [`Hat::signal_strength`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignalStrength {
+    /// Raw `+CSQ` RSSI (0-31, 99 - unknown).
+    pub rssi: u8,
+    pub level: SignalLevel,
+}
+
+impl SignalStrength {
+    pub fn from_rssi(rssi: u8) -> Self {
+        let level: SignalLevel = match rssi {
+            0..=1 => SignalLevel::NoSignal,
+            2..=9 => SignalLevel::Marginal,
+            10..=14 => SignalLevel::OK,
+            15..=19 => SignalLevel::Good,
+            20..=31 => SignalLevel::Excellent,
+            _ => SignalLevel::Unknown,
+        };
+        SignalStrength { rssi, level }
+    }
+
+    /// Received signal strength in dBm (-113 to -51), `None` if unknown.
+    pub fn dbm(&self) -> Option<i16> {
+        match self.rssi {
+            0..=31 => Some(-113 + 2 * self.rssi as i16),
+            _ => None,
+        }
+    }
+
+    /// Whether there is any signal, ie. the level is known and isn't [`SignalLevel::NoSignal`].
+    pub fn has_signal(&self) -> bool {
+        !matches!(self.level, SignalLevel::NoSignal | SignalLevel::Unknown)
+    }
+}
+
+/// Signal quality returned by [`Hat::signal_quality`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignalQuality {
+    /// Raw `+CSQ` RSSI (0-31, 99 - unknown).
+    pub rssi: u8,
+    /// Received signal strength in dBm (-113 to -51), `None` if unknown.
+    pub dbm: Option<i16>,
+    /// `+CSQ` bit error rate as RXQUAL (0 - less than 0.2 %, doubling up to 7 - more than 12.8 %), `None` if
+    /// unknown - eg. while the modem isn't in a call or a data transfer.
+    pub ber: Option<u8>,
+    pub level: SignalLevel,
+}
+
+impl SignalQuality {
+    /// Quality of the raw `+CSQ` values, 99 - unknown.
+    pub fn from_csq(rssi: u8, ber: u8) -> Self {
+        let strength: SignalStrength = SignalStrength::from_rssi(rssi);
+        SignalQuality {
+            rssi,
+            dbm: strength.dbm(),
+            ber: match ber {
+                0..=7 => Some(ber),
+                _ => None,
+            },
+            level: strength.level,
+        }
+    }
+
+    /// Whether there is any signal, ie. the level is known and isn't [`SignalLevel::NoSignal`].
+    pub fn has_signal(&self) -> bool {
+        SignalStrength::from_rssi(self.rssi).has_signal()
+    }
+}
+
+/// The handle doesn't keep the connection open, see [`crate::SIM868`].
+#[derive(Clone)]
 pub struct Hat {
     serial_port: Arc<SerialPort>,
+    /// `None` once [disabled](Hat::disable_power_pin).
+    power_pin: Arc<Mutex<Option<Box<dyn PowerPin>>>>,
+    /// Time the PWRKEY is held low, [`crate::profile::ModemProfile::power_key_pulse`] if `None`.
+    power_pulse: Arc<Mutex<Option<Duration>>>,
+    /// Time [`Hat::turn_on`] waits for the modem to answer.
+    boot_timeout: Arc<Mutex<Duration>>,
+    /// Commands sent by [`Hat::init`] after the `INIT_SEQUENCE`.
+    pub(crate) init_commands: Arc<Vec<String>>,
 }
 
-fn is_on(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<bool> {
+pub(crate) fn is_on(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<bool> {
     fn resolver(result: String) -> ResolverReturn<bool> {
         match ack_check(&result) {
             true => Ok(true),
@@ -30,27 +255,339 @@ fn is_on(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn
         task_id,
         "AT\n".to_string(),
         resolver,
-        Some(Duration::from_secs(2)),
+        Some(serial_port.timeouts.query),
     )
 }
 
-fn turn_off(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<()> {
+fn init(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    init_commands: Arc<Vec<String>>,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::HatInitFailed)
+    }
+
+    for command in INIT_SEQUENCE {
+        serial_port.process(task_id, command.to_string(), resolver, None)?;
+    }
+    // the capabilities only let the modules fail fast, so the init goes on without them
+    match capabilities::detect(serial_port, task_id) {
+        Ok(capabilities) => serial_port.set_capabilities(capabilities),
+        Err(e) if e.kind() == ErrorKind::TaskCancelled => return Err(e),
+        Err(e) => log::warn!("Detecting the firmware capabilities has failed: {e}"),
+    }
+    // eg. the auto-answer set by crate::phone::Phone::set_auto_answer
+    for command in serial_port.persisted_settings() {
+        serial_port.process(task_id, command, resolver, None)?;
+    }
+    for command in init_commands.iter() {
+        serial_port.process(task_id, format!("{command}\n"), resolver, None)?;
+    }
+
+    Ok(())
+}
+
+fn save_configuration(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::HatConfigurationNotSaved)
+    }
+
+    serial_port.process(task_id, "AT&W\n".to_string(), resolver, None)
+}
+
+/// Restores the saved profile (`ATZ`), or the factory defaults (`AT&F`) if `factory` is set.
+fn restore_configuration(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    factory: bool,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::HatConfigurationNotRestored)
+    }
+
+    let command: &str = if factory { "AT&F\n" } else { "ATZ\n" };
+    serial_port.process(task_id, command.to_string(), resolver, None)?;
+    serial_port.update_state(ModemState::invalidate);
+    if factory {
+        // the settings changed at runtime are gone, and the modem doesn't sleep by default
+        serial_port.clear_settings();
+        serial_port.set_sleep_mode(false);
+    }
+    // the restored profile may echo the commands, or report the errors without the codes
+    for command in INIT_SEQUENCE {
+        serial_port.process(task_id, command.to_string(), resolver, None)?;
+    }
+    Ok(())
+}
+
+/// `AT+CPOWD` mode, see [`Hat::turn_off`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PowerDownMode {
+    /// The modem logs off the network and confirms with `NORMAL POWER DOWN`.
+    #[default]
+    Normal,
+    /// The modem switches off straight away, without logging off the network. Not available on SIM7000
+    /// modems, which power down normally.
+    Urgent,
+}
+
+fn turn_off(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    mode: PowerDownMode,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        match result.contains("NORMAL POWER DOWN") {
+            true => Ok(()),
+            false => {
+                generic_resolver(&result, Error::HatNotPoweredDown).and(Err(Error::NotResolved))
+            }
+        }
+    }
+
+    match is_on(serial_port, task_id, ()) {
+        Ok(_) => (),
+        Err(e) if matches!(e.kind(), ErrorKind::NotResolved) => return Err(Error::HatAlreadyOff),
+        Err(e) => return Err(e),
+    }
+    serial_port.update_state(ModemState::invalidate);
+    let mode: PowerDownMode = serial_port.profile.power_down_mode(mode);
+    let command: String = AtCommand::set("+CPOWD")
+        .number(match mode {
+            PowerDownMode::Normal => 1,
+            PowerDownMode::Urgent => 0,
+        })
+        .into();
+    // only the normal power down is confirmed
+    if mode == PowerDownMode::Normal {
+        match serial_port.process(task_id, command, resolver, Some(POWER_DOWN_TIMEOUT)) {
+            Ok(()) => (),
+            // the modem may still have switched off, the probe below tells
+            Err(e) if matches!(e.kind(), ErrorKind::NotResolved) => {
+                log::warn!("HAT - no NORMAL POWER DOWN confirmation.")
+            }
+            Err(e) => return Err(e),
+        }
+    } else {
+        serial_port.write(task_id, command)?;
+        sleep(URGENT_POWER_DOWN_DELAY);
+    }
+
+    // the modem no longer answering AT is the proof it's off
     match is_on(serial_port, task_id, ()) {
-        Ok(_) => serial_port.write(task_id, "AT+CPOWD=0\n".to_string()),
-        Err(e) => {
-            if matches!(e.kind(), ErrorKind::NotResolved) {
-                Err(Error::HatAlreadyOff)
-            } else {
-                Err(e)
+        Err(e) if matches!(e.kind(), ErrorKind::NotResolved) => Ok(()),
+        Err(e) => Err(e),
+        Ok(_) => Err(Error::HatNotPoweredDown),
+    }
+}
+
+/// `AT+CFUN` functionality level, see [`Hat::set_functionality`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Functionality {
+    /// The RF and the SIM are off, the lowest consumption short of powering down.
+    Minimum,
+    Full,
+    /// The RF is off, the SIM stays on.
+    FlightMode,
+}
+
+impl Functionality {
+    fn level(&self) -> u8 {
+        match self {
+            Functionality::Minimum => 0,
+            Functionality::Full => 1,
+            Functionality::FlightMode => 4,
+        }
+    }
+}
+
+fn set_functionality(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    functionality: Functionality,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::HatFunctionalityNotSet)
+    }
+
+    serial_port.process(
+        task_id,
+        format!("AT+CFUN={}\n", functionality.level()),
+        resolver,
+        Some(FUNCTIONALITY_TIMEOUT),
+    )?;
+    if functionality != Functionality::Full {
+        // the bearer and the HTTP service don't survive switching the RF off
+        serial_port.update_state(ModemState::invalidate);
+    }
+    Ok(())
+}
+
+fn reset(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::HatFunctionalityNotSet)
+    }
+
+    serial_port.process(
+        task_id,
+        "AT+CFUN=1,1\n".to_string(),
+        resolver,
+        Some(FUNCTIONALITY_TIMEOUT),
+    )?;
+    serial_port.update_state(ModemState::invalidate);
+    // the modem answers right before restarting, so it's only probed once it has gone down
+    sleep(Duration::from_secs(1));
+    let start: Instant = Instant::now();
+    loop {
+        match is_on(serial_port, task_id, ()) {
+            Ok(_) => {
+                log::info!("SIM868 hat has restarted.");
+                return Ok(());
+            }
+            Err(e) if e.kind() == ErrorKind::NotResolved => {
+                if start.elapsed() > RESET_TIMEOUT {
+                    return Err(Error::HatNotBooted);
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn set_sleep_mode(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    enabled: bool,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::HatSleepModeNotSet)
+    }
+
+    let command: String = format!("AT+CSCLK={}\n", if enabled { 2 } else { 0 });
+    serial_port.process(task_id, command.clone(), resolver, None)?;
+    serial_port.set_sleep_mode(enabled);
+    serial_port.persist_setting("CSCLK", command);
+    Ok(())
+}
+
+/// Switches the `command` (`CNETLIGHT` or `CSGS`) setting of the NETLIGHT LED.
+fn set_light(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    (command, enabled): (&'static str, bool),
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::HatNetLightNotSet)
+    }
+
+    let at_command: String = format!("AT+{command}={}\n", enabled as u8);
+    serial_port.process(task_id, at_command.clone(), resolver, None)?;
+    serial_port.persist_setting(command, at_command);
+    Ok(())
+}
+
+fn set_jamming_detection(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    enabled: bool,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::HatJammingDetectionNotSet)
+    }
+
+    let command: String = format!("AT+SJDR={}\n", enabled as u8);
+    serial_port.process(task_id, command.clone(), resolver, None)?;
+    serial_port.persist_setting("SJDR", command);
+    Ok(())
+}
+
+/// `AT+CBAND` GSM band(s) the modem searches, see [`Hat::set_band`]. The SIM800 series are GSM only, so the
+/// radio access technology isn't selectable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Band {
+    /// Primary GSM 900 MHz.
+    Pgsm900,
+    /// DCS 1800 MHz.
+    Dcs1800,
+    /// PCS 1900 MHz.
+    Pcs1900,
+    /// Extended GSM 900 MHz and DCS 1800 MHz - Europe, Asia, Africa.
+    Egsm900Dcs1800,
+    /// GSM 850 MHz and PCS 1900 MHz - the Americas.
+    Gsm850Pcs1900,
+    /// All the bands, the longest search.
+    All,
+}
+
+impl Band {
+    fn name(&self) -> &'static str {
+        match self {
+            Band::Pgsm900 => "PGSM_MODE",
+            Band::Dcs1800 => "DCS_MODE",
+            Band::Pcs1900 => "PCS_MODE",
+            Band::Egsm900Dcs1800 => "EGSM_DCS_MODE",
+            Band::Gsm850Pcs1900 => "GSM850_PCS_MODE",
+            Band::All => "ALL_BAND",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Band> {
+        match name {
+            "PGSM_MODE" => Some(Band::Pgsm900),
+            "DCS_MODE" => Some(Band::Dcs1800),
+            "PCS_MODE" => Some(Band::Pcs1900),
+            // some firmware revisions report the first band of the pair only
+            "EGSM_MODE" | "EGSM_DCS_MODE" => Some(Band::Egsm900Dcs1800),
+            "GSM850_MODE" | "GSM850_PCS_MODE" => Some(Band::Gsm850Pcs1900),
+            "ALL_BAND" => Some(Band::All),
+            _ => None,
+        }
+    }
+}
+
+fn get_band(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<Band> {
+    fn resolver(result: String) -> ResolverReturn<Band> {
+        if error_check(&result) {
+            return Err(Error::HatBandNotSet);
+        }
+        match (HAT_BAND_REGEX.captures(&result), ack_check(&result)) {
+            (Some(captured), true) => {
+                Band::from_name(&captured["band"]).ok_or_else(|| Error::ParseFailure {
+                    field: "band",
+                    raw: captured["band"].to_string(),
+                })
             }
+            _ => Err(Error::NotResolved),
         }
     }
+
+    serial_port.process(task_id, "AT+CBAND?\n".to_string(), resolver, None)
+}
+
+fn set_band(serial_port: &Arc<SerialPort>, task_id: &Uuid, band: Band) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::HatBandNotSet)
+    }
+
+    let command: String = format!("AT+CBAND=\"{}\"\n", band.name());
+    serial_port.process(
+        task_id,
+        command.clone(),
+        resolver,
+        Some(FUNCTIONALITY_TIMEOUT),
+    )?;
+    serial_port.persist_setting("CBAND", command);
+    Ok(())
 }
 
 fn network_strength(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<u8> {
     fn resolver(result: String) -> ResolverReturn<u8> {
         match HAT_SIGNAL_STRENGHT_REGEX.captures(&result) {
-            Some(captured) => Ok(captured["number"].parse().expect(PARSING_ERROR)),
+            Some(captured) => parse_field("signal_strength", &captured["number"]),
             None => Err(Error::NotResolved),
         }
     }
@@ -58,67 +595,2030 @@ fn network_strength(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> Res
     serial_port.process(task_id, "AT+CSQ\n".to_string(), resolver, None)
 }
 
-impl Module for Hat {
-    fn new(serial_port: Arc<SerialPort>) -> Self {
-        Hat { serial_port }
+fn signal_quality(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<SignalQuality> {
+    fn resolver(result: String) -> ResolverReturn<SignalQuality> {
+        match HAT_SIGNAL_STRENGHT_REGEX.captures(&result) {
+            Some(captured) => Ok(SignalQuality::from_csq(
+                parse_field("signal_strength", &captured["number"])?,
+                match captured.name("ber") {
+                    Some(ber) => parse_field("bit_error_rate", ber.as_str())?,
+                    None => 99,
+                },
+            )),
+            None => Err(Error::NotResolved),
+        }
     }
+
+    serial_port.process(task_id, "AT+CSQ\n".to_string(), resolver, None)
 }
 
-impl Hat {
-    fn toggle_power(&self) {
-        let mut toggle_power_pin: OutputPin = Gpio::new()
-            .expect("Can't connect to GPIO")
-            .get(TOGGLE_POWER_PIN)
-            .expect(format!("Can't connect to the GPIO {TOGGLE_POWER_PIN} pin").as_str())
-            .into_output();
-        toggle_power_pin.set_low();
-        sleep(Duration::from_millis(4000));
-        toggle_power_pin.set_high();
+pub(crate) fn signal_strength(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<SignalStrength> {
+    network_strength(serial_port, task_id, ()).map(SignalStrength::from_rssi)
+}
+
+/// `+CBC` charging status of the battery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChargeState {
+    NotCharging,
+    Charging,
+    Charged,
+}
+
+impl ChargeState {
+    pub fn from_status(status: u8) -> Self {
+        match status {
+            1 => ChargeState::Charging,
+            2 => ChargeState::Charged,
+            _ => ChargeState::NotCharging,
+        }
     }
+}
 
-    pub fn is_on(&self) -> TaskJoinHandle<bool> {
-        spawn_task(
-            self.serial_port.clone(),
-            TaskPriority::NORMAL,
-            is_on,
-            Some("Checking hat status...".to_string()),
-            (),
-        )
+/// Battery charge read by `AT+CBC`, see [`Hat::battery_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Battery {
+    pub state: ChargeState,
+    /// %
+    pub charge: u8,
+    /// mV
+    pub voltage: u16,
+}
+
+pub(crate) fn battery(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<Battery> {
+    fn resolver(result: String) -> ResolverReturn<Battery> {
+        match HAT_BATTERY_REGEX.captures(&result) {
+            Some(captured) => Ok(Battery {
+                state: ChargeState::from_status(parse_field("charge_state", &captured["status"])?),
+                charge: parse_field("battery_charge", &captured["charge"])?,
+                voltage: parse_field("battery_voltage", &captured["voltage"])?,
+            }),
+            None => Err(Error::NotResolved),
+        }
     }
 
-    pub fn network_strength(&self) -> TaskJoinHandle<u8> {
-        spawn_task(
-            self.serial_port.clone(),
-            TaskPriority::NORMAL,
-            network_strength,
-            Some("Checking network strength...".to_string()),
-            (),
+    serial_port.process(task_id, "AT+CBC\n".to_string(), resolver, None)
+}
+
+/// `+CREG` network registration status (0 - not registered, 1 - home, 2 - searching, 3 - denied, 5 - roaming).
+pub(crate) fn registration_status(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<u8> {
+    fn resolver(result: String) -> ResolverReturn<u8> {
+        match HAT_REGISTRATION_REGEX.captures(&result) {
+            Some(captured) => parse_field("registration_status", &captured["status"]),
+            None => Err(Error::NotResolved),
+        }
+    }
+
+    serial_port.process(task_id, "AT+CREG?\n".to_string(), resolver, None)
+}
+
+/// `+CREG` / `+CGREG` network registration state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RegistrationState {
+    /// Not registered, and not searching for an operator.
+    NotRegistered,
+    RegisteredHome,
+    /// Not registered, searching for an operator.
+    Searching,
+    /// Registration denied, eg. the SIM isn't activated.
+    Denied,
+    Unknown,
+    RegisteredRoaming,
+}
+
+impl RegistrationState {
+    pub fn from_status(status: u8) -> Self {
+        match status {
+            0 => RegistrationState::NotRegistered,
+            1 => RegistrationState::RegisteredHome,
+            2 => RegistrationState::Searching,
+            3 => RegistrationState::Denied,
+            5 => RegistrationState::RegisteredRoaming,
+            _ => RegistrationState::Unknown,
+        }
+    }
+
+    /// `+CREG` / `+CGREG` status code of the state, the inverse of [`RegistrationState::from_status`].
+    pub fn status(&self) -> u8 {
+        match self {
+            RegistrationState::NotRegistered => 0,
+            RegistrationState::RegisteredHome => 1,
+            RegistrationState::Searching => 2,
+            RegistrationState::Denied => 3,
+            RegistrationState::Unknown => 4,
+            RegistrationState::RegisteredRoaming => 5,
+        }
+    }
+
+    /// Whether the modem is registered in the home network or roaming.
+    pub fn is_registered(&self) -> bool {
+        matches!(
+            self,
+            RegistrationState::RegisteredHome | RegistrationState::RegisteredRoaming
         )
     }
+}
+
+/// Network registration returned by [`Hat::registration_status`] and [`Hat::gprs_registration_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Registration {
+    pub state: RegistrationState,
+    /// Location area code, `None` unless registered.
+    pub lac: Option<u16>,
+    /// ID of the serving cell, `None` unless registered.
+    pub cell_id: Option<u32>,
+}
+
+/// Parses the optional hexadecimal value with the `from_str_radix` of its type.
+fn parse_hex<T>(
+    field: &'static str,
+    raw: Option<regex::Match>,
+    from_str_radix: fn(&str, u32) -> Result<T, ParseIntError>,
+) -> ResolverReturn<Option<T>> {
+    raw.map(|raw: regex::Match| parse_hex_field(field, raw.as_str(), from_str_radix))
+        .transpose()
+}
+
+fn parse_hex_field<T>(
+    field: &'static str,
+    raw: &str,
+    from_str_radix: fn(&str, u32) -> Result<T, ParseIntError>,
+) -> ResolverReturn<T> {
+    from_str_radix(raw, 16).map_err(|_| Error::ParseFailure {
+        field,
+        raw: raw.to_string(),
+    })
+}
+
+/// Reads the registration of the `domain` (`CREG` - circuit switched, `CGREG` - GPRS) with the location.
+fn registration(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    domain: &'static str,
+) -> ResolverReturn<Registration> {
+    fn mode_resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::HatRegistrationNotRead)
+    }
+    fn resolver(result: String) -> ResolverReturn<Registration> {
+        match HAT_REGISTRATION_LOCATION_REGEX.captures(&result) {
+            Some(captured) => Ok(Registration {
+                state: RegistrationState::from_status(parse_field(
+                    "registration_status",
+                    &captured["status"],
+                )?),
+                lac: parse_hex("lac", captured.name("lac"), u16::from_str_radix)?,
+                cell_id: parse_hex("cell_id", captured.name("cell_id"), u32::from_str_radix)?,
+            }),
+            None => Err(Error::NotResolved),
+        }
+    }
 
-    /// Turns on the HAT (only if connected to the GPIO pin).
-    pub async fn turn_on(&self) -> ResolverReturn<()> {
-        match self.is_on().await? {
-            Ok(_) => Err(Error::HatAlreadyOn),
-            Err(e) => match e.kind() {
-                ErrorKind::NotResolved => {
-                    log::info!("Turning SIM868 hat on...");
-                    self.toggle_power();
-                    Ok(())
-                }
-                _ => Err(e),
+    // the location is only reported in the mode 2, which also enables the registration URCs - so the
+    // default mode is restored right after the query
+    serial_port.process(task_id, format!("AT+{domain}=2\n"), mode_resolver, None)?;
+    let registration: ResolverReturn<Registration> =
+        serial_port.process(task_id, format!("AT+{domain}?\n"), resolver, None);
+    serial_port.process(task_id, format!("AT+{domain}=0\n"), mode_resolver, None)?;
+    registration
+}
+
+/// GSM cell reported by the engineering mode, see [`Hat::cell_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cell {
+    /// Mobile country code, eg. 262.
+    pub mcc: u16,
+    /// Mobile network code, eg. 1.
+    pub mnc: u16,
+    /// Location area code.
+    pub lac: u16,
+    pub cell_id: u32,
+    /// Absolute radio frequency channel number.
+    pub arfcn: u16,
+    /// `<rxl>` received level, 0 (-110 dBm and less) to 63 (-48 dBm and more).
+    pub rxlev: u8,
+}
+
+impl Cell {
+    /// Received level in dBm.
+    pub fn dbm(&self) -> i16 {
+        self.rxlev as i16 - 110
+    }
+}
+
+/// Serving and neighbour cells, eg. for the cell-based location services (OpenCellID, Mozilla Location).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CellInfo {
+    /// `None` if the modem isn't camped on a cell.
+    pub serving: Option<Cell>,
+    pub neighbors: Vec<Cell>,
+}
+
+/// Cell of the `+CENG` line, `None` for the empty slots.
+fn parse_cell(index: u8, data: &str) -> ResolverReturn<Option<Cell>> {
+    let fields: Vec<&str> = data.split(',').collect();
+    // the serving cell (0) and the neighbours list the fields in a different order
+    let (arfcn, rxlev, mcc, mnc, cell_id, lac) = match (index, fields.as_slice()) {
+        // <arfcn>,<rxl>,<rxq>,<mcc>,<mnc>,<bsic>,<cellid>,<rla>,<txp>,<lac>,<TA>
+        (0, [arfcn, rxlev, _, mcc, mnc, _, cell_id, _, _, lac, ..]) => {
+            (arfcn, rxlev, mcc, mnc, cell_id, lac)
+        }
+        // <arfcn>,<rxl>,<bsic>,<cellid>,<mcc>,<mnc>,<lac>
+        (index, [arfcn, rxlev, _, cell_id, mcc, mnc, lac, ..]) if index > 0 => {
+            (arfcn, rxlev, mcc, mnc, cell_id, lac)
+        }
+        _ => {
+            return Err(Error::ParseFailure {
+                field: "cell",
+                raw: data.to_string(),
+            })
+        }
+    };
+    if [mcc, cell_id, lac]
+        .iter()
+        .any(|field: &&&str| field.is_empty())
+    {
+        return Ok(None);
+    }
+    let cell: Cell = Cell {
+        mcc: parse_field("mcc", mcc)?,
+        mnc: parse_field("mnc", mnc)?,
+        lac: parse_hex_field("lac", lac, u16::from_str_radix)?,
+        cell_id: parse_hex_field("cell_id", cell_id, u32::from_str_radix)?,
+        arfcn: parse_field("arfcn", arfcn)?,
+        rxlev: parse_field("rxlev", rxlev)?,
+    };
+    match cell.mcc == 0 || cell.cell_id == 0 || cell.cell_id == 0xFFFF {
+        true => Ok(None),
+        false => Ok(Some(cell)),
+    }
+}
+
+fn cell_info(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<CellInfo> {
+    fn mode_resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::HatCellInfoNotRead)
+    }
+    fn resolver(result: String) -> ResolverReturn<CellInfo> {
+        if error_check(&result) {
+            return Err(Error::HatCellInfoNotRead);
+        }
+        if !ack_check(&result) {
+            return Err(Error::NotResolved);
+        }
+        let mut info: CellInfo = CellInfo::default();
+        for captured in HAT_CELL_REGEX.captures_iter(&result) {
+            let index: u8 = parse_field("cell", &captured["cell"])?;
+            match (index, parse_cell(index, &captured["data"])?) {
+                (_, None) => (),
+                (0, serving) => info.serving = serving,
+                (_, Some(neighbor)) => info.neighbors.push(neighbor),
+            }
+        }
+        Ok(info)
+    }
+
+    // the engineering mode with the neighbour cells slows down the modem, so it's switched off right
+    // after the query
+    serial_port.process(task_id, "AT+CENG=1,1\n".to_string(), mode_resolver, None)?;
+    let info: ResolverReturn<CellInfo> =
+        serial_port.process(task_id, "AT+CENG?\n".to_string(), resolver, None);
+    serial_port.process(task_id, "AT+CENG=0\n".to_string(), mode_resolver, None)?;
+    info
+}
+
+/// Operator the modem is registered to, see [`Hat::current_operator`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Operator {
+    /// Long alphanumeric name, eg. `T-Mobile D`.
+    pub name: String,
+    /// MCC and MNC, eg. `26201`.
+    pub numeric: String,
+    /// `<AcT>` access technology (0 - GSM, 3 - GSM with EGPRS), `None` if not reported - the SIM800 series
+    /// are GSM only and don't report it.
+    pub access_technology: Option<u8>,
+}
+
+/// `+COPS` availability of the network found by [`Hat::scan_operators`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OperatorStatus {
+    Unknown,
+    Available,
+    /// The modem is registered to it.
+    Current,
+    Forbidden,
+}
+
+impl OperatorStatus {
+    pub fn from_status(status: u8) -> Self {
+        match status {
+            1 => OperatorStatus::Available,
+            2 => OperatorStatus::Current,
+            3 => OperatorStatus::Forbidden,
+            _ => OperatorStatus::Unknown,
+        }
+    }
+}
+
+/// Network found by [`Hat::scan_operators`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NetworkOperator {
+    pub status: OperatorStatus,
+    pub long_name: String,
+    pub short_name: String,
+    /// MCC and MNC, eg. `26201`.
+    pub numeric: String,
+    /// `<AcT>` access technology, `None` if not reported.
+    pub access_technology: Option<u8>,
+}
+
+/// Network selection of [`Hat::select_operator`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OperatorSelection {
+    /// The modem chooses the network, the default.
+    Automatic,
+    /// Registers to the operator of the MCC and MNC (eg. `26201`) only.
+    Manual(String),
+    /// Registers to the operator of the MCC and MNC, falls back to the automatic selection if it isn't
+    /// available.
+    ManualWithFallback(String),
+}
+
+impl OperatorSelection {
+    fn command(&self) -> String {
+        match self {
+            OperatorSelection::Automatic => AtCommand::set("+COPS").number(0),
+            OperatorSelection::Manual(numeric) => {
+                AtCommand::set("+COPS").number(1).number(2).string(numeric)
+            }
+            OperatorSelection::ManualWithFallback(numeric) => {
+                AtCommand::set("+COPS").number(4).number(2).string(numeric)
+            }
+        }
+        .into()
+    }
+}
+
+fn current_operator(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<Option<Operator>> {
+    fn format_resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::NotResolved)
+    }
+    /// Operator and the access technology, `None` if the modem isn't registered (`+COPS: 0`).
+    fn resolver(result: String) -> ResolverReturn<Option<(String, Option<u8>)>> {
+        match HAT_OPERATOR_REGEX.captures(&result) {
+            Some(captured) => Ok(Some((
+                captured["operator"].to_string(),
+                captured
+                    .name("act")
+                    .map(|act: regex::Match| parse_field("access_technology", act.as_str()))
+                    .transpose()?,
+            ))),
+            None => match ack_check(&result) {
+                true => Ok(None),
+                false => Err(Error::NotResolved),
             },
         }
     }
 
-    /// Turns off the HAT.
-    pub fn turn_off(&self) -> TaskJoinHandle<()> {
-        spawn_task(
-            self.serial_port.clone(),
-            TaskPriority::HIGH,
-            turn_off,
-            Some("Turning SIM868 hat off...".to_string()),
-            (),
+    // the operator is reported either by its name or its number, per the format set with `AT+COPS=3`
+    serial_port.process(task_id, "AT+COPS=3,2\n".to_string(), format_resolver, None)?;
+    let numeric: ResolverReturn<Option<(String, Option<u8>)>> =
+        serial_port.process(task_id, "AT+COPS?\n".to_string(), resolver, None);
+    // the long name is the default format
+    serial_port.process(task_id, "AT+COPS=3,0\n".to_string(), format_resolver, None)?;
+    let Some((numeric, access_technology)) = numeric? else {
+        return Ok(None);
+    };
+    let name: Option<(String, Option<u8>)> =
+        serial_port.process(task_id, "AT+COPS?\n".to_string(), resolver, None)?;
+    Ok(name.map(|(name, _)| Operator {
+        name,
+        numeric,
+        access_technology,
+    }))
+}
+
+fn scan_operators(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<Vec<NetworkOperator>> {
+    fn resolver(result: String) -> ResolverReturn<Vec<NetworkOperator>> {
+        if !ack_check(&result) {
+            return Err(Error::NotResolved);
+        }
+        HAT_OPERATOR_LIST_REGEX
+            .captures_iter(&result)
+            .map(|captured: regex::Captures| {
+                Ok(NetworkOperator {
+                    status: OperatorStatus::from_status(parse_field(
+                        "operator_status",
+                        &captured["status"],
+                    )?),
+                    long_name: captured["long_name"].to_string(),
+                    short_name: captured["short_name"].to_string(),
+                    numeric: captured["numeric"].to_string(),
+                    access_technology: captured
+                        .name("act")
+                        .map(|act: regex::Match| parse_field("access_technology", act.as_str()))
+                        .transpose()?,
+                })
+            })
+            .collect()
+    }
+
+    serial_port.process(
+        task_id,
+        "AT+COPS=?\n".to_string(),
+        resolver,
+        Some(OPERATOR_TIMEOUT),
+    )
+}
+
+fn select_operator(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    selection: OperatorSelection,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::HatOperatorNotSelected)
+    }
+
+    serial_port.process(
+        task_id,
+        selection.command(),
+        resolver,
+        Some(OPERATOR_TIMEOUT),
+    )
+}
+
+/// Sends the `command` reporting the IMEI, IMSI or ICCID on a line of its own.
+fn identity(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    command: &'static str,
+) -> ResolverReturn<String> {
+    fn resolver(result: String) -> ResolverReturn<String> {
+        if error_check(&result) {
+            return Err(Error::HatIdentityNotRead);
+        }
+        match (HAT_IDENTITY_REGEX.captures(&result), ack_check(&result)) {
+            (Some(captured), true) => Ok(captured["id"].to_string()),
+            _ => Err(Error::NotResolved),
+        }
+    }
+
+    serial_port.process(task_id, format!("{command}\n"), resolver, None)
+}
+
+fn firmware_version(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<String> {
+    fn resolver(result: String) -> ResolverReturn<String> {
+        if error_check(&result) {
+            return Err(Error::HatIdentityNotRead);
+        }
+        match FIRMWARE_REVISION_REGEX.captures(&result) {
+            Some(captured) => Ok(captured["revision"].to_string()),
+            None => Err(Error::NotResolved),
+        }
+    }
+
+    serial_port.process(task_id, "AT+CGMR\n".to_string(), resolver, None)
+}
+
+/// `+CPIN` state of the SIM card, see [`Hat::sim_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SimStatus {
+    /// Unlocked, or not locked with a PIN.
+    Ready,
+    /// Waiting for the PIN, see [`Hat::unlock_pin`].
+    PinRequired,
+    /// Blocked by 3 wrong PINs, waiting for the PUK, see [`Hat::unlock_puk`].
+    PukRequired,
+    NotInserted,
+}
+
+fn sim_status(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<SimStatus> {
+    fn resolver(result: String) -> ResolverReturn<SimStatus> {
+        match HAT_SIM_STATUS_REGEX.captures(&result) {
+            Some(captured) => match captured["status"].trim() {
+                // the PIN2 / PUK2 only guard the fixed dialling numbers, the SIM itself is unlocked
+                "READY" | "SIM PIN2" | "SIM PUK2" => Ok(SimStatus::Ready),
+                "SIM PIN" | "PH_SIM PIN" => Ok(SimStatus::PinRequired),
+                "SIM PUK" | "PH_SIM PUK" => Ok(SimStatus::PukRequired),
+                status => Err(Error::ParseFailure {
+                    field: "sim_status",
+                    raw: status.to_string(),
+                }),
+            },
+            None => Err(Error::NotResolved),
+        }
+    }
+
+    match serial_port.process(task_id, "AT+CPIN?\n".to_string(), resolver, None) {
+        Err(e) if matches!(e.root(), Error::Cme(CmeError::SimNotInserted)) => {
+            Ok(SimStatus::NotInserted)
+        }
+        result => result,
+    }
+}
+
+/// Sends the `command` entering the PIN or the PUK, changing the PIN or (un)locking the SIM with it.
+fn sim_pin_command(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    command: String,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::HatSimPinRejected)
+    }
+
+    serial_port.process(task_id, command, resolver, Some(SIM_PIN_TIMEOUT))
+}
+
+/// State of the modem read by [`Hat::health`], the values which can't be read are `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HealthReport {
+    /// Whether the modem has answered `AT`, the rest isn't read if it hasn't.
+    pub responsive: bool,
+    pub signal: Option<SignalQuality>,
+    pub registration: Option<RegistrationState>,
+    pub sim: Option<SimStatus>,
+    pub battery: Option<Battery>,
+    /// `+CCLK` time of the RTC as reported, eg. `24/05/12,08:42:35+08`.
+    pub modem_time: Option<String>,
+}
+
+fn health(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<HealthReport> {
+    fn clock_resolver(result: String) -> ResolverReturn<String> {
+        match CLOCK_REGEX.captures(&result) {
+            Some(captured) => Ok(captured["datetime"].to_string()),
+            None => Err(Error::NotResolved),
+        }
+    }
+
+    match is_on(serial_port, task_id, ()) {
+        Ok(_) => (),
+        // the queries would only time out one after another
+        Err(e) if e.kind() == ErrorKind::NotResolved => return Ok(HealthReport::default()),
+        Err(e) => return Err(e),
+    }
+    Ok(HealthReport {
+        responsive: true,
+        signal: signal_quality(serial_port, task_id, ()).ok(),
+        registration: registration_status(serial_port, task_id, ())
+            .ok()
+            .map(RegistrationState::from_status),
+        sim: sim_status(serial_port, task_id, ()).ok(),
+        battery: battery(serial_port, task_id, ()).ok(),
+        modem_time: serial_port
+            .process(task_id, "AT+CCLK?\n".to_string(), clock_resolver, None)
+            .ok(),
+    })
+}
+
+fn autobaud(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<u32> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        match ack_check(&result) {
+            true => Ok(()),
+            false => Err(Error::NotResolved),
+        }
+    }
+
+    for baud_rate in BAUD_RATES {
+        serial_port.set_baud_rate(baud_rate)?;
+        // the auto-bauding modem (`AT+IPR=0`) synchronises on the first `AT` without answering it
+        for _ in 0..2 {
+            serial_port.write(task_id, "AT\n")?;
+            match serial_port.read(task_id, resolver, Some(AUTOBAUD_TIMEOUT)) {
+                Ok(_) => {
+                    log::info!("SIM868 hat responds at {baud_rate} bauds.");
+                    return Ok(baud_rate);
+                }
+                Err(e) if e.kind() == ErrorKind::NotResolved => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    Err(Error::HatBaudRateNotDetected)
+}
+
+fn set_baud_rate(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    baud_rate: u32,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::HatBaudRateNotSet)
+    }
+
+    // the modem answers at the previous rate, and switches right after
+    serial_port.process(task_id, format!("AT+IPR={baud_rate}\n"), resolver, None)?;
+    match baud_rate {
+        // the auto-bauding modem follows the rate of the port
+        0 => Ok(()),
+        baud_rate => serial_port.set_baud_rate(baud_rate),
+    }
+}
+
+impl Module for Hat {
+    fn new(serial_port: Arc<SerialPort>) -> Self {
+        Hat {
+            serial_port,
+            #[cfg(feature = "rpi")]
+            power_pin: Arc::new(Mutex::new(Some(Box::new(GpioPowerPin::new(
+                TOGGLE_POWER_PIN,
+            ))))),
+            #[cfg(not(feature = "rpi"))]
+            power_pin: Arc::new(Mutex::new(Some(Box::new(NoPowerPin)))),
+            power_pulse: Arc::new(Mutex::new(None)),
+            boot_timeout: Arc::new(Mutex::new(BOOT_TIMEOUT)),
+            init_commands: Arc::new(Vec::new()),
+        }
+    }
+}
+
+impl Hat {
+    fn set_power_key(&self, high: bool) -> ResolverReturn<()> {
+        match self.power_pin.lock().expect(MUTEX_POISONED_MSG).as_mut() {
+            Some(power_pin) if high => power_pin.set_high(),
+            Some(power_pin) => power_pin.set_low(),
+            None => Err(Error::HatNoPowerPin),
+        }
+    }
+
+    async fn toggle_power(&self) -> ResolverReturn<()> {
+        self.serial_port.update_state(ModemState::invalidate);
+        let pulse: Duration = self
+            .power_pulse
+            .lock()
+            .expect(MUTEX_POISONED_MSG)
+            .unwrap_or_else(|| self.serial_port.profile.power_key_pulse());
+        // the pin isn't locked across the pulse, the guard can't be held over an await
+        self.set_power_key(false)?;
+        tokio::time::sleep(pulse).await;
+        self.set_power_key(true)
+    }
+
+    /// Replaces the default GPIO 4 pin used by [`Hat::turn_on`].
+    pub fn set_power_pin<P>(&self, pin: P)
+    where
+        P: PowerPin + 'static,
+    {
+        self.set_power_pin_boxed(Box::new(pin));
+    }
+
+    pub(crate) fn set_power_pin_boxed(&self, pin: Box<dyn PowerPin>) {
+        *self.power_pin.lock().expect(MUTEX_POISONED_MSG) = Some(pin);
+    }
+
+    /// Stops using the power pin, for modems powered on their own - the GPIO isn't touched.
+    /// [`Hat::turn_on`] then only waits for the modem to answer `AT`.
+    pub fn disable_power_pin(&self) {
+        *self.power_pin.lock().expect(MUTEX_POISONED_MSG) = None;
+    }
+
+    /// Time [`Hat::turn_on`] waits for the modem to answer `AT` after toggling the power, 10 s by default.
+    pub fn set_boot_timeout(&self, timeout: Duration) {
+        *self.boot_timeout.lock().expect(MUTEX_POISONED_MSG) = timeout;
+    }
+
+    /// Time the PWRKEY is held low to toggle the power, instead of the
+    /// [`crate::profile::ModemProfile::power_key_pulse`] of the modem (eg. for a board with a different power circuit).
+    pub fn set_power_pulse(&self, pulse: Duration) {
+        *self.power_pulse.lock().expect(MUTEX_POISONED_MSG) = Some(pulse);
+    }
+
+    pub fn is_on(&self) -> TaskJoinHandle<bool> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            is_on,
+            Some("Checking hat status...".to_string()),
+            (),
+        )
+    }
+
+    /// Runs the initialisation sequence, which disables the echo of the commands (`ATE0`), enables numeric `+CME ERROR` / `+CMS ERROR` reporting, detects
+    /// the [firmware capabilities](crate::capabilities) and restores the settings changed at runtime (eg. [`crate::phone::Phone::set_auto_answer`]), followed by
+    /// the commands added with [`crate::SIM868Builder::init_command`].
+    /// Should be called once the HAT is on; it has to be repeated after every power cycle.
+    pub fn init(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            init,
+            Some("Initialising SIM868 hat...".to_string()),
+            self.init_commands.clone(),
+        )
+    }
+
+    /// Raw `+CSQ` RSSI (0-31, 99 - unknown), see [`Hat::signal_strength`] and [`Hat::signal_quality`] for its
+    /// interpretation.
+    pub fn network_strength(&self) -> TaskJoinHandle<u8> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            network_strength,
+            Some("Checking network strength...".to_string()),
+            (),
+        )
+    }
+
+    pub fn signal_strength(&self) -> TaskJoinHandle<SignalStrength> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            signal_strength,
+            Some("Checking network strength...".to_string()),
+            (),
+        )
+    }
+
+    /// RSSI, its dBm and level, and the bit error rate of the `+CSQ`.
+    pub fn signal_quality(&self) -> TaskJoinHandle<SignalQuality> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            signal_quality,
+            Some("Checking signal quality...".to_string()),
+            (),
+        )
+    }
+
+    /// Switches the functionality level (`AT+CFUN`), eg. the [`Functionality::FlightMode`] between the
+    /// transmissions of a battery powered tracker. The modem deregisters from the network below the
+    /// [`Functionality::Full`] level, dropping the GPRS bearer.
+    pub fn set_functionality(&self, functionality: Functionality) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::HIGH,
+            set_functionality,
+            Some(format!("Setting functionality to {functionality:?}...")),
+            functionality,
+        )
+    }
+
+    /// Restarts the modem (`AT+CFUN=1,1`) without the power pin, waiting up to 30 s for it to respond again.
+    /// Fails with [`Error::HatNotBooted`] if it doesn't. The restarted modem has lost its settings, so
+    /// [`Hat::init`] has to be run again.
+    pub fn reset(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::CRITICAL,
+            reset,
+            Some("Resetting SIM868 hat...".to_string()),
+            (),
+        )
+    }
+
+    /// Enables the slow clock mode (`AT+CSCLK=2`) - the modem falls asleep after 5 s without data on the UART,
+    /// drawing around 1 mA, and keeps the network registration. It's woken up by the incoming calls, SMS and
+    /// other URCs, and by the command sent after a pause (the port sends a waking `AT` before it). The setting
+    /// is restored by [`Hat::init`] after a power cycle.
+    pub fn enable_sleep_mode(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_sleep_mode,
+            Some("Enabling sleep mode...".to_string()),
+            true,
+        )
+    }
+
+    pub fn disable_sleep_mode(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_sleep_mode,
+            Some("Disabling sleep mode...".to_string()),
+            false,
+        )
+    }
+
+    /// Saves the current settings (eg. the echo off and the `+CNMI` message indications) as the modem's profile
+    /// (`AT&W`), loaded when it boots - instead of sending them again after every power cycle. Not all the
+    /// settings are saved, see the AT command manual of the modem.
+    pub fn save_configuration(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            save_configuration,
+            Some("Saving configuration...".to_string()),
+            (),
+        )
+    }
+
+    /// Restores the profile saved by [`Hat::save_configuration`] (`ATZ`). The echo stays off and the errors
+    /// numeric, as [`Hat::init`] sets them.
+    pub fn restore_defaults(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            restore_configuration,
+            Some("Restoring saved configuration...".to_string()),
+            false,
+        )
+    }
+
+    /// Restores the factory defaults (`AT&F`) - [`Hat::save_configuration`] then saves them. The settings
+    /// changed at runtime are no longer restored by [`Hat::init`], except for the echo off and the numeric
+    /// errors.
+    pub fn factory_reset(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            restore_configuration,
+            Some("Restoring factory defaults...".to_string()),
+            true,
+        )
+    }
+
+    /// Switches the NETLIGHT LED on or off (`AT+CNETLIGHT`) - on a battery powered tracker the blinking is a
+    /// measurable part of the consumption.
+    pub fn set_net_light(&self, enabled: bool) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_light,
+            Some(format!(
+                "{} net light...",
+                if enabled { "Enabling" } else { "Disabling" }
+            )),
+            ("CNETLIGHT", enabled),
+        )
+    }
+
+    /// Whether the NETLIGHT LED flashes faster while the GPRS is attached (`AT+CSGS`).
+    pub fn set_gprs_status_light(&self, enabled: bool) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_light,
+            Some(format!(
+                "{} GPRS status light...",
+                if enabled { "Enabling" } else { "Disabling" }
+            )),
+            ("CSGS", enabled),
+        )
+    }
+
+    /// Enables the jamming detection (`AT+SJDR`), the modem then reports the jamming of the GSM link - eg. of
+    /// an alarm system - published as [`Event::JammingDetected`] and [`Event::JammingCleared`].
+    pub fn enable_jamming_detect(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_jamming_detection,
+            Some("Enabling jamming detection...".to_string()),
+            true,
+        )
+    }
+
+    pub fn disable_jamming_detect(&self) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_jamming_detection,
+            Some("Disabling jamming detection...".to_string()),
+            false,
+        )
+    }
+
+    /// GSM band(s) the modem searches (`AT+CBAND`).
+    pub fn get_band(&self) -> TaskJoinHandle<Band> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            get_band,
+            Some("Reading band...".to_string()),
+            (),
+        )
+    }
+
+    /// Locks the modem to the `band` (`AT+CBAND`) - where the other bands have been refarmed, searching only
+    /// the working one cuts the registration from minutes to seconds. The modem re-registers on the new band.
+    pub fn set_band(&self, band: Band) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_band,
+            Some(format!("Setting band to {band:?}...")),
+            band,
+        )
+    }
+
+    /// Charging status, charge and voltage of the supply, see [`crate::battery::BatteryMonitor`] for the low
+    /// battery events. Without a battery, the voltage is the one of the power supply.
+    pub fn battery_status(&self) -> TaskJoinHandle<Battery> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            battery,
+            Some("Checking battery status...".to_string()),
+            (),
+        )
+    }
+
+    /// Responsiveness, signal quality, registration, SIM status, battery and the RTC time read in one task
+    /// (`AT`, `AT+CSQ`, `AT+CREG?`, `AT+CPIN?`, `AT+CBC`, `AT+CCLK?`) - eg. for the heartbeat of a telemetry
+    /// node. See [`crate::SIM868::snapshot`] for the one with the GNSS fix.
+    pub fn health(&self) -> TaskJoinHandle<HealthReport> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            health,
+            Some("Checking modem health...".to_string()),
+            (),
+        )
+    }
+
+    /// Registration to the network (`AT+CREG`), with the location area and the cell of the registered
+    /// modem. The calls and SMS need [`RegistrationState::is_registered`].
+    pub fn registration_status(&self) -> TaskJoinHandle<Registration> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            registration,
+            Some("Checking network registration...".to_string()),
+            "CREG",
+        )
+    }
+
+    /// Registration to the GPRS service (`AT+CGREG`), needed by the bearer of [`crate::gprs`].
+    pub fn gprs_registration_status(&self) -> TaskJoinHandle<Registration> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            registration,
+            Some("Checking GPRS registration...".to_string()),
+            "CGREG",
+        )
+    }
+
+    /// Serving and neighbour cells (`AT+CENG`) with their MCC, MNC, LAC, cell id and received level - a
+    /// coarse location with the cell-based location services when the GNSS has no fix.
+    pub fn cell_info(&self) -> TaskJoinHandle<CellInfo> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            cell_info,
+            Some("Reading cell information...".to_string()),
+            (),
+        )
+    }
+
+    /// Operator the modem is registered to, `None` if it isn't registered.
+    pub fn current_operator(&self) -> TaskJoinHandle<Option<Operator>> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            current_operator,
+            Some("Checking current operator...".to_string()),
+            (),
+        )
+    }
+
+    /// Networks in range (`AT+COPS=?`). The scan takes up to 2 minutes, and the modem doesn't take other
+    /// commands meanwhile.
+    pub fn scan_operators(&self) -> TaskJoinHandle<Vec<NetworkOperator>> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::LOW,
+            scan_operators,
+            Some("Scanning operators...".to_string()),
+            (),
+        )
+    }
+
+    /// Selects the network manually, eg. to keep the roaming tracker on a specific carrier, or restores
+    /// the automatic selection. The task waits for the registration to the selected operator, up to 2 minutes.
+    pub fn select_operator(&self, selection: OperatorSelection) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            select_operator,
+            Some(format!("Selecting operator {selection:?}...")),
+            selection,
+        )
+    }
+
+    /// IMEI of the modem (`AT+CGSN`), eg. to register the device with a fleet management backend.
+    pub fn imei(&self) -> TaskJoinHandle<String> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            identity,
+            Some("Reading IMEI...".to_string()),
+            "AT+CGSN",
+        )
+    }
+
+    /// IMSI of the SIM (`AT+CIMI`), `Error::Cme(CmeError::SimNotInserted)` without one.
+    pub fn imsi(&self) -> TaskJoinHandle<String> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            identity,
+            Some("Reading IMSI...".to_string()),
+            "AT+CIMI",
+        )
+    }
+
+    /// ICCID - the serial number of the SIM (`AT+CCID`).
+    pub fn iccid(&self) -> TaskJoinHandle<String> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            identity,
+            Some("Reading ICCID...".to_string()),
+            "AT+CCID",
+        )
+    }
+
+    /// Firmware revision of the modem (`AT+CGMR`), eg. `1418B05SIM868M32_BT`.
+    pub fn firmware_version(&self) -> TaskJoinHandle<String> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            firmware_version,
+            Some("Reading firmware version...".to_string()),
+            (),
+        )
+    }
+
+    /// State of the SIM card. The modem doesn't register to the network until the PIN locked SIM is
+    /// unlocked, see [`Hat::unlock_pin`].
+    pub fn sim_status(&self) -> TaskJoinHandle<SimStatus> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            sim_status,
+            Some("Checking SIM status...".to_string()),
+            (),
+        )
+    }
+
+    /// Enters the `pin` of the [`SimStatus::PinRequired`] SIM. A wrong one fails with
+    /// `Error::Cme(CmeError::IncorrectPassword)` - after 3 of them the SIM waits for the PUK.
+    pub fn unlock_pin(&self, pin: &str) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::HIGH,
+            sim_pin_command,
+            Some("Unlocking SIM...".to_string()),
+            AtCommand::set("+CPIN").string(pin).into(),
+        )
+    }
+
+    /// Enters the `puk` of the [`SimStatus::PukRequired`] SIM, setting its PIN to `new_pin`.
+    pub fn unlock_puk(&self, puk: &str, new_pin: &str) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::HIGH,
+            sim_pin_command,
+            Some("Unlocking SIM with PUK...".to_string()),
+            AtCommand::set("+CPIN").string(puk).string(new_pin).into(),
+        )
+    }
+
+    /// Changes the PIN of the SIM (`AT+CPWD`), the PIN lock has to be enabled.
+    pub fn change_pin(&self, old_pin: &str, new_pin: &str) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            sim_pin_command,
+            Some("Changing SIM PIN...".to_string()),
+            AtCommand::set("+CPWD")
+                .string("SC")
+                .string(old_pin)
+                .string(new_pin)
+                .into(),
+        )
+    }
+
+    /// Enables or disables asking for the PIN on the power up (`AT+CLCK`), the current `pin` confirms it.
+    pub fn enable_pin_lock(&self, enabled: bool, pin: &str) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            sim_pin_command,
+            Some(format!(
+                "{} SIM PIN lock...",
+                if enabled { "Enabling" } else { "Disabling" }
+            )),
+            AtCommand::set("+CLCK")
+                .string("SC")
+                .number(enabled as u8)
+                .string(pin)
+                .into(),
+        )
+    }
+
+    /// Turns on the HAT by toggling the power (see [`Hat::set_power_pin`]), then probes the modem with `AT`
+    /// until it answers. Fails with [`Error::HatAlreadyOn`] if it answers before the toggle, or with
+    /// [`Error::HatNotBooted`] if it hasn't answered within the [boot timeout](Hat::set_boot_timeout).
+    /// Publishes [`Event::PowerKeyToggled`] once the power has been toggled.
+    ///
+    /// With the [power pin disabled](Hat::disable_power_pin) the power isn't toggled, the modem is only
+    /// waited for.
+    pub async fn turn_on(&self) -> ResolverReturn<()> {
+        match self.is_on().await? {
+            Ok(_) => return Err(Error::HatAlreadyOn),
+            Err(e) if e.kind() != ErrorKind::NotResolved => return Err(e),
+            Err(_) => (),
+        }
+        if self.power_pin.lock().expect(MUTEX_POISONED_MSG).is_some() {
+            log::info!("Turning SIM868 hat on...");
+            self.toggle_power().await?;
+            let _ = self.serial_port.events.send(Event::PowerKeyToggled);
+            log::info!("SIM868 hat - power toggled, waiting for the modem to boot...");
+        } else {
+            log::info!("SIM868 hat - no power pin, waiting for the modem to answer...");
+        }
+
+        let boot_timeout: Duration = *self.boot_timeout.lock().expect(MUTEX_POISONED_MSG);
+        let start: Instant = Instant::now();
+        while start.elapsed() < boot_timeout {
+            match self.is_on().await? {
+                Ok(_) => {
+                    log::info!("SIM868 hat is on, booted in {:?}.", start.elapsed());
+                    return Ok(());
+                }
+                Err(e) if e.kind() == ErrorKind::NotResolved => {
+                    tokio::time::sleep(BOOT_POLL_INTERVAL).await
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(Error::HatNotBooted)
+    }
+
+    /// Finds the baud rate the modem responds at, probing the rates of `AT+IPR` (from 1200 to 460800, the
+    /// most common first) with `AT`, and leaves the transport switched to it. Fails with [`Error::HatBaudRateNotDetected`] if the modem doesn't respond at
+    /// all (eg. it's off, or connected to another serial device), or [`Error::TransportBaudRateUnsupported`].
+    pub fn autobaud(&self) -> TaskJoinHandle<u32> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::HIGH,
+            autobaud,
+            Some("Detecting baud rate...".to_string()),
+            (),
+        )
+    }
+
+    /// Sets the baud rate of the modem (`AT+IPR`) and switches the transport to it. 0 enables the
+    /// auto-bauding, the modem then follows the rate of the port. The rate is lost on the power cycle, unless
+    /// it's saved to the modem's profile with `AT&W`.
+    pub fn set_baud_rate(&self, baud_rate: u32) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::HIGH,
+            set_baud_rate,
+            Some(format!("Setting baud rate to {baud_rate}...")),
+            baud_rate,
+        )
+    }
+
+    /// Turns off the HAT (`AT+CPOWD`). In the [normal](PowerDownMode::Normal) mode waits up to 15 s for the
+    /// `NORMAL POWER DOWN` confirmation. Either way the modem is probed with `AT` afterwards, the task fails
+    /// with [`Error::HatNotPoweredDown`] if it still answers, or with [`Error::HatAlreadyOff`] if it hasn't
+    /// answered in the first place.
+    pub fn turn_off(&self, mode: PowerDownMode) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::CRITICAL,
+            turn_off,
+            Some("Turning SIM868 hat off...".to_string()),
+            mode,
         )
     }
 }
+
+impl Hat {
+    /// [`Hat::is_on`] returning the result directly.
+    pub async fn is_on_now(&self) -> ResolverReturn<bool> {
+        join_task(self.is_on()).await
+    }
+
+    /// [`Hat::init`] returning the result directly.
+    pub async fn init_now(&self) -> ResolverReturn<()> {
+        join_task(self.init()).await
+    }
+
+    /// [`Hat::network_strength`] returning the result directly.
+    pub async fn network_strength_now(&self) -> ResolverReturn<u8> {
+        join_task(self.network_strength()).await
+    }
+
+    /// [`Hat::signal_strength`] returning the result directly.
+    pub async fn signal_strength_now(&self) -> ResolverReturn<SignalStrength> {
+        join_task(self.signal_strength()).await
+    }
+
+    /// [`Hat::signal_quality`] returning the result directly.
+    pub async fn signal_quality_now(&self) -> ResolverReturn<SignalQuality> {
+        join_task(self.signal_quality()).await
+    }
+
+    /// [`Hat::set_functionality`] returning the result directly.
+    pub async fn set_functionality_now(&self, functionality: Functionality) -> ResolverReturn<()> {
+        join_task(self.set_functionality(functionality)).await
+    }
+
+    /// [`Hat::reset`] returning the result directly.
+    pub async fn reset_now(&self) -> ResolverReturn<()> {
+        join_task(self.reset()).await
+    }
+
+    /// [`Hat::enable_sleep_mode`] returning the result directly.
+    pub async fn enable_sleep_mode_now(&self) -> ResolverReturn<()> {
+        join_task(self.enable_sleep_mode()).await
+    }
+
+    /// [`Hat::disable_sleep_mode`] returning the result directly.
+    pub async fn disable_sleep_mode_now(&self) -> ResolverReturn<()> {
+        join_task(self.disable_sleep_mode()).await
+    }
+
+    /// [`Hat::save_configuration`] returning the result directly.
+    pub async fn save_configuration_now(&self) -> ResolverReturn<()> {
+        join_task(self.save_configuration()).await
+    }
+
+    /// [`Hat::restore_defaults`] returning the result directly.
+    pub async fn restore_defaults_now(&self) -> ResolverReturn<()> {
+        join_task(self.restore_defaults()).await
+    }
+
+    /// [`Hat::factory_reset`] returning the result directly.
+    pub async fn factory_reset_now(&self) -> ResolverReturn<()> {
+        join_task(self.factory_reset()).await
+    }
+
+    /// [`Hat::set_net_light`] returning the result directly.
+    pub async fn set_net_light_now(&self, enabled: bool) -> ResolverReturn<()> {
+        join_task(self.set_net_light(enabled)).await
+    }
+
+    /// [`Hat::set_gprs_status_light`] returning the result directly.
+    pub async fn set_gprs_status_light_now(&self, enabled: bool) -> ResolverReturn<()> {
+        join_task(self.set_gprs_status_light(enabled)).await
+    }
+
+    /// [`Hat::enable_jamming_detect`] returning the result directly.
+    pub async fn enable_jamming_detect_now(&self) -> ResolverReturn<()> {
+        join_task(self.enable_jamming_detect()).await
+    }
+
+    /// [`Hat::disable_jamming_detect`] returning the result directly.
+    pub async fn disable_jamming_detect_now(&self) -> ResolverReturn<()> {
+        join_task(self.disable_jamming_detect()).await
+    }
+
+    /// [`Hat::get_band`] returning the result directly.
+    pub async fn get_band_now(&self) -> ResolverReturn<Band> {
+        join_task(self.get_band()).await
+    }
+
+    /// [`Hat::set_band`] returning the result directly.
+    pub async fn set_band_now(&self, band: Band) -> ResolverReturn<()> {
+        join_task(self.set_band(band)).await
+    }
+
+    /// [`Hat::autobaud`] returning the result directly.
+    pub async fn autobaud_now(&self) -> ResolverReturn<u32> {
+        join_task(self.autobaud()).await
+    }
+
+    /// [`Hat::set_baud_rate`] returning the result directly.
+    pub async fn set_baud_rate_now(&self, baud_rate: u32) -> ResolverReturn<()> {
+        join_task(self.set_baud_rate(baud_rate)).await
+    }
+
+    /// [`Hat::battery_status`] returning the result directly.
+    pub async fn battery_status_now(&self) -> ResolverReturn<Battery> {
+        join_task(self.battery_status()).await
+    }
+
+    /// [`Hat::health`] returning the result directly.
+    pub async fn health_now(&self) -> ResolverReturn<HealthReport> {
+        join_task(self.health()).await
+    }
+
+    /// [`Hat::registration_status`] returning the result directly.
+    pub async fn registration_status_now(&self) -> ResolverReturn<Registration> {
+        join_task(self.registration_status()).await
+    }
+
+    /// [`Hat::gprs_registration_status`] returning the result directly.
+    pub async fn gprs_registration_status_now(&self) -> ResolverReturn<Registration> {
+        join_task(self.gprs_registration_status()).await
+    }
+
+    /// [`Hat::cell_info`] returning the result directly.
+    pub async fn cell_info_now(&self) -> ResolverReturn<CellInfo> {
+        join_task(self.cell_info()).await
+    }
+
+    /// [`Hat::current_operator`] returning the result directly.
+    pub async fn current_operator_now(&self) -> ResolverReturn<Option<Operator>> {
+        join_task(self.current_operator()).await
+    }
+
+    /// [`Hat::scan_operators`] returning the result directly.
+    pub async fn scan_operators_now(&self) -> ResolverReturn<Vec<NetworkOperator>> {
+        join_task(self.scan_operators()).await
+    }
+
+    /// [`Hat::select_operator`] returning the result directly.
+    pub async fn select_operator_now(&self, selection: OperatorSelection) -> ResolverReturn<()> {
+        join_task(self.select_operator(selection)).await
+    }
+
+    /// [`Hat::imei`] returning the result directly.
+    pub async fn imei_now(&self) -> ResolverReturn<String> {
+        join_task(self.imei()).await
+    }
+
+    /// [`Hat::imsi`] returning the result directly.
+    pub async fn imsi_now(&self) -> ResolverReturn<String> {
+        join_task(self.imsi()).await
+    }
+
+    /// [`Hat::iccid`] returning the result directly.
+    pub async fn iccid_now(&self) -> ResolverReturn<String> {
+        join_task(self.iccid()).await
+    }
+
+    /// [`Hat::firmware_version`] returning the result directly.
+    pub async fn firmware_version_now(&self) -> ResolverReturn<String> {
+        join_task(self.firmware_version()).await
+    }
+
+    /// [`Hat::sim_status`] returning the result directly.
+    pub async fn sim_status_now(&self) -> ResolverReturn<SimStatus> {
+        join_task(self.sim_status()).await
+    }
+
+    /// [`Hat::unlock_pin`] returning the result directly.
+    pub async fn unlock_pin_now(&self, pin: &str) -> ResolverReturn<()> {
+        join_task(self.unlock_pin(pin)).await
+    }
+
+    /// [`Hat::unlock_puk`] returning the result directly.
+    pub async fn unlock_puk_now(&self, puk: &str, new_pin: &str) -> ResolverReturn<()> {
+        join_task(self.unlock_puk(puk, new_pin)).await
+    }
+
+    /// [`Hat::change_pin`] returning the result directly.
+    pub async fn change_pin_now(&self, old_pin: &str, new_pin: &str) -> ResolverReturn<()> {
+        join_task(self.change_pin(old_pin, new_pin)).await
+    }
+
+    /// [`Hat::enable_pin_lock`] returning the result directly.
+    pub async fn enable_pin_lock_now(&self, enabled: bool, pin: &str) -> ResolverReturn<()> {
+        join_task(self.enable_pin_lock(enabled, pin)).await
+    }
+
+    /// [`Hat::turn_off`] returning the result directly.
+    pub async fn turn_off_now(&self, mode: PowerDownMode) -> ResolverReturn<()> {
+        join_task(self.turn_off(mode)).await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Hat {
+    /// Blocking version of [`Hat::is_on`].
+    pub fn is_on_blocking(&self) -> ResolverReturn<bool> {
+        block_on_task(|| self.is_on())
+    }
+
+    /// Blocking version of [`Hat::init`].
+    pub fn init_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.init())
+    }
+
+    /// Blocking version of [`Hat::network_strength`].
+    pub fn network_strength_blocking(&self) -> ResolverReturn<u8> {
+        block_on_task(|| self.network_strength())
+    }
+
+    /// Blocking version of [`Hat::signal_strength`].
+    pub fn signal_strength_blocking(&self) -> ResolverReturn<SignalStrength> {
+        block_on_task(|| self.signal_strength())
+    }
+
+    /// Blocking version of [`Hat::signal_quality`].
+    pub fn signal_quality_blocking(&self) -> ResolverReturn<SignalQuality> {
+        block_on_task(|| self.signal_quality())
+    }
+
+    /// Blocking version of [`Hat::set_functionality`].
+    pub fn set_functionality_blocking(&self, functionality: Functionality) -> ResolverReturn<()> {
+        block_on_task(|| self.set_functionality(functionality))
+    }
+
+    /// Blocking version of [`Hat::reset`].
+    pub fn reset_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.reset())
+    }
+
+    /// Blocking version of [`Hat::enable_sleep_mode`].
+    pub fn enable_sleep_mode_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.enable_sleep_mode())
+    }
+
+    /// Blocking version of [`Hat::disable_sleep_mode`].
+    pub fn disable_sleep_mode_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.disable_sleep_mode())
+    }
+
+    /// Blocking version of [`Hat::save_configuration`].
+    pub fn save_configuration_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.save_configuration())
+    }
+
+    /// Blocking version of [`Hat::restore_defaults`].
+    pub fn restore_defaults_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.restore_defaults())
+    }
+
+    /// Blocking version of [`Hat::factory_reset`].
+    pub fn factory_reset_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.factory_reset())
+    }
+
+    /// Blocking version of [`Hat::set_net_light`].
+    pub fn set_net_light_blocking(&self, enabled: bool) -> ResolverReturn<()> {
+        block_on_task(|| self.set_net_light(enabled))
+    }
+
+    /// Blocking version of [`Hat::set_gprs_status_light`].
+    pub fn set_gprs_status_light_blocking(&self, enabled: bool) -> ResolverReturn<()> {
+        block_on_task(|| self.set_gprs_status_light(enabled))
+    }
+
+    /// Blocking version of [`Hat::enable_jamming_detect`].
+    pub fn enable_jamming_detect_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.enable_jamming_detect())
+    }
+
+    /// Blocking version of [`Hat::disable_jamming_detect`].
+    pub fn disable_jamming_detect_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.disable_jamming_detect())
+    }
+
+    /// Blocking version of [`Hat::get_band`].
+    pub fn get_band_blocking(&self) -> ResolverReturn<Band> {
+        block_on_task(|| self.get_band())
+    }
+
+    /// Blocking version of [`Hat::set_band`].
+    pub fn set_band_blocking(&self, band: Band) -> ResolverReturn<()> {
+        block_on_task(|| self.set_band(band))
+    }
+
+    /// Blocking version of [`Hat::battery_status`].
+    pub fn battery_status_blocking(&self) -> ResolverReturn<Battery> {
+        block_on_task(|| self.battery_status())
+    }
+
+    /// Blocking version of [`Hat::health`].
+    pub fn health_blocking(&self) -> ResolverReturn<HealthReport> {
+        block_on_task(|| self.health())
+    }
+
+    /// Blocking version of [`Hat::turn_on`].
+    pub fn turn_on_blocking(&self) -> ResolverReturn<()> {
+        block_on(self.turn_on())
+    }
+
+    /// Blocking version of [`Hat::autobaud`].
+    pub fn autobaud_blocking(&self) -> ResolverReturn<u32> {
+        block_on_task(|| self.autobaud())
+    }
+
+    /// Blocking version of [`Hat::set_baud_rate`].
+    pub fn set_baud_rate_blocking(&self, baud_rate: u32) -> ResolverReturn<()> {
+        block_on_task(|| self.set_baud_rate(baud_rate))
+    }
+
+    /// Blocking version of [`Hat::registration_status`].
+    pub fn registration_status_blocking(&self) -> ResolverReturn<Registration> {
+        block_on_task(|| self.registration_status())
+    }
+
+    /// Blocking version of [`Hat::gprs_registration_status`].
+    pub fn gprs_registration_status_blocking(&self) -> ResolverReturn<Registration> {
+        block_on_task(|| self.gprs_registration_status())
+    }
+
+    /// Blocking version of [`Hat::cell_info`].
+    pub fn cell_info_blocking(&self) -> ResolverReturn<CellInfo> {
+        block_on_task(|| self.cell_info())
+    }
+
+    /// Blocking version of [`Hat::current_operator`].
+    pub fn current_operator_blocking(&self) -> ResolverReturn<Option<Operator>> {
+        block_on_task(|| self.current_operator())
+    }
+
+    /// Blocking version of [`Hat::scan_operators`].
+    pub fn scan_operators_blocking(&self) -> ResolverReturn<Vec<NetworkOperator>> {
+        block_on_task(|| self.scan_operators())
+    }
+
+    /// Blocking version of [`Hat::select_operator`].
+    pub fn select_operator_blocking(&self, selection: OperatorSelection) -> ResolverReturn<()> {
+        block_on_task(|| self.select_operator(selection))
+    }
+
+    /// Blocking version of [`Hat::imei`].
+    pub fn imei_blocking(&self) -> ResolverReturn<String> {
+        block_on_task(|| self.imei())
+    }
+
+    /// Blocking version of [`Hat::imsi`].
+    pub fn imsi_blocking(&self) -> ResolverReturn<String> {
+        block_on_task(|| self.imsi())
+    }
+
+    /// Blocking version of [`Hat::iccid`].
+    pub fn iccid_blocking(&self) -> ResolverReturn<String> {
+        block_on_task(|| self.iccid())
+    }
+
+    /// Blocking version of [`Hat::firmware_version`].
+    pub fn firmware_version_blocking(&self) -> ResolverReturn<String> {
+        block_on_task(|| self.firmware_version())
+    }
+
+    /// Blocking version of [`Hat::sim_status`].
+    pub fn sim_status_blocking(&self) -> ResolverReturn<SimStatus> {
+        block_on_task(|| self.sim_status())
+    }
+
+    /// Blocking version of [`Hat::unlock_pin`].
+    pub fn unlock_pin_blocking(&self, pin: &str) -> ResolverReturn<()> {
+        block_on_task(|| self.unlock_pin(pin))
+    }
+
+    /// Blocking version of [`Hat::unlock_puk`].
+    pub fn unlock_puk_blocking(&self, puk: &str, new_pin: &str) -> ResolverReturn<()> {
+        block_on_task(|| self.unlock_puk(puk, new_pin))
+    }
+
+    /// Blocking version of [`Hat::change_pin`].
+    pub fn change_pin_blocking(&self, old_pin: &str, new_pin: &str) -> ResolverReturn<()> {
+        block_on_task(|| self.change_pin(old_pin, new_pin))
+    }
+
+    /// Blocking version of [`Hat::enable_pin_lock`].
+    pub fn enable_pin_lock_blocking(&self, enabled: bool, pin: &str) -> ResolverReturn<()> {
+        block_on_task(|| self.enable_pin_lock(enabled, pin))
+    }
+
+    /// Blocking version of [`Hat::turn_off`].
+    pub fn turn_off_blocking(&self, mode: PowerDownMode) -> ResolverReturn<()> {
+        block_on_task(|| self.turn_off(mode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        profile::ModemProfile,
+        testing::{MockResponse, MockTransport},
+        LogLevelFilter, SIM868,
+    };
+
+    fn connect(mock: &MockTransport) -> SIM868 {
+        SIM868::with_transport(mock.clone(), LogLevelFilter::Off)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn escapes_pins() {
+        let mock: MockTransport = MockTransport::new();
+        mock.set_default(MockResponse::ok());
+        let sim: SIM868 = connect(&mock);
+
+        sim.hat
+            .unlock_pin("1234\"\r\nAT+CPOWD=1")
+            .await
+            .unwrap()
+            .unwrap();
+        sim.hat
+            .unlock_puk("12345678", "0000\"")
+            .await
+            .unwrap()
+            .unwrap();
+        sim.hat.change_pin("0000", "1\r\n2").await.unwrap().unwrap();
+        sim.hat
+            .enable_pin_lock(true, "\"1111")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            mock.written(),
+            [
+                r#"AT+CPIN="1234\22\0D\0AAT+CPOWD=1""#,
+                r#"AT+CPIN="12345678","0000\22""#,
+                r#"AT+CPWD="SC","0000","1\0D\0A2""#,
+                r#"AT+CLCK="SC",1,"\221111""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_operator() {
+        assert_eq!(OperatorSelection::Automatic.command(), "AT+COPS=0\n");
+        assert_eq!(
+            OperatorSelection::Manual("26201".into()).command(),
+            "AT+COPS=1,2,\"26201\"\n"
+        );
+        assert_eq!(
+            OperatorSelection::ManualWithFallback("26201\"\r\nAT+CPOWD=1".into()).command(),
+            "AT+COPS=4,2,\"26201\\22\\0D\\0AAT+CPOWD=1\"\n"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn parses_registration() {
+        let mock: MockTransport = MockTransport::new();
+        mock.set_default(MockResponse::ok());
+        // the URC enabled by the mode 2 (without the mode) may precede the response
+        mock.once(
+            "AT+CREG?",
+            MockResponse::lines(&[
+                r#"+CREG: 1,"1A2B","C3D4""#,
+                r#"+CREG: 2,5,"1a2b","0000c3d4""#,
+                "OK",
+            ]),
+        );
+        mock.once("AT+CREG?", MockResponse::lines(&["+CREG: 2,2", "OK"]));
+        mock.once(
+            "AT+CREG?",
+            MockResponse::lines(&[r#"+CREG: 2,1,"12345","C3D4""#, "OK"]),
+        );
+        mock.once(
+            "AT+CGREG?",
+            MockResponse::lines(&[r#"+CGREG: 2,1,"FFFF","FFFFFFFF""#, "OK"]),
+        );
+        mock.once(
+            "AT+CGREG?",
+            MockResponse::lines(&["+CGREG: 2", "+CGREG: 2,3", "OK"]),
+        );
+        let sim: SIM868 = connect(&mock);
+
+        assert_eq!(
+            sim.hat.registration_status_now().await.unwrap(),
+            Registration {
+                state: RegistrationState::RegisteredRoaming,
+                lac: Some(0x1A2B),
+                cell_id: Some(0xC3D4),
+            }
+        );
+        assert_eq!(
+            sim.hat.registration_status_now().await.unwrap(),
+            Registration {
+                state: RegistrationState::Searching,
+                lac: None,
+                cell_id: None,
+            }
+        );
+        assert_eq!(
+            sim.hat.registration_status_now().await.unwrap_err(),
+            Error::ParseFailure {
+                field: "lac",
+                raw: "12345".to_string(),
+            }
+        );
+        assert_eq!(
+            sim.hat.gprs_registration_status_now().await.unwrap(),
+            Registration {
+                state: RegistrationState::RegisteredHome,
+                lac: Some(0xFFFF),
+                cell_id: Some(0xFFFF_FFFF),
+            }
+        );
+        assert_eq!(
+            sim.hat.gprs_registration_status_now().await.unwrap().state,
+            RegistrationState::Denied
+        );
+        // the default mode is restored after every query
+        assert_eq!(mock.written()[..3], ["AT+CREG=2", "AT+CREG?", "AT+CREG=0"]);
+        assert_eq!(
+            mock.written()[9..],
+            [
+                "AT+CGREG=2",
+                "AT+CGREG?",
+                "AT+CGREG=0",
+                "AT+CGREG=2",
+                "AT+CGREG?",
+                "AT+CGREG=0"
+            ]
+        );
+    }
+
+    #[test]
+    fn maps_registration_status() {
+        for status in 0..=5 {
+            assert_eq!(RegistrationState::from_status(status).status(), status);
+        }
+        assert_eq!(RegistrationState::from_status(6).status(), 4);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn parses_current_operator() {
+        let mock: MockTransport = MockTransport::new();
+        mock.set_default(MockResponse::ok());
+        mock.once(
+            "AT+COPS?",
+            MockResponse::lines(&[r#"+COPS: 0,2,"26201",3"#, "OK"]),
+        );
+        // the SIM800 series don't report the access technology
+        mock.once(
+            "AT+COPS?",
+            MockResponse::lines(&[r#"+COPS: 0,0,"T-Mobile, D""#, "OK"]),
+        );
+        mock.once("AT+COPS?", MockResponse::lines(&["+COPS: 0", "OK"]));
+        let sim: SIM868 = connect(&mock);
+
+        assert_eq!(
+            sim.hat.current_operator_now().await.unwrap(),
+            Some(Operator {
+                name: "T-Mobile, D".to_string(),
+                numeric: "26201".to_string(),
+                access_technology: Some(3),
+            })
+        );
+        assert_eq!(sim.hat.current_operator_now().await.unwrap(), None);
+        assert_eq!(
+            mock.written(),
+            [
+                "AT+COPS=3,2",
+                "AT+COPS?",
+                "AT+COPS=3,0",
+                "AT+COPS?",
+                "AT+COPS=3,2",
+                "AT+COPS?",
+                "AT+COPS=3,0"
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn parses_operator_list() {
+        let mock: MockTransport = MockTransport::new();
+        mock.once(
+            "AT+COPS=?",
+            MockResponse::lines(&[
+                r#"+COPS: (2,"T-Mobile, D","TMO D","26201",0),(3,"Vodafone.de","","26202"),,(0-4),(0-2)"#,
+                "OK",
+            ]),
+        );
+        mock.once(
+            "AT+COPS=?",
+            MockResponse::lines(&["+COPS: ,,(0-4),(0-2)", "OK"]),
+        );
+        let sim: SIM868 = connect(&mock);
+
+        assert_eq!(
+            sim.hat.scan_operators_now().await.unwrap(),
+            [
+                NetworkOperator {
+                    status: OperatorStatus::Current,
+                    long_name: "T-Mobile, D".to_string(),
+                    short_name: "TMO D".to_string(),
+                    numeric: "26201".to_string(),
+                    access_technology: Some(0),
+                },
+                NetworkOperator {
+                    status: OperatorStatus::Forbidden,
+                    long_name: "Vodafone.de".to_string(),
+                    short_name: String::new(),
+                    numeric: "26202".to_string(),
+                    access_technology: None,
+                },
+            ]
+        );
+        assert!(sim.hat.scan_operators_now().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn parses_cells() {
+        let serving: Cell = Cell {
+            mcc: 262,
+            mnc: 1,
+            lac: 0x1A2B,
+            cell_id: 0xA2B3,
+            arfcn: 24,
+            rxlev: 32,
+        };
+        assert_eq!(
+            parse_cell(0, "0024,32,00,262,01,39,a2b3,05,05,1A2B,255").unwrap(),
+            Some(serving)
+        );
+        assert_eq!(serving.dbm(), -78);
+        assert_eq!(
+            parse_cell(1, "0025,20,23,c3d4,262,02,1a2c").unwrap(),
+            Some(Cell {
+                mcc: 262,
+                mnc: 2,
+                lac: 0x1A2C,
+                cell_id: 0xC3D4,
+                arfcn: 25,
+                rxlev: 20,
+            })
+        );
+        // the empty slots of the neighbours list
+        assert_eq!(parse_cell(2, "0030,15,,,,,").unwrap(), None);
+        assert_eq!(parse_cell(3, "0000,00,00,0000,000,00,0000").unwrap(), None);
+        assert_eq!(parse_cell(4, "0031,10,23,ffff,262,01,1a2b").unwrap(), None);
+        assert_eq!(
+            parse_cell(1, "0025,20,23,c3d4,262"),
+            Err(Error::ParseFailure {
+                field: "cell",
+                raw: "0025,20,23,c3d4,262".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_cell(1, "0025,-,23,c3d4,262,02,1a2c"),
+            Err(Error::ParseFailure {
+                field: "rxlev",
+                raw: "-".to_string(),
+            })
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn reads_cell_info() {
+        let mock: MockTransport = MockTransport::new();
+        mock.set_default(MockResponse::ok());
+        mock.on(
+            "AT+CENG?",
+            MockResponse::lines(&[
+                "+CENG: 1,1",
+                r#"+CENG: 0,"0024,32,00,262,01,39,a2b3,05,05,1a2b,255""#,
+                r#"+CENG: 1,"0025,20,23,c3d4,262,01,1a2b""#,
+                r#"+CENG: 2,"0030,15,,,,,""#,
+                r#"+CENG: 3,"0031,10,23,d4e5,262,01,1a2b""#,
+                "OK",
+            ]),
+        );
+        let sim: SIM868 = connect(&mock);
+
+        let info: CellInfo = sim.hat.cell_info_now().await.unwrap();
+        assert_eq!(info.serving.map(|cell: Cell| cell.cell_id), Some(0xA2B3));
+        assert_eq!(
+            info.neighbors
+                .iter()
+                .map(|cell: &Cell| cell.cell_id)
+                .collect::<Vec<u32>>(),
+            [0xC3D4, 0xD4E5]
+        );
+        assert_eq!(mock.written(), ["AT+CENG=1,1", "AT+CENG?", "AT+CENG=0"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn parses_battery() {
+        let mock: MockTransport = MockTransport::new();
+        mock.once("AT+CBC", MockResponse::lines(&["+CBC: 1,85,4123", "OK"]));
+        mock.once("AT+CBC", MockResponse::lines(&["+CBC: 2,100,4200", "OK"]));
+        mock.once("AT+CBC", MockResponse::lines(&["+CBC: 0,7,3412", "OK"]));
+        mock.once("AT+CBC", MockResponse::lines(&["+CBC: 0,300,4000", "OK"]));
+        let sim: SIM868 = connect(&mock);
+
+        assert_eq!(
+            sim.hat.battery_status_now().await.unwrap(),
+            Battery {
+                state: ChargeState::Charging,
+                charge: 85,
+                voltage: 4123,
+            }
+        );
+        assert_eq!(
+            sim.hat.battery_status_now().await.unwrap().state,
+            ChargeState::Charged
+        );
+        assert_eq!(
+            sim.hat.battery_status_now().await.unwrap(),
+            Battery {
+                state: ChargeState::NotCharging,
+                charge: 7,
+                voltage: 3412,
+            }
+        );
+        assert_eq!(
+            sim.hat.battery_status_now().await.unwrap_err(),
+            Error::ParseFailure {
+                field: "battery_charge",
+                raw: "300".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn parses_band() {
+        let mock: MockTransport = MockTransport::new();
+        mock.set_default(MockResponse::ok());
+        for band in [
+            "+CBAND: EGSM_DCS_MODE",
+            r#"+CBAND: "PCS_MODE""#,
+            // the first band of the pair, and the current band followed by the others
+            "+CBAND: GSM850_MODE",
+            "+CBAND: ALL_BAND,PGSM_MODE",
+            "+CBAND: GSM450_MODE",
+        ] {
+            mock.once("AT+CBAND?", MockResponse::lines(&[band, "OK"]));
+        }
+        mock.once("AT+CBAND?", MockResponse::error());
+        let sim: SIM868 = connect(&mock);
+
+        let mut bands: Vec<Band> = Vec::new();
+        for _ in 0..4 {
+            bands.push(sim.hat.get_band_now().await.unwrap());
+        }
+        assert_eq!(
+            bands,
+            [
+                Band::Egsm900Dcs1800,
+                Band::Pcs1900,
+                Band::Gsm850Pcs1900,
+                Band::All
+            ]
+        );
+        assert_eq!(
+            sim.hat.get_band_now().await.unwrap_err(),
+            Error::ParseFailure {
+                field: "band",
+                raw: "GSM450_MODE".to_string(),
+            }
+        );
+        assert_eq!(
+            sim.hat.get_band_now().await.unwrap_err(),
+            Error::HatBandNotSet
+        );
+
+        sim.hat.set_band_now(Band::Pgsm900).await.unwrap();
+        assert_eq!(mock.written().last().unwrap(), r#"AT+CBAND="PGSM_MODE""#);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn powers_down_in_supported_mode() {
+        for (profile, command) in [
+            (ModemProfile::SIM868, "AT+CPOWD=0"),
+            // the urgent power down isn't available, so the confirmation is awaited
+            (ModemProfile::SIM7000, "AT+CPOWD=1"),
+        ] {
+            let mock: MockTransport = MockTransport::new();
+            // the modem no longer answers once it's off
+            mock.set_default(MockResponse::silence());
+            mock.once("AT", MockResponse::ok());
+            mock.on("AT+CPOWD=1", MockResponse::lines(&["NORMAL POWER DOWN"]));
+            let sim: SIM868 = SIM868::builder()
+                .transport(mock.clone())
+                .profile(profile)
+                .log_level(LogLevelFilter::Off)
+                .build()
+                .unwrap();
+
+            sim.hat.turn_off_now(PowerDownMode::Urgent).await.unwrap();
+            assert_eq!(mock.written(), ["AT", command, "AT"]);
+        }
+    }
+}
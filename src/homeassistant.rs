@@ -0,0 +1,239 @@
+//! Home Assistant module
+//!
+//! Available with the `homeassistant` feature.
+//!
+//! Payload builders of the [MQTT discovery](https://www.home-assistant.io/integrations/mqtt/#mqtt-discovery)
+//! and state messages. [`HomeAssistant`] builds the [`MqttMessage`]s for the modem's sensors - signal strength,
+//! battery and the GNSS position as a `device_tracker` - and once they're published, the HAT shows up as a
+//! Home Assistant device. The crate doesn't connect to the broker itself, publish the messages with the MQTT
+//! client of your choice (eg. `rumqttc`).
+//!
+//! ```no_run
+//! use rpi_sim868::{homeassistant::{HomeAssistant, MqttMessage}, SIM868};
+//! use std::time::Duration;
+//!
+//! # fn publish(message: &MqttMessage) {}
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! #[tokio::main]
+//! async fn main() {
+//!     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//!     let home_assistant: HomeAssistant = HomeAssistant::new(&sim, "balloon");
+//!
+//!     // once, after connecting to the broker
+//!     for message in home_assistant.discovery_messages() {
+//!         publish(&message);
+//!     }
+//!     loop {
+//!         for message in home_assistant.state_messages().await {
+//!             publish(&message);
+//!         }
+//!         tokio::time::sleep(Duration::from_secs(60)).await;
+//!     }
+//! }
+//! ```
+
+use crate::{
+    hat::{battery, Battery, SignalLevel},
+    serial_port::{spawn_task, SerialPort, TaskPriority},
+    Module, SIM868,
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const DEFAULT_DISCOVERY_PREFIX: &str = "homeassistant";
+const ONLINE: &str = "online";
+const OFFLINE: &str = "offline";
+
+/// Message to be published to the MQTT broker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttMessage {
+    pub topic: String,
+    pub payload: String,
+    pub retain: bool,
+}
+
+/// Home Assistant device of the modem.
+pub struct HomeAssistant {
+    node_id: String,
+    discovery_prefix: String,
+    state_topic: String,
+    serial_port: Arc<SerialPort>,
+    hat: crate::hat::Hat,
+    #[cfg(feature = "gnss")]
+    gnss: crate::gnss::GNSS,
+}
+
+impl HomeAssistant {
+    /// `node_id` identifies the device in Home Assistant, the states are published under `rpi_sim868/<node_id>`.
+    pub fn new(sim: &SIM868, node_id: &str) -> Self {
+        HomeAssistant {
+            node_id: node_id.to_string(),
+            discovery_prefix: DEFAULT_DISCOVERY_PREFIX.to_string(),
+            state_topic: format!("rpi_sim868/{node_id}"),
+            serial_port: sim.serial_port.clone(),
+            hat: crate::hat::Hat::new(sim.serial_port.clone()),
+            #[cfg(feature = "gnss")]
+            gnss: crate::gnss::GNSS::new(sim.serial_port.clone()),
+        }
+    }
+
+    /// Changes the default `homeassistant` discovery prefix.
+    pub fn with_discovery_prefix(mut self, discovery_prefix: &str) -> Self {
+        self.discovery_prefix = discovery_prefix.to_string();
+        self
+    }
+
+    /// Uses `state_topic` instead of `rpi_sim868/<node_id>` for the states.
+    pub fn with_state_topic(mut self, state_topic: &str) -> Self {
+        self.state_topic = state_topic.to_string();
+        self
+    }
+
+    /// Topic of the `online`/`offline` availability, eg. for the last will of the MQTT connection.
+    pub fn availability_topic(&self) -> String {
+        format!("{}/availability", self.state_topic)
+    }
+
+    fn device(&self) -> Value {
+        json!({
+            "identifiers": [format!("rpi_sim868_{}", self.node_id)],
+            "name": self.node_id,
+            "manufacturer": "SIMCom",
+            "model": format!("{:?}", self.serial_port.profile),
+        })
+    }
+
+    fn config(&self, component: &str, object_id: &str, mut payload: Value) -> MqttMessage {
+        payload["unique_id"] = json!(format!("{}_{object_id}", self.node_id));
+        payload["availability_topic"] = json!(self.availability_topic());
+        payload["device"] = self.device();
+        MqttMessage {
+            topic: format!(
+                "{}/{component}/{}/{object_id}/config",
+                self.discovery_prefix, self.node_id
+            ),
+            payload: payload.to_string(),
+            retain: true,
+        }
+    }
+
+    fn state(&self, object_id: &str, payload: String) -> MqttMessage {
+        MqttMessage {
+            topic: format!("{}/{object_id}", self.state_topic),
+            payload,
+            retain: false,
+        }
+    }
+
+    /// Discovery configs to be published once (retained) after connecting to the broker.
+    pub fn discovery_messages(&self) -> Vec<MqttMessage> {
+        #[cfg_attr(not(feature = "gnss"), allow(unused_mut))]
+        let mut messages: Vec<MqttMessage> = vec![
+            self.config(
+                "sensor",
+                "signal_strength",
+                json!({
+                    "name": "Signal strength",
+                    "state_topic": format!("{}/signal_strength", self.state_topic),
+                    "state_class": "measurement",
+                    "icon": "mdi:signal",
+                }),
+            ),
+            self.config(
+                "sensor",
+                "battery",
+                json!({
+                    "name": "Battery",
+                    "state_topic": format!("{}/battery", self.state_topic),
+                    "value_template": "{{ value_json.charge }}",
+                    "device_class": "battery",
+                    "unit_of_measurement": "%",
+                    "state_class": "measurement",
+                }),
+            ),
+            self.config(
+                "sensor",
+                "battery_voltage",
+                json!({
+                    "name": "Battery voltage",
+                    "state_topic": format!("{}/battery", self.state_topic),
+                    "value_template": "{{ value_json.voltage }}",
+                    "device_class": "voltage",
+                    "unit_of_measurement": "V",
+                    "state_class": "measurement",
+                }),
+            ),
+        ];
+
+        #[cfg(feature = "gnss")]
+        messages.push(self.config(
+            "device_tracker",
+            "position",
+            json!({
+                "name": "Position",
+                "json_attributes_topic": format!("{}/position", self.state_topic),
+                "source_type": "gps",
+            }),
+        ));
+
+        messages
+    }
+
+    /// Queries the modem and returns the current states. Values which can't be read at the moment are left out.
+    pub async fn state_messages(&self) -> Vec<MqttMessage> {
+        let mut messages: Vec<MqttMessage> = Vec::new();
+
+        let online: bool = matches!(self.hat.is_on().await, Ok(Ok(true)));
+        let availability: &str = if online { ONLINE } else { OFFLINE };
+        messages.push(MqttMessage {
+            topic: self.availability_topic(),
+            payload: availability.to_string(),
+            retain: true,
+        });
+        if !online {
+            return messages;
+        }
+
+        if let Ok(Ok(strength)) = self.hat.signal_strength().await {
+            // the RSSI 99 is unknown, not a strength
+            if strength.level != SignalLevel::Unknown {
+                messages.push(self.state("signal_strength", strength.rssi.to_string()));
+            }
+        }
+
+        let battery: Option<Battery> = spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            battery,
+            None,
+            (),
+        )
+        .await
+        .ok()
+        .and_then(Result::ok);
+        if let Some(battery) = battery {
+            let payload: Value = json!({
+                "charge": battery.charge,
+                "voltage": battery.voltage as f64 / 1000.0,
+            });
+            messages.push(self.state("battery", payload.to_string()));
+        }
+
+        #[cfg(feature = "gnss")]
+        if let Ok(Ok(data)) = self.gnss.get_data().await {
+            let payload: Value = json!({
+                "latitude": data.lat,
+                "longitude": data.lon,
+                "altitude": data.alt,
+                "speed": data.ground_speed,
+                "course": data.ground_course,
+                "satellites": data.sats_in_use,
+            });
+            messages.push(self.state("position", payload.to_string()));
+        }
+
+        messages
+    }
+}
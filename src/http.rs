@@ -1,15 +1,29 @@
+#[cfg(feature = "gnss")]
+use crate::GPRS_HTTP_TO_FS_REGEX;
 use crate::{
-    error::Error,
+    capabilities::Capability,
+    command::AtCommand,
+    error::{Error, ErrorKind},
     error_check,
-    gprs::{ContentType, Request, RequestMethod},
+    gprs::{ContentType, Request, RequestMethod, TransferDirection, TransferProgress},
+    parse_field,
     serial_port::SerialPort,
-    ResolverReturn, ACK_REGEX, REGEX_COMP_ERROR,
+    ResolverReturn, GPRS_HTTP_ACTION_REGEX, REGEX_COMP_ERROR,
 };
 use regex::Regex;
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
+#[cfg(feature = "gnss")]
+use std::time::Duration;
+use tokio::sync::watch;
 use url::Url;
 use uuid::Uuid;
 
+/// Bytes written or read at once, [`TransferProgress`] is reported after each chunk.
+const CHUNK_SIZE: usize = 1024;
+/// Time the modem has to download a file to its file system, eg. the GNSS assistance data (about 50 kB).
+#[cfg(feature = "gnss")]
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+
 fn generic_resolver(result: String, regex: &str) -> ResolverReturn<()> {
     if error_check(&result) {
         return Err(Error::GprsHttpRequestFailed);
@@ -50,34 +64,121 @@ where
         url.set_query(Some(&serde_url_params::to_string(&request.data)?))
     }
 
-    let mut commands = vec![
-        "AT+HTTPINIT\n".to_string(),
-        "AT+HTTPPARA=CID,1\n".to_string(),
-        format!("AT+HTTPPARA=URL,{}\n", url),
+    let mut commands: Vec<AtCommand> = vec![
+        AtCommand::set("+HTTPPARA").token("CID").number(1),
+        AtCommand::set("+HTTPPARA")
+            .token("URL")
+            .string(url.as_str()),
     ];
 
+    if url.scheme() == "https" {
+        serial_port.require_capability(Capability::HTTPS)?;
+        commands.push(AtCommand::set("+HTTPSSL").number(1));
+    }
+
     if let Some(userdata_header) = &request.userdata_header {
-        commands.push(format!("AT+HTTPPARA=USERDATA,\"{}\"\n", userdata_header))
+        commands.push(
+            AtCommand::set("+HTTPPARA")
+                .token("USERDATA")
+                .string(userdata_header),
+        )
     }
 
     if matches!(request.method, RequestMethod::POST) {
-        commands.push(format!(
-            "AT+HTTPPARA=CONTENT,{}\n",
-            get_content_type(&request.content_type)
-        ));
+        commands.push(
+            AtCommand::set("+HTTPPARA")
+                .token("CONTENT")
+                .string(&get_content_type(&request.content_type)),
+        );
     }
 
+    initialise(serial_port, task_id)?;
     for command in commands {
-        serial_port.process(task_id, command, http_request_resolver, None)?;
+        serial_port.process(task_id, command.into(), http_request_resolver, None)?;
     }
 
     Ok(())
 }
 
+fn initialise(serial_port: &Arc<SerialPort>, task_id: &Uuid) -> ResolverReturn<()> {
+    let initialised: ResolverReturn<()> = serial_port.process(
+        task_id,
+        AtCommand::execute("+HTTPINIT").into(),
+        http_request_resolver,
+        None,
+    );
+    // `ERROR` may come from the service initialised already, so the state isn't known then
+    serial_port.update_state(|state| state.http_active = initialised.as_ref().ok().map(|_| true));
+    initialised
+}
+
+/// Has the modem download the `url` to the file at `path` of its file system (`AT+HTTPTOFS`), returning the
+/// length of the file. The service is terminated afterwards.
+#[cfg(feature = "gnss")]
+pub fn download_to_file(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    url: &str,
+    path: &str,
+) -> ResolverReturn<usize> {
+    fn resolver(result: String) -> ResolverReturn<usize> {
+        if error_check(&result) {
+            return Err(Error::GprsHttpRequestFailed);
+        }
+        let Some(captured) = GPRS_HTTP_TO_FS_REGEX.captures(&result) else {
+            return Err(Error::NotResolved);
+        };
+        match parse_field::<u16>("http_status", &captured["status"])? {
+            200 => parse_field("file_length", &captured["length"]),
+            status => Err(Error::GprsDownloadFailed(status)),
+        }
+    }
+
+    let url: Url = Url::parse(url)?;
+    initialise(serial_port, task_id)?;
+    let downloaded: ResolverReturn<usize> = serial_port
+        .process(
+            task_id,
+            AtCommand::set("+HTTPPARA").token("CID").number(1).into(),
+            http_request_resolver,
+            None,
+        )
+        .and_then(|_| {
+            serial_port.process(
+                task_id,
+                AtCommand::set("+HTTPTOFS")
+                    .string(url.as_str())
+                    .string(path)
+                    .into(),
+                resolver,
+                Some(DOWNLOAD_TIMEOUT),
+            )
+        });
+    terminate(serial_port, task_id)?;
+    downloaded
+}
+
+/// Splits the `data` into the chunks of about [`CHUNK_SIZE`] bytes, at the character boundaries.
+fn chunks(data: &str) -> Vec<&str> {
+    let mut chunks: Vec<&str> = Vec::new();
+    let mut rest: &str = data;
+    while !rest.is_empty() {
+        let mut end: usize = CHUNK_SIZE.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
+
 pub fn data<T>(
     serial_port: &Arc<SerialPort>,
     task_id: &Uuid,
     request: &Request<T>,
+    progress: &watch::Sender<TransferProgress>,
 ) -> ResolverReturn<()>
 where
     T: serde::Serialize,
@@ -98,55 +199,132 @@ where
 
     serial_port.process(
         task_id,
-        format!("AT+HTTPDATA={},6000\n", data.as_bytes().len()),
+        AtCommand::set("+HTTPDATA")
+            .number(data.len())
+            .number(6000)
+            .into(),
         http_data_resolver,
-        Some(Duration::from_secs(10)),
+        Some(serial_port.timeouts.http),
     )?;
-    serial_port.write(task_id, data)?;
-    serial_port.read(task_id, http_request_resolver, Some(Duration::from_secs(6)))
+    let total: usize = data.len();
+    let mut transferred: usize = 0;
+    for chunk in chunks(&data) {
+        serial_port.write(task_id, chunk)?;
+        transferred += chunk.len();
+        progress.send_replace(TransferProgress {
+            direction: TransferDirection::Upload,
+            transferred,
+            total,
+        });
+    }
+    serial_port.read(
+        task_id,
+        http_request_resolver,
+        Some(serial_port.timeouts.http),
+    )
 }
 
+/// Performs the request, returning the length of the response.
 pub fn action(
     serial_port: &Arc<SerialPort>,
     task_id: &Uuid,
     request_method: RequestMethod,
-) -> ResolverReturn<()> {
-    fn resolver(result: String) -> ResolverReturn<()> {
-        generic_resolver(result, r"\+HTTPACTION:.*")
+) -> ResolverReturn<usize> {
+    fn resolver(result: String) -> ResolverReturn<usize> {
+        if error_check(&result) {
+            return Err(Error::GprsHttpRequestFailed);
+        }
+        match GPRS_HTTP_ACTION_REGEX.captures(&result) {
+            Some(captured) => parse_field("response_length", &captured["length"]),
+            None => Err(Error::NotResolved),
+        }
     }
 
     serial_port.process(
         task_id,
-        format!("AT+HTTPACTION={}\n", request_method as u8),
+        AtCommand::set("+HTTPACTION")
+            .number(request_method as u8)
+            .into(),
         resolver,
-        Some(Duration::from_secs(10)),
+        Some(serial_port.timeouts.http),
     )
 }
 
-pub fn read(serial_port: &Arc<SerialPort>, task_id: &Uuid) -> ResolverReturn<String> {
+/// Reads `length` bytes of the response from the `offset` (`AT+HTTPREAD=<offset>,<length>`), returning the data.
+fn read_chunk(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    offset: usize,
+    length: usize,
+) -> ResolverReturn<String> {
     fn resolver(result: String) -> ResolverReturn<String> {
         if error_check(&result) {
             return Err(Error::GprsHttpRequestFailed);
         }
-        match ACK_REGEX.is_match(&result) {
-            true => Ok(result),
-            false => Err(Error::NotResolved),
+        match result.is_empty() {
+            true => Err(Error::NotResolved),
+            false => Ok(result),
         }
     }
 
-    serial_port.process(
+    // the chunk can arrive in several bursts, until the final OK
+    let mut response: String = serial_port.process(
         task_id,
-        "AT+HTTPREAD\n".to_string(),
+        AtCommand::set("+HTTPREAD")
+            .number(offset)
+            .number(length)
+            .into(),
         resolver,
-        Some(Duration::from_secs(10)),
-    )
+        Some(serial_port.timeouts.http),
+    )?;
+    while !(response.contains("+HTTPREAD:") && response.ends_with("\r\nOK\r\n")) {
+        response.push_str(&serial_port.read(task_id, resolver, Some(serial_port.timeouts.http))?);
+    }
+
+    let header: usize = response.find("+HTTPREAD:").unwrap_or_default();
+    let start: usize = response[header..]
+        .find("\r\n")
+        .map_or(response.len(), |end: usize| header + end + 2);
+    let end: usize = response.len() - "\r\nOK\r\n".len();
+    Ok(response.get(start..end).unwrap_or_default().to_string())
+}
+
+/// Reads the response of the `size` in chunks, returning it as the modem would in one piece:
+/// `+HTTPREAD: <size>`, the data and `OK`.
+pub fn read(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    size: usize,
+    progress: &watch::Sender<TransferProgress>,
+) -> ResolverReturn<String> {
+    let mut body: String = String::new();
+    let mut offset: usize = 0;
+    while offset < size {
+        let length: usize = CHUNK_SIZE.min(size - offset);
+        body.push_str(&read_chunk(serial_port, task_id, offset, length)?);
+        offset += length;
+        progress.send_replace(TransferProgress {
+            direction: TransferDirection::Download,
+            transferred: offset,
+            total: size,
+        });
+    }
+    Ok(format!("\r\n+HTTPREAD: {size}\r\n{body}\r\nOK\r\n"))
 }
 
 pub fn terminate(serial_port: &Arc<SerialPort>, task_id: &Uuid) -> ResolverReturn<()> {
-    serial_port.process(
+    let result: ResolverReturn<()> = serial_port.process(
         task_id,
-        format!("AT+HTTPTERM\n"),
+        AtCommand::execute("+HTTPTERM").into(),
         http_request_resolver,
         None,
-    )
+    );
+    let http_active: Option<bool> = match result {
+        Ok(_) => Some(false),
+        // `ERROR` - the service hasn't been initialised
+        Err(ref e) if e.kind() == ErrorKind::GprsHttpRequestFailed => Some(false),
+        Err(_) => None,
+    };
+    serial_port.update_state(|state| state.http_active = http_active);
+    result
 }
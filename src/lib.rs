@@ -15,11 +15,14 @@
 //! **Tested devices:** RPi 3 Model B, RPi 4 Model B, RPi Zero W, RPi Zero 2 W.
 //!
 //! ## Example usage
-//! ```
-//! use rpi_sim868::{SIM868, TaskJoinHandle};
+//! ```no_run
+//! use rpi_sim868::{hat::PowerDownMode, SIM868, TaskJoinHandle};
 //! use tokio::time::sleep;
 //! use std::time::Duration;
 //!
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
 //!     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
@@ -27,13 +30,16 @@
 //!     sim.hat.turn_on().await?;
 //!
 //!     // waiting for the GSM network connection...
-//!     while let Ok(strength) = sim.hat.network_strength().await? {
-//!         if strength > 0 {
+//!     while let Ok(strength) = sim.hat.signal_strength().await? {
+//!         if strength.has_signal() {
 //!             break;
 //!         }
 //!         sleep(Duration::from_secs(2)).await;
 //!     }
 //!
+//!     // enables numeric +CME/+CMS error reporting
+//!     sim.hat.init().await??;
+//!
 //!     // task is spawned by tokio::spawn and starts in the background
 //!     let send_sms: TaskJoinHandle<()> = sim.sms.send("+4799999999", "Hello!");
 //!
@@ -47,56 +53,350 @@
 //!         Err(e) => println!("Problem with sending the SMS: {e:?}"),
 //!     }
 //!
-//!     sim.hat.turn_off().await??;
+//!     sim.hat.turn_off(PowerDownMode::Normal).await??;
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! When the task doesn't need to run in the background, its `_now` counterpart awaits it and returns
+//! `Result<T, Error>` directly, eg. `sim.sms.send_now("+4799999999", "Hello!").await?`.
+//!
+//! ## Blocking API
+//! With the `blocking` feature, every method has a `_blocking` counterpart, which runs the task on a runtime
+//! managed by the crate and waits for its result - no tokio setup is needed.
+//! ```no_run
+//! use rpi_sim868::SIM868;
 //!
+//! # #[cfg(not(all(feature = "rpi", feature = "blocking")))]
+//! # fn main() {}
+//! # #[cfg(all(feature = "rpi", feature = "blocking"))]
+//! fn main() -> Result<(), rpi_sim868::Error> {
+//!     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//!     sim.hat.turn_on_blocking()?;
+//!     sim.sms.send_blocking("+4799999999", "Hello!")?;
 //!     Ok(())
 //! }
 //! ```
 
 #![doc(html_root_url = "https://docs.rs/rpi_sim868/0.1.1")]
 
+pub mod at;
+pub mod battery;
+#[cfg(feature = "blocking")]
+mod blocking;
+#[cfg(feature = "bluetooth")]
+pub mod bluetooth;
+mod builder;
+pub mod capabilities;
+#[cfg(feature = "clock")]
+pub mod clock;
+#[cfg(feature = "cmux")]
+pub mod cmux;
+pub mod command;
+pub mod events;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fs;
+#[cfg(feature = "gnss")]
 pub mod gnss;
+#[cfg(feature = "gprs")]
 pub mod gprs;
 pub mod hat;
+#[cfg(feature = "homeassistant")]
+pub mod homeassistant;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "outbox")]
+pub mod outbox;
+#[cfg(feature = "phone")]
 pub mod phone;
+pub mod profile;
+pub mod retry;
+pub mod scheduler;
+#[cfg(feature = "simulator")]
+pub mod simulator;
+#[cfg(feature = "sms")]
 pub mod sms;
+pub mod snapshot;
+pub mod state;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod timeouts;
+#[cfg(feature = "tracker")]
+pub mod tracker;
+pub mod transport;
+pub mod watchdog;
 
 mod error;
+mod framing;
+#[cfg(feature = "gprs")]
 mod http;
+mod logging;
 mod serial_port;
 
-pub use error::{Error, ErrorKind};
+pub use builder::SIM868Builder;
+pub use error::{CmeError, CmsError, Error, ErrorKind};
 pub use log::LevelFilter as LogLevelFilter;
+pub use logging::LogColors;
+pub use serial_port::TaskPriority;
 
 use lazy_static::lazy_static;
 use regex::Regex;
-use simple_logger::SimpleLogger;
 use std::sync::Arc;
-use tokio::task::JoinHandle;
+use tokio::{sync::broadcast, task::JoinHandle};
+
+/// Every method, except [`hat::Hat::turn_on`] (which is an `async fn`), returns a `TaskJoinHandle<T>`.
+///
+/// It's awaited like the [`tokio::task::JoinHandle`] it wraps, and carries the id the crate prefixes the task's
+/// log messages with, eg. `[67e55044-10b1-426f-9247-bb680e5fe0c8] - resolved.`.
+///
+/// ```no_run
+/// # use rpi_sim868::{SIM868, TaskJoinHandle};
+/// # #[cfg(not(feature = "rpi"))]
+/// # fn main() {}
+/// # #[cfg(feature = "rpi")]
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Info);
+/// let send_sms: TaskJoinHandle<()> = sim.sms.send("+4799999999", "Hello!").with_name("daily report");
+/// println!("sending the daily report as task {}", send_sms.id());
+/// send_sms.await??;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TaskJoinHandle<T> {
+    id: uuid::Uuid,
+    name: Option<String>,
+    handle: JoinHandle<Result<T, error::Error>>,
+    control: Arc<serial_port::TaskControl>,
+}
+
+impl<T> TaskJoinHandle<T> {
+    fn new(
+        id: uuid::Uuid,
+        handle: JoinHandle<Result<T, error::Error>>,
+        control: Arc<serial_port::TaskControl>,
+    ) -> Self {
+        TaskJoinHandle {
+            id,
+            name: None,
+            handle,
+            control,
+        }
+    }
+
+    /// Id of the task in the crate's log messages.
+    pub fn id(&self) -> uuid::Uuid {
+        self.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Names the task, logging the name with the task's id (on the info level) so its log messages can be found.
+    pub fn with_name(mut self, name: &str) -> Self {
+        log::info!("{} - named \"{name}\".", logging::task_label(&self.id));
+        self.control.set_name(name);
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Runs the task with the `priority` instead of the method's default (`NORMAL` for most of them), eg. to send
+    /// the alarm SMS ahead of the queued HTTP uploads.
+    pub fn with_priority(self, priority: TaskPriority) -> Self {
+        self.control.set_priority(&self.id, priority);
+        self
+    }
+
+    /// Fails the task with [`Error::TaskTimeout`] if it hasn't finished within the `deadline` (from now), eg. the
+    /// HTTP request whose every step would otherwise wait for its own timeout. The time spent in the queue counts
+    /// too. The command in progress is cut when the deadline passes, the port is then freed for the next task.
+    pub fn with_deadline(self, deadline: std::time::Duration) -> Self {
+        self.control.set_deadline(deadline);
+        self
+    }
+
+    /// Repeats the failed AT commands of the task according to the `policy` instead of the one set with
+    /// [`SIM868::set_retry_policy`], eg. to retry opening the GPRS bearer without retrying everything else.
+    pub fn with_retry_policy(self, policy: retry::RetryPolicy) -> Self {
+        self.control.set_retry_policy(policy);
+        self
+    }
+
+    /// Cancels the task, which then fails with [`Error::TaskCancelled`]. The queued task leaves the queue without
+    /// touching the serial port, the running one stops before its next AT command - the command in progress is
+    /// completed, so the modem isn't left in the middle of it (eg. of the HTTP data entry). The running task
+    /// occupies its runtime worker, so it can only be cancelled from another worker or thread.
+    pub fn cancel(&self) {
+        log::info!("{} - cancelling.", logging::task_label(&self.id));
+        self.control.cancel();
+    }
+
+    /// Aborts the task, see [`tokio::task::JoinHandle::abort`]. The running task completes its commands first,
+    /// use [`TaskJoinHandle::cancel`] to stop it between them.
+    pub fn abort(&self) {
+        self.handle.abort();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+}
+
+impl<T> std::fmt::Debug for TaskJoinHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaskJoinHandle")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl<T> std::future::Future for TaskJoinHandle<T> {
+    type Output = Result<Result<T, error::Error>, tokio::task::JoinError>;
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        std::pin::Pin::new(&mut self.handle).poll(cx)
+    }
+}
 
-/// Every method, except [`hat::Hat::turn_on`] (which is blocking), returns a `TaskJoinHandle<T>`.
-pub type TaskJoinHandle<T> = JoinHandle<Result<T, error::Error>>;
+/// Waits for the task, turning the [`tokio::task::JoinError`] into [`Error::TokioJoinError`].
+async fn join_task<T>(task: TaskJoinHandle<T>) -> ResolverReturn<T> {
+    task.await.map_err(error::Error::from)?
+}
 
 const REGEX_COMP_ERROR: &str = "Critical error: Regex compilation has failed.";
-const PARSING_ERROR: &str =
-    "Critical error: Parsing of the value which suppose to produce no errors has failed.";
 
 lazy_static! {
     static ref ACK_REGEX: Regex = Regex::new("\r\nOK\r\n").expect(REGEX_COMP_ERROR);
     static ref ERROR_REGEX: Regex = Regex::new("\r\nERROR\r\n").expect(REGEX_COMP_ERROR);
+    static ref CME_ERROR_REGEX: Regex =
+        Regex::new(r"\+CME ERROR: (?<code>\d+)").expect(REGEX_COMP_ERROR);
+    static ref CMS_ERROR_REGEX: Regex =
+        Regex::new(r"\+CMS ERROR: (?<code>\d+)").expect(REGEX_COMP_ERROR);
+    static ref HAT_SIGNAL_STRENGHT_REGEX: Regex =
+        Regex::new(r"\+CSQ: (?<number>\d*)(?:,(?<ber>\d+))?").expect(REGEX_COMP_ERROR);
+    static ref HAT_BATTERY_REGEX: Regex =
+        Regex::new(r"\+CBC: (?<status>\d+),(?<charge>\d+),(?<voltage>\d+)")
+            .expect(REGEX_COMP_ERROR);
+    static ref HAT_REGISTRATION_REGEX: Regex =
+        Regex::new(r"\+CREG: \d+,(?<status>\d+)").expect(REGEX_COMP_ERROR);
+    static ref HAT_REGISTRATION_LOCATION_REGEX: Regex = Regex::new(
+        r#"\+CG?REG: 2,(?<status>\d+)(?:,"(?<lac>[0-9A-Fa-f]+)","(?<cell_id>[0-9A-Fa-f]+)")?"#
+    )
+    .expect(REGEX_COMP_ERROR);
+    static ref CLOCK_REGEX: Regex =
+        Regex::new(r#"\+CCLK: "(?<datetime>[^"]+)""#).expect(REGEX_COMP_ERROR);
+    static ref FS_SIZE_REGEX: Regex =
+        Regex::new(r"\+FSFLSIZE: (?<size>\d+)").expect(REGEX_COMP_ERROR);
+    static ref HAT_BAND_REGEX: Regex =
+        Regex::new(r#"\+CBAND: "?(?<band>[A-Z0-9_]+)"#).expect(REGEX_COMP_ERROR);
+    static ref HAT_CELL_REGEX: Regex =
+        Regex::new(r#"\+CENG: (?<cell>\d+),"(?<data>[^"]*)""#).expect(REGEX_COMP_ERROR);
+    static ref HAT_OPERATOR_REGEX: Regex =
+        Regex::new(r#"\+COPS: \d,\d,"(?<operator>[^"]*)"(?:,(?<act>\d))?"#)
+            .expect(REGEX_COMP_ERROR);
+    static ref HAT_OPERATOR_LIST_REGEX: Regex = Regex::new(
+        r#"\((?<status>\d),"(?<long_name>[^"]*)","(?<short_name>[^"]*)","(?<numeric>\d+)"(?:,(?<act>\d))?\)"#
+    )
+    .expect(REGEX_COMP_ERROR);
+    static ref HAT_IDENTITY_REGEX: Regex =
+        Regex::new(r"(?m)^(?<id>[0-9A-Fa-f]{14,22})\r?$").expect(REGEX_COMP_ERROR);
+    static ref HAT_SIM_STATUS_REGEX: Regex =
+        Regex::new(r"\+CPIN: (?<status>[^\r\n]+)").expect(REGEX_COMP_ERROR);
+    static ref URC_CLIP_REGEX: Regex =
+        Regex::new(r"^\+CLIP: (?<data>.+)$").expect(REGEX_COMP_ERROR);
+    static ref FIRMWARE_REVISION_REGEX: Regex =
+        Regex::new(r"Revision:\s*(?<revision>\S+)").expect(REGEX_COMP_ERROR);
+    static ref HTTP_ACTION_METHODS_REGEX: Regex =
+        Regex::new(r"\+HTTPACTION: \((?:\d+-)?(?<last>\d+)\)").expect(REGEX_COMP_ERROR);
+}
+
+#[cfg(feature = "bluetooth")]
+lazy_static! {
+    static ref BLUETOOTH_CONNECT_REGEX: Regex = Regex::new(
+        r#"\+BTCONNECT: (?<id>\d+),"(?<name>[^"]*)",(?<address>[0-9A-Fa-f:]+),"(?<profile>[^"]*)""#
+    )
+    .expect(REGEX_COMP_ERROR);
+    static ref BLUETOOTH_HOST_REGEX: Regex =
+        Regex::new(r"\+BTHOST: (?<name>[^,\r\n]+),(?<address>[0-9A-Fa-f:]+)")
+            .expect(REGEX_COMP_ERROR);
+    static ref BLUETOOTH_PAIRED_REGEX: Regex =
+        Regex::new(r#"(?m)^P: (?<id>\d+),"(?<name>[^"]*)",(?<address>[0-9A-Fa-f:]+)"#)
+            .expect(REGEX_COMP_ERROR);
+    static ref BLUETOOTH_PROFILE_REGEX: Regex =
+        Regex::new(r#"\+BTGETPROF: (?<id>\d+),"(?<name>[^"]*)""#).expect(REGEX_COMP_ERROR);
+    static ref BLUETOOTH_SCAN_REGEX: Regex =
+        Regex::new(r"\+BTSCAN: (?:0,(?<data>[^\r\n]+)|1)").expect(REGEX_COMP_ERROR);
+    static ref BLUETOOTH_SPP_DATA_REGEX: Regex =
+        Regex::new(r"\+BTSPPDATA: (?<id>\d+),(?<length>\d+),(?<data>[^\r\n]*)")
+            .expect(REGEX_COMP_ERROR);
+    static ref BLUETOOTH_STATUS_REGEX: Regex =
+        Regex::new(r"\+BTSTATUS: (?<status>\d+)").expect(REGEX_COMP_ERROR);
+    static ref BLUETOOTH_VISIBILITY_REGEX: Regex =
+        Regex::new(r"\+BTVIS: (?<visibility>\d)").expect(REGEX_COMP_ERROR);
+}
+
+#[cfg(feature = "clock")]
+lazy_static! {
+    static ref CLOCK_NTP_REGEX: Regex =
+        Regex::new(r"\+CNTP: (?<code>\d+)").expect(REGEX_COMP_ERROR);
+}
+
+#[cfg(feature = "gnss")]
+lazy_static! {
     static ref GNSS_DATA_REGEX: Regex =
         Regex::new(r"\+CGNSINF: (?<data>.+)").expect(REGEX_COMP_ERROR);
     static ref GNSS_POWER_REGEX: Regex =
         Regex::new(r"\+CGNSPWR: (?<number>\d)").expect(REGEX_COMP_ERROR);
+}
+
+#[cfg(all(feature = "gprs", feature = "gnss"))]
+lazy_static! {
+    static ref GPRS_HTTP_TO_FS_REGEX: Regex =
+        Regex::new(r"\+HTTPTOFS: (?<status>\d+),(?<length>\d+)").expect(REGEX_COMP_ERROR);
+}
+
+#[cfg(feature = "gprs")]
+lazy_static! {
     static ref GPRS_CONN_STATUS_REGEX: Regex =
         Regex::new(r"\+SAPBR: (?<data>.+)").expect(REGEX_COMP_ERROR);
-    static ref HAT_SIGNAL_STRENGHT_REGEX: Regex =
-        Regex::new(r"\+CSQ: (?<number>\d*)").expect(REGEX_COMP_ERROR);
+    static ref GPRS_LOCATION_REGEX: Regex = Regex::new(
+        r"\+CIPGSMLOC: (?<code>\d+)(?:,(?<lon>-?[\d.]+),(?<lat>-?[\d.]+),(?<date>[\d/]+),(?<time>[\d:]+))?"
+    )
+    .expect(REGEX_COMP_ERROR);
+    static ref GPRS_QOS_REGEX: Regex =
+        Regex::new(r"(?m)^\+CGQ(?:REQ|MIN): 1,(?<data>[\d,]+)").expect(REGEX_COMP_ERROR);
+    static ref GPRS_HTTP_ACTION_REGEX: Regex =
+        Regex::new(r"\+HTTPACTION: \d,\d+,(?<length>\d+)").expect(REGEX_COMP_ERROR);
+}
+
+#[cfg(feature = "phone")]
+lazy_static! {
     static ref PHONE_INCOMING_CALL_REGEX: Regex =
         Regex::new(r"\+CLIP: (?<data>.+)").expect(REGEX_COMP_ERROR);
+    static ref PHONE_CALL_LIST_REGEX: Regex =
+        Regex::new(r"(?m)^\+CLCC: \d+,(?<direction>\d),(?<status>\d),").expect(REGEX_COMP_ERROR);
+    static ref PHONE_CALL_RESULT_REGEX: Regex =
+        Regex::new(r"(?m)^(?<code>NO CARRIER|BUSY|NO ANSWER|NO DIALTONE)\r?$")
+            .expect(REGEX_COMP_ERROR);
+}
+
+#[cfg(feature = "sms")]
+lazy_static! {
     static ref SMS_READ_MESSAGE_REGEX: Regex =
         Regex::new(r"\+CMGL: (?<index>\d*),(?<data>.+)\r\n(?<text>.+)").expect(REGEX_COMP_ERROR);
     static ref SMS_MESSAGE_SENT_REGEX: Regex = Regex::new(r"\+CMGS: \d").expect(REGEX_COMP_ERROR);
+    /// The text, with the `\n` line breaks, is ended with `\r\n` - followed by a blank line, or by the end of
+    /// the data received so far.
+    static ref SMS_DELIVERED_MESSAGE_REGEX: Regex =
+        Regex::new(r"\+CMT: (?<data>.+)\r\n(?<text>(?s:.*?))\r\n(?:\r\n|\z)")
+            .expect(REGEX_COMP_ERROR);
 }
 
 type ResolverReturn<T> = Result<T, error::Error>;
@@ -112,43 +412,316 @@ fn error_check(text: &str) -> bool {
     ERROR_REGEX.is_match(text)
 }
 
+/// Extracts the `+CME ERROR` / `+CMS ERROR` final result code, if present.
+fn device_error_check(text: &str) -> Option<error::Error> {
+    if let Some(captured) = CME_ERROR_REGEX.captures(text) {
+        return Some(match parse_field("cme_code", &captured["code"]) {
+            Ok(code) => error::Error::Cme(error::CmeError::from_code(code)),
+            Err(err) => err,
+        });
+    }
+    if let Some(captured) = CMS_ERROR_REGEX.captures(text) {
+        return Some(match parse_field("cms_code", &captured["code"]) {
+            Ok(code) => error::Error::Cms(error::CmsError::from_code(code)),
+            Err(err) => err,
+        });
+    }
+    None
+}
+
+/// Parses a single value of the modem response, returning [`error::Error::ParseFailure`] instead of panicking.
+fn parse_field<T: std::str::FromStr>(field: &'static str, raw: &str) -> ResolverReturn<T> {
+    raw.trim().parse().map_err(|_| error::Error::ParseFailure {
+        field,
+        raw: raw.to_string(),
+    })
+}
+
+#[cfg(any(
+    feature = "bluetooth",
+    feature = "gnss",
+    feature = "gprs",
+    feature = "sms"
+))]
+/// Returns the comma separated value at `index`, or [`error::Error::ParseFailure`] if the response is too short.
+fn nth_field<'a>(fields: &[&'a str], index: usize, field: &'static str) -> ResolverReturn<&'a str> {
+    fields
+        .get(index)
+        .copied()
+        .ok_or_else(|| error::Error::ParseFailure {
+            field,
+            raw: fields.join(","),
+        })
+}
+
 fn generic_resolver(result: &str, err: error::Error) -> ResolverReturn<()> {
-    if error_check(&result) {
+    if error_check(result) {
         return Err(err);
     }
-    match ack_check(&result) {
+    match ack_check(result) {
         true => Ok(()),
         false => Err(error::Error::NotResolved),
     }
 }
 
+/// Handle of the modem.
+///
+/// Cloning is cheap - clones share the serial port and its task queue, so the handle can be passed
+/// to other tasks without wrapping it in an `Arc`. The UART is released once the last clone is dropped.
+///
+/// Only the `SIM868` clones keep the connection open - a module handle cloned out of it (eg. `sim.gnss.clone()`)
+/// doesn't: once the last `SIM868` clone has been dropped, the queued tasks are cancelled and the tasks of the
+/// remaining module handles fail with [`Error::Closed`]. Keep a `SIM868` clone for as long
+/// as the modules are used.
+#[derive(Clone)]
 pub struct SIM868 {
     pub hat: hat::Hat,
+    pub at: at::AT,
+    #[cfg(feature = "bluetooth")]
+    pub bluetooth: bluetooth::Bluetooth,
+    #[cfg(feature = "clock")]
+    pub clock: clock::Clock,
+    pub fs: fs::FileSystem,
+    #[cfg(feature = "sms")]
     pub sms: sms::SMS,
+    #[cfg(feature = "gnss")]
     pub gnss: gnss::GNSS,
+    #[cfg(feature = "phone")]
     pub phone: phone::Phone,
+    #[cfg(feature = "gprs")]
     pub gprs: gprs::GPRS,
+    serial_port: Arc<serial_port::SerialPort>,
+    _guard: Arc<CloseGuard>,
+}
+
+/// Closes the serial port when the last [`SIM868`] clone is dropped.
+struct CloseGuard(Arc<serial_port::SerialPort>);
+
+impl Drop for CloseGuard {
+    fn drop(&mut self) {
+        // the queued tasks fail straight away, instead of one by one on the closed port
+        self.0.cancel_all();
+        self.0.close();
+    }
+}
+
+/// Time [`SIM868::shutdown`] waits for the cancelled task to stop, eg. to complete its HTTP request.
+const SHUTDOWN_CANCEL_WAIT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Steps of [`SIM868::shutdown`] before closing the connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownOptions {
+    /// Time the queued tasks have to finish before they're cancelled, 5 s by default. `None` cancels them
+    /// straight away.
+    pub drain: Option<std::time::Duration>,
+    /// Turns the GNSS module off (`AT+CGNSPWR=0`), `true` by default.
+    #[cfg(feature = "gnss")]
+    pub turn_off_gnss: bool,
+    /// Powers the HAT down normally (see [`hat::PowerDownMode::Normal`]), `false` by default.
+    pub power_down: bool,
+}
+
+impl Default for ShutdownOptions {
+    fn default() -> Self {
+        ShutdownOptions {
+            drain: Some(std::time::Duration::from_secs(5)),
+            #[cfg(feature = "gnss")]
+            turn_off_gnss: true,
+            power_down: false,
+        }
+    }
 }
 
 impl SIM868 {
+    /// Connects to the HAT through the Raspberry Pi UART at `path`, eg. `/dev/ttyS0`.
+    ///
+    /// Panics if the UART can't be opened, use [`SIM868::builder`] to get the error instead.
+    #[cfg(feature = "rpi")]
     pub fn new(path: &str, baud_rate: u32, log_level: LogLevelFilter) -> Self {
-        match log_level {
-            LogLevelFilter::Off => (),
-            _ => SimpleLogger::new()
-                .with_level(log_level)
-                .init()
-                .expect("Problems with initialising the logger."),
-        }
+        let uart: rppal::uart::Uart = transport::open_uart(path, baud_rate)
+            .unwrap_or_else(|e| panic!("Unable to establish UART connection: {e}"));
+        SIM868::with_transport(uart, log_level)
+    }
+
+    /// Configures the connection and the modules in one place, see [`SIM868Builder`].
+    pub fn builder() -> SIM868Builder {
+        SIM868Builder::default()
+    }
+
+    /// Connects to the modem through the given [`transport::Transport`].
+    pub fn with_transport<T>(transport: T, log_level: LogLevelFilter) -> Self
+    where
+        T: transport::Transport + 'static,
+    {
+        SIM868::with_profile(transport, profile::ModemProfile::default(), log_level)
+    }
+
+    /// Connects to a modem other than SIM868 (see [`profile::ModemProfile`]) through the given [`transport::Transport`].
+    pub fn with_profile<T>(
+        transport: T,
+        profile: profile::ModemProfile,
+        log_level: LogLevelFilter,
+    ) -> Self
+    where
+        T: transport::Transport + 'static,
+    {
+        SIM868::from_transport(
+            Box::new(transport),
+            profile,
+            timeouts::CommandTimeouts::default(),
+            log_level,
+            LogColors::default(),
+        )
+    }
+
+    fn from_transport(
+        transport: Box<dyn transport::Transport>,
+        profile: profile::ModemProfile,
+        timeouts: timeouts::CommandTimeouts,
+        log_level: LogLevelFilter,
+        log_colors: LogColors,
+    ) -> Self {
+        logging::init_logger(log_level, log_colors);
 
         let serial_port: Arc<serial_port::SerialPort> =
-            Arc::new(serial_port::SerialPort::new(path, baud_rate));
+            Arc::new(serial_port::SerialPort::new(transport, profile, timeouts));
+        serial_port::spawn_urc_reader(serial_port.clone());
 
         SIM868 {
+            at: at::AT::new(serial_port.clone()),
+            #[cfg(feature = "bluetooth")]
+            bluetooth: bluetooth::Bluetooth::new(serial_port.clone()),
+            #[cfg(feature = "clock")]
+            clock: clock::Clock::new(serial_port.clone()),
+            fs: fs::FileSystem::new(serial_port.clone()),
+            #[cfg(feature = "gnss")]
             gnss: gnss::GNSS::new(serial_port.clone()),
             hat: hat::Hat::new(serial_port.clone()),
+            #[cfg(feature = "sms")]
             sms: sms::SMS::new(serial_port.clone()),
+            #[cfg(feature = "gprs")]
             gprs: gprs::GPRS::new(serial_port.clone()),
-            phone: phone::Phone::new(serial_port),
+            #[cfg(feature = "phone")]
+            phone: phone::Phone::new(serial_port.clone()),
+            _guard: Arc::new(CloseGuard(serial_port.clone())),
+            serial_port,
         }
     }
+
+    pub fn profile(&self) -> profile::ModemProfile {
+        self.serial_port.profile
+    }
+
+    /// Capabilities of the firmware detected by [`hat::Hat::init`], `None` until it has run.
+    pub fn capabilities(&self) -> Option<capabilities::Capabilities> {
+        self.serial_port.capabilities()
+    }
+
+    /// Modes of the modem known to the crate, see [`state::ModemState`].
+    pub fn state(&self) -> state::ModemState {
+        self.serial_port.state()
+    }
+
+    /// Forgets the [state](state::ModemState) of the modem, so the modes are set again by the next commands - eg.
+    /// after the modem has restarted on its own.
+    pub fn invalidate_state(&self) {
+        self.serial_port.update_state(state::ModemState::invalidate);
+    }
+
+    /// Task queue of the serial port, see [`scheduler::Scheduler`].
+    pub fn scheduler(&self) -> scheduler::Scheduler {
+        scheduler::Scheduler::new(self.serial_port.clone())
+    }
+
+    /// Repeats the failed AT commands of all the tasks according to the `policy`, see [`retry::RetryPolicy`].
+    pub fn set_retry_policy(&self, policy: retry::RetryPolicy) {
+        self.serial_port.set_retry_policy(policy);
+    }
+
+    /// Subscribes to the [events](`events::Event`) published on the modem's unsolicited result codes.
+    pub fn events(&self) -> broadcast::Receiver<events::Event> {
+        self.serial_port.events.subscribe()
+    }
+
+    /// Gathers the GNSS fix, signal strength, registration and battery in one queued task, see [`snapshot::Snapshot`].
+    pub fn snapshot(&self) -> TaskJoinHandle<snapshot::Snapshot> {
+        serial_port::spawn_task(
+            self.serial_port.clone(),
+            serial_port::TaskPriority::NORMAL,
+            snapshot::snapshot,
+            Some("Taking a snapshot of the modem state...".to_string()),
+            (),
+        )
+    }
+
+    /// [`SIM868::snapshot`] returning the result directly.
+    pub async fn snapshot_now(&self) -> ResolverReturn<snapshot::Snapshot> {
+        join_task(self.snapshot()).await
+    }
+
+    /// Powers down the HAT normally (if `power_down` is set and it's on) and releases the UART device.
+    ///
+    /// Closes all the clones of the handle. Tasks still waiting in the queue fail with [`Error::Closed`], use
+    /// [`SIM868::shutdown`] to let them finish first. Dropping the last clone cancels the queued tasks and
+    /// releases the UART as well, but leaves the HAT powered.
+    pub async fn close(self, power_down: bool) -> ResolverReturn<()> {
+        if power_down {
+            match self.hat.turn_off(hat::PowerDownMode::Normal).await? {
+                Err(e) if e.kind() != ErrorKind::HatAlreadyOff => return Err(e),
+                _ => (),
+            }
+        }
+        self.serial_port.close();
+        Ok(())
+    }
+
+    /// Stops the connection in order: waits for the queued tasks (up to [`ShutdownOptions::drain`]), cancels the
+    /// rest, turns the GNSS module off and powers the HAT down if requested, and then [closes](SIM868::close)
+    /// the connection. Failing to turn the GNSS module off is only logged.
+    pub async fn shutdown(self, options: ShutdownOptions) -> ResolverReturn<()> {
+        let drain: std::time::Duration = options.drain.unwrap_or_default();
+        if !self
+            .serial_port
+            .wait_idle(std::time::Instant::now() + drain)
+            .await
+        {
+            log::warn!("Shutdown - cancelling the unfinished tasks.");
+        }
+        self.serial_port.cancel_all();
+        if !self
+            .serial_port
+            .wait_idle(std::time::Instant::now() + SHUTDOWN_CANCEL_WAIT)
+            .await
+        {
+            log::warn!("Shutdown - the cancelled tasks haven't stopped, closing anyway.");
+        }
+
+        #[cfg(feature = "gnss")]
+        if options.turn_off_gnss {
+            let turned_off: ResolverReturn<()> =
+                join_task(self.gnss.turn_off().with_priority(TaskPriority::CRITICAL)).await;
+            if let Err(e) = turned_off {
+                log::warn!("Shutdown - turning the GNSS module off has failed: {e}");
+            }
+        }
+        self.close(options.power_down).await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl SIM868 {
+    /// Blocking version of [`SIM868::snapshot`].
+    pub fn snapshot_blocking(&self) -> ResolverReturn<snapshot::Snapshot> {
+        blocking::block_on_task(|| self.snapshot())
+    }
+
+    /// Blocking version of [`SIM868::close`].
+    pub fn close_blocking(self, power_down: bool) -> ResolverReturn<()> {
+        blocking::block_on(self.close(power_down))
+    }
+
+    /// Blocking version of [`SIM868::shutdown`].
+    pub fn shutdown_blocking(self, options: ShutdownOptions) -> ResolverReturn<()> {
+        blocking::block_on(self.shutdown(options))
+    }
 }
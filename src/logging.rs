@@ -0,0 +1,54 @@
+use crate::LogLevelFilter;
+use colored::Colorize;
+use simple_logger::SimpleLogger;
+use std::sync::atomic::{AtomicBool, Ordering};
+use uuid::Uuid;
+
+static COLORS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// ANSI coloring of the crate's log messages, see [`crate::SIM868Builder::log_colors`].
+///
+/// The colors garble the logs collected by journald or syslog, where the escape codes are stored as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogColors {
+    /// Colored only if the standard output is a terminal and `NO_COLOR` isn't set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl LogColors {
+    fn enabled(self) -> bool {
+        match self {
+            LogColors::Auto => colored::control::SHOULD_COLORIZE.should_colorize(),
+            LogColors::Always => true,
+            LogColors::Never => false,
+        }
+    }
+}
+
+/// Sets the coloring (also of the messages passed to the application's own logger) and initialises the crate's
+/// logger, unless the `log_level` is [`LogLevelFilter::Off`].
+pub(crate) fn init_logger(log_level: LogLevelFilter, colors: LogColors) {
+    let colors: bool = colors.enabled();
+    COLORS_ENABLED.store(colors, Ordering::Relaxed);
+
+    match log_level {
+        LogLevelFilter::Off => (),
+        _ => SimpleLogger::new()
+            .with_level(log_level)
+            .with_colors(colors)
+            .init()
+            .expect("Problems with initialising the logger."),
+    }
+}
+
+/// `[<task_id>]` prefix of the task's log messages.
+pub(crate) fn task_label(task_id: &Uuid) -> String {
+    let label: String = format!("[{task_id}]");
+    match COLORS_ENABLED.load(Ordering::Relaxed) {
+        true => label.yellow().to_string(),
+        false => label,
+    }
+}
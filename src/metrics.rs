@@ -0,0 +1,382 @@
+//! Metrics module
+//!
+//! Available with the `metrics` feature.
+//!
+//! [`MetricsExporter`] serves the state of the modem in the Prometheus text format, so fleets of trackers can be
+//! monitored uniformly. The modem is queried on every scrape (with the `LOW` priority), metrics which can't be
+//! read at the moment are left out. Concurrent scrapes wait for the same collection, and the scrapes within
+//! a second of it are served from it, so they don't pile up tasks in the queue.
+//!
+//! ```no_run
+//! use rpi_sim868::{metrics::MetricsExporter, SIM868};
+//!
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! #[tokio::main]
+//! async fn main() -> Result<(), rpi_sim868::Error> {
+//!     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//!     let exporter: MetricsExporter = MetricsExporter::new(&sim);
+//!     // metrics are available at http://<host>:9868/metrics
+//!     tokio::spawn(exporter.serve("0.0.0.0:9868".parse().unwrap()));
+//!     // ... use the sim as usual
+//!     Ok(())
+//! }
+//! ```
+//!
+//! | Metric | Type | Description |
+//! |---|---|---|
+//! | `sim868_up` | gauge | 1 if the modem responds to `AT`. |
+//! | `sim868_signal_strength` | gauge | `+CSQ` RSSI (0-31), not exported while unknown. |
+//! | `sim868_signal_strength_dbm` | gauge | Received signal strength in dBm, not exported while unknown. |
+//! | `sim868_bit_error_rate` | gauge | `+CSQ` bit error rate as RXQUAL (0-7), not exported while unknown. |
+//! | `sim868_registration_status` | gauge | `+CREG` status of the [`RegistrationState`] (0 - not registered, 1 - home, 2 - searching, 3 - denied, 4 - unknown, 5 - roaming). |
+//! | `sim868_network_registered` | gauge | 1 if registered in the home network or roaming. |
+//! | `sim868_battery_charge_percent` | gauge | `+CBC` battery charge. |
+//! | `sim868_battery_voltage_volts` | gauge | `+CBC` supply voltage. |
+//! | `sim868_gnss_fix` | gauge | 1 if the GNSS position is fixed (`gnss` feature). |
+//! | `sim868_gnss_satellites_in_use` | gauge | Satellites used for the fix (`gnss` feature). |
+//! | `sim868_gnss_satellites_in_view` | gauge | Satellites in view (`gnss` feature). |
+//! | `sim868_queue_depth` | gauge | Tasks waiting for the serial port. |
+//! | `sim868_tasks_total` | counter | Finished tasks. |
+//! | `sim868_task_failures_total` | counter | Tasks finished with an error. |
+
+#[cfg(feature = "gnss")]
+use crate::error::ErrorKind;
+use crate::{
+    error::Error,
+    generic_resolver,
+    hat::{battery, registration_status, RegistrationState},
+    serial_port::{spawn_task, SerialPort, TaskPriority},
+    ResolverReturn, SIM868,
+};
+use std::{
+    fmt::Write,
+    net::SocketAddr,
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Mutex,
+};
+use uuid::Uuid;
+
+const MAX_REQUEST_SIZE: usize = 8192;
+/// Time for the client to send the request headers.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Age of the last collection below which it's served again instead of querying the modem.
+const SCRAPE_CACHE_TIME: Duration = Duration::from_secs(1);
+
+fn is_on(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::NotResolved)
+    }
+
+    serial_port.process(task_id, "AT\n".to_string(), resolver, None)
+}
+
+fn write_metric(output: &mut String, name: &str, metric_type: &str, help: &str, value: f64) {
+    // writing to String doesn't fail
+    let _ = writeln!(output, "# HELP {name} {help}");
+    let _ = writeln!(output, "# TYPE {name} {metric_type}");
+    let _ = writeln!(output, "{name} {value}");
+}
+
+/// Prometheus exporter of the modem state.
+pub struct MetricsExporter {
+    serial_port: Arc<SerialPort>,
+    #[cfg(feature = "gnss")]
+    gnss: crate::gnss::GNSS,
+    hat: crate::hat::Hat,
+    /// Last collection with its time, locked for the whole collection.
+    scraped: Mutex<Option<(Instant, String)>>,
+}
+
+impl MetricsExporter {
+    pub fn new(sim: &SIM868) -> Self {
+        use crate::Module;
+
+        MetricsExporter {
+            serial_port: sim.serial_port.clone(),
+            #[cfg(feature = "gnss")]
+            gnss: crate::gnss::GNSS::new(sim.serial_port.clone()),
+            hat: crate::hat::Hat::new(sim.serial_port.clone()),
+            scraped: Mutex::new(None),
+        }
+    }
+
+    async fn task<T, A>(
+        &self,
+        task_fn: fn(&Arc<SerialPort>, &Uuid, A) -> ResolverReturn<T>,
+        arguments: A,
+    ) -> Option<T>
+    where
+        T: 'static + Send,
+        A: 'static + Send,
+    {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::LOW,
+            task_fn,
+            None,
+            arguments,
+        )
+        .await
+        .ok()?
+        .ok()
+    }
+
+    /// Queries the modem and returns the metrics in the Prometheus text format.
+    pub async fn collect(&self) -> String {
+        let mut output: String = String::new();
+
+        let up: bool = self.task(is_on, ()).await.is_some();
+        write_metric(
+            &mut output,
+            "sim868_up",
+            "gauge",
+            "Whether the modem responds to AT.",
+            up as u8 as f64,
+        );
+
+        if up {
+            if let Ok(Ok(quality)) = self.hat.signal_quality().await {
+                // the RSSI 99 is unknown, not a strength
+                if let Some(dbm) = quality.dbm {
+                    write_metric(
+                        &mut output,
+                        "sim868_signal_strength",
+                        "gauge",
+                        "+CSQ RSSI (0-31).",
+                        quality.rssi as f64,
+                    );
+                    write_metric(
+                        &mut output,
+                        "sim868_signal_strength_dbm",
+                        "gauge",
+                        "Received signal strength in dBm.",
+                        dbm as f64,
+                    );
+                }
+                if let Some(ber) = quality.ber {
+                    write_metric(
+                        &mut output,
+                        "sim868_bit_error_rate",
+                        "gauge",
+                        "+CSQ bit error rate as RXQUAL (0-7).",
+                        ber as f64,
+                    );
+                }
+            }
+            if let Some(status) = self.task(registration_status, ()).await {
+                let state: RegistrationState = RegistrationState::from_status(status);
+                write_metric(
+                    &mut output,
+                    "sim868_registration_status",
+                    "gauge",
+                    "+CREG network registration status.",
+                    state.status() as f64,
+                );
+                write_metric(
+                    &mut output,
+                    "sim868_network_registered",
+                    "gauge",
+                    "Whether the modem is registered in the home network or roaming.",
+                    state.is_registered() as u8 as f64,
+                );
+            }
+            if let Some(battery) = self.task(battery, ()).await {
+                write_metric(
+                    &mut output,
+                    "sim868_battery_charge_percent",
+                    "gauge",
+                    "+CBC battery charge.",
+                    battery.charge as f64,
+                );
+                write_metric(
+                    &mut output,
+                    "sim868_battery_voltage_volts",
+                    "gauge",
+                    "+CBC supply voltage.",
+                    battery.voltage as f64 / 1000.0,
+                );
+            }
+            #[cfg(feature = "gnss")]
+            self.collect_gnss(&mut output).await;
+        }
+
+        let queue_depth: usize = self.serial_port.queue_depth();
+        write_metric(
+            &mut output,
+            "sim868_queue_depth",
+            "gauge",
+            "Tasks waiting for the serial port.",
+            queue_depth as f64,
+        );
+        let counters: &crate::serial_port::TaskCounters = &self.serial_port.counters;
+        write_metric(
+            &mut output,
+            "sim868_tasks_total",
+            "counter",
+            "Finished tasks.",
+            counters.finished.load(Ordering::Relaxed) as f64,
+        );
+        write_metric(
+            &mut output,
+            "sim868_task_failures_total",
+            "counter",
+            "Tasks finished with an error.",
+            counters.failed.load(Ordering::Relaxed) as f64,
+        );
+
+        output
+    }
+
+    #[cfg(feature = "gnss")]
+    async fn collect_gnss(&self, output: &mut String) {
+        let (fixed, sats_in_use, sats_in_view): (bool, u8, u8) = match self.gnss.get_data().await {
+            Ok(Ok(data)) => (true, data.sats_in_use, data.sats_in_view),
+            Ok(Err(e)) if matches!(e.kind(), ErrorKind::GnssNotFixed) => (false, 0, 0),
+            // GNSS module is off or not available
+            _ => return,
+        };
+        write_metric(
+            output,
+            "sim868_gnss_fix",
+            "gauge",
+            "Whether the GNSS position is fixed.",
+            fixed as u8 as f64,
+        );
+        write_metric(
+            output,
+            "sim868_gnss_satellites_in_use",
+            "gauge",
+            "Satellites used for the fix.",
+            sats_in_use as f64,
+        );
+        write_metric(
+            output,
+            "sim868_gnss_satellites_in_view",
+            "gauge",
+            "Satellites in view.",
+            sats_in_view as f64,
+        );
+    }
+
+    /// Returns the last collection if it's recent, otherwise waits for the running one or collects the metrics.
+    async fn scrape(&self) -> String {
+        let mut scraped: tokio::sync::MutexGuard<'_, Option<(Instant, String)>> =
+            self.scraped.lock().await;
+        if let Some((at, metrics)) = scraped.as_ref() {
+            if at.elapsed() < SCRAPE_CACHE_TIME {
+                return metrics.clone();
+            }
+        }
+        let metrics: String = self.collect().await;
+        *scraped = Some((Instant::now(), metrics.clone()));
+        metrics
+    }
+
+    /// Reads the request headers, `None` if the client closes the connection or sends too much.
+    async fn read_request(stream: &mut TcpStream) -> Result<Option<Vec<u8>>, Error> {
+        let mut request: Vec<u8> = Vec::new();
+        let mut buffer: [u8; 1024] = [0; 1024];
+        while !request
+            .windows(4)
+            .any(|window: &[u8]| window == b"\r\n\r\n")
+        {
+            let length: usize = stream.read(&mut buffer).await?;
+            if length == 0 || request.len() > MAX_REQUEST_SIZE {
+                return Ok(None);
+            }
+            request.extend_from_slice(&buffer[..length]);
+        }
+        Ok(Some(request))
+    }
+
+    async fn respond(&self, mut stream: TcpStream) -> Result<(), Error> {
+        let request: Vec<u8> =
+            match tokio::time::timeout(REQUEST_TIMEOUT, Self::read_request(&mut stream)).await {
+                Ok(request) => match request? {
+                    Some(request) => request,
+                    None => return Ok(()),
+                },
+                // the client holds the connection without sending the request
+                Err(_) => return Ok(()),
+            };
+
+        let request_line: String = String::from_utf8_lossy(&request)
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let (status, body): (&str, String) = match request_line.split(' ').nth(1) {
+            Some("/metrics") | Some("/") => ("200 OK", self.scrape().await),
+            _ => ("404 Not Found", "Not found.\n".to_string()),
+        };
+
+        let response: String = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).await?;
+        Ok(stream.shutdown().await?)
+    }
+
+    /// Serves the metrics on `http://<address>/metrics` until the listener fails.
+    pub async fn serve(self, address: SocketAddr) -> Result<(), Error> {
+        let listener: TcpListener = TcpListener::bind(address).await?;
+        log::info!("Serving metrics at http://{address}/metrics");
+        let exporter: Arc<MetricsExporter> = Arc::new(self);
+
+        loop {
+            let (stream, peer): (TcpStream, SocketAddr) = listener.accept().await?;
+            let exporter: Arc<MetricsExporter> = exporter.clone();
+            tokio::spawn(async move {
+                if let Err(e) = exporter.respond(stream).await {
+                    log::warn!("Metrics request from {peer} failed: {e}");
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{MockResponse, MockTransport},
+        LogLevelFilter,
+    };
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn shares_concurrent_scrapes() {
+        let mock: MockTransport = MockTransport::new();
+        mock.set_default(MockResponse::ok());
+        mock.on("AT+CSQ", MockResponse::lines(&["+CSQ: 20,0", "OK"]));
+        mock.on("AT+CREG?", MockResponse::lines(&["+CREG: 0,5", "OK"]));
+        mock.on("AT+CBC", MockResponse::lines(&["+CBC: 0,85,4123", "OK"]));
+        mock.on(
+            "AT+CGNSINF",
+            MockResponse::lines(&[
+                "+CGNSINF: 1,0,20240101120000.000,,,,,,0,,,,,,8,0,,,,,",
+                "OK",
+            ]),
+        );
+        let sim: SIM868 = SIM868::with_transport(mock.clone(), LogLevelFilter::Off);
+        let exporter: MetricsExporter = MetricsExporter::new(&sim);
+
+        let (first, second): (String, String) = tokio::join!(exporter.scrape(), exporter.scrape());
+        assert_eq!(first, second);
+        assert!(first.contains("sim868_registration_status 5\n"));
+        assert!(first.contains("sim868_network_registered 1\n"));
+        let probes = |mock: &MockTransport| mock.written().iter().filter(|c| *c == "AT").count();
+        assert_eq!(probes(&mock), 1);
+
+        tokio::time::sleep(SCRAPE_CACHE_TIME).await;
+        exporter.scrape().await;
+        assert_eq!(probes(&mock), 2);
+    }
+}
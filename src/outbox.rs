@@ -0,0 +1,445 @@
+//! Outbox module
+//!
+//! Available with the `outbox` feature.
+//!
+//! [`Outbox`] is a disk-backed store-and-forward queue of the outbound HTTP and MQTT payloads, for the
+//! deployments with intermittent coverage: the payloads are enqueued while offline and sent, oldest first,
+//! once the modem is back in the network. The queue is bounded by [`OutboxLimits`], the oldest payloads are
+//! dropped first.
+//!
+//! The queue is saved as JSON Lines after every change, so it survives restarts of the application.
+//!
+//! ```no_run
+//! use rpi_sim868::{
+//!     gprs::ContentType,
+//!     outbox::{Outbox, OutboxLimits},
+//!     SIM868,
+//! };
+//! use serde_json::json;
+//! use std::time::Duration;
+//!
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! #[tokio::main]
+//! async fn main() -> Result<(), rpi_sim868::Error> {
+//!     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//!     let outbox: Outbox = Outbox::open("/var/lib/tracker/outbox.jsonl", OutboxLimits::default())?;
+//!     // sends the HTTP payloads every minute, while the modem is registered in the network
+//!     let _flushing = outbox.auto_flush(&sim, Duration::from_secs(60));
+//!
+//!     outbox.push_http(
+//!         "http://example.com/telemetry",
+//!         ContentType::Json,
+//!         &json!({"temperature": 21.5}),
+//!     )?;
+//!     # Ok(())
+//! }
+//! ```
+
+use crate::{
+    error::Error,
+    gprs::{ContentType, Request, RequestMethod},
+    hat::{registration_status, RegistrationState},
+    join_task,
+    serial_port::{spawn_task, TaskPriority},
+    ResolverReturn, SIM868,
+};
+use serde_json::Value;
+use std::{
+    collections::VecDeque,
+    fs::File,
+    future::Future,
+    io::{ErrorKind as IoErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{task::JoinHandle, time::sleep};
+
+const MUTEX_POISONED_MSG: &str = "Critical error: Mutex is poisoned.";
+
+/// Bounds of the [`Outbox`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutboxLimits {
+    /// Maximum number of the queued payloads, 1000 by default.
+    pub max_entries: usize,
+    /// Payloads older than this are dropped, 7 days by default. `None` keeps them until sent.
+    pub max_age: Option<Duration>,
+}
+
+impl Default for OutboxLimits {
+    fn default() -> Self {
+        OutboxLimits {
+            max_entries: 1000,
+            max_age: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+        }
+    }
+}
+
+/// Destination and content of the queued payload.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Payload {
+    /// Sent as the HTTP POST request by [`Outbox::flush`].
+    Http {
+        url: String,
+        content_type: ContentType,
+        data: Value,
+    },
+    /// Left for the MQTT client of your choice, see [`Outbox::flush_with`].
+    Mqtt {
+        topic: String,
+        payload: String,
+        retain: bool,
+    },
+}
+
+/// Payload queued in the [`Outbox`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Entry {
+    pub id: u64,
+    /// UTC Unix timestamp in seconds.
+    pub enqueued_at: u64,
+    pub payload: Payload,
+}
+
+struct Queue {
+    path: PathBuf,
+    limits: OutboxLimits,
+    entries: VecDeque<Entry>,
+    next_id: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch: Duration| since_epoch.as_secs())
+        .unwrap_or_default()
+}
+
+impl Queue {
+    fn load(path: PathBuf, limits: OutboxLimits) -> Result<Self, Error> {
+        let text: String = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == IoErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        // a corrupted line (eg. written by another version) costs its payload, not the whole queue
+        let entries: VecDeque<Entry> = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line): &(usize, &str)| !line.trim().is_empty())
+            .filter_map(
+                |(number, line): (usize, &str)| match serde_json::from_str(line) {
+                    Ok(entry) => Some(entry),
+                    Err(e) => {
+                        log::warn!(
+                            "Outbox - skipping the line {} of {:?}: {e}",
+                            number + 1,
+                            path
+                        );
+                        None
+                    }
+                },
+            )
+            .collect();
+        let next_id: u64 = entries.back().map_or(0, |entry: &Entry| entry.id + 1);
+
+        let mut queue: Queue = Queue {
+            path,
+            limits,
+            entries,
+            next_id,
+        };
+        queue.enforce_limits();
+        Ok(queue)
+    }
+
+    fn enforce_limits(&mut self) {
+        if let Some(max_age) = self.limits.max_age {
+            let oldest: u64 = now().saturating_sub(max_age.as_secs());
+            self.entries
+                .retain(|entry: &Entry| entry.enqueued_at >= oldest);
+        }
+        while self.entries.len() > self.limits.max_entries {
+            if let Some(dropped) = self.entries.pop_front() {
+                log::warn!("Outbox - full, dropping the payload {}", dropped.id);
+            }
+        }
+    }
+
+    /// Rewrites the file through a temporary one, so it isn't left half-written. Both are synced to the disk,
+    /// so the queue survives a power cut right after the change.
+    fn save(&self) -> Result<(), Error> {
+        let mut text: String = String::new();
+        for entry in &self.entries {
+            text.push_str(&serde_json::to_string(entry)?);
+            text.push('\n');
+        }
+        let temporary: PathBuf = self.path.with_extension("tmp");
+        let mut file: File = File::create(&temporary)?;
+        file.write_all(text.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&temporary, &self.path)?;
+        // the rename is durable once the directory is synced
+        #[cfg(unix)]
+        {
+            let directory: &Path = match self.path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            File::open(directory)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+/// Disk-backed queue of the outbound payloads, see the [module](self) documentation.
+///
+/// Clones share the queue.
+#[derive(Clone)]
+pub struct Outbox {
+    queue: Arc<Mutex<Queue>>,
+    /// Held while flushing, so [`Outbox::auto_flush`] and a direct flush don't send the same payload twice.
+    flushing: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl Outbox {
+    /// Opens the queue saved at `path`, or creates an empty one if the file doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P, limits: OutboxLimits) -> Result<Self, Error> {
+        let queue: Queue = Queue::load(path.as_ref().to_path_buf(), limits)?;
+        queue.save()?;
+        Ok(Outbox {
+            queue: Arc::new(Mutex::new(queue)),
+            flushing: Arc::default(),
+        })
+    }
+
+    fn queue(&self) -> std::sync::MutexGuard<'_, Queue> {
+        self.queue.lock().expect(MUTEX_POISONED_MSG)
+    }
+
+    pub fn push(&self, payload: Payload) -> Result<(), Error> {
+        let mut queue = self.queue();
+        let entry: Entry = Entry {
+            id: queue.next_id,
+            enqueued_at: now(),
+            payload,
+        };
+        queue.next_id += 1;
+        queue.entries.push_back(entry);
+        queue.enforce_limits();
+        queue.save()
+    }
+
+    /// Enqueues the HTTP POST request of the `data` to the `url`.
+    pub fn push_http<T: serde::Serialize>(
+        &self,
+        url: &str,
+        content_type: ContentType,
+        data: &T,
+    ) -> Result<(), Error> {
+        self.push(Payload::Http {
+            url: url.to_string(),
+            content_type,
+            data: serde_json::to_value(data)?,
+        })
+    }
+
+    pub fn push_mqtt(&self, topic: &str, payload: &str, retain: bool) -> Result<(), Error> {
+        self.push(Payload::Mqtt {
+            topic: topic.to_string(),
+            payload: payload.to_string(),
+            retain,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue().entries.is_empty()
+    }
+
+    /// Returns the queued payloads, oldest first.
+    pub fn entries(&self) -> Vec<Entry> {
+        self.queue().entries.iter().cloned().collect()
+    }
+
+    fn remove(&self, id: u64) -> Result<(), Error> {
+        let mut queue = self.queue();
+        queue.entries.retain(|entry: &Entry| entry.id != id);
+        queue.save()
+    }
+
+    /// Passes the payloads selected by the `filter`, oldest first, to `send` and removes the sent ones.
+    /// Stops at the first failure, returning the number of the payloads sent until then. Waits for the flush
+    /// already running to finish.
+    async fn flush_filtered<F, Fut>(
+        &self,
+        filter: fn(&Entry) -> bool,
+        mut send: F,
+    ) -> Result<usize, Error>
+    where
+        F: FnMut(Entry) -> Fut,
+        Fut: Future<Output = Result<(), Error>>,
+    {
+        let _flushing: tokio::sync::MutexGuard<'_, ()> = self.flushing.lock().await;
+        let mut sent: usize = 0;
+        let mut last_id: Option<u64> = None;
+        loop {
+            let next: Option<Entry> = self
+                .queue()
+                .entries
+                .iter()
+                .find(|entry: &&Entry| {
+                    last_id.map_or(true, |id: u64| entry.id > id) && filter(entry)
+                })
+                .cloned();
+            let Some(entry) = next else {
+                return Ok(sent);
+            };
+            let id: u64 = entry.id;
+            send(entry).await?;
+            self.remove(id)?;
+            last_id = Some(id);
+            sent += 1;
+        }
+    }
+
+    /// Sends all the payloads with `send` (eg. publishing the MQTT ones with your client), see [`Outbox::flush`].
+    pub async fn flush_with<F, Fut>(&self, send: F) -> Result<usize, Error>
+    where
+        F: FnMut(Entry) -> Fut,
+        Fut: Future<Output = Result<(), Error>>,
+    {
+        self.flush_filtered(|_: &Entry| true, send).await
+    }
+
+    /// Sends the HTTP payloads, oldest first, and removes them from the queue. The MQTT payloads are kept.
+    /// Stops at the first failed request, returning the number of the payloads sent until then.
+    pub async fn flush(&self, sim: &SIM868) -> Result<usize, Error> {
+        let http = |entry: &Entry| matches!(entry.payload, Payload::Http { .. });
+        self.flush_filtered(http, |entry: Entry| async move {
+            let Payload::Http {
+                url,
+                content_type,
+                data,
+            } = entry.payload
+            else {
+                return Ok(());
+            };
+            sim.gprs
+                .request_now(Request {
+                    content_type: Some(content_type),
+                    data,
+                    userdata_header: None,
+                    method: RequestMethod::POST,
+                    url,
+                })
+                .await
+                .map(|_| ())
+        })
+        .await
+    }
+
+    async fn is_registered(sim: &SIM868) -> bool {
+        let status: ResolverReturn<u8> = join_task(spawn_task(
+            sim.serial_port.clone(),
+            TaskPriority::NORMAL,
+            registration_status,
+            None,
+            (),
+        ))
+        .await;
+        matches!(status.map(RegistrationState::from_status), Ok(state) if state.is_registered())
+    }
+
+    /// Flushes the HTTP payloads every `interval` in the background, whenever the modem is registered
+    /// in the home network or roaming. Abort the returned handle to stop it.
+    pub fn auto_flush(&self, sim: &SIM868, interval: Duration) -> JoinHandle<()> {
+        let outbox: Outbox = self.clone();
+        let sim: SIM868 = sim.clone();
+        tokio::spawn(async move {
+            loop {
+                let pending: bool = outbox
+                    .queue()
+                    .entries
+                    .iter()
+                    .any(|entry: &Entry| matches!(entry.payload, Payload::Http { .. }));
+                if pending && Outbox::is_registered(&sim).await {
+                    match outbox.flush(&sim).await {
+                        Ok(sent) => log::info!("Outbox - sent {sent} payloads"),
+                        Err(e) => log::warn!("Outbox - flushing has failed: {e}"),
+                    }
+                }
+                sleep(interval).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rpi_sim868_{name}_{}.jsonl", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn skips_corrupted_lines() {
+        let path: PathBuf = path("corrupted");
+        let outbox: Outbox = Outbox::open(&path, OutboxLimits::default()).unwrap();
+        outbox
+            .push_mqtt("tracker/position", "first", false)
+            .unwrap();
+        outbox
+            .push_mqtt("tracker/position", "second", false)
+            .unwrap();
+        let text: String = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        std::fs::write(&path, format!("{}\n{{\"id\":\n{}\n", lines[0], lines[1])).unwrap();
+
+        let reopened: Outbox = Outbox::open(&path, OutboxLimits::default()).unwrap();
+        assert_eq!(reopened.entries(), outbox.entries());
+        // the corrupted line is dropped from the file too
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn sends_once_while_flushing_concurrently() {
+        let path: PathBuf = path("concurrent");
+        let outbox: Outbox = Outbox::open(&path, OutboxLimits::default()).unwrap();
+        for payload in ["a", "b", "c"] {
+            outbox
+                .push_mqtt("tracker/position", payload, false)
+                .unwrap();
+        }
+        let sent: Arc<AtomicUsize> = Arc::default();
+        let flush = || {
+            let (outbox, sent) = (outbox.clone(), sent.clone());
+            tokio::spawn(async move {
+                outbox
+                    .flush_with(|_: Entry| {
+                        let sent: Arc<AtomicUsize> = sent.clone();
+                        async move {
+                            sleep(Duration::from_millis(20)).await;
+                            sent.fetch_add(1, Ordering::SeqCst);
+                            Ok(())
+                        }
+                    })
+                    .await
+                    .unwrap()
+            })
+        };
+        let (first, second) = (flush(), flush());
+        assert_eq!(first.await.unwrap() + second.await.unwrap(), 3);
+        assert_eq!(sent.load(Ordering::SeqCst), 3);
+        assert!(outbox.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
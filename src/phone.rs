@@ -2,51 +2,288 @@
 //!
 //! See [`Phone`] to discover available methods.
 //! # Example
-//! ```
+//! ```no_run
+//! # use rpi_sim868::SIM868;
+//! # use std::time::Duration;
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
 //! // This will call a number, and hang up after 20 seconds.
-//! let _ = sim.phone.call("+123456789").join();
-//! std::thread::sleep(time::Duration::from_secs(20));
-//! let _ = sim.phone.end_call().join();
+//! sim.phone.call("+123456789").await??;
+//! tokio::time::sleep(Duration::from_secs(20)).await;
+//! sim.phone.end_call().await??;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Phone::call_with_events`] follows the progress of the outgoing call instead:
+//! ```no_run
+//! # use rpi_sim868::{phone::CallState, SIM868};
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), rpi_sim868::Error> {
+//! # let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//! let mut states = sim.phone.call_with_events("+123456789").await?;
+//! while let Some(state) = states.recv().await {
+//!     match state {
+//!         CallState::Active => println!("Answered"),
+//!         CallState::Ended { reason } => println!("Ended: {reason:?}"),
+//!         _ => (),
+//!     }
+//! }
+//! # Ok(())
+//! # }
 //! ```
 
+#[cfg(feature = "blocking")]
+use crate::blocking::{block_on, block_on_task};
 use crate::{
-    error::Error,
-    generic_resolver,
+    ack_check,
+    error::{Error, ErrorKind},
+    error_check,
+    events::Event,
+    generic_resolver, join_task,
+    profile::Feature,
     serial_port::{spawn_task, SerialPort, TaskPriority},
-    Module, ResolverReturn, TaskJoinHandle, PHONE_INCOMING_CALL_REGEX,
+    Module, ResolverReturn, TaskJoinHandle, PHONE_CALL_LIST_REGEX, PHONE_CALL_RESULT_REGEX,
+    PHONE_INCOMING_CALL_REGEX,
 };
 use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::{broadcast, mpsc},
+    time::sleep,
+};
 use uuid::Uuid;
 
+/// Pause between the `AT+CLCC` polls, leaving the serial port (and the runtime) to the other tasks.
+const CALL_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Call states buffered by [`Phone::call_with_events`] before the polling waits for the receiver.
+const CALL_STATES_CAPACITY: usize = 8;
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IncomingCall {
     pub caller_id: String,
 }
 
+/// Why the outgoing call has ended, see [`CallState::Ended`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CallEndReason {
+    /// The call has left the call list without a final result code, eg. it has been hung up with
+    /// [`Phone::end_call`].
+    Disconnected,
+    /// `NO CARRIER` - the connection has been terminated, or it couldn't be established.
+    NoCarrier,
+    /// `BUSY`
+    Busy,
+    /// `NO ANSWER`
+    NoAnswer,
+    /// `NO DIALTONE`
+    NoDialtone,
+}
+
+impl CallEndReason {
+    /// Final result code in the `text`, if any.
+    pub(crate) fn from_result(text: &str) -> Option<Self> {
+        let captured: regex::Captures<'_> = PHONE_CALL_RESULT_REGEX.captures(text)?;
+        Some(match &captured["code"] {
+            "BUSY" => CallEndReason::Busy,
+            "NO ANSWER" => CallEndReason::NoAnswer,
+            "NO DIALTONE" => CallEndReason::NoDialtone,
+            _ => CallEndReason::NoCarrier,
+        })
+    }
+}
+
+/// State of the outgoing call, published by [`Phone::call_with_events`] on every transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CallState {
+    Dialing,
+    /// The other side is ringing.
+    Alerting,
+    Active,
+    Held,
+    Ended {
+        reason: CallEndReason,
+    },
+}
+
 fn answer(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<()> {
     fn resolver(result: String) -> ResolverReturn<()> {
+        if let Some(reason) = CallEndReason::from_result(&result) {
+            return Err(Error::PhoneCallEnded(reason));
+        }
         generic_resolver(&result, Error::PhoneCallNotAnswered)
     }
 
+    serial_port.require(Feature::VOICE)?;
     serial_port.process(task_id, "ATA\n".to_string(), resolver, None)
 }
 
 fn call(serial_port: &Arc<SerialPort>, task_id: &Uuid, number: String) -> ResolverReturn<()> {
     fn resolver(result: String) -> ResolverReturn<()> {
+        if let Some(reason) = CallEndReason::from_result(&result) {
+            return Err(Error::PhoneCallEnded(reason));
+        }
         generic_resolver(&result, Error::PhoneCallNotCalled)
     }
 
+    serial_port.require(Feature::VOICE)?;
     serial_port.process(task_id, format!("ATD{number};\n"), resolver, None)
 }
 
+fn dial(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    number: String,
+) -> ResolverReturn<Option<CallEndReason>> {
+    fn resolver(result: String) -> ResolverReturn<Option<CallEndReason>> {
+        if error_check(&result) {
+            return Err(Error::PhoneCallNotCalled);
+        }
+        if let Some(reason) = CallEndReason::from_result(&result) {
+            return Ok(Some(reason));
+        }
+        match ack_check(&result) {
+            true => Ok(None),
+            false => Err(Error::NotResolved),
+        }
+    }
+
+    serial_port.require(Feature::VOICE)?;
+    serial_port.process(task_id, format!("ATD{number};\n"), resolver, None)
+}
+
+/// State of the outgoing call in the call list (`None` once it's gone), and the final result code if the modem
+/// has sent it along with the list.
+fn outgoing_call_state(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<(Option<CallState>, Option<CallEndReason>)> {
+    fn resolver(result: String) -> ResolverReturn<(Option<CallState>, Option<CallEndReason>)> {
+        if !ack_check(&result) {
+            return Err(Error::NotResolved);
+        }
+        let state: Option<CallState> = PHONE_CALL_LIST_REGEX
+            .captures_iter(&result)
+            // 0 - mobile originated
+            .find(|captured: &regex::Captures<'_>| &captured["direction"] == "0")
+            .and_then(|captured: regex::Captures<'_>| match &captured["status"] {
+                "0" => Some(CallState::Active),
+                "1" => Some(CallState::Held),
+                "2" => Some(CallState::Dialing),
+                "3" => Some(CallState::Alerting),
+                _ => None,
+            });
+        Ok((state, CallEndReason::from_result(&result)))
+    }
+
+    serial_port.process(
+        task_id,
+        "AT+CLCC\n".to_string(),
+        resolver,
+        Some(serial_port.timeouts.query),
+    )
+}
+
+/// Lists the calls until the outgoing one has ended, sending its state on every change. The result code
+/// received between the polls is published as [`Event::CallEnded`] by the URC filter, and read from `events`.
+async fn follow_call(
+    serial_port: Arc<SerialPort>,
+    states: mpsc::Sender<CallState>,
+    mut events: broadcast::Receiver<Event>,
+) {
+    let mut last: CallState = CallState::Dialing;
+    loop {
+        sleep(CALL_POLL_INTERVAL).await;
+        // the receiver has been dropped
+        if states.is_closed() {
+            return;
+        }
+        let polled: ResolverReturn<(Option<CallState>, Option<CallEndReason>)> =
+            join_task(spawn_task(
+                serial_port.clone(),
+                TaskPriority::NORMAL,
+                outgoing_call_state,
+                None,
+                (),
+            ))
+            .await;
+        let state: CallState = match polled {
+            Ok((state, reason)) => {
+                match (state, reason.or_else(|| published_end_reason(&mut events))) {
+                    (Some(state), None) => state,
+                    (_, reason) => CallState::Ended {
+                        reason: reason.unwrap_or(CallEndReason::Disconnected),
+                    },
+                }
+            }
+            // the connection is gone, so is the call
+            Err(e) if matches!(e.kind(), ErrorKind::Closed | ErrorKind::TaskCancelled) => return,
+            Err(e) => {
+                log::warn!("Phone - listing the calls has failed: {e}");
+                continue;
+            }
+        };
+        if state == last {
+            continue;
+        }
+        // the receiver has been dropped
+        if states.send(state).await.is_err() {
+            return;
+        }
+        if matches!(state, CallState::Ended { .. }) {
+            return;
+        }
+        last = state;
+    }
+}
+
+/// The last final result code published since the call has been dialed.
+fn published_end_reason(events: &mut broadcast::Receiver<Event>) -> Option<CallEndReason> {
+    let mut reason: Option<CallEndReason> = None;
+    loop {
+        match events.try_recv() {
+            Ok(Event::CallEnded(ended)) => reason = Some(ended),
+            Ok(_) | Err(broadcast::error::TryRecvError::Lagged(_)) => (),
+            Err(_) => return reason,
+        }
+    }
+}
+
 fn end_call(serial_port: &Arc<SerialPort>, task_id: &Uuid, _: ()) -> ResolverReturn<()> {
     fn resolver(result: String) -> ResolverReturn<()> {
         generic_resolver(&result, Error::PhoneCallNotEnded)
     }
 
+    serial_port.require(Feature::VOICE)?;
     serial_port.process(task_id, "ATH\n".to_string(), resolver, None)
 }
 
+fn set_auto_answer(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    rings: Option<u8>,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::PhoneAutoAnswerNotSet)
+    }
+
+    serial_port.require(Feature::VOICE)?;
+    let command: String = format!("ATS0={}\n", rings.unwrap_or(0));
+    serial_port.process(task_id, command.clone(), resolver, None)?;
+    serial_port.persist_setting("ATS0", command);
+    Ok(())
+}
+
 fn get_incoming_call(
     serial_port: &Arc<SerialPort>,
     task_id: &Uuid,
@@ -63,9 +300,12 @@ fn get_incoming_call(
         })
     }
 
+    serial_port.require(Feature::VOICE)?;
     serial_port.read(task_id, resolver, Some(Duration::from_secs(4)))
 }
 
+/// The handle doesn't keep the connection open, see [`crate::SIM868`].
+#[derive(Clone)]
 pub struct Phone {
     serial_port: Arc<SerialPort>,
 }
@@ -77,6 +317,8 @@ impl Module for Phone {
 }
 
 impl Phone {
+    /// Calls the `number`. Fails with [`Error::PhoneCallEnded`] if the modem answers with the final result
+    /// code (eg. `BUSY` or `NO DIALTONE`) instead of `OK`.
     pub fn call(&self, number: &str) -> TaskJoinHandle<()> {
         spawn_task(
             self.serial_port.clone(),
@@ -87,6 +329,38 @@ impl Phone {
         )
     }
 
+    /// Calls the `number` like [`Phone::call`], returning the receiver of the call's [`CallState`]s, starting
+    /// with [`CallState::Dialing`] and closed after [`CallState::Ended`]. The call list is polled (`AT+CLCC`) in
+    /// the background until then, or until the receiver is dropped - which leaves the call going.
+    pub async fn call_with_events(
+        &self,
+        number: &str,
+    ) -> ResolverReturn<mpsc::Receiver<CallState>> {
+        // subscribed before dialling, so the result code received right after it isn't missed
+        let events: broadcast::Receiver<Event> = self.serial_port.events.subscribe();
+        let ended: Option<CallEndReason> = join_task(spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            dial,
+            Some(format!("Calling {number}...")),
+            number.to_string(),
+        ))
+        .await?;
+
+        let (sender, receiver) = mpsc::channel(CALL_STATES_CAPACITY);
+        match ended {
+            Some(reason) => {
+                // the receiver is still held here
+                let _ = sender.try_send(CallState::Ended { reason });
+            }
+            None => {
+                let _ = sender.try_send(CallState::Dialing);
+                tokio::spawn(follow_call(self.serial_port.clone(), sender, events));
+            }
+        }
+        Ok(receiver)
+    }
+
     pub fn end_call(&self) -> TaskJoinHandle<()> {
         spawn_task(
             self.serial_port.clone(),
@@ -97,6 +371,8 @@ impl Phone {
         )
     }
 
+    /// Answers the incoming call, failing with [`Error::PhoneCallEnded`] (`NO CARRIER`) if there is none
+    /// or the caller has hung up.
     pub fn answer(&self) -> TaskJoinHandle<()> {
         spawn_task(
             self.serial_port.clone(),
@@ -107,6 +383,21 @@ impl Phone {
         )
     }
 
+    /// Makes the modem answer the incoming calls by itself after the number of `rings` (`ATS0`), even if the
+    /// application is busy. `None` (or 0) disables it. The setting is restored by [`crate::hat::Hat::init`].
+    pub fn set_auto_answer(&self, rings: Option<u8>) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_auto_answer,
+            Some(match rings {
+                Some(rings) if rings > 0 => format!("Enabling auto-answer after {rings} rings..."),
+                _ => "Disabling auto-answer...".to_string(),
+            }),
+            rings,
+        )
+    }
+
     pub fn get_incoming_call(&self) -> TaskJoinHandle<IncomingCall> {
         spawn_task(
             self.serial_port.clone(),
@@ -117,3 +408,134 @@ impl Phone {
         )
     }
 }
+
+impl Phone {
+    /// [`Phone::call`] returning the result directly.
+    pub async fn call_now(&self, number: &str) -> ResolverReturn<()> {
+        join_task(self.call(number)).await
+    }
+
+    /// [`Phone::end_call`] returning the result directly.
+    pub async fn end_call_now(&self) -> ResolverReturn<()> {
+        join_task(self.end_call()).await
+    }
+
+    /// [`Phone::answer`] returning the result directly.
+    pub async fn answer_now(&self) -> ResolverReturn<()> {
+        join_task(self.answer()).await
+    }
+
+    /// [`Phone::set_auto_answer`] returning the result directly.
+    pub async fn set_auto_answer_now(&self, rings: Option<u8>) -> ResolverReturn<()> {
+        join_task(self.set_auto_answer(rings)).await
+    }
+
+    /// [`Phone::get_incoming_call`] returning the result directly.
+    pub async fn get_incoming_call_now(&self) -> ResolverReturn<IncomingCall> {
+        join_task(self.get_incoming_call()).await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Phone {
+    /// Blocking version of [`Phone::call`].
+    pub fn call_blocking(&self, number: &str) -> ResolverReturn<()> {
+        block_on_task(|| self.call(number))
+    }
+
+    /// Blocking version of [`Phone::call_with_events`], the states are received with
+    /// [`mpsc::Receiver::blocking_recv`].
+    pub fn call_with_events_blocking(
+        &self,
+        number: &str,
+    ) -> ResolverReturn<mpsc::Receiver<CallState>> {
+        block_on(self.call_with_events(number))
+    }
+
+    /// Blocking version of [`Phone::end_call`].
+    pub fn end_call_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.end_call())
+    }
+
+    /// Blocking version of [`Phone::answer`].
+    pub fn answer_blocking(&self) -> ResolverReturn<()> {
+        block_on_task(|| self.answer())
+    }
+
+    /// Blocking version of [`Phone::set_auto_answer`].
+    pub fn set_auto_answer_blocking(&self, rings: Option<u8>) -> ResolverReturn<()> {
+        block_on_task(|| self.set_auto_answer(rings))
+    }
+
+    /// Blocking version of [`Phone::get_incoming_call`].
+    pub fn get_incoming_call_blocking(&self) -> ResolverReturn<IncomingCall> {
+        block_on_task(|| self.get_incoming_call())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{MockResponse, MockTransport},
+        LogLevelFilter, SIM868,
+    };
+
+    fn calls(mock: &MockTransport) -> usize {
+        mock.written()
+            .iter()
+            .filter(|line: &&String| *line == "AT+CLCC")
+            .count()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn follows_call_until_result_code() {
+        let mock: MockTransport = MockTransport::new();
+        mock.set_default(MockResponse::ok());
+        for status in [2, 3] {
+            mock.once(
+                "AT+CLCC",
+                MockResponse::lines(&[
+                    &format!(r#"+CLCC: 1,0,{status},0,0,"+4799999999",145,"""#),
+                    "OK",
+                ]),
+            );
+        }
+        let sim: SIM868 = SIM868::with_transport(mock.clone(), LogLevelFilter::Off);
+
+        let mut states: mpsc::Receiver<CallState> =
+            sim.phone.call_with_events("+4799999999").await.unwrap();
+        assert_eq!(states.recv().await, Some(CallState::Dialing));
+        assert_eq!(states.recv().await, Some(CallState::Alerting));
+        // the result code received between the polls is picked up from the events
+        mock.unsolicited("BUSY", Duration::ZERO);
+        assert_eq!(
+            states.recv().await,
+            Some(CallState::Ended {
+                reason: CallEndReason::Busy
+            })
+        );
+        assert_eq!(states.recv().await, None);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn stops_following_dropped_call() {
+        let mock: MockTransport = MockTransport::new();
+        mock.set_default(MockResponse::ok());
+        mock.on(
+            "AT+CLCC",
+            MockResponse::lines(&[r#"+CLCC: 1,0,0,0,0,"+4799999999",145,"""#, "OK"]),
+        );
+        let sim: SIM868 = SIM868::with_transport(mock.clone(), LogLevelFilter::Off);
+
+        let mut states: mpsc::Receiver<CallState> =
+            sim.phone.call_with_events("+4799999999").await.unwrap();
+        assert_eq!(states.recv().await, Some(CallState::Dialing));
+        assert_eq!(states.recv().await, Some(CallState::Active));
+        drop(states);
+        sleep(CALL_POLL_INTERVAL * 2).await;
+        let polled: usize = calls(&mock);
+        sleep(CALL_POLL_INTERVAL * 4).await;
+        assert_eq!(calls(&mock), polled);
+    }
+}
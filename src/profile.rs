@@ -0,0 +1,73 @@
+//! Profile module
+//!
+//! The crate drives the SIMCom modems sharing the SIM868 command set. [`ModemProfile`] describes the
+//! differences between them; select it with [`crate::SIM868::with_profile`].
+
+use crate::{events::Event, hat::PowerDownMode};
+use std::time::Duration;
+
+const SIM800_BOOT_URCS: [Event; 3] = [Event::Ready, Event::CallReady, Event::SmsReady];
+const SIM7000_BOOT_URCS: [Event; 2] = [Event::Ready, Event::SmsReady];
+
+/// Supported modem.
+#[allow(clippy::upper_case_acronyms)]
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ModemProfile {
+    /// Waveshare SIM868 HAT - GSM/GPRS, GNSS and Bluetooth.
+    #[default]
+    SIM868,
+    /// GSM/GPRS, GNSS and Bluetooth (firmware R14 and newer).
+    SIM808,
+    /// GSM/GPRS only.
+    SIM800L,
+    /// LTE Cat-M/NB-IoT and GNSS, no voice calls.
+    SIM7000,
+}
+
+/// Optional feature of the modem, see [`ModemProfile::supports`].
+#[allow(clippy::upper_case_acronyms)]
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Feature {
+    BLUETOOTH,
+    GNSS,
+    VOICE,
+}
+
+impl ModemProfile {
+    pub fn supports(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::BLUETOOTH => matches!(self, ModemProfile::SIM868 | ModemProfile::SIM808),
+            Feature::GNSS => !matches!(self, ModemProfile::SIM800L),
+            Feature::VOICE => !matches!(self, ModemProfile::SIM7000),
+        }
+    }
+
+    /// Unsolicited result codes sent by the modem once it has booted, in order.
+    pub fn boot_urcs(&self) -> &'static [Event] {
+        match self {
+            ModemProfile::SIM7000 => &SIM7000_BOOT_URCS,
+            _ => &SIM800_BOOT_URCS,
+        }
+    }
+
+    /// How long the PWRKEY is held low to switch the modem on or off.
+    pub fn power_key_pulse(&self) -> Duration {
+        match self {
+            ModemProfile::SIM7000 => Duration::from_millis(1500),
+            _ => Duration::from_millis(4000),
+        }
+    }
+
+    /// Mode the modem powers down in when asked for the `mode`.
+    pub(crate) fn power_down_mode(&self, mode: PowerDownMode) -> PowerDownMode {
+        match self {
+            // urgent power down isn't available
+            ModemProfile::SIM7000 => PowerDownMode::Normal,
+            _ => mode,
+        }
+    }
+}
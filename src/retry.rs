@@ -0,0 +1,197 @@
+//! Retry module
+//!
+//! Some failures of the modem are transient - eg. the intermittent `ERROR` of `AT+SAPBR=1,1` or
+//! `+CME ERROR: 30` (no network service) while the modem is still registering. [`RetryPolicy`] repeats the
+//! failed AT command after a backoff instead of failing the task straight away. It's set for all the tasks with
+//! [`crate::SIM868Builder::retry_policy`] or [`crate::SIM868::set_retry_policy`], and for a single one with
+//! [`crate::TaskJoinHandle::with_retry_policy`]. By default the commands aren't repeated.
+//!
+//! The commands which can't be repeated safely - sending SMS (`AT+CMGS`), dialling (`ATD`), the HTTP request
+//! (`AT+HTTPACTION`) and the data sent over a connection (`AT+CIPSEND`, `AT+BTSPPSEND`) - are never retried,
+//! whatever the policy: the failed one may have taken effect anyway (eg. the SMS has been sent, but `+CMGS` is
+//! lost), so repeating it would send the message or dial the number again.
+//!
+//! ```no_run
+//! use rpi_sim868::{retry::RetryPolicy, SIM868};
+//! use std::time::Duration;
+//!
+//! # fn main() -> Result<(), rpi_sim868::Error> {
+//! let sim: SIM868 = SIM868::builder()
+//!     // up to 4 attempts, 500 ms, 1 s and 2 s apart
+//!     .retry_policy(RetryPolicy::new(4, Duration::from_millis(500)))
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::Error;
+use std::time::Duration;
+
+/// Commands which have an effect beyond the modem, never repeated, see the [module](self) documentation.
+const NON_IDEMPOTENT_COMMANDS: [&str; 5] = [
+    "AT+CMGS",
+    "ATD",
+    "AT+HTTPACTION",
+    "AT+CIPSEND",
+    "AT+BTSPPSEND",
+];
+
+/// Attempts, backoff and the errors of the AT command to repeat it on, see the [module](self) documentation.
+///
+/// `retry_on` covers errors like [`Error::SmsNotSent`] or [`Error::PhoneCallNotCalled`] too, but the commands
+/// sending SMS, dialling, making the HTTP request or sending data are never repeated - see
+/// [`RetryPolicy::retries`].
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Attempts of the command, the first one included. 1 - no retries.
+    pub max_attempts: u32,
+    /// Pause before the first retry, doubled before each of the next ones.
+    pub backoff: Duration,
+    /// Longest pause between the attempts.
+    pub max_backoff: Duration,
+    /// Whether the command failed with the error is repeated, [`Error::is_retryable`] by default.
+    pub retry_on: fn(&Error) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::NEVER
+    }
+}
+
+impl RetryPolicy {
+    /// The failed commands aren't repeated.
+    pub const NEVER: RetryPolicy = RetryPolicy {
+        max_attempts: 1,
+        backoff: Duration::ZERO,
+        max_backoff: Duration::ZERO,
+        retry_on: Error::is_retryable,
+    };
+
+    /// Up to `max_attempts` attempts of the command failed with a retryable error (see
+    /// [`Error::is_retryable`]), the first retry after `backoff`. The pause is doubled up to 30 s.
+    pub fn new(max_attempts: u32, backoff: Duration) -> Self {
+        RetryPolicy {
+            max_attempts,
+            backoff,
+            max_backoff: Duration::from_secs(30),
+            retry_on: Error::is_retryable,
+        }
+    }
+
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Repeats the commands failed with the errors `retry_on` returns `true` for, eg. only the `+CME ERROR`s:
+    /// `|e| e.kind() == ErrorKind::Cme`.
+    pub fn with_retry_on(mut self, retry_on: fn(&Error) -> bool) -> Self {
+        self.retry_on = retry_on;
+        self
+    }
+
+    /// Whether the failed `command` may be repeated at all, `false` for `AT+CMGS`, `ATD`, `AT+HTTPACTION`,
+    /// `AT+CIPSEND` and `AT+BTSPPSEND`.
+    pub fn retries(command: &str) -> bool {
+        !NON_IDEMPOTENT_COMMANDS
+            .iter()
+            .any(|prefix: &&str| command.starts_with(prefix))
+    }
+
+    /// Pause before repeating the command failed with the `error` at the given `attempt` (starting from 1),
+    /// `None` if it isn't repeated.
+    pub fn retry_delay(&self, attempt: u32, error: &Error) -> Option<Duration> {
+        if attempt >= self.max_attempts || !(self.retry_on)(error.root()) {
+            return None;
+        }
+        let factor: u32 = 2u32.saturating_pow(attempt.saturating_sub(1));
+        Some(self.backoff.saturating_mul(factor).min(self.max_backoff))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{CmeError, ErrorKind};
+
+    #[test]
+    fn doubles_backoff() {
+        let policy: RetryPolicy = RetryPolicy::new(4, Duration::from_millis(500));
+        let delays: Vec<Option<Duration>> = (1..=4)
+            .map(|attempt: u32| policy.retry_delay(attempt, &Error::GprsNoConnection))
+            .collect();
+        assert_eq!(
+            delays,
+            [
+                Some(Duration::from_millis(500)),
+                Some(Duration::from_secs(1)),
+                Some(Duration::from_secs(2)),
+                None
+            ]
+        );
+        assert_eq!(
+            RetryPolicy::NEVER.retry_delay(1, &Error::GprsNoConnection),
+            None
+        );
+    }
+
+    #[test]
+    fn caps_backoff() {
+        let policy: RetryPolicy = RetryPolicy::new(10, Duration::from_millis(500))
+            .with_max_backoff(Duration::from_millis(1500));
+        assert_eq!(
+            policy.retry_delay(2, &Error::NotResolved),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            policy.retry_delay(3, &Error::NotResolved),
+            Some(Duration::from_millis(1500))
+        );
+        // the factor saturates instead of overflowing
+        let policy: RetryPolicy = RetryPolicy::new(u32::MAX, Duration::from_secs(1));
+        assert_eq!(
+            policy.retry_delay(100, &Error::NotResolved),
+            Some(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn retries_matching_errors() {
+        let policy: RetryPolicy = RetryPolicy::new(3, Duration::from_secs(1));
+        let wrapped: Error = Error::Context {
+            command: "AT+SAPBR=1,1".to_string(),
+            task_id: uuid::Uuid::nil(),
+            elapsed: Duration::from_millis(10),
+            error: Box::new(Error::Cme(CmeError::NoNetworkService)),
+        };
+        assert_eq!(
+            policy.retry_delay(1, &wrapped),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(policy.retry_delay(1, &Error::HatSimPinRejected), None);
+
+        let policy: RetryPolicy = policy.with_retry_on(|e: &Error| e.kind() == ErrorKind::Cme);
+        assert_eq!(
+            policy.retry_delay(1, &wrapped),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(policy.retry_delay(1, &Error::GprsNoConnection), None);
+    }
+
+    #[test]
+    fn skips_non_idempotent_commands() {
+        for command in [
+            "AT+CMGS=\"+4799999999\"",
+            "ATD+4799999999;",
+            "AT+HTTPACTION=1",
+            "AT+CIPSEND=5",
+            "AT+BTSPPSEND=5",
+        ] {
+            assert!(!RetryPolicy::retries(command), "{command}");
+        }
+        for command in ["AT+SAPBR=1,1", "ATA", "AT+CMGR=1", "AT+HTTPREAD"] {
+            assert!(RetryPolicy::retries(command), "{command}");
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Scheduler module
+//!
+//! Every method call queues a task waiting for the serial port (see [`TaskPriority`]). [`Scheduler`], returned
+//! by [`crate::SIM868::scheduler`], shows what's waiting and how long the tasks take - eg. to find out why the
+//! HTTP uploads of a long-running tracker starve its GNSS polling.
+//!
+//! ```no_run
+//! use rpi_sim868::{scheduler::SchedulerStats, SIM868};
+//!
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! # fn main() {
+//! let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//! let stats: SchedulerStats = sim.scheduler().stats();
+//! println!("{} queued, waiting {:?} on average", stats.queue_depth, stats.average_wait);
+//! for task in stats.pending {
+//!     println!("{} {:?} - {:?}, queued for {:?}", task.id, task.priority, task.description, task.elapsed);
+//! }
+//! # }
+//! ```
+
+use crate::serial_port::{SerialPort, TaskPriority};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+use uuid::Uuid;
+
+/// Queued or running task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskInfo {
+    pub id: Uuid,
+    /// Set with [`crate::TaskJoinHandle::with_name`].
+    pub name: Option<String>,
+    /// Log message of the task, eg. `Sending SMS...`.
+    pub description: Option<String>,
+    /// Current priority, raised by the aging.
+    pub priority: TaskPriority,
+    /// Time the task has been queued for, or running for.
+    pub elapsed: Duration,
+}
+
+/// Task which has finished, successfully or not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinishedTask {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Priority the task has started with.
+    pub priority: TaskPriority,
+    /// Time spent in the queue.
+    pub waited: Duration,
+    /// Execution time.
+    pub ran: Duration,
+    pub succeeded: bool,
+}
+
+/// Snapshot of the task queue, see [`Scheduler::stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchedulerStats {
+    /// Tasks waiting for the serial port.
+    pub queue_depth: usize,
+    /// Queued tasks by their current priority.
+    pub queued_by_priority: BTreeMap<TaskPriority, usize>,
+    /// Task using the serial port.
+    pub running: Option<TaskInfo>,
+    /// Queued tasks, the first to run first.
+    pub pending: Vec<TaskInfo>,
+    /// Tasks started since the connection has been opened.
+    pub started: u64,
+    /// Average time the started tasks have waited in the queue.
+    pub average_wait: Option<Duration>,
+    /// Up to 32 recently finished tasks, the oldest first.
+    pub finished: Vec<FinishedTask>,
+}
+
+/// Task queue of the serial port.
+#[derive(Clone)]
+pub struct Scheduler {
+    serial_port: Arc<SerialPort>,
+}
+
+impl Scheduler {
+    pub(crate) fn new(serial_port: Arc<SerialPort>) -> Self {
+        Scheduler { serial_port }
+    }
+
+    pub fn stats(&self) -> SchedulerStats {
+        self.serial_port.scheduler_stats()
+    }
+
+    /// Queued tasks, the first to run first.
+    pub fn pending(&self) -> Vec<TaskInfo> {
+        self.serial_port.pending_tasks()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        at::ResponsePattern,
+        testing::{MockResponse, MockTransport},
+        TaskJoinHandle, SIM868,
+    };
+    use std::time::Instant;
+
+    /// Connection whose `AT+BLOCK` holds the serial port for `blocked`.
+    fn connect(blocked: Duration, aging: Option<Duration>) -> (SIM868, MockTransport) {
+        let mock: MockTransport = MockTransport::new();
+        mock.set_default(MockResponse::ok());
+        mock.on("AT+BLOCK", MockResponse::ok().delayed(blocked));
+        let sim: SIM868 = SIM868::builder()
+            .transport(mock.clone())
+            .priority_aging(aging)
+            .build()
+            .unwrap();
+        (sim, mock)
+    }
+
+    async fn wait_until(condition: impl Fn() -> bool) {
+        let start: Instant = Instant::now();
+        while !condition() {
+            assert!(start.elapsed() < Duration::from_secs(2), "timed out");
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    /// Queues the `command`, waiting until it's in the queue so the tasks are queued in order.
+    async fn queue(sim: &SIM868, command: &str, priority: TaskPriority) -> TaskJoinHandle<String> {
+        let queued: usize = sim.scheduler().pending().len();
+        let task: TaskJoinHandle<String> = sim
+            .at
+            .send(command, ResponsePattern::Ok, None)
+            .with_priority(priority);
+        wait_until(|| sim.scheduler().pending().len() > queued).await;
+        task
+    }
+
+    fn priorities(sim: &SIM868) -> Vec<TaskPriority> {
+        sim.scheduler()
+            .pending()
+            .iter()
+            .map(|task: &TaskInfo| task.priority)
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn runs_by_priority_then_in_order() {
+        let (sim, mock) = connect(Duration::from_millis(200), None);
+        let blocker = sim.at.send("AT+BLOCK", ResponsePattern::Ok, None);
+        wait_until(|| sim.scheduler().stats().running.is_some()).await;
+
+        let tasks: Vec<TaskJoinHandle<String>> = vec![
+            queue(&sim, "AT+LOW", TaskPriority::LOW).await,
+            queue(&sim, "AT+NORMAL1", TaskPriority::NORMAL).await,
+            queue(&sim, "AT+HIGH", TaskPriority::HIGH).await,
+            queue(&sim, "AT+NORMAL2", TaskPriority::NORMAL).await,
+            queue(&sim, "AT+CRITICAL", TaskPriority::CRITICAL).await,
+        ];
+        assert_eq!(
+            priorities(&sim),
+            [
+                TaskPriority::CRITICAL,
+                TaskPriority::HIGH,
+                TaskPriority::NORMAL,
+                TaskPriority::NORMAL,
+                TaskPriority::LOW
+            ]
+        );
+
+        blocker.await.unwrap().unwrap();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        assert_eq!(
+            mock.written(),
+            [
+                "AT+BLOCK",
+                "AT+CRITICAL",
+                "AT+HIGH",
+                "AT+NORMAL1",
+                "AT+NORMAL2",
+                "AT+LOW"
+            ]
+        );
+        let stats: SchedulerStats = sim.scheduler().stats();
+        assert_eq!((stats.queue_depth, stats.started), (0, 6));
+        assert!(stats
+            .finished
+            .iter()
+            .all(|task: &FinishedTask| task.succeeded));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn raises_waiting_task_priority() {
+        let (sim, mock) = connect(Duration::from_millis(600), Some(Duration::from_millis(100)));
+        let blocker = sim.at.send("AT+BLOCK", ResponsePattern::Ok, None);
+        wait_until(|| sim.scheduler().stats().running.is_some()).await;
+
+        let low = queue(&sim, "AT+LOW", TaskPriority::LOW).await;
+        // LOW to NORMAL, then to HIGH, which is as far as the aging goes
+        wait_until(|| priorities(&sim) == [TaskPriority::HIGH]).await;
+        let high = queue(&sim, "AT+HIGH", TaskPriority::HIGH).await;
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(priorities(&sim), [TaskPriority::HIGH, TaskPriority::HIGH]);
+        assert_eq!(sim.scheduler().pending()[0].id, low.id());
+
+        blocker.await.unwrap().unwrap();
+        low.await.unwrap().unwrap();
+        high.await.unwrap().unwrap();
+        assert_eq!(mock.written(), ["AT+BLOCK", "AT+LOW", "AT+HIGH"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn keeps_priority_without_aging() {
+        let (sim, mock) = connect(Duration::from_millis(300), None);
+        let blocker = sim.at.send("AT+BLOCK", ResponsePattern::Ok, None);
+        wait_until(|| sim.scheduler().stats().running.is_some()).await;
+
+        let low = queue(&sim, "AT+LOW", TaskPriority::LOW).await;
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let normal = queue(&sim, "AT+NORMAL", TaskPriority::NORMAL).await;
+        assert_eq!(priorities(&sim), [TaskPriority::NORMAL, TaskPriority::LOW]);
+
+        blocker.await.unwrap().unwrap();
+        low.await.unwrap().unwrap();
+        normal.await.unwrap().unwrap();
+        assert_eq!(mock.written(), ["AT+BLOCK", "AT+NORMAL", "AT+LOW"]);
+    }
+}
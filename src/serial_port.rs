@@ -1,111 +1,556 @@
 use crate::{
+    capabilities::Capabilities,
+    device_error_check,
     error::{Error, ErrorKind},
-    ResolverReturn, TaskJoinHandle,
+    events::{Event, EVENTS_CAPACITY},
+    framing::FramedTransport,
+    logging::task_label,
+    profile::ModemProfile,
+    retry::RetryPolicy,
+    scheduler::{FinishedTask, SchedulerStats, TaskInfo},
+    state::ModemState,
+    timeouts::CommandTimeouts,
+    transport::{Queue, Transport},
+    ResolverReturn, TaskJoinHandle, ACK_REGEX,
 };
-use colored::Colorize;
+#[cfg(feature = "gnss")]
+use crate::{events::NMEA_CAPACITY, gnss::FixCache};
 use priority_queue::PriorityQueue;
-use rppal::uart::{Parity, Uart};
 use std::{
-    sync::{Arc, Mutex},
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex,
+    },
     time::{Duration, Instant},
 };
-use tokio::{spawn, sync::RwLock, time::sleep};
+use tokio::{
+    spawn,
+    sync::{broadcast, Notify},
+    time::timeout,
+};
 use uuid::Uuid;
 
 const MUTEX_POISONED_MSG: &str = "Critical error: Mutex is poisoned.";
+/// Time the task waits in the queue before its priority is raised, unless set by
+/// [`crate::SIM868Builder::priority_aging`].
+pub const DEFAULT_PRIORITY_AGING: Duration = Duration::from_secs(10);
+/// Pause of the URC reader while the UART is used outside of a task, eg. by [`SerialPort::set_baud_rate`].
+const URC_READER_PAUSE: Duration = Duration::from_millis(100);
+/// Time without any data written after which the modem in the sleep mode (`AT+CSCLK=2`) may be asleep - it
+/// falls asleep after 5 s.
+const SLEEP_IDLE: Duration = Duration::from_secs(4);
+/// Time the woken up modem has to answer the `AT`.
+const WAKE_UP_TIMEOUT: Duration = Duration::from_millis(200);
 
 pub struct SerialPort {
-    uart: Arc<Mutex<Uart>>,
-    queue: Arc<RwLock<PriorityQueue<Uuid, TaskPriority>>>,
+    uart: Mutex<FramedTransport>,
+    scheduler: Mutex<SchedulerState>,
+    /// Notified whenever the serial port is freed, or a queued task leaves the queue or is cancelled.
+    queue_changed: Notify,
+    /// Notified with the scheduler lock whenever the serial port is freed or a task leaves the queue, and once
+    /// the port is closed - wakes the URC reader up.
+    port_freed: Condvar,
+    /// See [`SerialPort::close`].
+    closed: AtomicBool,
+    /// Order of the queued tasks of the same priority.
+    sequence: AtomicU64,
+    /// See [`SerialPort::set_priority_aging`].
+    priority_aging: Mutex<Option<Duration>>,
+    /// See [`SerialPort::set_retry_policy`].
+    retry_policy: Mutex<RetryPolicy>,
+    pub events: broadcast::Sender<Event>,
+    /// NMEA sentences sent by the modem while its NMEA output is on, see [`crate::gnss::GNSS::nmea_stream`].
+    #[cfg(feature = "gnss")]
+    pub nmea: broadcast::Sender<String>,
+    /// Last fix of the duty cycle, see [`crate::gnss::GNSS::duty_cycle`].
+    #[cfg(feature = "gnss")]
+    pub(crate) fix_cache: Arc<Mutex<FixCache>>,
+    pub profile: ModemProfile,
+    pub timeouts: CommandTimeouts,
+    /// When the connection has been opened.
+    pub opened_at: Instant,
+    /// Commands restoring the settings changed at runtime, by setting, see [`SerialPort::persist_setting`].
+    settings: Mutex<BTreeMap<&'static str, String>>,
+    /// Detected by [`crate::hat::Hat::init`].
+    capabilities: Arc<Mutex<Option<Capabilities>>>,
+    /// Modes set by the tasks, shared with the other channels.
+    state: Arc<Mutex<ModemState>>,
+    /// Ports of the other channels of the multiplexed connection, see [`SerialPort::channel`].
+    channels: Mutex<Vec<Arc<SerialPort>>>,
+    /// See [`SerialPort::set_sleep_mode`].
+    sleep_mode: AtomicBool,
+    #[cfg(feature = "metrics")]
+    pub counters: TaskCounters,
+}
+
+/// Numbers of finished tasks, exposed by the [metrics exporter](crate::metrics).
+#[cfg(feature = "metrics")]
+#[derive(Default)]
+pub struct TaskCounters {
+    pub finished: std::sync::atomic::AtomicU64,
+    pub failed: std::sync::atomic::AtomicU64,
 }
 
-#[derive(PartialEq, PartialOrd, Ord, Eq, Debug)]
+/// Priority of the task in the queue, the tasks of the same priority run in the order they were queued.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(PartialEq, PartialOrd, Ord, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TaskPriority {
+    /// Background work, eg. the periodic metrics.
+    LOW,
     NORMAL,
+    /// Time-sensitive tasks, eg. the call handling.
     HIGH,
+    /// Tasks going before everything else, eg. [`crate::hat::Hat::turn_off`]. Not reached by the aging.
+    CRITICAL,
+}
+
+impl TaskPriority {
+    /// Priority the task waiting for too long is raised to, `None` if it's not raised any further.
+    fn promoted(self) -> Option<TaskPriority> {
+        match self {
+            TaskPriority::LOW => Some(TaskPriority::NORMAL),
+            TaskPriority::NORMAL => Some(TaskPriority::HIGH),
+            TaskPriority::HIGH | TaskPriority::CRITICAL => None,
+        }
+    }
+}
+
+/// Queued tasks run by priority, then in the order they were queued.
+#[derive(PartialEq, PartialOrd, Ord, Eq, Debug, Clone, Copy)]
+struct QueuedPriority {
+    priority: TaskPriority,
+    sequence: Reverse<u64>,
+}
+
+#[cfg(feature = "metrics")]
+impl TaskCounters {
+    fn count<T>(&self, result: &Result<T, Error>) {
+        use std::sync::atomic::Ordering;
+
+        self.finished.fetch_add(1, Ordering::Relaxed);
+        if result.is_err() {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
 }
 
 fn debug_log(task_id: &Uuid, msg: &str) {
-    log::debug!("{} - {msg}", format!("[{task_id}]").yellow())
+    log::debug!("{} - {msg}", task_label(task_id))
 }
 
 fn info_log(task_id: &Uuid, msg: &str) {
-    log::info!("{} - {msg}", format!("[{task_id}]").yellow())
+    log::info!("{} - {msg}", task_label(task_id))
 }
 
-async fn add_to_queue(serial_port: &Arc<SerialPort>, priority: TaskPriority) -> Uuid {
-    let task_id: Uuid = Uuid::new_v4();
+/// Tasks waiting for the serial port, and the control of the one using it.
+#[derive(Default)]
+struct SchedulerState {
+    queue: PriorityQueue<Uuid, QueuedPriority>,
+    /// Controls of the queued tasks, listed by [`SerialPort::pending_tasks`].
+    queued: HashMap<Uuid, Arc<TaskControl>>,
+    running: Option<Arc<TaskControl>>,
+    /// Wait and execution times of the finished tasks.
+    history: TaskHistory,
+}
+
+impl SchedulerState {
+    /// Whether no task is queued or running.
+    fn is_idle(&self) -> bool {
+        self.queue.is_empty() && self.running.is_none()
+    }
+
+    /// Removes the task which has left the queue, returning whether it was queued.
+    fn remove_queued(&mut self, task_id: &Uuid) -> bool {
+        self.queued.remove(task_id);
+        self.queue.remove(task_id).is_some()
+    }
+}
+
+/// Number of the finished tasks kept for [`SchedulerStats::finished`].
+const TASK_HISTORY_SIZE: usize = 32;
+
+#[derive(Default)]
+struct TaskHistory {
+    started: u64,
+    total_wait: Duration,
+    finished: VecDeque<FinishedTask>,
+}
+
+impl TaskHistory {
+    fn started(&mut self, waited: Duration) {
+        self.started += 1;
+        self.total_wait += waited;
+    }
+
+    fn finished(&mut self, task: FinishedTask) {
+        if self.finished.len() == TASK_HISTORY_SIZE {
+            self.finished.pop_front();
+        }
+        self.finished.push_back(task);
+    }
+}
+
+/// Changes of the task requested through its [`TaskJoinHandle`] - cancellation, deadline, the priority and the
+/// retry policy.
+pub(crate) struct TaskControl {
+    id: Uuid,
+    /// log message of the task, eg. `Sending SMS...`
+    description: Option<String>,
+    /// name set with `TaskJoinHandle::with_name`
+    name: Mutex<Option<String>>,
+    created_at: Instant,
+    /// the time the task has started at, with its priority then
+    started: Mutex<Option<(Instant, TaskPriority)>>,
+    cancelled: AtomicBool,
+    /// the time the task fails at, and the deadline it has been given
+    deadline: Mutex<Option<(Instant, Duration)>>,
+    /// priority set with `TaskJoinHandle::with_priority`, replacing the default one of the task
+    priority: Mutex<Option<TaskPriority>>,
+    /// policy set with `TaskJoinHandle::with_retry_policy`, replacing the one of the serial port
+    retry_policy: Mutex<Option<RetryPolicy>>,
+    serial_port: Arc<SerialPort>,
+}
+
+impl TaskControl {
+    fn new(id: Uuid, description: Option<String>, serial_port: Arc<SerialPort>) -> Self {
+        TaskControl {
+            id,
+            description,
+            name: Mutex::new(None),
+            created_at: Instant::now(),
+            started: Mutex::new(None),
+            cancelled: AtomicBool::new(false),
+            deadline: Mutex::new(None),
+            priority: Mutex::new(None),
+            retry_policy: Mutex::new(None),
+            serial_port,
+        }
+    }
+
+    /// Replaces the priority of the task, whether it's been queued already or not.
+    pub(crate) fn set_priority(&self, task_id: &Uuid, priority: TaskPriority) {
+        // the scheduler lock keeps it from racing with add_to_queue
+        let mut scheduler = self.serial_port.scheduler();
+        *self.priority.lock().expect(MUTEX_POISONED_MSG) = Some(priority);
+        let queued: Option<QueuedPriority> = scheduler.queue.get_priority(task_id).copied();
+        if let Some(queued) = queued {
+            debug_log(task_id, &format!("priority changed to {priority:?}."));
+            scheduler
+                .queue
+                .change_priority(task_id, QueuedPriority { priority, ..queued });
+        }
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        // wakes the task if it's waiting in the queue
+        self.serial_port.queue_changed.notify_waiters();
+    }
+
+    pub(crate) fn set_name(&self, name: &str) {
+        *self.name.lock().expect(MUTEX_POISONED_MSG) = Some(name.to_string());
+    }
+
+    fn info(&self, priority: TaskPriority, since: Instant) -> TaskInfo {
+        TaskInfo {
+            id: self.id,
+            name: self.name.lock().expect(MUTEX_POISONED_MSG).clone(),
+            description: self.description.clone(),
+            priority,
+            elapsed: since.elapsed(),
+        }
+    }
+
+    pub(crate) fn set_retry_policy(&self, policy: RetryPolicy) {
+        *self.retry_policy.lock().expect(MUTEX_POISONED_MSG) = Some(policy);
+    }
+
+    pub(crate) fn set_deadline(&self, deadline: Duration) {
+        *self.deadline.lock().expect(MUTEX_POISONED_MSG) =
+            Some((Instant::now() + deadline, deadline));
+        self.serial_port.queue_changed.notify_waiters();
+    }
+
+    /// Fails once the task has been cancelled or has exceeded its deadline.
+    fn check(&self) -> ResolverReturn<()> {
+        if self.cancelled.load(Ordering::SeqCst) {
+            return Err(Error::TaskCancelled);
+        }
+        match *self.deadline.lock().expect(MUTEX_POISONED_MSG) {
+            Some((at, deadline)) if Instant::now() >= at => Err(Error::TaskTimeout(deadline)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Time left before the deadline.
+    fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .lock()
+            .expect(MUTEX_POISONED_MSG)
+            .map(|(at, _)| at.saturating_duration_since(Instant::now()))
+    }
+}
+
+/// Removes the task from the queue when it stops waiting - it has started, been cancelled, or its future has
+/// been dropped (eg. by [`TaskJoinHandle::abort`]).
+struct QueuedTask<'a> {
+    task_id: &'a Uuid,
+    serial_port: &'a SerialPort,
+}
+
+impl Drop for QueuedTask<'_> {
+    fn drop(&mut self) {
+        let removed: bool = self.serial_port.scheduler().remove_queued(self.task_id);
+        // the next task may be the first one now
+        if removed {
+            self.serial_port.queue_changed.notify_waiters();
+            self.serial_port.port_freed.notify_all();
+        }
+    }
+}
+
+/// Frees the serial port when the task has finished (or its future has been dropped), waking the queued tasks.
+struct RunningTask<'a> {
+    task_id: &'a Uuid,
+    serial_port: &'a SerialPort,
+    control: Arc<TaskControl>,
+    priority: TaskPriority,
+    started_at: Instant,
+}
+
+impl RunningTask<'_> {
+    /// Records the times of the finished task and frees the serial port.
+    fn finish(self, succeeded: bool) {
+        self.serial_port.scheduler().history.finished(FinishedTask {
+            id: *self.task_id,
+            name: self.control.name.lock().expect(MUTEX_POISONED_MSG).clone(),
+            description: self.control.description.clone(),
+            priority: self.priority,
+            waited: self.started_at - self.control.created_at,
+            ran: self.started_at.elapsed(),
+            succeeded,
+        });
+    }
+}
+
+impl Drop for RunningTask<'_> {
+    fn drop(&mut self) {
+        self.serial_port.scheduler().running = None;
+        debug_log(self.task_id, "removed from the queue.");
+        self.serial_port.queue_changed.notify_waiters();
+        self.serial_port.port_freed.notify_all();
+    }
+}
+
+fn add_to_queue(
+    task_id: Uuid,
+    serial_port: &SerialPort,
+    control: &Arc<TaskControl>,
+    priority: TaskPriority,
+) {
+    let mut scheduler = serial_port.scheduler();
+    let priority: TaskPriority = control
+        .priority
+        .lock()
+        .expect(MUTEX_POISONED_MSG)
+        .unwrap_or(priority);
     debug_log(&task_id, &format!("created with {priority:?} priority."));
-    serial_port.queue.write().await.push(task_id, priority);
-    task_id
+    let sequence: u64 = serial_port.sequence.fetch_add(1, Ordering::Relaxed);
+    scheduler.queue.push(
+        task_id,
+        QueuedPriority {
+            priority,
+            sequence: Reverse(sequence),
+        },
+    );
+    scheduler.queued.insert(task_id, control.clone());
+}
+
+/// Takes the serial port if it's free and the task is the first in the queue.
+fn try_start<'a>(
+    task_id: &'a Uuid,
+    serial_port: &'a SerialPort,
+    control: &Arc<TaskControl>,
+) -> Option<RunningTask<'a>> {
+    let mut scheduler = serial_port.scheduler();
+    let first: bool = matches!(scheduler.queue.peek(), Some((next, _)) if next == task_id);
+    if scheduler.running.is_some() || !first {
+        return None;
+    }
+    let (_, queued) = scheduler.queue.remove(task_id)?;
+    scheduler.queued.remove(task_id);
+    scheduler.running = Some(control.clone());
+    let started_at: Instant = Instant::now();
+    *control.started.lock().expect(MUTEX_POISONED_MSG) = Some((started_at, queued.priority));
+    scheduler.history.started(started_at - control.created_at);
+    Some(RunningTask {
+        task_id,
+        serial_port,
+        control: control.clone(),
+        priority: queued.priority,
+        started_at,
+    })
 }
 
-async fn await_in_queue(task_id: &Uuid, serial_port: &Arc<SerialPort>) {
+/// Raises the priority of the queued task by one level.
+fn promote(task_id: &Uuid, serial_port: &SerialPort) {
+    let mut scheduler = serial_port.scheduler();
+    let promoted: Option<QueuedPriority> =
+        scheduler
+            .queue
+            .get_priority(task_id)
+            .and_then(|queued: &QueuedPriority| {
+                queued
+                    .priority
+                    .promoted()
+                    .map(|priority: TaskPriority| QueuedPriority {
+                        priority,
+                        ..*queued
+                    })
+            });
+    if let Some(promoted) = promoted {
+        debug_log(
+            task_id,
+            &format!("raised to {:?} priority.", promoted.priority),
+        );
+        scheduler.queue.change_priority(task_id, promoted);
+    }
+}
+
+/// Waits until the serial port is free and the task is the first in the queue, woken whenever the queue
+/// changes. Its priority is raised every [`SerialPort::set_priority_aging`] period, so a flood of higher
+/// priority tasks can't starve it.
+async fn await_in_queue<'a>(
+    task_id: &'a Uuid,
+    serial_port: &'a SerialPort,
+    control: &Arc<TaskControl>,
+) -> ResolverReturn<RunningTask<'a>> {
+    let _queued: QueuedTask<'_> = QueuedTask {
+        task_id,
+        serial_port,
+    };
+    let aging: Option<Duration> = *serial_port.priority_aging.lock().expect(MUTEX_POISONED_MSG);
+    let mut waiting_since: Instant = Instant::now();
     loop {
-        let queue: tokio::sync::RwLockReadGuard<'_, PriorityQueue<Uuid, TaskPriority>> =
-            serial_port.queue.read().await;
-        let (next, _) = queue
-            .peek()
-            .expect("Critical error: task queue is corrupted.");
-        if *next == *task_id {
-            break;
+        let finished = serial_port.queue_changed.notified();
+        tokio::pin!(finished);
+        // registered before checking the queue, so the change in between isn't missed
+        finished.as_mut().enable();
+        if let Err(e) = control.check() {
+            debug_log(task_id, &format!("left the queue: {e}"));
+            return Err(e);
+        }
+        if let Some(running) = try_start(task_id, serial_port, control) {
+            return Ok(running);
         }
 
-        drop(queue);
-        sleep(Duration::from_millis(100)).await;
+        let aging_remaining: Option<Duration> =
+            aging.map(|aging: Duration| aging.saturating_sub(waiting_since.elapsed()));
+        match [aging_remaining, control.remaining()]
+            .into_iter()
+            .flatten()
+            .min()
+        {
+            Some(wait) => {
+                let timed_out: bool = timeout(wait, finished).await.is_err();
+                if timed_out && matches!(aging, Some(aging) if waiting_since.elapsed() >= aging) {
+                    promote(task_id, serial_port);
+                    waiting_since = Instant::now();
+                }
+            }
+            None => finished.await,
+        }
     }
 }
 
-async fn remove_from_queue(task_id: &Uuid, serial_port: &Arc<SerialPort>) {
-    serial_port.queue.write().await.remove(&task_id);
-    debug_log(task_id, "removed from the queue.");
+/// Command of the [`CommandBatch`] with the resolver of its response.
+struct BatchStep<T> {
+    command: String,
+    resolver: Box<dyn Fn(String) -> ResolverReturn<T> + Send>,
+    timeout: Option<Duration>,
+}
+
+/// Commands run one after another by [`SerialPort::process_batch`], without releasing the port in between.
+pub(crate) struct CommandBatch<T> {
+    steps: Vec<BatchStep<T>>,
+}
+
+impl<T> CommandBatch<T> {
+    pub(crate) fn new() -> Self {
+        CommandBatch { steps: Vec::new() }
+    }
+
+    /// Appends the `command` (with the trailing newline), resolved like with [`SerialPort::process`].
+    pub(crate) fn step<R>(mut self, command: String, resolver: R, timeout: Option<Duration>) -> Self
+    where
+        R: Fn(String) -> ResolverReturn<T> + Send + 'static,
+    {
+        self.steps.push(BatchStep {
+            command,
+            resolver: Box::new(resolver),
+            timeout,
+        });
+        self
+    }
 }
 
 fn uart_read<T>(
     task_id: &Uuid,
-    uart: &mut std::sync::MutexGuard<'_, Uart>,
+    uart: &mut std::sync::MutexGuard<'_, FramedTransport>,
+    serial_port: &SerialPort,
     timeout: Duration,
-    resolver: fn(String) -> ResolverReturn<T>,
+    resolver: &dyn Fn(String) -> ResolverReturn<T>,
 ) -> ResolverReturn<T> {
     let mut data: Option<T> = None;
     let mut error: Option<Error> = None;
+    // complete lines received so far, handed to the resolver whenever more arrive
+    let mut response: String = String::new();
     let start: Instant = Instant::now();
 
-    while start.elapsed() <= timeout {
-        let mut read_vec: Vec<u8> = Vec::new();
-        let mut read_buffer: [u8; 1] = [0];
-
-        while uart.read(&mut read_buffer)? > 0 {
-            read_vec.push(read_buffer[0]);
-        }
-
-        if !read_vec.is_empty() {
-            debug_log(task_id, &format!("read vector: {read_vec:?}"));
-        }
+    loop {
+        let timed_out: bool = start.elapsed() > timeout;
+        let lines: Option<String> = match timed_out {
+            // the last chance for the response missing the final line break
+            true => uart.take_rest(),
+            false => uart.read_lines()?,
+        };
 
-        let read: String = String::from_utf8(read_vec).unwrap_or("".to_string());
-        if !read.is_empty() {
-            debug_log(task_id, &format!("parsed string: {read}"));
-        }
+        if let Some(lines) = lines {
+            debug_log(task_id, &format!("parsed string: {lines}"));
+            let filtered: String = uart.urc_filter.filter(&lines, serial_port);
+            response.push_str(&filtered);
 
-        match resolver(read) {
-            Ok(d) => {
-                debug_log(task_id, "resolved.");
-                data = Some(d);
+            if let Some(err) = device_error_check(&response) {
+                error = Some(err);
                 break;
             }
-            Err(e) => match e.kind() {
-                ErrorKind::NotResolved => (),
-                _ => {
-                    error = Some(e);
+
+            match resolver(response.clone()) {
+                Ok(d) => {
+                    debug_log(task_id, "resolved.");
+                    data = Some(d);
                     break;
                 }
-            },
+                Err(e) => match e.kind() {
+                    ErrorKind::NotResolved => (),
+                    _ => {
+                        error = Some(e);
+                        break;
+                    }
+                },
+            }
+        }
+
+        if timed_out {
+            break;
         }
     }
 
     if let Some(err) = error {
-        log::error!("{} - error: {err:?}", format!("[{task_id}]").yellow());
+        log::error!("{} - error: {err:?}", task_label(task_id));
         return Err(err);
     }
 
@@ -126,36 +571,438 @@ where
     T1: 'static + Send,
     T2: 'static + Send,
 {
-    spawn(async move {
-        let task_id: Uuid = add_to_queue(&serial_port, priority).await;
+    let task_id: Uuid = Uuid::new_v4();
+    let control: Arc<TaskControl> = Arc::new(TaskControl::new(
+        task_id,
+        log_msg.clone(),
+        serial_port.clone(),
+    ));
+    let task_control: Arc<TaskControl> = control.clone();
+    let handle: tokio::task::JoinHandle<Result<T1, Error>> = spawn(async move {
+        add_to_queue(task_id, &serial_port, &task_control, priority);
         if let Some(msg) = log_msg {
             info_log(&task_id, &msg);
         }
-        await_in_queue(&task_id, &serial_port).await;
-        let result: Result<T1, Error> = task_fn(&serial_port, &task_id, arguments);
-        remove_from_queue(&task_id, &serial_port).await;
+        let result: Result<T1, Error> =
+            match await_in_queue(&task_id, &serial_port, &task_control).await {
+                Ok(running) => {
+                    let result: Result<T1, Error> = task_fn(&serial_port, &task_id, arguments);
+                    running.finish(result.is_ok());
+                    result
+                }
+                Err(e) => Err(e),
+            };
+        #[cfg(feature = "metrics")]
+        serial_port.counters.count(&result);
         result
-    })
+    });
+    TaskJoinHandle::new(task_id, handle, control)
+}
+
+/// Starts the thread reading the serial port while no task is running, so the URCs which arrive between the
+/// tasks (eg. `RING` or `+CMTI`) are published as [`Event`]s instead of being flushed by the next command.
+/// The rest of the data read is discarded. Runs until the port has been closed.
+///
+/// The reader sleeps while the port is used by the tasks, and is paced by the read of the transport, waiting
+/// shortly for the data (see [`Transport::read`]) - so it doesn't poll the idle port.
+pub(crate) fn spawn_urc_reader(serial_port: Arc<SerialPort>) {
+    let reader = move || loop {
+        let scheduler: std::sync::MutexGuard<'_, SchedulerState> = serial_port
+            .port_freed
+            .wait_while(serial_port.scheduler(), |scheduler: &mut SchedulerState| {
+                !scheduler.is_idle() && !serial_port.closed.load(Ordering::SeqCst)
+            })
+            .expect(MUTEX_POISONED_MSG);
+        if serial_port.closed.load(Ordering::SeqCst) {
+            return;
+        }
+        // taken before the scheduler is released, so no task starts in between - a task started meanwhile
+        // waits for the read to finish
+        let uart: Option<std::sync::MutexGuard<'_, FramedTransport>> =
+            serial_port.uart.try_lock().ok();
+        drop(scheduler);
+        let Some(mut uart) = uart else {
+            std::thread::sleep(URC_READER_PAUSE);
+            continue;
+        };
+        match read_idle(&mut uart) {
+            Ok(read) if !read.is_empty() => {
+                let rest: String = uart.urc_filter.filter(&read, &serial_port);
+                if !rest.trim().is_empty() {
+                    log::debug!("URC reader - discarded: {rest:?}");
+                }
+            }
+            Ok(_) => (),
+            Err(Error::Closed) => return,
+            Err(e) => log::warn!("URC reader - reading has failed: {e}"),
+        }
+    };
+
+    if let Err(e) = std::thread::Builder::new()
+        .name("rpi_sim868-urc".to_string())
+        .spawn(reader)
+    {
+        log::warn!("URC reader - unable to start the thread: {e}");
+    }
+}
+
+/// Complete lines received so far, until the read times out.
+fn read_idle(uart: &mut std::sync::MutexGuard<'_, FramedTransport>) -> ResolverReturn<String> {
+    Ok(uart.read_lines()?.unwrap_or_default())
 }
 
 impl SerialPort {
-    pub fn new(path: &str, baud_rate: u32) -> Self {
-        let mut uart: Uart = Uart::with_path(path, baud_rate, Parity::None, 8, 1)
-            .expect("Unable to establish UART connection.");
-        uart.set_read_mode(0, Duration::from_millis(100))
-            .expect("Unable to set UART read mode.");
+    fn scheduler(&self) -> std::sync::MutexGuard<'_, SchedulerState> {
+        self.scheduler.lock().expect(MUTEX_POISONED_MSG)
+    }
 
+    pub fn new(
+        transport: Box<dyn Transport>,
+        profile: ModemProfile,
+        timeouts: CommandTimeouts,
+    ) -> Self {
         SerialPort {
-            uart: Arc::new(Mutex::new(uart)),
-            queue: Arc::new(RwLock::new(PriorityQueue::new())),
+            uart: Mutex::new(FramedTransport::new(transport)),
+            scheduler: Mutex::new(SchedulerState::default()),
+            queue_changed: Notify::new(),
+            port_freed: Condvar::new(),
+            closed: AtomicBool::new(false),
+            sequence: AtomicU64::new(0),
+            priority_aging: Mutex::new(Some(DEFAULT_PRIORITY_AGING)),
+            retry_policy: Mutex::new(RetryPolicy::default()),
+            events: broadcast::channel(EVENTS_CAPACITY).0,
+            #[cfg(feature = "gnss")]
+            nmea: broadcast::channel(NMEA_CAPACITY).0,
+            #[cfg(feature = "gnss")]
+            fix_cache: Arc::default(),
+            profile,
+            timeouts,
+            opened_at: Instant::now(),
+            settings: Mutex::new(BTreeMap::new()),
+            capabilities: Arc::new(Mutex::new(None)),
+            state: Arc::new(Mutex::new(ModemState::default())),
+            channels: Mutex::new(Vec::new()),
+            sleep_mode: AtomicBool::new(false),
+            #[cfg(feature = "metrics")]
+            counters: TaskCounters::default(),
+        }
+    }
+
+    /// Remembers the `command` (replacing the previous one of the `setting`), so [`crate::hat::Hat::init`]
+    /// restores the setting the modem forgets when it restarts.
+    pub fn persist_setting(&self, setting: &'static str, command: String) {
+        let mut settings = self.settings.lock().expect(MUTEX_POISONED_MSG);
+        settings.insert(setting, command);
+    }
+
+    /// Forgets the settings remembered by [`SerialPort::persist_setting`], eg. once the modem is back to its
+    /// factory defaults.
+    pub fn clear_settings(&self) {
+        self.settings.lock().expect(MUTEX_POISONED_MSG).clear();
+    }
+
+    /// Time the task waits in the queue before its priority is raised by one level, `None` disables it.
+    pub fn set_priority_aging(&self, aging: Option<Duration>) {
+        *self.priority_aging.lock().expect(MUTEX_POISONED_MSG) = aging;
+        for channel in self.channels.lock().expect(MUTEX_POISONED_MSG).iter() {
+            channel.set_priority_aging(aging);
+        }
+    }
+
+    /// Policy of repeating the failed AT commands of the tasks without their own, see [`RetryPolicy`].
+    pub fn set_retry_policy(&self, policy: RetryPolicy) {
+        *self.retry_policy.lock().expect(MUTEX_POISONED_MSG) = policy;
+        for channel in self.channels.lock().expect(MUTEX_POISONED_MSG).iter() {
+            channel.set_retry_policy(policy);
+        }
+    }
+
+    /// Retry policy of the running task, or the default one.
+    fn retry_policy(&self) -> RetryPolicy {
+        let task_policy: Option<RetryPolicy> =
+            self.scheduler()
+                .running
+                .as_ref()
+                .and_then(|control: &Arc<TaskControl>| {
+                    *control.retry_policy.lock().expect(MUTEX_POISONED_MSG)
+                });
+        task_policy.unwrap_or_else(|| *self.retry_policy.lock().expect(MUTEX_POISONED_MSG))
+    }
+
+    pub fn persisted_settings(&self) -> Vec<String> {
+        let settings = self.settings.lock().expect(MUTEX_POISONED_MSG);
+        settings.values().cloned().collect()
+    }
+
+    /// Queued tasks, the first to run first.
+    pub fn pending_tasks(&self) -> Vec<TaskInfo> {
+        let scheduler = self.scheduler();
+        let mut queue: Vec<(&Uuid, &QueuedPriority)> = scheduler.queue.iter().collect();
+        queue.sort_by(|a, b| b.1.cmp(a.1));
+        queue
+            .into_iter()
+            .filter_map(|(task_id, queued)| {
+                let control: &Arc<TaskControl> = scheduler.queued.get(task_id)?;
+                Some(control.info(queued.priority, control.created_at))
+            })
+            .collect()
+    }
+
+    pub fn scheduler_stats(&self) -> SchedulerStats {
+        let pending: Vec<TaskInfo> = self.pending_tasks();
+        let scheduler = self.scheduler();
+        let mut queued_by_priority: BTreeMap<TaskPriority, usize> = BTreeMap::new();
+        for task in pending.iter() {
+            *queued_by_priority.entry(task.priority).or_default() += 1;
+        }
+        let running: Option<TaskInfo> = scheduler.running.as_ref().and_then(|control| {
+            let started = *control.started.lock().expect(MUTEX_POISONED_MSG);
+            started.map(|(started_at, priority)| control.info(priority, started_at))
+        });
+        let history: &TaskHistory = &scheduler.history;
+        SchedulerStats {
+            queue_depth: pending.len(),
+            queued_by_priority,
+            running,
+            pending,
+            started: history.started,
+            average_wait: u32::try_from(history.started)
+                .ok()
+                .filter(|started: &u32| *started > 0)
+                .map(|started: u32| history.total_wait / started),
+            finished: history.finished.iter().cloned().collect(),
+        }
+    }
+
+    /// Cancels the queued tasks and the running one, which stops before its next command - of this port and
+    /// the other channels.
+    pub fn cancel_all(&self) {
+        let controls: Vec<Arc<TaskControl>> = {
+            let scheduler = self.scheduler();
+            scheduler
+                .queued
+                .values()
+                .chain(scheduler.running.iter())
+                .cloned()
+                .collect()
+        };
+        for control in controls {
+            control.cancel();
+        }
+        for channel in self.channels.lock().expect(MUTEX_POISONED_MSG).iter() {
+            channel.cancel_all();
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.scheduler().is_idle()
+    }
+
+    /// Waits until no task of this port and the other channels is queued or running, returning `false` if
+    /// they haven't finished by the `deadline`.
+    pub async fn wait_idle(&self, deadline: Instant) -> bool {
+        let channels: Vec<Arc<SerialPort>> =
+            self.channels.lock().expect(MUTEX_POISONED_MSG).clone();
+        for port in std::iter::once(self).chain(channels.iter().map(Arc::as_ref)) {
+            loop {
+                let changed = port.queue_changed.notified();
+                tokio::pin!(changed);
+                changed.as_mut().enable();
+                if port.is_idle() {
+                    break;
+                }
+                let remaining: Duration = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return false;
+                }
+                let _ = timeout(remaining, changed).await;
+            }
+        }
+        true
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn queue_depth(&self) -> usize {
+        self.scheduler().queue.len()
+    }
+
+    pub fn capabilities(&self) -> Option<Capabilities> {
+        self.capabilities.lock().expect(MUTEX_POISONED_MSG).clone()
+    }
+
+    pub fn set_capabilities(&self, capabilities: Capabilities) {
+        *self.capabilities.lock().expect(MUTEX_POISONED_MSG) = Some(capabilities);
+    }
+
+    /// Fails fast with [`Error::UnsupportedByModem`] (or [`Error::UnsupportedByFirmware`], see
+    /// [`SerialPort::require_capability`]) before sending commands the modem doesn't know.
+    #[cfg(any(feature = "bluetooth", feature = "gnss", feature = "phone"))]
+    pub fn require(&self, feature: crate::profile::Feature) -> ResolverReturn<()> {
+        use crate::{capabilities::Capability, profile::Feature};
+
+        if !self.profile.supports(feature) {
+            return Err(Error::UnsupportedByModem {
+                profile: self.profile,
+                feature,
+            });
+        }
+        match feature {
+            Feature::BLUETOOTH => self.require_capability(Capability::BLUETOOTH),
+            Feature::GNSS => self.require_capability(Capability::GNSS),
+            Feature::VOICE => Ok(()),
         }
     }
 
-    pub fn write(&self, task_id: &Uuid, input: String) -> ResolverReturn<()> {
-        let mut uart: std::sync::MutexGuard<'_, Uart> = self.uart.lock().expect(MUTEX_POISONED_MSG);
-        uart.flush(rppal::uart::Queue::Input)?;
+    /// Fails fast with [`Error::UnsupportedByFirmware`] if the detected capabilities lack the `capability`.
+    /// Passes until they have been detected by [`crate::hat::Hat::init`].
+    #[cfg(any(
+        feature = "bluetooth",
+        feature = "gnss",
+        feature = "gprs",
+        feature = "phone"
+    ))]
+    pub fn require_capability(
+        &self,
+        capability: crate::capabilities::Capability,
+    ) -> ResolverReturn<()> {
+        match self.capabilities.lock().expect(MUTEX_POISONED_MSG).as_ref() {
+            Some(capabilities) if !capabilities.supports(capability) => {
+                Err(Error::UnsupportedByFirmware {
+                    revision: capabilities.revision.clone(),
+                    capability,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Drops the transport (releasing the UART device) and stops the URC reader, the following commands fail
+    /// with [`Error::Closed`].
+    pub fn close(&self) {
+        self.uart.lock().expect(MUTEX_POISONED_MSG).close();
+        {
+            // with the scheduler lock, so the URC reader can't miss it
+            let _scheduler: std::sync::MutexGuard<'_, SchedulerState> = self.scheduler();
+            self.closed.store(true, Ordering::SeqCst);
+            self.port_freed.notify_all();
+        }
+        for channel in self.channels.lock().expect(MUTEX_POISONED_MSG).iter() {
+            channel.close();
+        }
+    }
+
+    pub fn state(&self) -> ModemState {
+        *self.state.lock().expect(MUTEX_POISONED_MSG)
+    }
+
+    #[cfg(feature = "gnss")]
+    pub(crate) fn fix_cache(&self) -> std::sync::MutexGuard<'_, FixCache> {
+        self.fix_cache.lock().expect(MUTEX_POISONED_MSG)
+    }
+
+    pub fn update_state<F>(&self, update: F)
+    where
+        F: FnOnce(&mut ModemState),
+    {
+        update(&mut self.state.lock().expect(MUTEX_POISONED_MSG));
+    }
+
+    /// Port of another channel of the multiplexed connection (see [`crate::cmux`]), with its own task queue.
+    /// It publishes the events and shares the capabilities and the [state](ModemState) of this port, and is closed
+    /// with it.
+    #[cfg(feature = "gnss")]
+    pub fn channel(&self, transport: Box<dyn Transport>) -> Arc<SerialPort> {
+        let mut channel: SerialPort = SerialPort::new(transport, self.profile, self.timeouts);
+        channel.events = self.events.clone();
+        channel.nmea = self.nmea.clone();
+        channel.fix_cache = self.fix_cache.clone();
+        channel.capabilities = self.capabilities.clone();
+        channel.state = self.state.clone();
+        channel.set_priority_aging(*self.priority_aging.lock().expect(MUTEX_POISONED_MSG));
+        channel.set_retry_policy(*self.retry_policy.lock().expect(MUTEX_POISONED_MSG));
+
+        let channel: Arc<SerialPort> = Arc::new(channel);
+        spawn_urc_reader(channel.clone());
+        self.channels
+            .lock()
+            .expect(MUTEX_POISONED_MSG)
+            .push(channel.clone());
+        channel
+    }
+
+    /// Fails with [`Error::TaskCancelled`] or [`Error::TaskTimeout`] once the running task has been cancelled, so
+    /// it stops before the next command.
+    fn check_cancelled(&self) -> ResolverReturn<()> {
+        match self.scheduler().running {
+            Some(ref control) => control.check(),
+            None => Ok(()),
+        }
+    }
+
+    /// `timeout` of the command, cut to the time left before the running task's deadline.
+    fn command_timeout(&self, timeout: Option<Duration>) -> Duration {
+        let timeout: Duration = timeout.unwrap_or(self.timeouts.query);
+        match self
+            .scheduler()
+            .running
+            .as_ref()
+            .and_then(|control: &Arc<TaskControl>| control.remaining())
+        {
+            Some(remaining) => timeout.min(remaining),
+            None => timeout,
+        }
+    }
+
+    /// Whether the modem may fall asleep when the UART is idle (`AT+CSCLK=2`). If so, the port sends an `AT`
+    /// waking it up before the command following a pause, as the modem loses the first character it receives
+    /// while asleep.
+    pub fn set_sleep_mode(&self, enabled: bool) {
+        self.sleep_mode.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Wakes the modem up if it may have fallen asleep, see [`SerialPort::set_sleep_mode`].
+    fn wake_up(
+        &self,
+        uart: &mut std::sync::MutexGuard<'_, FramedTransport>,
+        task_id: &Uuid,
+    ) -> ResolverReturn<()> {
+        let asleep: bool = self.sleep_mode.load(Ordering::SeqCst)
+            && uart.idle().map_or(true, |idle: Duration| idle > SLEEP_IDLE);
+        if !asleep {
+            return Ok(());
+        }
+        debug_log(task_id, "Waking the modem up...");
+        uart.write(b"AT\r\n")?;
+        // the sleeping modem doesn't answer, the one awake already does
+        let _ = uart_read(
+            task_id,
+            uart,
+            self,
+            WAKE_UP_TIMEOUT,
+            &|result: String| match ACK_REGEX.is_match(&result) {
+                true => Ok(()),
+                false => Err(Error::NotResolved),
+            },
+        );
+        uart.flush(Queue::Input)
+    }
+
+    /// Switches the transport to the `baud_rate`, eg. once the modem's one has been changed by `AT+IPR`.
+    pub fn set_baud_rate(&self, baud_rate: u32) -> ResolverReturn<()> {
+        self.uart
+            .lock()
+            .expect(MUTEX_POISONED_MSG)
+            .set_baud_rate(baud_rate)
+    }
+
+    pub fn write<B: AsRef<[u8]>>(&self, task_id: &Uuid, input: B) -> ResolverReturn<()> {
+        self.check_cancelled()?;
+        let mut uart: std::sync::MutexGuard<'_, FramedTransport> =
+            self.uart.lock().expect(MUTEX_POISONED_MSG);
+        self.wake_up(&mut uart, task_id)?;
+        uart.flush(Queue::Input)?;
         debug_log(task_id, "Writing to UART...");
-        uart.write(input.as_bytes())?;
+        uart.write(input.as_ref())?;
         Ok(())
     }
 
@@ -165,10 +1012,20 @@ impl SerialPort {
         resolver: fn(String) -> ResolverReturn<T>,
         timeout: Option<Duration>,
     ) -> ResolverReturn<T> {
-        let timeout: Duration = timeout.unwrap_or(Duration::from_millis(1000));
-        let mut uart: std::sync::MutexGuard<'_, Uart> = self.uart.lock().expect(MUTEX_POISONED_MSG);
-        let read: ResolverReturn<T> = uart_read(&task_id, &mut uart, timeout, resolver);
-        read
+        self.check_cancelled()?;
+        let timeout: Duration = self.command_timeout(timeout);
+        let mut uart: std::sync::MutexGuard<'_, FramedTransport> =
+            self.uart.lock().expect(MUTEX_POISONED_MSG);
+        let read: ResolverReturn<T> = uart_read(task_id, &mut uart, self, timeout, &resolver);
+        read.map_err(|error: Error| self.deadline_error(error))
+    }
+
+    /// [`Error::TaskTimeout`] instead of the read cut by the deadline.
+    fn deadline_error(&self, error: Error) -> Error {
+        match (error.kind(), self.check_cancelled()) {
+            (ErrorKind::NotResolved, Err(deadline)) => deadline,
+            _ => error,
+        }
     }
 
     pub fn process<T>(
@@ -178,11 +1035,92 @@ impl SerialPort {
         resolver: fn(String) -> ResolverReturn<T>,
         timeout: Option<Duration>,
     ) -> ResolverReturn<T> {
-        let timeout: Duration = timeout.unwrap_or(Duration::from_millis(1000));
-        let mut uart: std::sync::MutexGuard<'_, Uart> = self.uart.lock().expect(MUTEX_POISONED_MSG);
-        uart.flush(rppal::uart::Queue::Both)?;
-        uart.write(input.as_bytes())?;
-        let read: ResolverReturn<T> = uart_read(task_id, &mut uart, timeout, resolver);
-        read
+        self.with_retries(task_id, &input, || {
+            let mut uart: std::sync::MutexGuard<'_, FramedTransport> =
+                self.uart.lock().expect(MUTEX_POISONED_MSG);
+            self.transact(&mut uart, task_id, &input, &resolver, timeout)
+        })
+    }
+
+    /// Runs the steps of the `batch` in order, holding the port for all of them - so no URC is read and nothing
+    /// else is written in between. Stops at the first failed step.
+    pub(crate) fn process_batch<T>(
+        &self,
+        task_id: &Uuid,
+        batch: CommandBatch<T>,
+    ) -> ResolverReturn<Vec<T>> {
+        let mut uart: std::sync::MutexGuard<'_, FramedTransport> =
+            self.uart.lock().expect(MUTEX_POISONED_MSG);
+        batch
+            .steps
+            .iter()
+            .map(|step: &BatchStep<T>| {
+                self.with_retries(task_id, &step.command, || {
+                    self.transact(
+                        &mut uart,
+                        task_id,
+                        &step.command,
+                        &*step.resolver,
+                        step.timeout,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Repeats the `transact`ion of the `input` as the retry policy says.
+    fn with_retries<T>(
+        &self,
+        task_id: &Uuid,
+        input: &str,
+        mut transact: impl FnMut() -> ResolverReturn<T>,
+    ) -> ResolverReturn<T> {
+        let start: Instant = Instant::now();
+        let policy: RetryPolicy = self.retry_policy();
+        let mut attempt: u32 = 1;
+        loop {
+            let error: Error = match transact() {
+                Ok(data) => return Ok(data),
+                Err(error) => self.deadline_error(error),
+            };
+            match policy.retry_delay(attempt, &error) {
+                // neither the cancelled task nor the one past its deadline is repeated
+                Some(delay) if self.check_cancelled().is_ok() && RetryPolicy::retries(input) => {
+                    log::warn!(
+                        "{} - {} has failed ({error}), retrying in {delay:?} (attempt {attempt} of {}).",
+                        task_label(task_id),
+                        input.trim_end(),
+                        policy.max_attempts
+                    );
+                    std::thread::sleep(self.command_timeout(Some(delay)));
+                    attempt += 1;
+                }
+                _ => {
+                    return Err(Error::Context {
+                        command: input.trim_end().to_string(),
+                        task_id: *task_id,
+                        elapsed: start.elapsed(),
+                        error: Box::new(error),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Writes the `input` and reads the response, once.
+    fn transact<T>(
+        &self,
+        uart: &mut std::sync::MutexGuard<'_, FramedTransport>,
+        task_id: &Uuid,
+        input: &str,
+        resolver: &dyn Fn(String) -> ResolverReturn<T>,
+        timeout: Option<Duration>,
+    ) -> ResolverReturn<T> {
+        let timeout: Duration = self.command_timeout(timeout);
+        self.check_cancelled()
+            .and_then(|_| self.wake_up(uart, task_id))
+            .and_then(|_| uart.flush(Queue::Both))
+            .and_then(|_| uart.write(input.as_bytes()))
+            .and_then(|_| uart_read(task_id, uart, self, timeout, resolver))
     }
 }
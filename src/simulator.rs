@@ -0,0 +1,1454 @@
+//! Simulator module
+//!
+//! Available with the `simulator` feature.
+//!
+//! [`Simulator`] is a virtual SIM868 implementing [`Transport`], so the crate (and the code using it)
+//! can run in CI or on a laptop without the HAT. It answers the AT commands used by the crate with realistic
+//! responses - the network registration and the GNSS fix are delayed, the GNSS position follows a fake track,
+//! and there is an SMS inbox which can be filled with [`Simulator::receive_sms`].
+//!
+//! ```no_run
+//! use rpi_sim868::{simulator::Simulator, SIM868};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let simulator: Simulator = Simulator::default();
+//!     let sim: SIM868 = SIM868::with_transport(simulator.clone(), rpi_sim868::LogLevelFilter::Off);
+//!
+//!     simulator.receive_sms("+4799999999", "Hello!");
+//!     let messages = sim.sms.get_messages(rpi_sim868::sms::MessageStorage::ALL).await??;
+//!     assert_eq!(messages[0].text, "Hello!");
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{
+    error::Error,
+    transport::{Queue, Transport},
+};
+use chrono::{Local, NaiveDateTime, Utc};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+const MUTEX_POISONED_MSG: &str = "Critical error: Mutex is poisoned.";
+/// Time the read waits for the data, similar to the UART read timeout.
+const READ_WAIT: Duration = Duration::from_millis(20);
+const SMS_SEND_DELAY: Duration = Duration::from_millis(500);
+const HTTP_ACTION_DELAY: Duration = Duration::from_secs(1);
+const NTP_DELAY: Duration = Duration::from_secs(1);
+/// Time without a command after which the modem in the sleep mode falls asleep.
+const SLEEP_DELAY: Duration = Duration::from_secs(5);
+/// Time the modem takes to restart after `AT+CFUN=1,1`.
+const RESET_DELAY: Duration = Duration::from_secs(1);
+/// The outgoing call rings after the first delay and is answered after the second one.
+const CALL_ALERTING_DELAY: Duration = Duration::from_secs(1);
+const CALL_ANSWER_DELAY: Duration = Duration::from_secs(3);
+const BLUETOOTH_SCAN_DELAY: Duration = Duration::from_millis(500);
+const BLUETOOTH_CONNECT_DELAY: Duration = Duration::from_millis(500);
+const BLUETOOTH_PASSKEY: &str = "048213";
+const BLUETOOTH_ADDRESS: &str = "33:7d:13:9a:55:7a";
+/// Networks found by the operator scan, as `"long name","short name","MCC and MNC"` - the modem is
+/// registered to the first one.
+const OPERATORS: [&str; 2] = [
+    "\"Simulated Network\",\"SIMNET\",\"00101\"",
+    "\"Test Network\",\"TEST\",\"00102\"",
+];
+/// Devices found by the Bluetooth scan, as `name,address,rssi`.
+const BLUETOOTH_DEVICES: [&str; 2] = [
+    "\"Pixel 7\",78:dd:08:4d:94:a4,-54",
+    "\"HC-05\",98:d3:31:fb:12:0c,-71",
+];
+/// GSV sentences (without the checksum) of the satellites in view, matching the 12 reported by `AT+CGNSINF`.
+const GSV_SENTENCES: [&str; 3] = [
+    "GPGSV,2,1,08,02,68,116,44,05,41,302,39,12,22,054,36,13,57,187,42",
+    "GPGSV,2,2,08,15,12,251,31,18,35,089,38,25,08,320,,29,74,010,45",
+    "GLGSV,1,1,04,65,45,030,35,71,24,145,29,72,61,210,40,88,10,330,",
+];
+const NMEA_INTERVAL: Duration = Duration::from_secs(1);
+const CTRL_Z: u8 = 0x1A;
+
+/// Behaviour of the [`Simulator`].
+#[derive(Debug, Clone)]
+pub struct SimulatorConfig {
+    /// Time after which the modem registers to the network - until then the signal strength is 0.
+    pub registration_delay: Duration,
+    /// Time from turning the GNSS module on until the position is fixed.
+    pub fix_delay: Duration,
+    /// Start of the fake GNSS track.
+    pub start_lat: f64,
+    pub start_lon: f64,
+    /// Body of the response to every HTTP request.
+    pub http_response: String,
+    /// Baud rate the modem starts at (`AT+IPR`), 0 - auto-bauding. The commands written by the transport
+    /// switched to another rate (see [`Transport::set_baud_rate`]) are garbled and left unanswered.
+    pub baud_rate: u32,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        SimulatorConfig {
+            registration_delay: Duration::from_secs(2),
+            fix_delay: Duration::from_secs(5),
+            start_lat: 51.5072,
+            start_lon: -0.1276,
+            http_response: "{\"status\": \"ok\"}".to_string(),
+            baud_rate: 115200,
+        }
+    }
+}
+
+struct StoredMessage {
+    index: u8,
+    read: bool,
+    sender: String,
+    text: String,
+    received: chrono::DateTime<Local>,
+}
+
+struct State {
+    config: SimulatorConfig,
+    powered_since: Option<Instant>,
+    /// `AT+CFUN` level, the modem doesn't register to the network unless it's 1
+    functionality: u8,
+    /// `AT+CBAND` band(s) searched
+    band: String,
+    /// `AT+CSCLK=2` - the modem falls asleep after 5 s without a command, and loses the one waking it up
+    sleep_mode: bool,
+    last_command: Instant,
+    gnss_on_since: Option<Instant>,
+    /// `AT+CGNSURC` - interval of the `+UGNSINF` reports and the time of the next one
+    gnss_reports: Option<(Duration, Instant)>,
+    /// `AT+CGNSTST=1` - time of the next set of the NMEA sentences
+    nmea_output: Option<Instant>,
+    bearer_open: bool,
+    http_active: bool,
+    http_data_expected: Option<usize>,
+    /// Files of the file system, by path
+    files: BTreeMap<String, Vec<u8>>,
+    /// `AT+FSWRITE` - path of the file, whether the data is appended, and its length
+    file_data_expected: Option<(String, bool, usize)>,
+    /// `AT+CGNSAID` - the receiver starts with the EPO data
+    gnss_assisted: bool,
+    /// `$PMTK353` - the GLONASS satellites are used along with the GPS ones
+    glonass: bool,
+    /// `$PMTK314` - output of the NMEA sentences, in the order of the command; `None` - the default output
+    nmea_mask: Option<Vec<bool>>,
+    /// `$PMTK220` - time between the fixes
+    fix_interval: Duration,
+    /// `AT+CGQREQ` / `AT+CGQMIN` parameters of the context 1
+    qos_requested: Option<String>,
+    qos_minimum: Option<String>,
+    /// the RTC has been set by `AT+CNTP`, otherwise it's set by the network on the registration
+    ntp_synchronised: bool,
+    /// offset of the time set with `AT+CCLK` from the UTC, and its timezone
+    rtc: Option<(chrono::Duration, String)>,
+    bluetooth_on: bool,
+    bluetooth_name: String,
+    bluetooth_visible: bool,
+    /// index of the connected device in [`BLUETOOTH_DEVICES`]
+    bluetooth_connected: Option<usize>,
+    /// index of the device asking to connect
+    bluetooth_connection_request: Option<usize>,
+    bluetooth_data_expected: Option<usize>,
+    /// indexes of the paired devices, in the order of pairing
+    bluetooth_paired: Vec<usize>,
+    /// index of the device waiting for the pairing confirmation
+    bluetooth_pairing: Option<usize>,
+    spp_sent: Vec<String>,
+    /// number and the dialing time of the outgoing call
+    outgoing_call: Option<(String, Instant)>,
+    /// the incoming call is waiting to be answered
+    ringing: bool,
+    inbox: Vec<StoredMessage>,
+    /// new messages are passed on with `+CMT` instead of being stored (`AT+CNMI=2,2,...`)
+    direct_delivery: bool,
+    sent: Vec<(String, String)>,
+    /// written, not yet processed bytes
+    input: Vec<u8>,
+    /// bytes waiting to be read
+    output: VecDeque<u8>,
+    /// the commands are echoed back, until `ATE0`
+    echo: bool,
+    /// echo of the profile saved by `AT&W`, restored by `ATZ`
+    saved_echo: bool,
+    /// `AT+CREG` / `AT+CGREG` result code modes, 2 - with the location
+    creg_mode: u8,
+    cgreg_mode: u8,
+    /// `AT+COPS=3` operator format, 0 - long name, 2 - numeric
+    cops_format: u8,
+    /// `AT+IPR` rate of the modem, 0 - auto-bauding
+    baud_rate: u32,
+    /// rate the transport is switched to
+    local_baud_rate: u32,
+    /// responses sent after a delay
+    scheduled: Vec<(Instant, String)>,
+}
+
+/// Virtual SIM868. Cloning returns a handle to the same modem, so it can be controlled after being passed to
+/// [`crate::SIM868::with_transport`].
+#[derive(Clone)]
+pub struct Simulator {
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Simulator::new(SimulatorConfig::default())
+    }
+}
+
+fn ok() -> String {
+    "\r\nOK\r\n".to_string()
+}
+
+fn error() -> String {
+    "\r\nERROR\r\n".to_string()
+}
+
+fn with_ok(response: &str) -> String {
+    format!("\r\n{response}\r\n\r\nOK\r\n")
+}
+
+/// Line of the QoS profile query, the modem lists the context with the default (subscribed) profile.
+fn qos_line(command: &str, qos: &Option<String>) -> String {
+    format!("+{command}: 1,{}", qos.as_deref().unwrap_or("0,0,0,0,0"))
+}
+
+/// NMEA sentence with the checksum, the XOR of the bytes between `$` and `*`.
+fn nmea_sentence(body: &str) -> String {
+    let checksum: u8 = body
+        .bytes()
+        .fold(0, |checksum: u8, byte: u8| checksum ^ byte);
+    format!("${body}*{checksum:02X}\r\n")
+}
+
+/// `ddmm.mmmm,N` (with the `degree_digits` of the degrees) of the signed `degrees`.
+fn nmea_coordinate(degrees: f64, degree_digits: usize, hemispheres: (char, char)) -> String {
+    let hemisphere: char = match degrees < 0.0 {
+        true => hemispheres.1,
+        false => hemispheres.0,
+    };
+    let whole: f64 = degrees.abs().trunc();
+    let minutes: f64 = (degrees.abs() - whole) * 60.0;
+    format!(
+        "{:0width$}{:07.4},{}",
+        whole as u32,
+        minutes,
+        hemisphere,
+        width = degree_digits
+    )
+}
+
+/// Index in [`BLUETOOTH_DEVICES`] of the device with the (1-based) `id` returned by the scan.
+fn device_index(id: &str) -> Option<usize> {
+    let id: usize = id.parse().ok()?;
+    (1..=BLUETOOTH_DEVICES.len()).contains(&id).then(|| id - 1)
+}
+
+/// Quoted name and the address of the device.
+fn bluetooth_device(device: usize) -> (&'static str, &'static str) {
+    let mut fields = BLUETOOTH_DEVICES[device].split(',');
+    (
+        fields.next().unwrap_or_default(),
+        fields.next().unwrap_or_default(),
+    )
+}
+
+impl State {
+    fn registered(&self) -> bool {
+        self.functionality == 1
+            && self
+                .powered_since
+                .map(|since: Instant| since.elapsed() >= self.config.registration_delay)
+                .unwrap_or(false)
+    }
+
+    fn power_down(&mut self) {
+        self.powered_since = None;
+        self.gnss_on_since = None;
+        self.gnss_reports = None;
+        self.nmea_output = None;
+        self.bearer_open = false;
+        self.http_active = false;
+        self.bluetooth_on = false;
+        self.bluetooth_connected = None;
+        self.outgoing_call = None;
+        self.ringing = false;
+    }
+
+    fn push(&mut self, response: &str) {
+        self.output.extend(response.as_bytes());
+    }
+
+    fn schedule(&mut self, delay: Duration, response: String) {
+        self.scheduled.push((Instant::now() + delay, response));
+    }
+
+    fn release_scheduled(&mut self) {
+        let now: Instant = Instant::now();
+        while let Some(position) = self.scheduled.iter().position(|(at, _)| *at <= now) {
+            let (_, response) = self.scheduled.remove(position);
+            self.push(&response);
+        }
+        if let Some((interval, next)) = self.gnss_reports {
+            if next <= now {
+                let report: String = self.gnss_info().replacen("+CGNSINF", "+UGNSINF", 1);
+                self.push(&format!("\r\n{report}\r\n"));
+                self.gnss_reports = Some((interval, now + interval));
+            }
+        }
+        if let Some(next) = self.nmea_output {
+            if next <= now && self.gnss_on_since.is_some() {
+                let sentences: String = self.nmea_output();
+                self.push(&sentences);
+                self.nmea_output = Some(now + self.fix_interval);
+            }
+        }
+    }
+
+    /// Takes the next complete command (or HTTP/SPP data) from the input.
+    fn next_command(&mut self) -> Option<Vec<u8>> {
+        let file_data_expected: Option<usize> = self
+            .file_data_expected
+            .as_ref()
+            .map(|(_, _, length)| *length);
+        if let Some(length) = self
+            .http_data_expected
+            .or(self.bluetooth_data_expected)
+            .or(file_data_expected)
+        {
+            if self.input.len() < length {
+                return None;
+            }
+            return Some(self.input.drain(..length).collect());
+        }
+
+        let is_sms: bool = String::from_utf8_lossy(&self.input)
+            .trim_start()
+            .starts_with("AT+CMGS=");
+        let terminator: u8 = if is_sms { CTRL_Z } else { b'\n' };
+        let end: usize = self
+            .input
+            .iter()
+            .position(|byte: &u8| *byte == terminator)?;
+        let mut command: Vec<u8> = self.input.drain(..=end).collect();
+        command.pop();
+        if is_sms && self.input.first() == Some(&b'\n') {
+            self.input.remove(0);
+        }
+        Some(command)
+    }
+
+    fn handle(&mut self, command: Vec<u8>) {
+        if self.powered_since.is_none() {
+            // switched off modem doesn't respond
+            return;
+        }
+        let asleep: bool = self.sleep_mode && self.last_command.elapsed() > SLEEP_DELAY;
+        self.last_command = Instant::now();
+        if asleep {
+            log::trace!("simulator: woken up");
+            return;
+        }
+
+        if let Some(length) = self.http_data_expected.take() {
+            log::trace!("simulator: received {length} bytes of HTTP data");
+            let response: String = ok();
+            self.push(&response);
+            return;
+        }
+        if let Some((path, append, length)) = self.file_data_expected.take() {
+            log::trace!("simulator: received {length} bytes of {path}");
+            let file: &mut Vec<u8> = self.files.entry(path).or_default();
+            if !append {
+                file.clear();
+            }
+            file.extend(command);
+            let response: String = ok();
+            self.push(&response);
+            return;
+        }
+        if self.bluetooth_data_expected.take().is_some() {
+            self.spp_sent
+                .push(String::from_utf8_lossy(&command).to_string());
+            self.push("\r\nSEND OK\r\n");
+            return;
+        }
+
+        let command: String = String::from_utf8_lossy(&command).trim().to_string();
+        if self.echo {
+            let line: &str = command.lines().next().unwrap_or_default();
+            self.push(&format!("{line}\r"));
+        }
+        if let Some(echo) = command.strip_prefix("ATE") {
+            self.echo = echo != "0";
+        }
+        let response: Option<String> = self.respond(&command);
+        if let Some(response) = response {
+            self.push(&response);
+        }
+    }
+
+    fn respond(&mut self, command: &str) -> Option<String> {
+        let response: String = match command {
+            "" => return None,
+            "AT" => ok(),
+            "AT+CGMR" => with_ok("Revision:1418B05SIM868M32_BT"),
+            "AT+HTTPACTION=?" => with_ok("+HTTPACTION: (0-2)"),
+            "AT+HTTPSSL=?" => with_ok("+HTTPSSL: (0,1)"),
+            "AT+BTPOWER=?" | "AT+CGNSPWR=?" => ok(),
+            "AT+HTTPSSL=0" | "AT+HTTPSSL=1" => match self.http_active {
+                true => ok(),
+                false => error(),
+            },
+            "AT+CPOWD=0" => {
+                self.power_down();
+                String::new()
+            }
+            "AT+CPOWD=1" => {
+                self.power_down();
+                "\r\nNORMAL POWER DOWN\r\n".to_string()
+            }
+            "AT&W" => {
+                self.saved_echo = self.echo;
+                ok()
+            }
+            "ATZ" => {
+                self.echo = self.saved_echo;
+                ok()
+            }
+            "AT&F" => {
+                self.echo = true;
+                self.sleep_mode = false;
+                ok()
+            }
+            "AT+CNETLIGHT=0" | "AT+CNETLIGHT=1" | "AT+CSGS=0" | "AT+CSGS=1" | "AT+SJDR=0"
+            | "AT+SJDR=1" => ok(),
+            "AT+CSCLK=0" | "AT+CSCLK=2" => {
+                self.sleep_mode = command == "AT+CSCLK=2";
+                ok()
+            }
+            "AT+CBAND?" => with_ok(&format!("+CBAND: {}", self.band)),
+            "AT+CBAND=\"PGSM_MODE\""
+            | "AT+CBAND=\"DCS_MODE\""
+            | "AT+CBAND=\"PCS_MODE\""
+            | "AT+CBAND=\"EGSM_DCS_MODE\""
+            | "AT+CBAND=\"GSM850_PCS_MODE\""
+            | "AT+CBAND=\"ALL_BAND\"" => {
+                self.band = command[10..command.len() - 1].to_string();
+                ok()
+            }
+            "AT+CFUN?" => with_ok(&format!("+CFUN: {}", self.functionality)),
+            "AT+CFUN=0" | "AT+CFUN=1" | "AT+CFUN=4" => {
+                self.functionality = command.as_bytes()[8] - b'0';
+                if self.functionality != 1 {
+                    self.bearer_open = false;
+                    self.http_active = false;
+                }
+                ok()
+            }
+            "AT+CFUN=1,1" => {
+                // the restarted modem boots with the default settings
+                self.power_down();
+                self.powered_since = Some(Instant::now());
+                self.sleep_mode = false;
+                self.functionality = 1;
+                self.echo = true;
+                self.schedule(
+                    RESET_DELAY,
+                    "\r\nRDY\r\n\r\n+CFUN: 1\r\n\r\n+CPIN: READY\r\n\r\nCall Ready\r\n\r\nSMS Ready\r\n"
+                        .to_string(),
+                );
+                ok()
+            }
+            "AT+CSQ" => with_ok(if self.registered() {
+                "+CSQ: 20,0"
+            } else {
+                "+CSQ: 0,0"
+            }),
+            "AT+CREG?" => with_ok(&self.registration("CREG", self.creg_mode)),
+            "AT+CGREG?" => with_ok(&self.registration("CGREG", self.cgreg_mode)),
+            "AT+CREG=0" | "AT+CREG=1" | "AT+CREG=2" => {
+                self.creg_mode = command.as_bytes()[8] - b'0';
+                ok()
+            }
+            "AT+CENG=0" | "AT+CENG=1,1" => ok(),
+            "AT+CENG?" => with_ok(&self.cells()),
+            "AT+COPS?" => with_ok(&self.operator()),
+            "AT+COPS=?" => with_ok(&format!(
+                "+COPS: (2,{}),(1,{}),,(0-4),(0-2)",
+                OPERATORS[0], OPERATORS[1]
+            )),
+            "AT+COPS=3,0" | "AT+COPS=3,2" => {
+                self.cops_format = command.as_bytes()[10] - b'0';
+                ok()
+            }
+            "AT+COPS=0" | "AT+COPS=1,2,\"00101\"" | "AT+COPS=4,2,\"00101\"" => ok(),
+            "AT+CGREG=0" | "AT+CGREG=1" | "AT+CGREG=2" => {
+                self.cgreg_mode = command.as_bytes()[9] - b'0';
+                ok()
+            }
+            "AT+CBC" => with_ok("+CBC: 0,85,4012"),
+            "AT+CPIN?" => with_ok("+CPIN: READY"),
+            "AT+CGSN" => with_ok("490154203237518"),
+            "AT+CIMI" => with_ok("001010123456789"),
+            "AT+CCID" => with_ok("89001012012341234012"),
+            "AT+IPR?" => with_ok(&format!("+IPR: {}", self.baud_rate)),
+            "AT+CCLK?" => with_ok(&self.clock()),
+            "AT+CNTP" => {
+                let code: u8 = if self.bearer_open { 1 } else { 61 };
+                self.ntp_synchronised |= self.bearer_open;
+                if self.bearer_open {
+                    self.rtc = None;
+                }
+                self.schedule(NTP_DELAY, format!("\r\n+CNTP: {code}\r\n"));
+                ok()
+            }
+            "AT+CGQREQ?" => with_ok(&qos_line("CGQREQ", &self.qos_requested)),
+            "AT+CGQMIN?" => with_ok(&qos_line("CGQMIN", &self.qos_minimum)),
+            "AT+CGNSPWR?" => with_ok(&format!("+CGNSPWR: {}", self.gnss_on_since.is_some() as u8)),
+            "AT+CGNSPWR=1" => {
+                if self.gnss_on_since.is_none() {
+                    self.gnss_on_since = Some(Instant::now());
+                    if self.gnss_assisted {
+                        // the EPO data cuts the start to a tenth
+                        self.restart_gnss(10);
+                    }
+                }
+                ok()
+            }
+            "AT+CGNSCHK=3,1" => match self.epo_loaded() {
+                true => ok(),
+                false => error(),
+            },
+            "AT+CGNSAID=31,1,1" => match self.epo_loaded() {
+                true => {
+                    self.gnss_assisted = true;
+                    ok()
+                }
+                false => error(),
+            },
+            "AT+CGNSPWR=0" => {
+                self.gnss_on_since = None;
+                self.gnss_reports = None;
+                ok()
+            }
+            "AT+CGNSINF" => with_ok(&self.gnss_info()),
+            "AT+CGNSCOLD" => self.restart_gnss(1),
+            "AT+CGNSWARM" => self.restart_gnss(2),
+            "AT+CGNSHOT" => self.restart_gnss(10),
+            "AT+CGNSTST=1" => {
+                // the first sentences follow the OK
+                self.nmea_output = Some(Instant::now() + READ_WAIT);
+                ok()
+            }
+            "AT+CGNSTST=0" => {
+                self.nmea_output = None;
+                ok()
+            }
+            "AT+CMGF=1" => ok(),
+            "AT+CNMI=2,1,0,0,0" | "AT+CNMI=2,2,0,0,0" => {
+                self.direct_delivery = command == "AT+CNMI=2,2,0,0,0";
+                ok()
+            }
+            "AT+CMGL=\"ALL\"" => self.list_messages(false),
+            "AT+CMGL=\"REC UNREAD\"" => self.list_messages(true),
+            "ATH" => {
+                self.outgoing_call = None;
+                self.ringing = false;
+                ok()
+            }
+            "ATA" => match std::mem::take(&mut self.ringing) {
+                true => ok(),
+                false => "\r\nNO CARRIER\r\n".to_string(),
+            },
+            "AT+CLCC" => match &self.outgoing_call {
+                Some((number, since)) => {
+                    let status: u8 = match since.elapsed() {
+                        elapsed if elapsed >= CALL_ANSWER_DELAY => 0,
+                        elapsed if elapsed >= CALL_ALERTING_DELAY => 3,
+                        _ => 2,
+                    };
+                    with_ok(&format!("+CLCC: 1,0,{status},0,0,\"{number}\",129"))
+                }
+                None => ok(),
+            },
+            "AT+CIPGSMLOC=1,1" => with_ok(&match self.bearer_open {
+                true => format!(
+                    "+CIPGSMLOC: 0,{:.6},{:.6},{}",
+                    self.config.start_lon,
+                    self.config.start_lat,
+                    Utc::now().format("%Y/%m/%d,%H:%M:%S")
+                ),
+                // network error
+                false => "+CIPGSMLOC: 601".to_string(),
+            }),
+            "AT+SAPBR=2,1" => with_ok(if self.bearer_open {
+                "+SAPBR: 1,1,\"10.0.0.2\""
+            } else {
+                "+SAPBR: 1,3,\"0.0.0.0\""
+            }),
+            "AT+SAPBR=1,1" => {
+                if !self.registered() {
+                    return Some(error());
+                }
+                self.bearer_open = true;
+                ok()
+            }
+            "AT+SAPBR=0,1" | "AT+CGATT=0" => {
+                self.bearer_open = false;
+                ok()
+            }
+            "AT+HTTPINIT" => {
+                if self.http_active {
+                    return Some(error());
+                }
+                self.http_active = true;
+                ok()
+            }
+            "AT+HTTPTERM" => {
+                if !self.http_active {
+                    return Some(error());
+                }
+                self.http_active = false;
+                ok()
+            }
+            "AT+BTPOWER=1" | "AT+BTPOWER=0" => {
+                let on: bool = command.ends_with('1');
+                if self.bluetooth_on == on {
+                    return Some(error());
+                }
+                self.bluetooth_on = on;
+                self.bluetooth_connected = None;
+                ok()
+            }
+            "AT+BTSTATUS?" => with_ok(&self.bluetooth_status()),
+            "AT+BTHOST?" => with_ok(&format!(
+                "+BTHOST: {},{BLUETOOTH_ADDRESS}",
+                self.bluetooth_name
+            )),
+            "AT+BTVIS?" => with_ok(&format!("+BTVIS: {}", self.bluetooth_visible as u8)),
+            _ => self.respond_with_arguments(command),
+        };
+
+        Some(response)
+    }
+
+    fn respond_with_arguments(&mut self, command: &str) -> String {
+        if command.starts_with("AT+BT") {
+            return self.respond_bluetooth(command);
+        }
+        if command.starts_with("AT+CMEE=")
+            || command.starts_with("AT+CNTP")
+            || command.starts_with("AT+CLTS=")
+            || command.starts_with("AT+SAPBR=3,1,")
+            || command.starts_with("ATE")
+            || command.starts_with("ATS0=")
+        {
+            return ok();
+        }
+        if let Some(datetime) = command.strip_prefix("AT+CCLK=") {
+            return self.set_clock(datetime.trim_matches('"'));
+        }
+        if let Some(baud_rate) = command.strip_prefix("AT+IPR=") {
+            return match baud_rate.parse::<u32>() {
+                Ok(baud_rate) if baud_rate == 0 || crate::hat::BAUD_RATES.contains(&baud_rate) => {
+                    // the response is still sent at the previous rate
+                    self.baud_rate = baud_rate;
+                    ok()
+                }
+                _ => error(),
+            };
+        }
+        if let Some(rate) = command.strip_prefix("AT+CGNSURC=") {
+            return match rate.parse::<u32>() {
+                Ok(0) => {
+                    self.gnss_reports = None;
+                    ok()
+                }
+                Ok(rate) if rate <= 255 => {
+                    let interval: Duration = self.fix_interval * rate;
+                    self.gnss_reports = Some((interval, Instant::now() + interval));
+                    ok()
+                }
+                _ => error(),
+            };
+        }
+        if let Some(sentence) = command.strip_prefix("AT+CGNSCMD=0,") {
+            return match self.gnss_on_since.is_some()
+                && self.pmtk_command(sentence.trim_matches('"'))
+            {
+                true => ok(),
+                false => error(),
+            };
+        }
+        if let Some(path) = command.strip_prefix("AT+FSCREATE=") {
+            if self.files.contains_key(path) {
+                return error();
+            }
+            self.files.insert(path.to_string(), Vec::new());
+            return ok();
+        }
+        if let Some(path) = command.strip_prefix("AT+FSDEL=") {
+            return match self.files.remove(path) {
+                Some(_) => ok(),
+                None => error(),
+            };
+        }
+        if let Some(path) = command.strip_prefix("AT+FSFLSIZE=") {
+            return match self.files.get(path) {
+                Some(file) => with_ok(&format!("+FSFLSIZE: {}", file.len())),
+                None => error(),
+            };
+        }
+        if let Some(arguments) = command.strip_prefix("AT+FSWRITE=") {
+            let arguments: Vec<&str> = arguments.split(',').collect();
+            return match (
+                arguments.first(),
+                arguments.get(1),
+                arguments
+                    .get(2)
+                    .and_then(|length: &&str| length.parse().ok()),
+            ) {
+                (Some(path), Some(mode), Some(length))
+                    if self.files.contains_key(*path) && length <= 10240 =>
+                {
+                    self.file_data_expected = Some((path.to_string(), *mode == "1", length));
+                    "\r\n> ".to_string()
+                }
+                _ => error(),
+            };
+        }
+        if let Some(arguments) = command.strip_prefix("AT+HTTPTOFS=") {
+            let Some((_, path)) = arguments.split_once("\",\"") else {
+                return error();
+            };
+            if !self.http_active {
+                return error();
+            }
+            let status: u16 = match self.bearer_open {
+                true => {
+                    // about the size of an EPO file
+                    self.files
+                        .insert(path.trim_end_matches('"').to_string(), vec![0; 27648]);
+                    200
+                }
+                false => 601,
+            };
+            let length: usize = if status == 200 { 27648 } else { 0 };
+            self.schedule(
+                Duration::from_secs(1),
+                format!("\r\n+HTTPTOFS: {status},{length}\r\n"),
+            );
+            return ok();
+        }
+        if let Some(sms) = command.strip_prefix("AT+CMGS=") {
+            return self.send_sms(sms);
+        }
+        if let Some(index) = command.strip_prefix("AT+CMGD=") {
+            let index: Option<u8> = index.parse().ok();
+            let before: usize = self.inbox.len();
+            self.inbox
+                .retain(|message: &StoredMessage| Some(message.index) != index);
+            return match self.inbox.len() < before {
+                true => ok(),
+                false => "\r\n+CMS ERROR: 321\r\n".to_string(),
+            };
+        }
+        if let Some(storage) = command.strip_prefix("AT+CMGDA=") {
+            match storage.trim_matches('"') {
+                "DEL ALL" => self.inbox.clear(),
+                "DEL READ" => self.inbox.retain(|message: &StoredMessage| !message.read),
+                "DEL UNREAD" => self.inbox.retain(|message: &StoredMessage| message.read),
+                _ => return error(),
+            }
+            return ok();
+        }
+        if let Some(number) = command
+            .strip_prefix("ATD")
+            .and_then(|number: &str| number.strip_suffix(';'))
+        {
+            if !self.registered() {
+                return "\r\n+CME ERROR: 30\r\n".to_string();
+            }
+            self.outgoing_call = Some((number.to_string(), Instant::now()));
+            return ok();
+        }
+        if command.starts_with("AT+HTTPPARA=") {
+            return if self.http_active { ok() } else { error() };
+        }
+        if let Some(arguments) = command.strip_prefix("AT+HTTPDATA=") {
+            let length: Option<usize> = arguments
+                .split(',')
+                .next()
+                .and_then(|length: &str| length.parse().ok());
+            return match (self.http_active, length) {
+                (true, Some(length)) => {
+                    self.http_data_expected = Some(length);
+                    "\r\nDOWNLOAD\r\n".to_string()
+                }
+                _ => error(),
+            };
+        }
+        if let Some(qos) = command.strip_prefix("AT+CGQREQ=1,") {
+            self.qos_requested = Some(qos.to_string());
+            return ok();
+        }
+        if let Some(qos) = command.strip_prefix("AT+CGQMIN=1,") {
+            self.qos_minimum = Some(qos.to_string());
+            return ok();
+        }
+        if let Some(arguments) = command.strip_prefix("AT+HTTPREAD=") {
+            let (offset, length) = arguments.split_once(',').unwrap_or((arguments, ""));
+            let body: &[u8] = self.config.http_response.as_bytes();
+            let start: usize = offset.parse().unwrap_or(0).min(body.len());
+            let end: usize = length
+                .parse()
+                .map_or(body.len(), |length: usize| start + length)
+                .min(body.len());
+            let chunk: String = String::from_utf8_lossy(&body[start..end]).to_string();
+            return format!("\r\n+HTTPREAD: {}\r\n{chunk}\r\nOK\r\n", end - start);
+        }
+        if let Some(method) = command.strip_prefix("AT+HTTPACTION=") {
+            if !self.http_active {
+                return error();
+            }
+            let (status, length): (u16, usize) = match (self.bearer_open, method) {
+                (false, _) => (601, 0),
+                // HEAD
+                (true, "2") => (200, 0),
+                (true, _) => (200, self.config.http_response.len()),
+            };
+            self.schedule(
+                HTTP_ACTION_DELAY,
+                format!("\r\n+HTTPACTION: {method},{status},{length}\r\n"),
+            );
+            return ok();
+        }
+
+        error()
+    }
+
+    fn respond_bluetooth(&mut self, command: &str) -> String {
+        if !self.bluetooth_on {
+            return error();
+        }
+        if let Some(name) = command.strip_prefix("AT+BTHOST=") {
+            self.bluetooth_name = name.trim_matches('"').to_string();
+            return ok();
+        }
+        if let Some(visible) = command.strip_prefix("AT+BTVIS=") {
+            self.bluetooth_visible = visible == "1";
+            return ok();
+        }
+        if command.starts_with("AT+BTSCAN=1,") {
+            for (number, device) in BLUETOOTH_DEVICES.iter().enumerate() {
+                self.schedule(
+                    BLUETOOTH_SCAN_DELAY * (number as u32 + 1),
+                    format!("\r\n+BTSCAN: 0,{},{device}\r\n", number + 1),
+                );
+            }
+            self.schedule(
+                BLUETOOTH_SCAN_DELAY * (BLUETOOTH_DEVICES.len() as u32 + 1),
+                "\r\n+BTSCAN: 1\r\n".to_string(),
+            );
+            return ok();
+        }
+        if let Some(id) = command.strip_prefix("AT+BTGETPROF=") {
+            return match device_index(id) {
+                Some(_) => {
+                    "\r\n+BTGETPROF: 2,\"HFG\"\r\n+BTGETPROF: 4,\"SPP\"\r\n\r\nOK\r\n".to_string()
+                }
+                None => error(),
+            };
+        }
+        if let Some(arguments) = command.strip_prefix("AT+BTCONNECT=") {
+            let device: Option<usize> = arguments.split(',').next().and_then(device_index);
+            return match (device, self.bluetooth_connected) {
+                (Some(device), None) => self.connect_bluetooth(device),
+                _ => error(),
+            };
+        }
+        if command == "AT+BTACPT=1" || command == "AT+BTACPT=0" {
+            return match self.bluetooth_connection_request.take() {
+                Some(device) if command.ends_with('1') => self.connect_bluetooth(device),
+                Some(_) => ok(),
+                None => error(),
+            };
+        }
+        if command.starts_with("AT+BTDISCONN=") {
+            return match self.bluetooth_connected.take() {
+                Some(device) => {
+                    let (name, address) = bluetooth_device(device);
+                    self.schedule(
+                        BLUETOOTH_CONNECT_DELAY,
+                        format!("\r\n+BTDISCONN: {name},{address},\"SPP\"\r\n"),
+                    );
+                    ok()
+                }
+                None => error(),
+            };
+        }
+        if let Some(id) = command.strip_prefix("AT+BTPAIR=0,") {
+            return match device_index(id) {
+                Some(device) => {
+                    self.bluetooth_pairing = Some(device);
+                    let (name, address) = bluetooth_device(device);
+                    self.schedule(
+                        BLUETOOTH_CONNECT_DELAY,
+                        format!("\r\n+BTPAIRING: {name},{address},{BLUETOOTH_PASSKEY}\r\n"),
+                    );
+                    ok()
+                }
+                None => error(),
+            };
+        }
+        if command == "AT+BTPAIR=1,1" || command.starts_with("AT+BTPAIR=2,") {
+            let Some(device) = self.bluetooth_pairing.take() else {
+                return error();
+            };
+            if !self.bluetooth_paired.contains(&device) {
+                self.bluetooth_paired.push(device);
+            }
+            let (name, address) = bluetooth_device(device);
+            self.schedule(
+                BLUETOOTH_CONNECT_DELAY,
+                format!(
+                    "\r\n+BTPAIR: {},{name},{address}\r\n",
+                    self.bluetooth_paired.len()
+                ),
+            );
+            return ok();
+        }
+        if command == "AT+BTPAIR=1,0" {
+            return match self.bluetooth_pairing.take() {
+                Some(_) => ok(),
+                None => error(),
+            };
+        }
+        if let Some(id) = command.strip_prefix("AT+BTUNPAIR=") {
+            let id: Option<usize> = id.parse().ok();
+            return match id {
+                Some(0) => {
+                    self.bluetooth_paired.clear();
+                    ok()
+                }
+                Some(id) if id <= self.bluetooth_paired.len() => {
+                    self.bluetooth_paired.remove(id - 1);
+                    ok()
+                }
+                _ => error(),
+            };
+        }
+        if let Some(length) = command.strip_prefix("AT+BTSPPSEND=") {
+            return match (self.bluetooth_connected, length.parse().ok()) {
+                (Some(_), Some(length)) => {
+                    self.bluetooth_data_expected = Some(length);
+                    "\r\n> ".to_string()
+                }
+                _ => error(),
+            };
+        }
+        error()
+    }
+
+    /// `+BTSTATUS` followed by the paired (`P:`) and connected (`C:`) devices.
+    fn bluetooth_status(&self) -> String {
+        let mut status: String = format!("+BTSTATUS: {}", if self.bluetooth_on { 5 } else { 0 });
+        for (number, device) in self.bluetooth_paired.iter().enumerate() {
+            let (name, address) = bluetooth_device(*device);
+            status.push_str(&format!("\r\nP: {},{name},{address}", number + 1));
+        }
+        if let Some(device) = self.bluetooth_connected {
+            let (name, address) = bluetooth_device(device);
+            status.push_str(&format!("\r\nC: 1,{name},{address},\"SPP\""));
+        }
+        status
+    }
+
+    fn connect_bluetooth(&mut self, device: usize) -> String {
+        let (name, address) = bluetooth_device(device);
+        self.bluetooth_connected = Some(device);
+        self.schedule(
+            BLUETOOTH_CONNECT_DELAY,
+            format!("\r\n+BTCONNECT: 1,{name},{address},\"SPP\"\r\n"),
+        );
+        ok()
+    }
+
+    fn send_sms(&mut self, sms: &str) -> String {
+        let (number, text) = sms.split_once('\n').unwrap_or((sms, ""));
+        if !self.registered() {
+            return "\r\n+CMS ERROR: 331\r\n".to_string();
+        }
+        self.sent
+            .push((number.trim_matches('"').to_string(), text.to_string()));
+        let reference: usize = self.sent.len() % 256;
+        self.schedule(SMS_SEND_DELAY, with_ok(&format!("+CMGS: {reference}")));
+        String::new()
+    }
+
+    fn list_messages(&mut self, unread_only: bool) -> String {
+        let mut response: String = String::new();
+        for message in self.inbox.iter_mut() {
+            if unread_only && message.read {
+                continue;
+            }
+            response.push_str(&format!(
+                "\r\n+CMGL: {},\"{}\",\"{}\",\"\",\"{}\"\r\n{}",
+                message.index,
+                if message.read {
+                    "REC READ"
+                } else {
+                    "REC UNREAD"
+                },
+                message.sender,
+                message.received.format("%y/%m/%d,%H:%M:%S+00"),
+                message.text
+            ));
+            message.read = true;
+        }
+        response.push_str("\r\n\r\nOK\r\n");
+        response
+    }
+
+    fn registration(&self, command: &str, mode: u8) -> String {
+        match (self.registered(), mode) {
+            (true, 2) => format!("+{command}: 2,1,\"1A2B\",\"00C3\""),
+            (true, mode) => format!("+{command}: {mode},1"),
+            // searching, unless the RF is off
+            (false, mode) => format!("+{command}: {mode},{}", (self.functionality == 1) as u8 * 2),
+        }
+    }
+
+    /// `+CENG` of the engineering mode with the neighbour cells, the slots are empty until registered.
+    fn cells(&self) -> String {
+        let (serving, neighbor): (&str, &str) = match self.registered() {
+            true => (
+                "0024,45,32,001,01,31,00c3,10,05,1a2b,1",
+                "0018,30,26,00c4,001,01,1a2b",
+            ),
+            false => (
+                "0000,00,00,000,00,00,0000,00,00,0000,00",
+                "0000,00,00,0000,000,00,0000",
+            ),
+        };
+        format!("+CENG: 1,1\r\n\r\n+CENG: 0,\"{serving}\"\r\n+CENG: 1,\"{neighbor}\"\r\n+CENG: 2,\"0000,00,00,0000,000,00,0000\"")
+    }
+
+    fn operator(&self) -> String {
+        match (self.registered(), self.cops_format) {
+            (false, _) => "+COPS: 0".to_string(),
+            (true, 2) => "+COPS: 0,2,\"00101\"".to_string(),
+            (true, format) => format!("+COPS: 0,{format},\"Simulated Network\""),
+        }
+    }
+
+    fn set_clock(&mut self, datetime: &str) -> String {
+        let time: Option<NaiveDateTime> = datetime
+            .get(..17)
+            .and_then(|time: &str| NaiveDateTime::parse_from_str(time, "%y/%m/%d,%H:%M:%S").ok());
+        match (time, datetime.get(17..)) {
+            (Some(time), Some(timezone)) if timezone.parse::<i8>().is_ok() => {
+                self.rtc = Some((time - Utc::now().naive_utc(), timezone.to_string()));
+                ok()
+            }
+            _ => error(),
+        }
+    }
+
+    /// `+CCLK` - the RTC runs from its power-on default until it's synchronised.
+    fn clock(&self) -> String {
+        if let Some((offset, timezone)) = &self.rtc {
+            let time: NaiveDateTime = Utc::now().naive_utc() + *offset;
+            return format!("+CCLK: \"{}{timezone}\"", time.format("%y/%m/%d,%H:%M:%S"));
+        }
+        let time: String = match self.registered() || self.ntp_synchronised {
+            true => Utc::now().format("%y/%m/%d,%H:%M:%S").to_string(),
+            false => {
+                let powered_for: u64 = self
+                    .powered_since
+                    .map(|since: Instant| since.elapsed().as_secs())
+                    .unwrap_or_default();
+                format!(
+                    "04/01/01,00:{:02}:{:02}",
+                    powered_for / 60 % 60,
+                    powered_for % 60
+                )
+            }
+        };
+        format!("+CCLK: \"{time}+00\"")
+    }
+
+    /// Applies the PMTK command of the receiver, `false` if it isn't valid or known.
+    fn pmtk_command(&mut self, sentence: &str) -> bool {
+        let Some((body, _)) = sentence
+            .strip_prefix('$')
+            .and_then(|rest| rest.split_once('*'))
+        else {
+            return false;
+        };
+        if nmea_sentence(body) != format!("{sentence}\r\n") {
+            return false;
+        }
+        match body.split(',').collect::<Vec<&str>>().as_slice() {
+            ["PMTK353", _, glonass, ..] => self.glonass = *glonass == "1",
+            ["PMTK314", output @ ..] if output.len() == 19 => {
+                self.nmea_mask = Some(output.iter().map(|frequency| *frequency != "0").collect())
+            }
+            ["PMTK220", interval] => match interval.parse::<u64>() {
+                Ok(interval @ 100..=10000) => self.fix_interval = Duration::from_millis(interval),
+                _ => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    /// Loses the fix, the next one takes the `fix_delay` divided by the `speedup` of the restart.
+    fn restart_gnss(&mut self, speedup: u32) -> String {
+        if self.gnss_on_since.is_none() {
+            return error();
+        }
+        let kept: Duration = self.config.fix_delay - self.config.fix_delay / speedup;
+        let now: Instant = Instant::now();
+        self.gnss_on_since = Some(now.checked_sub(kept).unwrap_or(now));
+        ok()
+    }
+
+    fn epo_loaded(&self) -> bool {
+        self.files
+            .get("C:\\User\\EPO_GR_3_1.DAT")
+            .is_some_and(|file: &Vec<u8>| !file.is_empty())
+    }
+
+    /// Latitude, longitude and altitude of the fix, `None` until the receiver is fixed.
+    fn position(&self) -> Option<(f64, f64, f64)> {
+        let fixed_for: Duration = self
+            .gnss_on_since?
+            .elapsed()
+            .checked_sub(self.config.fix_delay)?;
+        // slowly ascending balloon, drifting north-east
+        let seconds: f64 = fixed_for.as_secs_f64();
+        Some((
+            self.config.start_lat + 0.00005 * seconds,
+            self.config.start_lon + 0.00008 * seconds,
+            100.0 + 5.0 * seconds,
+        ))
+    }
+
+    fn gnss_info(&self) -> String {
+        if self.gnss_on_since.is_none() {
+            return "+CGNSINF: 0,,,,,,,,,,,,,,,,,,,,".to_string();
+        }
+        let datetime: String = Utc::now().format("%Y%m%d%H%M%S%.3f").to_string();
+        let Some((lat, lon, alt)) = self.position() else {
+            return format!("+CGNSINF: 1,0,{datetime},,,,,,0,,,,,,8,0,,,,,");
+        };
+
+        // 12 satellites in view, 4 of them GLONASS, and 9 in use
+        let (in_view, in_use, glonass_in_use) = match self.glonass {
+            true => (12, 9, 4),
+            false => (8, 5, 0),
+        };
+        format!(
+            "+CGNSINF: 1,1,{},{:.6},{:.6},{:.3},{:.2},{:.1},3,,0.9,1.2,0.8,,{},{},{},,42,3.2,5.1",
+            datetime, lat, lon, alt, 28.8, 45.0, in_view, in_use, glonass_in_use
+        )
+    }
+
+    /// One second of the NMEA output - the position (once fixed) and the satellites in view.
+    fn nmea_output(&self) -> String {
+        let now: chrono::DateTime<Utc> = Utc::now();
+        let (time, date) = (now.format("%H%M%S%.3f"), now.format("%d%m%y"));
+        let mut sentences: Vec<String> = match self.position() {
+            Some((lat, lon, alt)) => {
+                let lat: String = nmea_coordinate(lat, 2, ('N', 'S'));
+                let lon: String = nmea_coordinate(lon, 3, ('E', 'W'));
+                vec![
+                    format!("GPGGA,{time},{lat},{lon},1,09,0.9,{alt:.1},M,0.0,M,,"),
+                    // 28.8 km/h in knots
+                    format!("GPRMC,{time},A,{lat},{lon},15.55,45.0,{date},,,A"),
+                ]
+            }
+            None => vec![format!("GPRMC,{time},V,,,,,,,{date},,,N")],
+        };
+        sentences.extend(GSV_SENTENCES.iter().map(|body| body.to_string()));
+        sentences
+            .iter()
+            .filter(|body| self.glonass || !body.starts_with("GL"))
+            .filter(|body| {
+                // GLL, RMC, VTG, GGA, GSA and GSV come first in `$PMTK314`
+                let position: Option<usize> = ["GLL", "RMC", "VTG", "GGA", "GSA", "GSV"]
+                    .iter()
+                    .position(|kind: &&str| body.get(2..5) == Some(*kind));
+                match (&self.nmea_mask, position) {
+                    (Some(mask), Some(position)) => mask[position],
+                    _ => true,
+                }
+            })
+            .map(|body| nmea_sentence(body))
+            .collect()
+    }
+}
+
+impl Simulator {
+    pub fn new(config: SimulatorConfig) -> Self {
+        Simulator {
+            state: Arc::new(Mutex::new(State {
+                echo: true,
+                saved_echo: true,
+                creg_mode: 0,
+                cgreg_mode: 0,
+                cops_format: 0,
+                baud_rate: config.baud_rate,
+                local_baud_rate: 115200,
+                config,
+                powered_since: Some(Instant::now()),
+                functionality: 1,
+                band: "EGSM_DCS_MODE".to_string(),
+                sleep_mode: false,
+                last_command: Instant::now(),
+                gnss_on_since: None,
+                gnss_reports: None,
+                nmea_output: None,
+                bearer_open: false,
+                http_active: false,
+                http_data_expected: None,
+                files: BTreeMap::new(),
+                file_data_expected: None,
+                gnss_assisted: false,
+                glonass: true,
+                nmea_mask: None,
+                fix_interval: NMEA_INTERVAL,
+                qos_requested: None,
+                qos_minimum: None,
+                ntp_synchronised: false,
+                rtc: None,
+                bluetooth_on: false,
+                bluetooth_name: "SIM868".to_string(),
+                bluetooth_visible: true,
+                bluetooth_connected: None,
+                bluetooth_connection_request: None,
+                bluetooth_data_expected: None,
+                bluetooth_paired: Vec::new(),
+                bluetooth_pairing: None,
+                spp_sent: Vec::new(),
+                outgoing_call: None,
+                ringing: false,
+                inbox: Vec::new(),
+                direct_delivery: false,
+                sent: Vec::new(),
+                input: Vec::new(),
+                output: VecDeque::new(),
+                scheduled: Vec::new(),
+            })),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect(MUTEX_POISONED_MSG)
+    }
+
+    /// Stores a new message in the inbox and notifies about it with the `+CMTI` URC, or passes it on with the
+    /// `+CMT` URC if direct delivery has been enabled (`AT+CNMI=2,2,0,0,0`).
+    pub fn receive_sms(&self, sender: &str, text: &str) {
+        let mut state = self.state();
+        if state.direct_delivery {
+            let received: String = Local::now().format("%y/%m/%d,%H:%M:%S+00").to_string();
+            // the line breaks of the text are sent as `\n`, the text is ended with `\r\n`
+            let text: String = text.replace("\r\n", "\n");
+            state.push(&format!(
+                "\r\n+CMT: \"{sender}\",\"\",\"{received}\"\r\n{text}\r\n"
+            ));
+            return;
+        }
+        let index: u8 = state
+            .inbox
+            .iter()
+            .map(|message: &StoredMessage| message.index)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        state.inbox.push(StoredMessage {
+            index,
+            read: false,
+            sender: sender.to_string(),
+            text: text.to_string(),
+            received: Local::now(),
+        });
+        state.push(&format!("\r\n+CMTI: \"SM\",{index}\r\n"));
+    }
+
+    /// Simulates an incoming call - sends the `RING` and `+CLIP` URCs.
+    pub fn ring(&self, caller_id: &str) {
+        let mut state = self.state();
+        state.ringing = true;
+        state.push(&format!(
+            "\r\nRING\r\n\r\n+CLIP: \"{caller_id}\",145,\"\",0,\"\",0\r\n"
+        ));
+    }
+
+    /// Simulates the GSM link being jammed, or the jamming stopping - sends the `+SJDR` URC.
+    pub fn jam(&self, jammed: bool) {
+        self.state().push(match jammed {
+            true => "\r\n+SJDR: JAMMING DETECTED\r\n",
+            false => "\r\n+SJDR: NO JAMMING\r\n",
+        });
+    }
+
+    /// Simulates the other side ending the outgoing call with the final `result_code`, eg. `BUSY` or
+    /// `NO CARRIER`.
+    pub fn end_outgoing_call(&self, result_code: &str) {
+        let mut state = self.state();
+        if state.outgoing_call.take().is_some() {
+            state.push(&format!("\r\n{result_code}\r\n"));
+        }
+    }
+
+    /// Messages sent with [`crate::sms::SMS::send`] as `(recipient, text)` pairs.
+    pub fn sent_messages(&self) -> Vec<(String, String)> {
+        self.state().sent.clone()
+    }
+
+    /// Simulates the scanned device `device_id` asking to connect - sends the `+BTCONNECTING` URC.
+    pub fn request_bluetooth_connection(&self, device_id: u8) {
+        let mut state = self.state();
+        let Some(device) = device_index(&device_id.to_string()) else {
+            return;
+        };
+        state.bluetooth_connection_request = Some(device);
+        let (_, address) = bluetooth_device(device);
+        state.push(&format!("\r\n+BTCONNECTING: \"{address}\",\"SPP\"\r\n"));
+    }
+
+    /// Simulates the scanned device `device_id` asking to pair - sends the `+BTPAIRING` URC.
+    pub fn request_bluetooth_pairing(&self, device_id: u8) {
+        let mut state = self.state();
+        let Some(device) = device_index(&device_id.to_string()) else {
+            return;
+        };
+        state.bluetooth_pairing = Some(device);
+        let (name, address) = bluetooth_device(device);
+        state.push(&format!(
+            "\r\n+BTPAIRING: {name},{address},{BLUETOOTH_PASSKEY}\r\n"
+        ));
+    }
+
+    /// Simulates the connected device sending `data` - sends the `+BTSPPDATA` URC.
+    pub fn receive_spp_data(&self, data: &str) {
+        let mut state = self.state();
+        if state.bluetooth_connected.is_some() {
+            state.push(&format!("\r\n+BTSPPDATA: 1,{},{data}\r\n", data.len()));
+        }
+    }
+
+    /// Data sent with [`crate::bluetooth::Bluetooth::send`].
+    pub fn sent_spp_data(&self) -> Vec<String> {
+        self.state().spp_sent.clone()
+    }
+
+    /// Whether the modem is powered, ie. it hasn't been turned off by `AT+CPOWD`.
+    pub fn is_powered(&self) -> bool {
+        self.state().powered_since.is_some()
+    }
+}
+
+impl Transport for Simulator {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let mut state = self.state();
+        state.release_scheduled();
+        if state.output.is_empty() {
+            drop(state);
+            sleep(READ_WAIT);
+            state = self.state();
+            state.release_scheduled();
+        }
+
+        let length: usize = buffer.len().min(state.output.len());
+        for (byte, read) in buffer.iter_mut().zip(state.output.drain(..length)) {
+            *byte = read;
+        }
+        Ok(length)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
+        let mut state = self.state();
+        if state.baud_rate != 0 && state.baud_rate != state.local_baud_rate {
+            // sent at another rate than the modem expects
+            return Ok(data.len());
+        }
+        state.input.extend_from_slice(data);
+        while let Some(command) = state.next_command() {
+            state.handle(command);
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self, queue: Queue) -> Result<(), Error> {
+        let mut state = self.state();
+        if matches!(queue, Queue::Input | Queue::Both) {
+            state.output.clear();
+        }
+        Ok(())
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Error> {
+        self.state().local_baud_rate = baud_rate;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "sms"))]
+mod tests {
+    use super::*;
+    use crate::{events::Event, LogLevelFilter, SIM868};
+
+    /// Message delivered with `+CMT`, its lines ended with `\n`.
+    const MULTI_LINE_TEXT: &str = "First line\nRING\n\nLast line";
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn delivers_multi_line_message() {
+        let simulator: Simulator = Simulator::new(SimulatorConfig::default());
+        let sim: SIM868 = SIM868::with_transport(simulator.clone(), LogLevelFilter::Off);
+        let mut events = sim.events();
+        sim.sms.set_direct_delivery_now(true).await.unwrap();
+
+        let delivered = sim.sms.get_delivered_message(Duration::from_secs(2));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        simulator.receive_sms("+4788888888", MULTI_LINE_TEXT);
+        let message = delivered.await.unwrap().unwrap();
+        assert_eq!(message.sender, "+4788888888");
+        assert_eq!(message.text, MULTI_LINE_TEXT);
+
+        loop {
+            match events.recv().await.unwrap() {
+                Event::SmsDelivered(message) => break assert_eq!(message.text, MULTI_LINE_TEXT),
+                Event::Ring => panic!("the text has been taken for a URC"),
+                _ => continue,
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn publishes_message_delivered_between_tasks() {
+        let simulator: Simulator = Simulator::new(SimulatorConfig::default());
+        let sim: SIM868 = SIM868::with_transport(simulator.clone(), LogLevelFilter::Off);
+        let mut events = sim.events();
+        sim.sms.set_direct_delivery_now(true).await.unwrap();
+
+        simulator.receive_sms("+4788888888", "Line one\r\nLine two");
+        let event = tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                if let Ok(Event::SmsDelivered(message)) = events.recv().await {
+                    return message;
+                }
+            }
+        });
+        assert_eq!(event.await.unwrap().text, "Line one\nLine two");
+    }
+}
@@ -1,47 +1,353 @@
 //! SMS module
 //!
 //! See [`SMS`] to discover available methods.
+//!
+//! MMS notifications (binary WAP push messages, listed as hex in the text mode) are decoded into
+//! [`Message::mms_notification`], with the URL the MMS can be downloaded from.
+//!
+//! With [`SMS::set_direct_delivery`], new messages aren't stored on the SIM at all - the modem passes them
+//! on in the `+CMT` URC, published as [`crate::events::Event::SmsDelivered`] and returned by
+//! [`SMS::get_delivered_message`].
 
+#[cfg(feature = "blocking")]
+use crate::blocking::block_on_task;
 use crate::{
+    command::{self, AtCommand},
     error::Error,
-    error_check, generic_resolver,
+    error_check, generic_resolver, join_task, nth_field, parse_field,
     serial_port::{spawn_task, SerialPort, TaskPriority},
-    Module, ResolverReturn, TaskJoinHandle, PARSING_ERROR, SMS_MESSAGE_SENT_REGEX,
+    Module, ResolverReturn, TaskJoinHandle, SMS_DELIVERED_MESSAGE_REGEX, SMS_MESSAGE_SENT_REGEX,
     SMS_READ_MESSAGE_REGEX,
 };
 use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
 use std::{sync::Arc, time::Duration};
 use uuid::Uuid;
 
-fn parse_message(captured: regex::Captures<'_>) -> Message {
-    let raw_data: &str = &captured["data"].to_string().trim().replace('"', "");
-    let parsed_data: &Vec<&str> = &raw_data.split(",").collect();
-    let raw_datetime: String = format!("{} {}", &parsed_data[3], &parsed_data[4][0..8]);
-    let date_time: DateTime<Local> = TimeZone::from_local_datetime(
-        &Local,
-        &NaiveDateTime::parse_from_str(&raw_datetime, "%y/%m/%d %H:%M:%S").expect(PARSING_ERROR),
-    )
-    .unwrap();
-    Message {
-        index: captured["index"].parse::<u8>().expect(PARSING_ERROR),
-        text: captured["text"].trim().to_string(),
-        sender: parsed_data[1].to_string(),
+/// `AT+CNMI` new message indications: stored and notified with `+CMTI`, or passed on with `+CMT`.
+const STORED_DELIVERY: [u8; 5] = [2, 1, 0, 0, 0];
+const DIRECT_DELIVERY: [u8; 5] = [2, 2, 0, 0, 0];
+
+/// Parses the `+CMGL` (with the `index`, status first) or `+CMT` (sender first) header `data` and the `text`.
+fn parse_message(index: Option<u8>, data: &str, text: &str) -> ResolverReturn<Message> {
+    let raw_data: &str = &data.trim().replace('"', "");
+    let parsed_data: &Vec<&str> = &raw_data.split(',').collect();
+    // the status precedes the sender in the list
+    let sender: usize = usize::from(index.is_some());
+    let date: &str = nth_field(parsed_data, sender + 2, "datetime")?;
+    // time is followed by the timezone, eg. 12:30:45+04
+    let time: &str = nth_field(parsed_data, sender + 3, "datetime")?;
+    let raw_datetime: String = format!("{} {}", date, time.get(0..8).unwrap_or(time));
+    let date_time: DateTime<Local> =
+        NaiveDateTime::parse_from_str(&raw_datetime, "%y/%m/%d %H:%M:%S")
+            .ok()
+            .and_then(|datetime: NaiveDateTime| {
+                TimeZone::from_local_datetime(&Local, &datetime).earliest()
+            })
+            .ok_or(Error::ParseFailure {
+                field: "datetime",
+                raw: raw_datetime,
+            })?;
+    let text: String = text.trim().to_string();
+    Ok(Message {
+        index,
+        mms_notification: parse_mms_notification(&text),
+        text,
+        sender: nth_field(parsed_data, sender, "sender")?.to_string(),
         datetime: date_time,
+    })
+}
+
+fn parse_stored_message(captured: regex::Captures<'_>) -> ResolverReturn<Message> {
+    let index: u8 = parse_field("index", &captured["index"])?;
+    parse_message(Some(index), &captured["data"], &captured["text"])
+}
+
+/// Parses the `+CMT` URC, the `header` line (without `+CMT: `) followed by the `text` line.
+pub(crate) fn parse_delivered_message(header: &str, text: &str) -> ResolverReturn<Message> {
+    parse_message(None, header, text)
+}
+
+/// Destination port of the WAP push messages.
+const WAP_PUSH_PORT: u16 = 2948;
+/// WSP PDU type of the push.
+const WSP_PUSH: u8 = 0x06;
+/// WSP content type `application/vnd.wap.mms-message`, as the well-known code and the text.
+const MMS_CONTENT_TYPE_CODE: u8 = 0x3E;
+const MMS_CONTENT_TYPE: &[u8] = b"application/vnd.wap.mms-message";
+/// `X-Mms-Message-Type` of the `m-notification-ind` PDU.
+const MMS_NOTIFICATION_IND: u8 = 0x82;
+
+/// Reader of the WSP (WAP-230) and MMS (OMA-WAP-MMS-ENC) encoded values.
+struct PduReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> PduReader<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.first().copied()
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        let (byte, rest) = self.bytes.split_first()?;
+        self.bytes = rest;
+        Some(*byte)
+    }
+
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        let taken: &[u8] = self.bytes.get(..length)?;
+        self.bytes = &self.bytes[length..];
+        Some(taken)
+    }
+
+    /// Variable length unsigned integer, 7 bits per byte.
+    fn uintvar(&mut self) -> Option<usize> {
+        let mut value: usize = 0;
+        for _ in 0..5 {
+            let byte: u8 = self.byte()?;
+            value = (value << 7) | usize::from(byte & 0x7F);
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn value_length(&mut self) -> Option<usize> {
+        match self.byte()? {
+            length @ 0..=30 => Some(usize::from(length)),
+            31 => self.uintvar(),
+            _ => None,
+        }
+    }
+
+    /// Null-terminated text, without the leading quote.
+    fn text(&mut self) -> Option<String> {
+        if self.peek() == Some(0x7F) {
+            self.byte();
+        }
+        let end: usize = self.bytes.iter().position(|byte: &u8| *byte == 0)?;
+        let text: String = String::from_utf8_lossy(&self.bytes[..end]).into_owned();
+        self.bytes = &self.bytes[end + 1..];
+        Some(text)
+    }
+
+    fn long_integer(&mut self) -> Option<u64> {
+        let length: u8 = self.byte().filter(|length: &u8| (1..=8).contains(length))?;
+        let bytes: &[u8] = self.take(usize::from(length))?;
+        Some(
+            bytes
+                .iter()
+                .fold(0, |value: u64, byte: &u8| (value << 8) | u64::from(*byte)),
+        )
+    }
+
+    /// Text, optionally preceded by the length and the charset (assumed to be ASCII or UTF-8).
+    fn encoded_string(&mut self) -> Option<String> {
+        if self.peek()? >= 32 {
+            return self.text();
+        }
+        let length: usize = self.value_length()?;
+        let mut value: PduReader = PduReader {
+            bytes: self.take(length)?,
+        };
+        match value.peek()? {
+            0x80..=0xFF => value.byte().map(|_| ())?,
+            _ => value.long_integer().map(|_| ())?,
+        }
+        value.text()
+    }
+
+    /// Skips a value of any type, telling them apart by the first byte.
+    fn skip_value(&mut self) -> Option<()> {
+        match self.peek()? {
+            0..=31 => {
+                let length: usize = self.value_length()?;
+                self.take(length).map(|_| ())
+            }
+            32..=127 => self.text().map(|_| ()),
+            _ => self.byte().map(|_| ()),
+        }
     }
 }
 
+/// Decodes the user data (UDH, WSP push and MMS headers) of an MMS notification, listed as hex.
+/// The notifications split into multiple SMS aren't supported.
+fn parse_mms_notification(text: &str) -> Option<MmsNotification> {
+    if text.len() % 2 != 0 || !text.bytes().all(|byte: u8| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let bytes: Vec<u8> = (0..text.len())
+        .step_by(2)
+        .map(|i: usize| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    let mut reader: PduReader = PduReader { bytes: &bytes };
+
+    // user data header with the 16 bit application port addressing
+    let header_length: usize = usize::from(reader.byte()?);
+    let mut header: PduReader = PduReader {
+        bytes: reader.take(header_length)?,
+    };
+    let mut port: Option<u16> = None;
+    while let Some(element) = header.byte() {
+        let length: usize = usize::from(header.byte()?);
+        let data: &[u8] = header.take(length)?;
+        match (element, data) {
+            (0x05, [high, low, ..]) => port = Some(u16::from_be_bytes([*high, *low])),
+            // concatenated message
+            (0x00, [_, parts, ..]) | (0x08, [_, _, parts, ..]) if *parts > 1 => return None,
+            _ => {}
+        }
+    }
+    if port != Some(WAP_PUSH_PORT) {
+        return None;
+    }
+
+    // WSP push, the transaction id is followed by the PDU type
+    reader.byte()?;
+    if reader.byte()? != WSP_PUSH {
+        return None;
+    }
+    let headers_length: usize = reader.uintvar()?;
+    let mut headers: PduReader = PduReader {
+        bytes: reader.take(headers_length)?,
+    };
+    let is_mms: bool = match headers.peek()? {
+        0x80..=0xFF => headers.byte()? & 0x7F == MMS_CONTENT_TYPE_CODE,
+        32..=127 => headers.text()?.as_bytes() == MMS_CONTENT_TYPE,
+        _ => {
+            let length: usize = headers.value_length()?;
+            let mut content_type: PduReader = PduReader {
+                bytes: headers.take(length)?,
+            };
+            match content_type.peek()? {
+                0x80..=0xFF => content_type.byte()? & 0x7F == MMS_CONTENT_TYPE_CODE,
+                _ => content_type.text()?.as_bytes() == MMS_CONTENT_TYPE,
+            }
+        }
+    };
+    if !is_mms {
+        return None;
+    }
+
+    let mut content_location: Option<String> = None;
+    let mut notification: MmsNotification = MmsNotification::default();
+    while let Some(field) = reader.byte() {
+        match field {
+            0x8C => {
+                if reader.byte()? != MMS_NOTIFICATION_IND {
+                    return None;
+                }
+            }
+            0x83 => content_location = reader.text(),
+            0x98 => notification.transaction_id = reader.text(),
+            0x96 => notification.subject = reader.encoded_string(),
+            0x8E => notification.size = reader.long_integer(),
+            0x89 => {
+                let length: usize = reader.value_length()?;
+                let mut from: PduReader = PduReader {
+                    bytes: reader.take(length)?,
+                };
+                // address-present-token, otherwise the address is hidden
+                if from.byte()? == 0x80 {
+                    notification.from = from.encoded_string().map(|address: String| {
+                        address
+                            .split("/TYPE=")
+                            .next()
+                            .unwrap_or_default()
+                            .to_string()
+                    });
+                }
+            }
+            0x80..=0xFF => reader.skip_value()?,
+            // application header, the name is followed by the value
+            _ => {
+                reader.text()?;
+                reader.skip_value()?;
+            }
+        }
+    }
+    notification.content_location = content_location?;
+    Some(notification)
+}
+
+/// Sets the text mode, unless it's known to be set already (see [`crate::state`]).
 fn set_text_mode(serial_port: &Arc<SerialPort>, task_id: &Uuid) -> ResolverReturn<()> {
     fn resolver(result: String) -> ResolverReturn<()> {
         generic_resolver(&result, Error::SmsProblemWithSettingTextMode)
     }
 
-    serial_port.process(task_id, "AT+CMGF=1\n".to_string(), resolver, None)
+    if serial_port.state().text_mode == Some(true) {
+        return Ok(());
+    }
+    let result: ResolverReturn<()> = serial_port.process(
+        task_id,
+        AtCommand::set("+CMGF").number(1).into(),
+        resolver,
+        None,
+    );
+    serial_port.update_state(|state| state.text_mode = result.as_ref().ok().map(|_| true));
+    result
 }
 
-fn send(
+/// Characters of a single SMS in the text mode.
+const SMS_LENGTH: usize = 160;
+
+/// Splits the text into chunks of up to `size` characters, breaking at a whitespace if there is one
+/// in the second half of the chunk.
+fn chunk_text(chars: &[char], size: usize) -> Vec<String> {
+    let mut chunks: Vec<String> = Vec::new();
+    let mut start: usize = 0;
+    while start < chars.len() {
+        let mut end: usize = (start + size).min(chars.len());
+        if end < chars.len() {
+            if let Some(space) = chars[start + size / 2..end]
+                .iter()
+                .rposition(|c: &char| c.is_whitespace())
+            {
+                end = start + size / 2 + space + 1;
+            }
+        }
+        chunks.push(
+            chars[start..end]
+                .iter()
+                .collect::<String>()
+                .trim_end()
+                .to_string(),
+        );
+        start = end;
+    }
+    chunks
+}
+
+/// Splits the text longer than a single SMS into the parts numbered `(1/3) `, `(2/3) `...
+fn split_text(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= SMS_LENGTH {
+        return vec![text.to_string()];
+    }
+
+    // the prefix grows with the number of parts, eg. "(1/9) " and "(1/10) "
+    let mut parts: usize = 2;
+    loop {
+        let prefix_length: usize = format!("({parts}/{parts}) ").len();
+        let chunks: Vec<String> = chunk_text(&chars, SMS_LENGTH - prefix_length);
+        if chunks.len() <= parts {
+            let total: usize = chunks.len();
+            return chunks
+                .iter()
+                .enumerate()
+                .map(|(index, chunk): (usize, &String)| format!("({}/{total}) {chunk}", index + 1))
+                .collect();
+        }
+        parts = chunks.len();
+    }
+}
+
+fn send_text(
     serial_port: &Arc<SerialPort>,
     task_id: &Uuid,
-    args: (String, String),
+    number: &str,
+    text: &str,
 ) -> ResolverReturn<()> {
     fn resolver(result: String) -> ResolverReturn<()> {
         if error_check(&result) {
@@ -53,52 +359,125 @@ fn send(
         }
     }
 
-    let (number, text) = args;
-
-    set_text_mode(&serial_port, &task_id)?;
     serial_port.process(
         task_id,
-        format!("AT+CMGS={number}\n{text}\x1A\n"),
+        format!(
+            "{}\n{}\x1A\n",
+            AtCommand::set("+CMGS").string(number),
+            command::data(text)
+        ),
         resolver,
-        Some(Duration::from_secs(20)),
+        Some(serial_port.timeouts.sms),
     )
 }
 
+fn send(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    args: (String, String),
+) -> ResolverReturn<()> {
+    let (number, text) = args;
+
+    set_text_mode(serial_port, task_id)?;
+    send_text(serial_port, task_id, &number, &text)
+}
+
+fn send_split(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    args: (String, String),
+) -> ResolverReturn<usize> {
+    let (number, text) = args;
+    let parts: Vec<String> = split_text(&text);
+
+    set_text_mode(serial_port, task_id)?;
+    if let [text] = parts.as_slice() {
+        return send_text(serial_port, task_id, &number, text).map(|_| 1);
+    }
+    for (sent, part) in parts.iter().enumerate() {
+        send_text(serial_port, task_id, &number, part).map_err(|error: Error| {
+            Error::SmsPartNotSent {
+                sent,
+                parts: parts.len(),
+                error: Box::new(error),
+            }
+        })?;
+    }
+    Ok(parts.len())
+}
+
 fn get_messages(
     serial_port: &Arc<SerialPort>,
     task_id: &Uuid,
     storage: MessageStorage,
 ) -> ResolverReturn<Vec<Message>> {
     fn resolver(result: String) -> ResolverReturn<Vec<Message>> {
-        let ok: Result<(), Error> = generic_resolver(&result, Error::SmsProblemWithReadingMessages);
-        if let Err(err) = ok {
-            return Err(err);
-        }
+        generic_resolver(&result, Error::SmsProblemWithReadingMessages)?;
 
-        let messages: Vec<Message> = SMS_READ_MESSAGE_REGEX
+        SMS_READ_MESSAGE_REGEX
             .captures_iter(&result)
-            .map(|captured: regex::Captures<'_>| parse_message(captured))
-            .collect();
-
-        Ok(messages)
+            .map(parse_stored_message)
+            .collect()
     }
 
-    set_text_mode(&serial_port, &task_id)?;
+    set_text_mode(serial_port, task_id)?;
     serial_port.process(
         task_id,
-        format!(
-            "AT+CMGL=\"{}\"\n",
-            if matches!(storage, MessageStorage::UNREAD) {
+        AtCommand::set("+CMGL")
+            .string(if matches!(storage, MessageStorage::UNREAD) {
                 "REC UNREAD"
             } else {
                 "ALL"
-            }
-        ),
+            })
+            .into(),
         resolver,
-        Some(Duration::from_secs(20)),
+        Some(serial_port.timeouts.sms),
     )
 }
 
+fn set_direct_delivery(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    enabled: bool,
+) -> ResolverReturn<()> {
+    fn resolver(result: String) -> ResolverReturn<()> {
+        generic_resolver(&result, Error::SmsDeliveryNotSet)
+    }
+
+    // the URC follows the message format
+    set_text_mode(serial_port, task_id)?;
+    let indications: [u8; 5] = if enabled {
+        DIRECT_DELIVERY
+    } else {
+        STORED_DELIVERY
+    };
+    let command: String = indications
+        .iter()
+        .fold(AtCommand::set("+CNMI"), |command: AtCommand, n: &u8| {
+            command.number(*n)
+        })
+        .into();
+    serial_port.process(task_id, command.clone(), resolver, None)?;
+    serial_port.persist_setting("CMGF", AtCommand::set("+CMGF").number(1).into());
+    serial_port.persist_setting("CNMI", command);
+    Ok(())
+}
+
+fn get_delivered_message(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    timeout: Duration,
+) -> ResolverReturn<Message> {
+    fn resolver(result: String) -> ResolverReturn<Message> {
+        let Some(captured) = SMS_DELIVERED_MESSAGE_REGEX.captures(&result) else {
+            return Err(Error::NotResolved);
+        };
+        parse_delivered_message(&captured["data"], &captured["text"])
+    }
+
+    serial_port.read(task_id, resolver, Some(timeout))
+}
+
 fn remove_all_messages(
     serial_port: &Arc<SerialPort>,
     task_id: &Uuid,
@@ -108,7 +487,7 @@ fn remove_all_messages(
         generic_resolver(&result, Error::SmsRemoveMessageFailed)
     }
 
-    set_text_mode(&serial_port, &task_id)?;
+    set_text_mode(serial_port, task_id)?;
 
     let msg_storage: &str = match storage {
         MessageStorage::ALL => "DEL ALL",
@@ -118,9 +497,9 @@ fn remove_all_messages(
 
     serial_port.process(
         task_id,
-        format!("AT+CMGDA=\"{msg_storage}\"\n"),
+        AtCommand::set("+CMGDA").string(msg_storage).into(),
         resolver,
-        Some(Duration::from_secs(30)),
+        Some(serial_port.timeouts.sms),
     )
 }
 
@@ -131,27 +510,49 @@ fn remove_message(serial_port: &Arc<SerialPort>, task_id: &Uuid, index: u8) -> R
 
     serial_port.process(
         task_id,
-        format!("AT+CMGD={index}\n"),
+        AtCommand::set("+CMGD").number(index).into(),
         resolver,
-        Some(Duration::from_secs(10)),
+        Some(serial_port.timeouts.sms),
     )
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MessageStorage {
     UNREAD,
     READ,
     ALL,
 }
 
-#[derive(Debug)]
+/// MMS notification (`m-notification-ind`) pushed over WAP, see [`Message::mms_notification`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MmsNotification {
+    /// URL the MMS is downloaded from, usually over the operator's MMS APN and WAP proxy.
+    pub content_location: String,
+    pub transaction_id: Option<String>,
+    /// Sender's number or e-mail, `None` if hidden.
+    pub from: Option<String>,
+    pub subject: Option<String>,
+    /// Size of the MMS in bytes.
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Message {
-    pub index: u8,
+    /// Index in the SIM storage, `None` if the message has been delivered directly (`+CMT`).
+    pub index: Option<u8>,
+    /// Text of the SMS, the hex encoded user data of the MMS notification.
     pub text: String,
     pub sender: String,
     pub datetime: DateTime<Local>,
+    /// Decoded MMS notification if the message is one.
+    pub mms_notification: Option<MmsNotification>,
 }
 
+/// The handle doesn't keep the connection open, see [`crate::SIM868`].
+#[derive(Clone)]
 pub struct SMS {
     serial_port: Arc<SerialPort>,
 }
@@ -165,13 +566,45 @@ impl Module for SMS {
 impl SMS {
     /// Sends an SMS up to 160 characters.
     pub fn send(&self, recipient: &str, text: &str) -> TaskJoinHandle<()> {
-        let number: String = format!(r#""{recipient}""#);
         spawn_task(
             self.serial_port.clone(),
             TaskPriority::NORMAL,
             send,
-            Some(format!("Sending SMS to {number}: {text}")),
-            (number, text.to_string()),
+            Some(format!("Sending SMS to \"{recipient}\": {text}")),
+            (recipient.to_string(), text.to_string()),
+        )
+    }
+
+    /// [`SMS::send`] with the `priority` instead of `NORMAL`, eg. `CRITICAL` for the alarm which should go
+    /// before the queued tasks. Same as `send(..).with_priority(priority)`, see [`TaskJoinHandle::with_priority`].
+    pub fn send_with_priority(
+        &self,
+        recipient: &str,
+        text: &str,
+        priority: TaskPriority,
+    ) -> TaskJoinHandle<()> {
+        self.send(recipient, text).with_priority(priority)
+    }
+
+    /// Sends the text longer than 160 characters as multiple SMS numbered `(1/3) `, `(2/3) `..., returning the number
+    /// of the parts. The parts are sent one after another in a single task, if any of them fails the error is
+    /// [`Error::SmsPartNotSent`]. The shorter text is sent as a single SMS, like with [`SMS::send`].
+    ///
+    /// The send isn't all-or-nothing: the parts sent before the failed one have been delivered and can't be
+    /// taken back, `sent` of the error tells how many. The following parts aren't sent, so the recipient gets
+    /// the beginning of the text only - send the rest again, eg. numbered from `sent`, if it matters.
+    ///
+    /// The text is split by the characters, not by the GSM 7-bit septets. The characters of the GSM extension
+    /// table (`^{}\\[~]|€`) take two septets each, so a part holding them may not fit a single SMS, and with
+    /// the modem in the UCS2 character set (`AT+CSCS="UCS2"`) only 70 characters fit into one - such a part
+    /// fails to send.
+    pub fn send_split(&self, recipient: &str, text: &str) -> TaskJoinHandle<usize> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            send_split,
+            Some(format!("Sending split SMS to \"{recipient}\": {text}")),
+            (recipient.to_string(), text.to_string()),
         )
     }
 
@@ -207,4 +640,291 @@ impl SMS {
             index,
         )
     }
+
+    /// Makes the modem pass the new messages on in the `+CMT` URC without storing them (`AT+CNMI=2,2,0,0,0`),
+    /// or store them and notify with `+CMTI` (`AT+CNMI=2,1,0,0,0`) when not `enabled`. The setting is restored
+    /// by [`crate::hat::Hat::init`].
+    ///
+    /// ⚠️ The URCs are only read while a task talks to the modem, so the messages delivered while the serial
+    /// port is idle are lost - keep [`SMS::get_delivered_message`] listening.
+    pub fn set_direct_delivery(&self, enabled: bool) -> TaskJoinHandle<()> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            set_direct_delivery,
+            Some(match enabled {
+                true => "Enabling direct delivery of messages...".to_string(),
+                false => "Storing new messages on the SIM...".to_string(),
+            }),
+            enabled,
+        )
+    }
+
+    /// Waits up to the `timeout` for the message delivered directly, see [`SMS::set_direct_delivery`].
+    pub fn get_delivered_message(&self, timeout: Duration) -> TaskJoinHandle<Message> {
+        spawn_task(
+            self.serial_port.clone(),
+            TaskPriority::NORMAL,
+            get_delivered_message,
+            None,
+            timeout,
+        )
+    }
+}
+
+impl SMS {
+    /// [`SMS::send`] returning the result directly.
+    pub async fn send_now(&self, recipient: &str, text: &str) -> ResolverReturn<()> {
+        join_task(self.send(recipient, text)).await
+    }
+
+    /// [`SMS::send_with_priority`] returning the result directly.
+    pub async fn send_with_priority_now(
+        &self,
+        recipient: &str,
+        text: &str,
+        priority: TaskPriority,
+    ) -> ResolverReturn<()> {
+        join_task(self.send_with_priority(recipient, text, priority)).await
+    }
+
+    /// [`SMS::send_split`] returning the result directly.
+    pub async fn send_split_now(&self, recipient: &str, text: &str) -> ResolverReturn<usize> {
+        join_task(self.send_split(recipient, text)).await
+    }
+
+    /// [`SMS::get_messages`] returning the result directly.
+    pub async fn get_messages_now(&self, storage: MessageStorage) -> ResolverReturn<Vec<Message>> {
+        join_task(self.get_messages(storage)).await
+    }
+
+    /// [`SMS::remove_all_messages`] returning the result directly.
+    pub async fn remove_all_messages_now(&self, storage: MessageStorage) -> ResolverReturn<()> {
+        join_task(self.remove_all_messages(storage)).await
+    }
+
+    /// [`SMS::remove_message`] returning the result directly.
+    pub async fn remove_message_now(&self, index: u8) -> ResolverReturn<()> {
+        join_task(self.remove_message(index)).await
+    }
+
+    /// [`SMS::set_direct_delivery`] returning the result directly.
+    pub async fn set_direct_delivery_now(&self, enabled: bool) -> ResolverReturn<()> {
+        join_task(self.set_direct_delivery(enabled)).await
+    }
+
+    /// [`SMS::get_delivered_message`] returning the result directly.
+    pub async fn get_delivered_message_now(&self, timeout: Duration) -> ResolverReturn<Message> {
+        join_task(self.get_delivered_message(timeout)).await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl SMS {
+    /// Blocking version of [`SMS::send`].
+    pub fn send_blocking(&self, recipient: &str, text: &str) -> ResolverReturn<()> {
+        block_on_task(|| self.send(recipient, text))
+    }
+
+    /// Blocking version of [`SMS::send_with_priority`].
+    pub fn send_with_priority_blocking(
+        &self,
+        recipient: &str,
+        text: &str,
+        priority: TaskPriority,
+    ) -> ResolverReturn<()> {
+        block_on_task(|| self.send_with_priority(recipient, text, priority))
+    }
+
+    /// Blocking version of [`SMS::send_split`].
+    pub fn send_split_blocking(&self, recipient: &str, text: &str) -> ResolverReturn<usize> {
+        block_on_task(|| self.send_split(recipient, text))
+    }
+
+    /// Blocking version of [`SMS::get_messages`].
+    pub fn get_messages_blocking(&self, storage: MessageStorage) -> ResolverReturn<Vec<Message>> {
+        block_on_task(|| self.get_messages(storage))
+    }
+
+    /// Blocking version of [`SMS::remove_all_messages`].
+    pub fn remove_all_messages_blocking(&self, storage: MessageStorage) -> ResolverReturn<()> {
+        block_on_task(|| self.remove_all_messages(storage))
+    }
+
+    /// Blocking version of [`SMS::remove_message`].
+    pub fn remove_message_blocking(&self, index: u8) -> ResolverReturn<()> {
+        block_on_task(|| self.remove_message(index))
+    }
+
+    /// Blocking version of [`SMS::set_direct_delivery`].
+    pub fn set_direct_delivery_blocking(&self, enabled: bool) -> ResolverReturn<()> {
+        block_on_task(|| self.set_direct_delivery(enabled))
+    }
+
+    /// Blocking version of [`SMS::get_delivered_message`].
+    pub fn get_delivered_message_blocking(&self, timeout: Duration) -> ResolverReturn<Message> {
+        block_on_task(|| self.get_delivered_message(timeout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// User data header addressing the WAP push port, followed by the WSP push of `application/vnd.wap.mms-message`.
+    const PUSH: &[u8] = &[
+        0x06, 0x05, 0x04, 0x0B, 0x84, 0x23, 0xF0, 0x01, 0x06, 0x03, 0xBE, 0xAF, 0x84,
+    ];
+
+    fn hex(parts: &[&[u8]]) -> String {
+        parts
+            .concat()
+            .iter()
+            .map(|byte: &u8| format!("{byte:02X}"))
+            .collect()
+    }
+
+    #[test]
+    fn decodes_mms_notification() {
+        let text: String = hex(&[
+            PUSH,
+            &[0x8C, 0x82, 0x98],
+            b"T1\0",
+            &[0x8D, 0x90, 0x89, 0x17, 0x80],
+            b"+4712345678/TYPE=PLMN\0",
+            &[0x96],
+            b"Hello\0",
+            // message class, size, expiry
+            &[
+                0x8A, 0x80, 0x8E, 0x02, 0x1F, 0x40, 0x88, 0x05, 0x81, 0x03, 0x03, 0xF4, 0x80, 0x83,
+            ],
+            b"http://mms.example.com/m1\0",
+        ]);
+        assert_eq!(
+            parse_mms_notification(&text),
+            Some(MmsNotification {
+                content_location: "http://mms.example.com/m1".into(),
+                transaction_id: Some("T1".into()),
+                from: Some("+4712345678".into()),
+                subject: Some("Hello".into()),
+                size: Some(8000),
+            })
+        );
+    }
+
+    #[test]
+    fn decodes_mms_notification_with_charset_and_hidden_sender() {
+        let push: &[u8] = &[
+            &[
+                0x0B, 0x00, 0x03, 0x2A, 0x01, 0x01, 0x05, 0x04, 0x0B, 0x84, 0x23, 0xF0, 0x01, 0x06,
+                0x20,
+            ][..],
+            b"application/vnd.wap.mms-message\0",
+        ]
+        .concat();
+        let text: String = hex(&[
+            push,
+            &[0x8C, 0x82, 0x89, 0x01, 0x81, 0x96, 0x07, 0xEA],
+            b"Cze\xC5\x9B\0",
+            &[0x83],
+            b"http://mms.example.com/m2\0",
+        ]);
+        let notification: MmsNotification = parse_mms_notification(&text).unwrap();
+        assert_eq!(notification.from, None);
+        assert_eq!(notification.subject.as_deref(), Some("Cześ"));
+        assert_eq!(notification.content_location, "http://mms.example.com/m2");
+    }
+
+    #[test]
+    fn ignores_other_messages() {
+        let location: &[u8] = b"\x83http://mms.example.com/m1\0";
+        // plain text, other port, other PDU and content type, split into 2 parts, no content location
+        assert_eq!(parse_mms_notification("Hello"), None);
+        assert_eq!(parse_mms_notification("0605040B8"), None);
+        let other_port: &[u8] = &[
+            0x06, 0x05, 0x04, 0x0B, 0x85, 0x23, 0xF0, 0x01, 0x06, 0x03, 0xBE, 0xAF, 0x84,
+        ];
+        assert_eq!(
+            parse_mms_notification(&hex(&[other_port, &[0x8C, 0x82], location])),
+            None
+        );
+        let other_type: &[u8] = &[
+            0x06, 0x05, 0x04, 0x0B, 0x84, 0x23, 0xF0, 0x01, 0x06, 0x01, 0x83,
+        ];
+        assert_eq!(
+            parse_mms_notification(&hex(&[other_type, &[0x8C, 0x82], location])),
+            None
+        );
+        let split: &[u8] = &[
+            0x0B, 0x00, 0x03, 0x2A, 0x02, 0x01, 0x05, 0x04, 0x0B, 0x84, 0x23, 0xF0, 0x01, 0x06,
+            0x03, 0xBE, 0xAF, 0x84,
+        ];
+        assert_eq!(
+            parse_mms_notification(&hex(&[split, &[0x8C, 0x82], location])),
+            None
+        );
+        assert_eq!(
+            parse_mms_notification(&hex(&[PUSH, &[0x8C, 0x82, 0x98], b"T1\0"])),
+            None
+        );
+        // m-retrieve-conf
+        assert_eq!(
+            parse_mms_notification(&hex(&[PUSH, &[0x8C, 0x84], location])),
+            None
+        );
+    }
+
+    #[test]
+    fn keeps_single_message() {
+        let text: String = "a".repeat(SMS_LENGTH);
+        assert_eq!(split_text(&text), [text]);
+    }
+
+    #[test]
+    fn splits_over_single_message() {
+        let parts: Vec<String> = split_text(&"a".repeat(SMS_LENGTH + 1));
+        assert_eq!(
+            parts,
+            [
+                format!("(1/2) {}", "a".repeat(154)),
+                format!("(2/2) {}", "a".repeat(7))
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_at_whitespace() {
+        let text: String = format!("{} {}", "a".repeat(100), "b".repeat(100));
+        assert_eq!(
+            split_text(&text),
+            [
+                format!("(1/2) {}", "a".repeat(100)),
+                format!("(2/2) {}", "b".repeat(100))
+            ]
+        );
+    }
+
+    #[test]
+    fn widens_prefix_from_nine_to_ten_parts() {
+        // 9 parts of 154 characters after "(n/9) "
+        let nine: Vec<String> = split_text(&"a".repeat(9 * 154));
+        assert_eq!(nine.len(), 9);
+        assert!(nine
+            .iter()
+            .all(|part: &String| part.chars().count() == SMS_LENGTH));
+        assert!(nine[8].starts_with("(9/9) a"));
+
+        // one more character needs the 10th part, which leaves 152 characters after "(n/10) "
+        let ten: Vec<String> = split_text(&"a".repeat(9 * 154 + 1));
+        assert_eq!(ten.len(), 10);
+        assert!(ten
+            .iter()
+            .all(|part: &String| part.chars().count() <= SMS_LENGTH));
+        assert!(ten[0].starts_with("(1/10) a"));
+        assert_eq!(ten[0].chars().count(), SMS_LENGTH - 1);
+        assert_eq!(
+            ten[9],
+            format!("(10/10) {}", "a".repeat(9 * 154 + 1 - 9 * 152))
+        );
+    }
 }
@@ -0,0 +1,60 @@
+//! Snapshot module
+//!
+//! [`crate::SIM868::snapshot`] gathers the state of the modem in one queued task, eg. for the heartbeat
+//! of a telemetry node. With the `serde` feature, the [`Snapshot`] can be serialised and uploaded as is.
+
+#[cfg(feature = "gnss")]
+use crate::gnss::GNSSData;
+use crate::{
+    hat::{
+        battery, registration_status, signal_strength, Battery, RegistrationState, SignalStrength,
+    },
+    serial_port::SerialPort,
+    ResolverReturn,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use uuid::Uuid;
+
+/// State of the modem returned by [`crate::SIM868::snapshot`]. Values which can't be read are `None`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    /// GNSS fix, `None` if the module is off or the position isn't fixed.
+    #[cfg(feature = "gnss")]
+    pub gnss: Option<GNSSData>,
+    pub signal: SignalStrength,
+    /// `+CREG` network registration state.
+    pub registration: Option<RegistrationState>,
+    pub battery: Option<Battery>,
+    /// Time since the connection to the modem has been opened.
+    pub uptime: Duration,
+    /// UTC Unix timestamp in seconds.
+    pub timestamp: u64,
+}
+
+pub(crate) fn snapshot(
+    serial_port: &Arc<SerialPort>,
+    task_id: &Uuid,
+    _: (),
+) -> ResolverReturn<Snapshot> {
+    // the signal strength fails if the modem doesn't respond at all
+    let signal: SignalStrength = signal_strength(serial_port, task_id, ())?;
+
+    Ok(Snapshot {
+        #[cfg(feature = "gnss")]
+        gnss: crate::gnss::get_data(serial_port, task_id, ()).ok(),
+        signal,
+        registration: registration_status(serial_port, task_id, ())
+            .ok()
+            .map(RegistrationState::from_status),
+        battery: battery(serial_port, task_id, ()).ok(),
+        uptime: serial_port.opened_at.elapsed(),
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch: Duration| since_epoch.as_secs())
+            .unwrap_or_default(),
+    })
+}
@@ -0,0 +1,30 @@
+//! State module
+//!
+//! The modules keep track of the modes they set (eg. the SMS text mode) in the [`ModemState`] shared by all the
+//! tasks, so the commands re-setting the mode the modem is already in are skipped - `AT+CMGF=1` isn't sent
+//! before every SMS operation, the bearer status isn't queried before every HTTP request once it's known.
+//! The state is forgotten when the modem is power-cycled by the crate (eg. [`crate::hat::Hat::turn_off`]); call
+//! [`crate::SIM868::invalidate_state`] after the modem has been restarted otherwise (eg. [`crate::events::Event::Ready`]).
+
+/// Modes of the modem known to the crate, `None` - not known.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModemState {
+    /// SMS text mode (`AT+CMGF=1`).
+    pub text_mode: Option<bool>,
+    /// GNSS module power (`AT+CGNSPWR`).
+    pub gnss_power: Option<bool>,
+    /// NMEA output on the serial port (`AT+CGNSTST`).
+    pub nmea_output: Option<bool>,
+    /// GPRS bearer of the HTTP requests (`AT+SAPBR`).
+    pub bearer_open: Option<bool>,
+    /// HTTP service (`AT+HTTPINIT` / `AT+HTTPTERM`).
+    pub http_active: Option<bool>,
+}
+
+impl ModemState {
+    /// Forgets everything, eg. after the modem has been restarted.
+    pub fn invalidate(&mut self) {
+        *self = ModemState::default();
+    }
+}
@@ -0,0 +1,667 @@
+//! Testing module
+//!
+//! Available with the `testing` feature.
+//!
+//! [`RecordingTransport`] wraps the real [`Transport`] and captures the session as a [`Transcript`],
+//! which [`ReplayTransport`] plays back later - so the code driving the modem can be tested in CI against
+//! the responses of a real device. [`MockTransport`] answers the commands with the scripted responses instead,
+//! with delays, errors and URCs to exercise the timeout and error paths.
+//!
+//! Transcripts are saved as text, one entry per line: `>` for the data written to the modem, `<` for the data
+//! received from it. Control characters are escaped (`\r`, `\n`, `\t`, `\\`, `\xNN`), empty lines and lines
+//! starting with `#` are skipped.
+//!
+//! ```
+//! use rpi_sim868::{
+//!     testing::{ReplayTransport, Transcript},
+//!     LogLevelFilter, SIM868,
+//! };
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), rpi_sim868::Error> {
+//!     // usually recorded on the device with `RecordingTransport` and loaded by `Transcript::load`
+//!     let transcript: Transcript = "> AT\\n\n< \\r\\nOK\\r\\n\n".parse()?;
+//!     let replay: ReplayTransport = ReplayTransport::new(transcript);
+//!     let sim: SIM868 = SIM868::with_transport(replay.clone(), LogLevelFilter::Off);
+//!
+//!     assert!(sim.hat.is_on().await??);
+//!     assert!(replay.is_finished());
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{
+    error::Error,
+    transport::{Queue, Transport},
+};
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    io,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+const MUTEX_POISONED_MSG: &str = "Critical error: Mutex is poisoned.";
+/// Time the replay read waits when there is no data, similar to the UART read timeout.
+const READ_WAIT: Duration = Duration::from_millis(10);
+
+/// Single exchange of the recorded session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Entry {
+    /// Data written to the modem.
+    Sent(Vec<u8>),
+    /// Data received from the modem in one burst, ie. until the read has timed out.
+    Received(Vec<u8>),
+}
+
+/// Recorded session, see the [module](self) documentation for the text format.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transcript {
+    pub entries: Vec<Entry>,
+}
+
+fn escape(data: &[u8]) -> String {
+    let mut escaped: String = String::new();
+    for byte in data {
+        match byte {
+            b'\r' => escaped.push_str("\\r"),
+            b'\n' => escaped.push_str("\\n"),
+            b'\t' => escaped.push_str("\\t"),
+            b'\\' => escaped.push_str("\\\\"),
+            0x20..=0x7E => escaped.push(*byte as char),
+            _ => {
+                write!(escaped, "\\x{byte:02X}").expect("Critical error: writing to String failed.")
+            }
+        }
+    }
+    escaped
+}
+
+fn unescape(line: &str) -> Result<Vec<u8>, Error> {
+    let parse_failure = || Error::ParseFailure {
+        field: "transcript_entry",
+        raw: line.to_string(),
+    };
+    let mut data: Vec<u8> = Vec::new();
+    let mut bytes = line.bytes();
+
+    while let Some(byte) = bytes.next() {
+        if byte != b'\\' {
+            data.push(byte);
+            continue;
+        }
+        match bytes.next().ok_or_else(parse_failure)? {
+            b'r' => data.push(b'\r'),
+            b'n' => data.push(b'\n'),
+            b't' => data.push(b'\t'),
+            b'\\' => data.push(b'\\'),
+            b'x' => {
+                let hex: Vec<u8> = bytes.by_ref().take(2).collect();
+                let hex: &str = std::str::from_utf8(&hex).map_err(|_| parse_failure())?;
+                data.push(u8::from_str_radix(hex, 16).map_err(|_| parse_failure())?);
+            }
+            _ => return Err(parse_failure()),
+        }
+    }
+
+    Ok(data)
+}
+
+impl Transcript {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        Ok(std::fs::write(path, self.to_string())?)
+    }
+
+    fn push(&mut self, entry: Entry) {
+        match (self.entries.last_mut(), entry) {
+            (Some(Entry::Sent(last)), Entry::Sent(data)) => last.extend(data),
+            (_, entry) => self.entries.push(entry),
+        }
+    }
+}
+
+impl std::fmt::Display for Transcript {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for entry in &self.entries {
+            match entry {
+                Entry::Sent(ref data) => writeln!(f, "> {}", escape(data))?,
+                Entry::Received(ref data) => writeln!(f, "< {}", escape(data))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Transcript {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut transcript: Transcript = Transcript::default();
+        for line in text.lines() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry: Entry = match (line.get(..2), line.get(2..)) {
+                (Some("> "), Some(data)) => Entry::Sent(unescape(data)?),
+                (Some("< "), Some(data)) => Entry::Received(unescape(data)?),
+                _ => {
+                    return Err(Error::ParseFailure {
+                        field: "transcript_entry",
+                        raw: line.to_string(),
+                    })
+                }
+            };
+            transcript.entries.push(entry);
+        }
+        Ok(transcript)
+    }
+}
+
+struct Recording<T> {
+    inner: T,
+    transcript: Transcript,
+    burst: Vec<u8>,
+}
+
+impl<T> Recording<T> {
+    fn end_burst(&mut self) {
+        if !self.burst.is_empty() {
+            let burst: Vec<u8> = std::mem::take(&mut self.burst);
+            self.transcript.push(Entry::Received(burst));
+        }
+    }
+}
+
+/// [`Transport`] recording the session of the wrapped one.
+///
+/// Clones share the recording, so keep one to get the [`Transcript`] once the other is passed to [`crate::SIM868`].
+pub struct RecordingTransport<T> {
+    recording: Arc<Mutex<Recording<T>>>,
+}
+
+impl<T> Clone for RecordingTransport<T> {
+    fn clone(&self) -> Self {
+        RecordingTransport {
+            recording: self.recording.clone(),
+        }
+    }
+}
+
+impl<T: Transport> RecordingTransport<T> {
+    pub fn new(inner: T) -> Self {
+        RecordingTransport {
+            recording: Arc::new(Mutex::new(Recording {
+                inner,
+                transcript: Transcript::default(),
+                burst: Vec::new(),
+            })),
+        }
+    }
+
+    fn recording(&self) -> std::sync::MutexGuard<'_, Recording<T>> {
+        self.recording.lock().expect(MUTEX_POISONED_MSG)
+    }
+
+    /// Returns the session recorded so far.
+    pub fn transcript(&self) -> Transcript {
+        let recording = self.recording();
+        let mut transcript: Transcript = recording.transcript.clone();
+        if !recording.burst.is_empty() {
+            transcript.push(Entry::Received(recording.burst.clone()));
+        }
+        transcript
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.transcript().save(path)
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let mut recording = self.recording();
+        let length: usize = recording.inner.read(buffer)?;
+        match length {
+            0 => recording.end_burst(),
+            _ => recording.burst.extend_from_slice(&buffer[..length]),
+        }
+        Ok(length)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
+        let mut recording = self.recording();
+        recording.end_burst();
+        let length: usize = recording.inner.write(data)?;
+        recording
+            .transcript
+            .push(Entry::Sent(data[..length].to_vec()));
+        Ok(length)
+    }
+
+    fn flush(&mut self, queue: Queue) -> Result<(), Error> {
+        self.recording().inner.flush(queue)
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Error> {
+        self.recording().inner.set_baud_rate(baud_rate)
+    }
+}
+
+struct Replay {
+    entries: VecDeque<Entry>,
+    pending: VecDeque<u8>,
+    burst_ended: bool,
+}
+
+/// [`Transport`] playing back a recorded [`Transcript`].
+///
+/// Data written to it has to match the recorded one, otherwise the write fails with [`Error::Io`].
+/// Every recorded burst is followed by a read returning `0`, as if the UART read has timed out.
+/// Clones share the playback, so keep one to check [`ReplayTransport::is_finished`].
+#[derive(Clone)]
+pub struct ReplayTransport {
+    replay: Arc<Mutex<Replay>>,
+}
+
+impl ReplayTransport {
+    pub fn new(transcript: Transcript) -> Self {
+        ReplayTransport {
+            replay: Arc::new(Mutex::new(Replay {
+                entries: transcript.entries.into(),
+                pending: VecDeque::new(),
+                burst_ended: true,
+            })),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(ReplayTransport::new(Transcript::load(path)?))
+    }
+
+    fn replay(&self) -> std::sync::MutexGuard<'_, Replay> {
+        self.replay.lock().expect(MUTEX_POISONED_MSG)
+    }
+
+    /// Whether the whole transcript has been played back.
+    pub fn is_finished(&self) -> bool {
+        let replay = self.replay();
+        replay.entries.is_empty() && replay.pending.is_empty()
+    }
+
+    /// Returns the part of the transcript which hasn't been played back yet.
+    pub fn remaining(&self) -> Transcript {
+        Transcript {
+            entries: self.replay().entries.iter().cloned().collect(),
+        }
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let mut replay = self.replay();
+        if replay.pending.is_empty() {
+            if !replay.burst_ended {
+                replay.burst_ended = true;
+                return Ok(0);
+            }
+            match replay.entries.front() {
+                Some(Entry::Received(_)) => {
+                    if let Some(Entry::Received(data)) = replay.entries.pop_front() {
+                        replay.pending.extend(data);
+                        replay.burst_ended = false;
+                    }
+                }
+                _ => {
+                    drop(replay);
+                    sleep(READ_WAIT);
+                    return Ok(0);
+                }
+            }
+        }
+
+        let length: usize = buffer.len().min(replay.pending.len());
+        for (byte, read) in buffer.iter_mut().zip(replay.pending.drain(..length)) {
+            *byte = read;
+        }
+        Ok(length)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
+        let mut replay = self.replay();
+        // data received, but not read during the recording has been flushed
+        replay.pending.clear();
+        replay.burst_ended = true;
+        while let Some(Entry::Received(_)) = replay.entries.front() {
+            replay.entries.pop_front();
+        }
+
+        let expected: Option<Entry> = replay.entries.pop_front();
+        match expected {
+            Some(Entry::Sent(ref expected)) if expected.starts_with(data) => {
+                if expected.len() > data.len() {
+                    replay
+                        .entries
+                        .push_front(Entry::Sent(expected[data.len()..].to_vec()));
+                }
+                Ok(data.len())
+            }
+            _ => {
+                let expected: String = match expected {
+                    Some(Entry::Sent(ref expected)) => escape(expected),
+                    _ => "end of the transcript".to_string(),
+                };
+                Err(Error::Io(Arc::new(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "replay mismatch - expected: {expected}, written: {}",
+                        escape(data)
+                    ),
+                ))))
+            }
+        }
+    }
+
+    fn flush(&mut self, _: Queue) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// The baud rate isn't recorded, so the playback goes on at any rate.
+    fn set_baud_rate(&mut self, _: u32) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Response of [`MockTransport`] to a scripted command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockResponse {
+    data: Vec<u8>,
+    delay: Duration,
+}
+
+impl MockResponse {
+    /// Response of the `lines`, each framed with `\r\n` like the modem's, eg. `&["+CSQ: 20,0", "OK"]`.
+    pub fn lines(lines: &[&str]) -> Self {
+        MockResponse::raw(
+            &lines
+                .iter()
+                .map(|line| format!("\r\n{line}\r\n"))
+                .collect::<String>(),
+        )
+    }
+
+    /// Response sent exactly as `data`, eg. the `> ` prompt of `AT+CMGS`.
+    pub fn raw(data: &str) -> Self {
+        MockResponse {
+            data: data.as_bytes().to_vec(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn ok() -> Self {
+        MockResponse::lines(&["OK"])
+    }
+
+    pub fn error() -> Self {
+        MockResponse::lines(&["ERROR"])
+    }
+
+    /// `+CME ERROR: <code>`, see [`crate::error::CmeError`].
+    pub fn cme(code: u16) -> Self {
+        MockResponse::lines(&[&format!("+CME ERROR: {code}")])
+    }
+
+    /// `+CMS ERROR: <code>`, see [`crate::error::CmsError`].
+    pub fn cms(code: u16) -> Self {
+        MockResponse::lines(&[&format!("+CMS ERROR: {code}")])
+    }
+
+    /// No response at all, so the task times out.
+    pub fn silence() -> Self {
+        MockResponse::raw("")
+    }
+
+    /// Sends the response `delay` after the command has been written.
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+struct Rule {
+    command: String,
+    response: MockResponse,
+    once: bool,
+}
+
+impl Rule {
+    /// `*` at the end of the scripted command matches any rest of the line.
+    fn matches(&self, line: &str) -> bool {
+        match self.command.strip_suffix('*') {
+            Some(prefix) => line.starts_with(prefix),
+            None => line == self.command,
+        }
+    }
+}
+
+struct Mock {
+    rules: Vec<Rule>,
+    default: MockResponse,
+    /// bursts to be received, with the time they arrive at
+    pending: VecDeque<(Instant, Vec<u8>)>,
+    /// written bytes of the unfinished line
+    line: Vec<u8>,
+    written: Vec<String>,
+    unmatched: Vec<String>,
+    /// the last burst has been read whole
+    burst_ended: bool,
+}
+
+impl Mock {
+    fn schedule(&mut self, delay: Duration, data: Vec<u8>) {
+        if data.is_empty() {
+            return;
+        }
+        let at: Instant = Instant::now() + delay;
+        let position: usize = self
+            .pending
+            .iter()
+            .position(|(pending_at, _)| *pending_at > at)
+            .unwrap_or(self.pending.len());
+        self.pending.insert(position, (at, data));
+    }
+
+    fn respond(&mut self, line: String) {
+        // the one-shot rules go before the persistent ones
+        let position: Option<usize> = self
+            .rules
+            .iter()
+            .position(|rule: &Rule| rule.once && rule.matches(&line))
+            .or_else(|| {
+                self.rules
+                    .iter()
+                    .position(|rule: &Rule| rule.matches(&line))
+            });
+        let response: MockResponse = match position {
+            Some(position) if self.rules[position].once => self.rules.remove(position).response,
+            Some(position) => self.rules[position].response.clone(),
+            None => {
+                self.unmatched.push(line.clone());
+                self.default.clone()
+            }
+        };
+        self.written.push(line);
+        self.schedule(response.delay, response.data);
+    }
+}
+
+/// [`Transport`] answering the written commands with the scripted responses, for testing the code driving the
+/// modem without the device or a recorded [`Transcript`].
+///
+/// Every line written (ended with `\n`, or with Ctrl+Z after the `AT+CMGS` prompt) is matched against the
+/// commands scripted with [`MockTransport::on`] and [`MockTransport::once`] - an exact match, or a prefix if the
+/// command ends with `*`. The one-shot responses are used first, the commands without any get
+/// [`MockTransport::set_default`] (`ERROR` unless set). Like the UART, flushing the input discards the responses
+/// and the URCs which have arrived, but haven't been read yet.
+///
+/// Clones share the script, so keep one to change it and to check [`MockTransport::written`].
+///
+/// ```
+/// use rpi_sim868::{
+///     testing::{MockResponse, MockTransport},
+///     ErrorKind, LogLevelFilter, SIM868,
+/// };
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let mock: MockTransport = MockTransport::new();
+///     mock.on("AT+CSQ", MockResponse::lines(&["+CSQ: 20,0", "OK"]))
+///         .once("AT+CSQ", MockResponse::cme(30).delayed(Duration::from_millis(50)));
+///     let sim: SIM868 = SIM868::with_transport(mock.clone(), LogLevelFilter::Off);
+///
+///     let failed = sim.hat.network_strength().await.unwrap().unwrap_err();
+///     assert_eq!(failed.kind(), ErrorKind::Cme);
+///     assert_eq!(sim.hat.network_strength().await.unwrap().unwrap(), 20);
+///     assert_eq!(mock.written(), ["AT+CSQ", "AT+CSQ"]);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct MockTransport {
+    mock: Arc<Mutex<Mock>>,
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        MockTransport::new()
+    }
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        MockTransport {
+            mock: Arc::new(Mutex::new(Mock {
+                rules: Vec::new(),
+                default: MockResponse::error(),
+                pending: VecDeque::new(),
+                line: Vec::new(),
+                written: Vec::new(),
+                unmatched: Vec::new(),
+                burst_ended: false,
+            })),
+        }
+    }
+
+    fn mock(&self) -> std::sync::MutexGuard<'_, Mock> {
+        self.mock.lock().expect(MUTEX_POISONED_MSG)
+    }
+
+    fn add_rule(&self, command: &str, response: MockResponse, once: bool) -> &Self {
+        self.mock().rules.push(Rule {
+            command: command.to_string(),
+            response,
+            once,
+        });
+        self
+    }
+
+    /// Answers every `command` (without the line ending, eg. `AT+CSQ`) with the `response`.
+    pub fn on(&self, command: &str, response: MockResponse) -> &Self {
+        self.add_rule(command, response, false)
+    }
+
+    /// Answers the next `command` with the `response`, eg. to inject a single error.
+    pub fn once(&self, command: &str, response: MockResponse) -> &Self {
+        self.add_rule(command, response, true)
+    }
+
+    /// Response to the commands which haven't been scripted, `ERROR` by default.
+    pub fn set_default(&self, response: MockResponse) -> &Self {
+        self.mock().default = response;
+        self
+    }
+
+    /// Sends the unsolicited `line` (eg. `RING` or `+CMTI: "SM",1`) `delay` from now.
+    pub fn unsolicited(&self, line: &str, delay: Duration) -> &Self {
+        self.mock()
+            .schedule(delay, format!("\r\n{line}\r\n").into_bytes());
+        self
+    }
+
+    /// Lines written so far, without the line endings.
+    pub fn written(&self) -> Vec<String> {
+        self.mock().written.clone()
+    }
+
+    /// Written lines which haven't matched any scripted command.
+    pub fn unmatched(&self) -> Vec<String> {
+        self.mock().unmatched.clone()
+    }
+}
+
+impl Transport for MockTransport {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let mut mock = self.mock();
+        // every burst is followed by a read returning 0, as if the UART read has timed out
+        if mock.burst_ended {
+            mock.burst_ended = false;
+            return Ok(0);
+        }
+        let arrived: bool = matches!(mock.pending.front(), Some((at, _)) if *at <= Instant::now());
+        if !arrived {
+            drop(mock);
+            sleep(READ_WAIT);
+            return Ok(0);
+        }
+
+        let (_, data) = mock
+            .pending
+            .front_mut()
+            .expect("Critical error: mock response is missing.");
+        let length: usize = buffer.len().min(data.len());
+        buffer[..length].copy_from_slice(&data[..length]);
+        data.drain(..length);
+        if data.is_empty() {
+            mock.pending.pop_front();
+            mock.burst_ended = true;
+        }
+        Ok(length)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
+        let mut mock = self.mock();
+        for byte in data {
+            match byte {
+                b'\n' | 0x1A => {
+                    let line: Vec<u8> = std::mem::take(&mut mock.line);
+                    let line: String = String::from_utf8_lossy(&line).trim().to_string();
+                    if !line.is_empty() {
+                        mock.respond(line);
+                    }
+                }
+                _ => mock.line.push(*byte),
+            }
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self, queue: Queue) -> Result<(), Error> {
+        if queue != Queue::Output {
+            let now: Instant = Instant::now();
+            self.mock().pending.retain(|(at, _)| *at > now);
+        }
+        Ok(())
+    }
+
+    /// The mock answers at any rate.
+    fn set_baud_rate(&mut self, _: u32) -> Result<(), Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+//! Timeouts module
+//!
+//! [`CommandTimeouts`] bounds the time the tasks wait for the modem's response, per command category.
+//! Override the defaults with [`crate::SIM868Builder::timeouts`], eg. on slow networks.
+
+use std::time::Duration;
+
+/// Response timeouts of the AT commands, per category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommandTimeouts {
+    /// Simple queries and settings, eg. `AT`, `AT+CSQ`. 1 s by default.
+    pub query: Duration,
+    /// Sending, listing and removing SMS (`AT+CMGS`, `AT+CMGL`, `AT+CMGD`...). 30 s by default.
+    pub sms: Duration,
+    /// Opening and closing the GPRS bearer (`AT+SAPBR`, `AT+CGATT`). 20 s by default.
+    pub sapbr: Duration,
+    /// HTTP requests (`AT+HTTPDATA`, `AT+HTTPACTION`, `AT+HTTPREAD`). 10 s by default.
+    pub http: Duration,
+    /// GNSS power and data (`AT+CGNSPWR`, `AT+CGNSINF`). 1 s by default.
+    pub gnss: Duration,
+}
+
+impl Default for CommandTimeouts {
+    fn default() -> Self {
+        CommandTimeouts {
+            query: Duration::from_secs(1),
+            sms: Duration::from_secs(30),
+            sapbr: Duration::from_secs(20),
+            http: Duration::from_secs(10),
+            gnss: Duration::from_secs(1),
+        }
+    }
+}
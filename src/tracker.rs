@@ -0,0 +1,171 @@
+//! Tracker module
+//!
+//! Available with the `tracker` feature.
+//!
+//! [`Tracker`] packages the balloon use case the crate was written for: it reads the GNSS position
+//! periodically, uploads it with an HTTP POST request and, if the uploads keep failing (eg. out of the GPRS
+//! coverage), texts the position to a fallback number instead.
+//!
+//! ```no_run
+//! use rpi_sim868::{
+//!     gprs::ApnConfig,
+//!     tracker::{Tracker, TrackerConfig},
+//!     SIM868,
+//! };
+//! use std::time::Duration;
+//!
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! #[tokio::main]
+//! async fn main() -> Result<(), rpi_sim868::Error> {
+//!     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//!     sim.hat.turn_on().await?;
+//!
+//!     let tracker: Tracker = Tracker::start(
+//!         &sim,
+//!         TrackerConfig {
+//!             url: "http://example.com/position".to_string(),
+//!             apn: ApnConfig {
+//!                 apn: "internet".to_string(),
+//!                 user: String::new(),
+//!                 password: String::new(),
+//!             },
+//!             interval: Duration::from_secs(30),
+//!             fallback_number: Some("+4799999999".to_string()),
+//!             fallback_after: 3,
+//!         },
+//!     )
+//!     .await?;
+//!
+//!     tokio::time::sleep(Duration::from_secs(3600)).await;
+//!     tracker.stop();
+//!     Ok(())
+//! }
+//! ```
+//!
+//! The position is posted as JSON:
+//! `{"lat": .., "lon": .., "alt": .., "ground_speed": .., "ground_course": .., "sats_in_use": .., "utc_datetime": ".."}`,
+//! with `null` for the values the modem hasn't reported.
+
+use crate::{
+    gnss::GNSSData,
+    gprs::{ApnConfig, ContentType, Request, RequestMethod},
+    ResolverReturn, SIM868,
+};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::{task::JoinHandle, time::sleep};
+
+/// Configuration of the [`Tracker`].
+#[derive(Debug, Clone)]
+pub struct TrackerConfig {
+    /// URL the position is posted to. Because of SIM868 limitations, HTTPS is not supported.
+    pub url: String,
+    pub apn: ApnConfig,
+    /// Time between the positions.
+    pub interval: Duration,
+    /// Number the position is texted to if the uploads fail, `None` disables the SMS fallback.
+    pub fallback_number: Option<String>,
+    /// Number of consecutive failed uploads after which the position is texted.
+    pub fallback_after: u32,
+}
+
+/// Tracking task running in the background, see the [module](self) documentation.
+pub struct Tracker {
+    handle: JoinHandle<()>,
+}
+
+fn position_payload(data: &GNSSData) -> Value {
+    json!({
+        "lat": data.lat,
+        "lon": data.lon,
+        "alt": data.alt,
+        "ground_speed": data.ground_speed,
+        "ground_course": data.ground_course,
+        "sats_in_use": data.sats_in_use,
+        "utc_datetime": data.utc_datetime.to_rfc3339(),
+    })
+}
+
+fn position_message(data: &GNSSData) -> String {
+    let alt: String = match data.alt {
+        Some(alt) => format!(" alt {alt:.0} m"),
+        None => String::new(),
+    };
+    format!(
+        "{:.6},{:.6}{} at {}",
+        data.lat,
+        data.lon,
+        alt,
+        data.utc_datetime.format("%Y-%m-%d %H:%M:%S UTC")
+    )
+}
+
+async fn upload(sim: &SIM868, config: &TrackerConfig, data: &GNSSData) -> ResolverReturn<()> {
+    sim.gprs
+        .request_now(Request {
+            content_type: Some(ContentType::Json),
+            data: position_payload(data),
+            userdata_header: None,
+            method: RequestMethod::POST,
+            url: config.url.clone(),
+        })
+        .await?;
+    Ok(())
+}
+
+async fn track(sim: SIM868, config: TrackerConfig) {
+    let mut failed_uploads: u32 = 0;
+
+    loop {
+        match sim.gnss.get_data_now().await {
+            Ok(data) => match upload(&sim, &config, &data).await {
+                Ok(_) => failed_uploads = 0,
+                Err(e) => {
+                    failed_uploads += 1;
+                    log::warn!("Tracker - uploading the position failed ({failed_uploads}x): {e}");
+                    if let Some(ref number) = config.fallback_number {
+                        if failed_uploads >= config.fallback_after {
+                            match sim.sms.send_now(number, &position_message(&data)).await {
+                                Ok(_) => failed_uploads = 0,
+                                Err(e) => log::warn!("Tracker - texting the position failed: {e}"),
+                            }
+                        }
+                    }
+                }
+            },
+            Err(e) => log::info!("Tracker - position not available: {e}"),
+        }
+        sleep(config.interval).await;
+    }
+}
+
+impl Tracker {
+    /// Turns on the GNSS module, initialises the APN and starts tracking in the background.
+    pub async fn start(sim: &SIM868, config: TrackerConfig) -> ResolverReturn<Tracker> {
+        if !sim.gnss.is_on_now().await? {
+            sim.gnss.turn_on_now().await?;
+        }
+        sim.gprs.init_now(config.apn.clone()).await?;
+
+        Ok(Tracker {
+            handle: tokio::spawn(track(sim.clone(), config)),
+        })
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.handle.is_finished()
+    }
+
+    /// Stops tracking, the GNSS module is left on.
+    pub fn stop(self) {
+        self.handle.abort();
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
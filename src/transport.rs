@@ -0,0 +1,211 @@
+//! Transport module
+//!
+//! The SIM868 is driven over a byte stream described by the [`Transport`] trait.
+//! With the default `rpi` feature, `SIM868::new` opens the Raspberry Pi UART (`rppal`); any other implementation - a USB
+//! serial adapter, a mock in the tests - can be passed to [`crate::SIM868::with_transport`] or
+//! [`crate::SIM868Builder::transport`].
+//!
+//! With the `serialport` feature, [`SerialPortTransport`] connects to the modem through any serial device,
+//! eg. an USB-UART adapter on Linux or macOS.
+
+use crate::error::Error;
+#[cfg(feature = "rpi")]
+use rppal::uart::{Parity, Uart};
+#[cfg(any(feature = "rpi", feature = "serialport"))]
+use std::time::Duration;
+
+/// Buffered data to be discarded by [`Transport::flush`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Queue {
+    /// Received, but not yet read data.
+    Input,
+    /// Written, but not yet transmitted data.
+    Output,
+    Both,
+}
+
+/// Byte stream the SIM868 is connected through.
+pub trait Transport: Send {
+    /// Reads the received bytes into `buffer`, returning the number of bytes read.
+    /// Should wait shortly (~100 ms) for the data to arrive and return `0` if there is none.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error>;
+
+    /// Writes `data`, returning the number of bytes written.
+    fn write(&mut self, data: &[u8]) -> Result<usize, Error>;
+
+    /// Discards the data buffered in the given `queue`.
+    fn flush(&mut self, queue: Queue) -> Result<(), Error>;
+
+    /// Switches the local serial port to the `baud_rate`, see [`crate::hat::Hat::set_baud_rate`]. Fails with
+    /// [`Error::TransportBaudRateUnsupported`] unless implemented.
+    fn set_baud_rate(&mut self, _baud_rate: u32) -> Result<(), Error> {
+        Err(Error::TransportBaudRateUnsupported)
+    }
+}
+
+#[cfg(feature = "rpi")]
+impl Transport for Uart {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        Ok(Uart::read(self, buffer)?)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
+        Ok(Uart::write(self, data)?)
+    }
+
+    fn flush(&mut self, queue: Queue) -> Result<(), Error> {
+        let queue: rppal::uart::Queue = match queue {
+            Queue::Input => rppal::uart::Queue::Input,
+            Queue::Output => rppal::uart::Queue::Output,
+            Queue::Both => rppal::uart::Queue::Both,
+        };
+        Ok(Uart::flush(self, queue)?)
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Error> {
+        Ok(Uart::set_baud_rate(self, baud_rate)?)
+    }
+}
+
+/// Transport left behind by [`crate::SIM868::close`], failing every operation with [`Error::Closed`].
+pub(crate) struct ClosedTransport;
+
+impl Transport for ClosedTransport {
+    fn read(&mut self, _: &mut [u8]) -> Result<usize, Error> {
+        Err(Error::Closed)
+    }
+
+    fn write(&mut self, _: &[u8]) -> Result<usize, Error> {
+        Err(Error::Closed)
+    }
+
+    fn flush(&mut self, _: Queue) -> Result<(), Error> {
+        Err(Error::Closed)
+    }
+}
+
+/// Parity bit of the UART frame.
+#[cfg(feature = "rpi")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UartParity {
+    None,
+    Even,
+    Odd,
+}
+
+/// Settings of the Raspberry Pi UART, see [`crate::SIM868Builder::uart_config`]. Defaults to `/dev/ttyS0` at
+/// 115200 bauds, 8N1, without flow control - the wiring of the Waveshare HAT.
+#[cfg(feature = "rpi")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UartConfig {
+    pub path: String,
+    pub baud_rate: u32,
+    pub parity: UartParity,
+    /// 5 to 8.
+    pub data_bits: u8,
+    /// 1 or 2.
+    pub stop_bits: u8,
+    /// RTS/CTS hardware flow control, for the boards with the lines wired.
+    pub hardware_flow_control: bool,
+    /// Time a read waits for the data to arrive before it returns the bytes received so far, 100 ms by default.
+    /// The longer, the fewer reads of the responses split into chunks, but the slower the tasks notice the end
+    /// of the modem output.
+    pub read_timeout: Duration,
+}
+
+#[cfg(feature = "rpi")]
+impl Default for UartConfig {
+    fn default() -> Self {
+        UartConfig {
+            path: "/dev/ttyS0".to_string(),
+            baud_rate: 115200,
+            parity: UartParity::None,
+            data_bits: 8,
+            stop_bits: 1,
+            hardware_flow_control: false,
+            read_timeout: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Opens the UART at `path` (eg. `/dev/ttyS0`) in 8N1 mode.
+#[cfg(feature = "rpi")]
+pub(crate) fn open_uart(path: &str, baud_rate: u32) -> Result<Uart, Error> {
+    open_uart_with(&UartConfig {
+        path: path.to_string(),
+        baud_rate,
+        ..UartConfig::default()
+    })
+}
+
+#[cfg(feature = "rpi")]
+pub(crate) fn open_uart_with(config: &UartConfig) -> Result<Uart, Error> {
+    let parity: Parity = match config.parity {
+        UartParity::None => Parity::None,
+        UartParity::Even => Parity::Even,
+        UartParity::Odd => Parity::Odd,
+    };
+    let mut uart: Uart = Uart::with_path(
+        &config.path,
+        config.baud_rate,
+        parity,
+        config.data_bits,
+        config.stop_bits,
+    )?;
+    uart.set_hardware_flow_control(config.hardware_flow_control)?;
+    uart.set_read_mode(0, config.read_timeout)?;
+    Ok(uart)
+}
+
+/// Transport over a serial device opened with the [`serialport`] crate.
+#[cfg(feature = "serialport")]
+pub struct SerialPortTransport {
+    port: Box<dyn serialport::SerialPort>,
+}
+
+#[cfg(feature = "serialport")]
+impl SerialPortTransport {
+    /// Opens the serial device at `path` (eg. `/dev/ttyUSB0` or `/dev/cu.usbserial-0001`) in 8N1 mode.
+    pub fn open(path: &str, baud_rate: u32) -> Result<Self, Error> {
+        let port: Box<dyn serialport::SerialPort> = serialport::new(path, baud_rate)
+            .data_bits(serialport::DataBits::Eight)
+            .parity(serialport::Parity::None)
+            .stop_bits(serialport::StopBits::One)
+            .timeout(Duration::from_millis(100))
+            .open()
+            .map_err(std::io::Error::from)?;
+        Ok(SerialPortTransport { port })
+    }
+}
+
+#[cfg(feature = "serialport")]
+impl Transport for SerialPortTransport {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        match self.port.read(buffer) {
+            Ok(read) => Ok(read),
+            Err(err) if err.kind() == std::io::ErrorKind::TimedOut => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
+        Ok(self.port.write(data)?)
+    }
+
+    fn flush(&mut self, queue: Queue) -> Result<(), Error> {
+        let buffer: serialport::ClearBuffer = match queue {
+            Queue::Input => serialport::ClearBuffer::Input,
+            Queue::Output => serialport::ClearBuffer::Output,
+            Queue::Both => serialport::ClearBuffer::All,
+        };
+        self.port.clear(buffer).map_err(std::io::Error::from)?;
+        Ok(())
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Error> {
+        self.port
+            .set_baud_rate(baud_rate)
+            .map_err(std::io::Error::from)?;
+        Ok(())
+    }
+}
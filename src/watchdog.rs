@@ -0,0 +1,317 @@
+//! Watchdog module
+//!
+//! [`ModemWatchdog`] checks the modem periodically with `AT` and, once it stops responding, recovers it - it
+//! power-cycles the modem, runs [`crate::hat::Hat::init`] and restores the GNSS and GPRS state. The outcome
+//! is published as [`Event::Recovered`] or [`Event::Unreachable`], so an unattended deployment (eg. a tracker on
+//! a balloon) survives the modem lockups.
+//!
+//! ```no_run
+//! use rpi_sim868::{watchdog::{ModemWatchdog, WatchdogConfig}, SIM868};
+//! use std::time::Duration;
+//!
+//! # #[cfg(not(feature = "rpi"))]
+//! # fn main() {}
+//! # #[cfg(feature = "rpi")]
+//! #[tokio::main]
+//! async fn main() -> Result<(), rpi_sim868::Error> {
+//!     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+//!     sim.hat.turn_on().await?;
+//!     let watchdog: ModemWatchdog = ModemWatchdog::start(
+//!         &sim,
+//!         WatchdogConfig {
+//!             interval: Duration::from_secs(60),
+//!             ..Default::default()
+//!         },
+//!     );
+//!     // ... use the sim as usual
+//!     watchdog.stop();
+//!     Ok(())
+//! }
+//! ```
+//!
+//! With the `systemd` feature, `SystemdWatchdog` pings the systemd watchdog only while the modem is healthy.
+
+#[cfg(feature = "gprs")]
+use crate::gprs::ApnConfig;
+use crate::{
+    error::{Error, ErrorKind},
+    events::Event,
+    hat::is_on,
+    serial_port::{spawn_task, SerialPort, TaskPriority},
+    ResolverReturn, SIM868,
+};
+#[cfg(feature = "systemd")]
+use std::{env, os::unix::net::UnixDatagram};
+use std::{sync::Arc, time::Duration};
+use tokio::{task::JoinHandle, time::sleep};
+
+/// `AT` sent with the high priority, ahead of the queued tasks.
+async fn is_healthy(serial_port: &Arc<SerialPort>) -> bool {
+    let check: Result<Result<bool, Error>, tokio::task::JoinError> =
+        spawn_task(serial_port.clone(), TaskPriority::HIGH, is_on, None, ()).await;
+    matches!(check, Ok(Ok(true)))
+}
+
+/// Configuration of the [`ModemWatchdog`].
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    /// Time between the checks, 30 s by default.
+    pub interval: Duration,
+    /// Consecutive failed checks after which the modem is recovered, 3 by default.
+    pub max_failures: u32,
+    /// Power-cycles the modem with the power pin (see [`crate::hat::Hat::set_power_pin`]) before the
+    /// initialisation, `true` by default.
+    pub power_cycle: bool,
+    /// Turns the GNSS module on again, `false` by default.
+    #[cfg(feature = "gnss")]
+    pub restore_gnss: bool,
+    /// APN set again with [`crate::gprs::GPRS::init`], `None` by default.
+    #[cfg(feature = "gprs")]
+    pub apn: Option<ApnConfig>,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        WatchdogConfig {
+            interval: Duration::from_secs(30),
+            max_failures: 3,
+            power_cycle: true,
+            #[cfg(feature = "gnss")]
+            restore_gnss: false,
+            #[cfg(feature = "gprs")]
+            apn: None,
+        }
+    }
+}
+
+/// Watchdog task running in the background, see the [module](self) documentation.
+pub struct ModemWatchdog {
+    handle: JoinHandle<()>,
+}
+
+/// Toggles the power until the modem responds - the wedged modem is switched off by the first toggle.
+async fn power_cycle(sim: &SIM868) -> ResolverReturn<()> {
+    for _ in 0..2 {
+        match sim.hat.turn_on().await {
+            Err(e) if e.kind() == ErrorKind::HatAlreadyOn => return Ok(()),
+            // the toggle has switched the wedged modem off
+            Err(e) if e.kind() == ErrorKind::HatNotBooted => continue,
+            Err(e) => return Err(e),
+            Ok(_) => (),
+        }
+        if is_healthy(&sim.serial_port).await {
+            return Ok(());
+        }
+    }
+    Err(Error::NotResolved)
+}
+
+async fn recover(sim: &SIM868, config: &WatchdogConfig) -> ResolverReturn<()> {
+    // the modem may have restarted on its own
+    sim.invalidate_state();
+    if config.power_cycle {
+        power_cycle(sim).await?;
+    }
+    sim.hat.init_now().await?;
+    #[cfg(feature = "gnss")]
+    if config.restore_gnss && !sim.gnss.is_on_now().await? {
+        sim.gnss.turn_on_now().await?;
+    }
+    #[cfg(feature = "gprs")]
+    if let Some(ref apn) = config.apn {
+        sim.gprs.init_now(apn.clone()).await?;
+    }
+    Ok(())
+}
+
+async fn watch(sim: SIM868, config: WatchdogConfig) {
+    let mut failures: u32 = 0;
+
+    loop {
+        sleep(config.interval).await;
+        if is_healthy(&sim.serial_port).await {
+            failures = 0;
+            continue;
+        }
+        failures += 1;
+        log::warn!("Watchdog - the modem hasn't responded ({failures}x).");
+        if failures < config.max_failures {
+            continue;
+        }
+
+        failures = 0;
+        let event: Event = match recover(&sim, &config).await {
+            Ok(_) => {
+                log::info!("Watchdog - the modem has been recovered.");
+                Event::Recovered
+            }
+            Err(e) => {
+                log::error!("Watchdog - recovering the modem has failed: {e}");
+                Event::Unreachable
+            }
+        };
+        let _ = sim.serial_port.events.send(event);
+    }
+}
+
+impl ModemWatchdog {
+    /// Starts checking the modem in the background. The watchdog keeps a handle of the `sim`, so the connection
+    /// stays open until it's stopped or dropped.
+    pub fn start(sim: &SIM868, config: WatchdogConfig) -> ModemWatchdog {
+        ModemWatchdog {
+            handle: tokio::spawn(watch(sim.clone(), config)),
+        }
+    }
+
+    pub fn stop(self) {
+        self.handle.abort();
+    }
+}
+
+impl Drop for ModemWatchdog {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Sends the `state` (eg. `READY=1`) to the service manager. Returns `false` if not running under systemd.
+#[cfg(feature = "systemd")]
+pub fn notify(state: &str) -> Result<bool, Error> {
+    let Some(socket_path) = env::var_os("NOTIFY_SOCKET") else {
+        return Ok(false);
+    };
+    let socket: UnixDatagram = UnixDatagram::unbound()?;
+    let socket_path: String = socket_path.to_string_lossy().to_string();
+
+    match socket_path.strip_prefix('@') {
+        // abstract namespace socket, supported by std since 1.70
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+
+            let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &address)?
+        }
+        // only Linux has the abstract namespace
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "abstract NOTIFY_SOCKET outside Linux",
+            )
+            .into())
+        }
+        None => socket.send_to(state.as_bytes(), &socket_path)?,
+    };
+    Ok(true)
+}
+
+/// Pings the systemd watchdog (`sd_notify(WATCHDOG=1)`) only while the modem passes the health
+/// check, so systemd restarts the service when the modem or the task queue wedges. The check is an `AT` command
+/// sent with the high priority - keep `WatchdogSec=` well above the longest task (eg. an HTTP request).
+///
+/// ```ini
+/// [Service]
+/// Type=notify
+/// WatchdogSec=60
+/// Restart=on-failure
+/// ```
+///
+/// ```no_run
+/// use rpi_sim868::{watchdog::SystemdWatchdog, SIM868};
+///
+/// # #[cfg(not(feature = "rpi"))]
+/// # fn main() {}
+/// # #[cfg(feature = "rpi")]
+/// #[tokio::main]
+/// async fn main() -> Result<(), rpi_sim868::Error> {
+///     let sim: SIM868 = SIM868::new("/dev/ttyS0", 115200, rpi_sim868::LogLevelFilter::Error);
+///     // the restarted service power-cycles the modem on its way up
+///     let _ = sim.hat.turn_on().await;
+///     if let Some(watchdog) = SystemdWatchdog::new(&sim) {
+///         tokio::spawn(watchdog.run());
+///     }
+///     // ... use the sim as usual
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "systemd")]
+pub struct SystemdWatchdog {
+    serial_port: Arc<SerialPort>,
+    interval: Duration,
+}
+
+#[cfg(feature = "systemd")]
+impl SystemdWatchdog {
+    /// Returns `None` if the watchdog isn't enabled for the service (no `WATCHDOG_USEC`, or it's meant for another process).
+    pub fn new(sim: &SIM868) -> Option<Self> {
+        let timeout: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+        if let Ok(pid) = env::var("WATCHDOG_PID") {
+            if pid.parse::<u32>().ok()? != std::process::id() {
+                return None;
+            }
+        }
+        // ping twice per timeout, as recommended by sd_watchdog_enabled(3)
+        Some(SystemdWatchdog::with_interval(
+            sim,
+            Duration::from_micros(timeout / 2),
+        ))
+    }
+
+    /// Pings every `interval`, regardless of the environment.
+    pub fn with_interval(sim: &SIM868, interval: Duration) -> Self {
+        SystemdWatchdog {
+            serial_port: sim.serial_port.clone(),
+            interval,
+        }
+    }
+
+    pub async fn is_healthy(&self) -> bool {
+        is_healthy(&self.serial_port).await
+    }
+
+    /// Notifies systemd the service is ready and keeps pinging the watchdog while the modem is healthy.
+    /// Returns only if notifying fails.
+    pub async fn run(self) -> Result<(), Error> {
+        notify("READY=1")?;
+        loop {
+            match self.is_healthy().await {
+                true => {
+                    notify("WATCHDOG=1")?;
+                }
+                false => log::warn!("Modem health check has failed, the watchdog isn't pinged."),
+            }
+            sleep(self.interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{MockResponse, MockTransport},
+        LogLevelFilter,
+    };
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn stops_when_dropped() {
+        let mock: MockTransport = MockTransport::new();
+        mock.set_default(MockResponse::ok());
+        let sim: SIM868 = SIM868::with_transport(mock.clone(), LogLevelFilter::Off);
+        let config: WatchdogConfig = WatchdogConfig {
+            interval: Duration::from_millis(10),
+            ..WatchdogConfig::default()
+        };
+
+        let watchdog: ModemWatchdog = ModemWatchdog::start(&sim, config);
+        while mock.written().len() < 2 {
+            sleep(Duration::from_millis(10)).await;
+        }
+        drop(watchdog);
+        sleep(Duration::from_millis(20)).await;
+        let checks: usize = mock.written().len();
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(mock.written().len(), checks);
+    }
+}